@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use cgmath::{ MetricSpace, Point3 };
+use gekraftet_core::world::SoundMaterial;
+use crate::settings::AudioSettings;
+
+/// What made a sound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundKind {
+    Footstep,
+    Break,
+    Place,
+}
+
+impl SoundKind {
+    pub const ALL: [SoundKind; 3] = [SoundKind::Footstep, SoundKind::Break, SoundKind::Place];
+
+    /// The name of the folder the sounds of the kind are in, in resource
+    /// packs.
+    pub fn name(self) -> &'static str {
+        match self {
+            SoundKind::Footstep => "footstep",
+            SoundKind::Break => "break",
+            SoundKind::Place => "place",
+        }
+    }
+}
+
+/// A sound played somewhere in the world. The position is in world units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sound {
+    pub kind: SoundKind,
+    pub material: SoundMaterial,
+    pub position: Point3<f32>,
+}
+
+/// The file a sound is played from, e.g. an Ogg Vorbis file, and the path
+/// of the resource it was loaded from.
+pub struct SoundClip {
+    pub path: String,
+    pub bytes: Vec<u8>,
+}
+
+/// The clip every kind of sound of every material is played from, as loaded
+/// from the resource packs. Sounds without a clip are left to the backend.
+pub struct SoundBank {
+    clips: HashMap<(SoundKind, SoundMaterial), SoundClip>,
+}
+
+impl SoundBank {
+    /// The materials that make sounds.
+    pub const MATERIALS: [SoundMaterial; 3] = [SoundMaterial::Stone, SoundMaterial::Glass, SoundMaterial::Liquid];
+
+    pub fn new() -> Self {
+        Self {
+            clips: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, kind: SoundKind, material: SoundMaterial, path: String, bytes: Vec<u8>) {
+        self.clips.insert((kind, material), SoundClip { path, bytes });
+    }
+
+    pub fn get(&self, kind: SoundKind, material: SoundMaterial) -> Option<&SoundClip> {
+        self.clips.get(&(kind, material))
+    }
+}
+
+/// Something that turns sounds into noise.
+pub trait AudioBackend {
+    /// Plays the sound from the clip, if there is one, at the volume, which
+    /// is between 0 and 1.
+    fn play(&mut self, sound: &Sound, clip: Option<&SoundClip>, volume: f32);
+}
+
+/// Logs every sound instead of playing it. There is no audio output yet, so
+/// this is what `Audio` uses by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogBackend;
+
+impl AudioBackend for LogBackend {
+    fn play(&mut self, sound: &Sound, clip: Option<&SoundClip>, volume: f32) {
+        let source = match clip {
+            Some(clip) => format!("{} ({} bytes)", clip.path, clip.bytes.len()),
+            None => "no clip".to_owned(),
+        };
+
+        log::trace!(
+            "{:?} sound ({:?}) at {:?}, volume {:.2}, from {}",
+            sound.kind, sound.material, sound.position, volume, source
+        );
+    }
+}
+
+/// Plays sounds relative to a listener, who hears far away sounds quieter and
+/// does not hear sounds beyond `HEARING_DISTANCE` at all.
+pub struct Audio {
+    backend: Box<dyn AudioBackend>,
+    sounds: SoundBank,
+    listener: Point3<f32>,
+    volumes: AudioSettings,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        Self::with_backend(Box::new(LogBackend))
+    }
+
+    pub fn with_backend(backend: Box<dyn AudioBackend>) -> Self {
+        Self {
+            backend,
+            sounds: SoundBank::new(),
+            listener: Point3::new(0.0, 0.0, 0.0),
+            volumes: AudioSettings::default(),
+        }
+    }
+
+    /// Replaces the clips sounds are played from.
+    pub fn set_sounds(&mut self, sounds: SoundBank) {
+        self.sounds = sounds;
+    }
+
+    pub fn set_volumes(&mut self, volumes: &AudioSettings) {
+        self.volumes = volumes.clone();
+    }
+
+    /// Moves the listener, usually to where the camera is.
+    pub fn set_listener(&mut self, listener: Point3<f32>) {
+        self.listener = listener;
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        if sound.material == SoundMaterial::Silent {
+            return
+        }
+
+        let distance = self.listener.distance(sound.position);
+        let falloff = 1.0 - distance / HEARING_DISTANCE;
+        let setting = match sound.kind {
+            SoundKind::Footstep => self.volumes.footsteps,
+            SoundKind::Break | SoundKind::Place => self.volumes.blocks,
+        };
+        let volume = self.volumes.master * setting * base_volume(sound.kind) * falloff;
+
+        if volume > 0.0 {
+            let clip = self.sounds.get(sound.kind, sound.material);
+            self.backend.play(&sound, clip, volume);
+        }
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// How loud each kind of sound is, before the distance is taken into account.
+fn base_volume(kind: SoundKind) -> f32 {
+    match kind {
+        SoundKind::Footstep => 0.3,
+        SoundKind::Break | SoundKind::Place => 1.0,
+    }
+}
+
+// How far (in world units) sounds can be heard, 16 blocks.
+const HEARING_DISTANCE: f32 = 4.0;
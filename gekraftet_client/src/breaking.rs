@@ -0,0 +1,70 @@
+use gekraftet_core::world::{ Block, BlockPos };
+
+/// Tracks how far the player has got breaking the block it is hitting.
+/// Blocks break once they have been hit for as long as their hardness, and
+/// hitting another block (or letting go) starts over.
+pub struct BlockBreaking {
+    // The block being hit, and for how long (in seconds) it has been.
+    target: Option<BlockPos>,
+    time: f32,
+    hardness: f32,
+}
+
+impl BlockBreaking {
+    pub fn new() -> Self {
+        Self {
+            target: None,
+            time: 0.0,
+            hardness: 0.0,
+        }
+    }
+
+    /// Hits the block at the position for `delta` seconds. Returns true if
+    /// it breaks, which also starts over.
+    pub fn hit(&mut self, at: BlockPos, block: &Block, delta: f32) -> bool {
+        let hardness = match block.hardness() {
+            Some(hardness) => hardness,
+            None => {
+                self.reset();
+                return false
+            },
+        };
+
+        if self.target != Some(at) {
+            self.target = Some(at);
+            self.time = 0.0;
+        }
+
+        self.time += delta;
+        self.hardness = hardness;
+
+        match self.time >= hardness {
+            true => {
+                self.reset();
+                true
+            },
+            false => false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.time = 0.0;
+    }
+
+    /// The block being broken, and how far along it is, from 0 to 1.
+    pub fn progress(&self) -> Option<(BlockPos, f32)> {
+        let progress = match self.hardness > 0.0 {
+            true => self.time / self.hardness,
+            false => 1.0,
+        };
+
+        self.target.map(|at| (at, progress.min(1.0)))
+    }
+}
+
+impl Default for BlockBreaking {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,71 +1,505 @@
-use cgmath::{ Deg, InnerSpace, Matrix4, Point3, Rad, Vector3 };
+use cgmath::{ Deg, InnerSpace, Matrix4, Point3, Quaternion, Rad, Rotation, Rotation3, Vector3, Zero };
+use gekraftet_core::utils::Frustum;
+use gekraftet_core::world::BlockPos;
+use crate::settings::MouseSettings;
+use crate::world::BLOCK_LENGTH;
+
+// How quickly the field of view follows its modifier, in seconds.
+const FOV_HALF_LIFE: f32 = 0.06;
+
+// The frame time frame-independent rotation behaves like, so that the same
+// sensitivity feels the same in both modes at 60 FPS.
+const REFERENCE_FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// Where the view is rendered from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// From the position of the camera itself.
+    FirstPerson,
+    /// From behind the position of the camera, orbiting around it.
+    ThirdPerson,
+    /// From straight above the position of the camera, looking down with an
+    /// orthographic projection. Used for the map view.
+    TopDown,
+}
+
+// How much a shake rotates the view per world unit it moves it, in radians.
+const SHAKE_ROTATION: f32 = 0.5;
+
+// A procedural shake of the view, see `Camera::add_shake()`.
+struct Shake {
+    amplitude: f32,
+    frequency: f32,
+    duration: f32,
+    elapsed: f32,
+    seed: u32,
+}
+
+impl Shake {
+    // Returns the offset of the view in view space, and its rotation around
+    // the x, y and z axes, in radians.
+    fn offset(&self) -> (Vector3<f32>, Vector3<f32>) {
+        // Shakes die down quadratically over their duration.
+        let falloff = (1.0 - self.elapsed / self.duration).max(0.0).powi(2);
+        let amplitude = self.amplitude * falloff;
+        let t = self.elapsed * self.frequency;
+
+        let channel = |i: u32| amplitude * value_noise(self.seed.wrapping_mul(6).wrapping_add(i), t);
+
+        let offset = Vector3::new(channel(0), channel(1), channel(2));
+        let angles = Vector3::new(channel(3), channel(4), channel(5)) * SHAKE_ROTATION;
+
+        (offset, angles)
+    }
+}
+
+// Half the size of the box around the camera that blocks cannot be placed
+// into, in world units. It is kept larger than the near plane, so the camera
+// cannot peek into blocks.
+const COLLISION_HALF_EXTENT: f32 = 0.11;
+
+// How far the third-person camera stays from the terrain it is pulled in by.
+const PULL_IN_MARGIN: f32 = 0.05;
 
 pub struct Camera {
     position: Point3<f32>,
-    target: Vector3<f32>,
-    sensitivity: f32,
+    // Derived from the yaw, pitch and roll. Facing +X with +Y up when all of
+    // them are zero.
+    orientation: Quaternion<f32>,
+    mouse: MouseSettings,
     pitch: f32,
     yaw: f32,
+    roll: f32,
+    mode: CameraMode,
+    third_person_distance: f32,
+    // How far above the position the top-down camera is, and half the height
+    // of the area it shows, both in world units.
+    map_height: f32,
+    map_scale: f32,
+
+    // Smoothing: the camera eases towards these, losing half of the remaining
+    // distance every half-life. A half-life of zero disables smoothing.
+    goal_position: Point3<f32>,
+    goal_pitch: f32,
+    goal_yaw: f32,
+    goal_roll: f32,
+    position_half_life: f32,
+    rotation_half_life: f32,
+
+    // The field of view is the base one scaled by a modifier (e.g. for
+    // sprinting or zooming), which always eases towards its goal.
+    fov: Deg<f32>,
+    fov_modifier: f32,
+    goal_fov_modifier: f32,
+
+    // Only ever applied to the view matrix, never to the position and the
+    // orientation themselves.
+    shakes: Vec<Shake>,
+    next_shake_seed: u32,
 }
 
 impl Camera {
     pub fn new(position: Point3<f32>, target: Vector3<f32>) -> Self {
-        Self {
+        let target = target.normalize();
+        let yaw = target.z.atan2(target.x);
+        let pitch = clamp_pitch(target.y.asin());
+
+        let mut camera = Self {
             position,
-            target,
-            sensitivity: 0.325,
-            pitch: 0.0,
-            yaw: 0.0,
+            orientation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            mouse: MouseSettings::default(),
+            pitch,
+            yaw,
+            roll: 0.0,
+            mode: CameraMode::FirstPerson,
+            third_person_distance: 1.0,
+            map_height: 64.0,
+            map_scale: 16.0,
+            goal_position: position,
+            goal_pitch: pitch,
+            goal_yaw: yaw,
+            goal_roll: 0.0,
+            position_half_life: 0.0,
+            rotation_half_life: 0.0,
+            fov: Deg(55.0),
+            fov_modifier: 1.0,
+            goal_fov_modifier: 1.0,
+            shakes: Vec::new(),
+            next_shake_seed: 0,
+        };
+
+        camera.update_orientation();
+        camera
+    }
+
+    /// Returns the field of view, with its current modifier applied.
+    pub fn fov(&self) -> Deg<f32> {
+        self.fov * self.fov_modifier
+    }
+
+    /// Sets the base field of view.
+    pub fn set_fov(&mut self, fov: Deg<f32>) {
+        self.fov = fov;
+    }
+
+    /// Scales the field of view, easing into the new scale over time. Values
+    /// above 1.0 widen it (sprinting), values below narrow it (zooming).
+    pub fn set_fov_modifier(&mut self, modifier: f32) {
+        self.goal_fov_modifier = modifier;
+    }
+
+    /// Sets the half-lives (in seconds) of the positional and rotational
+    /// smoothing. Zero disables the respective smoothing.
+    pub fn set_smoothing(&mut self, position_half_life: f32, rotation_half_life: f32) {
+        self.position_half_life = position_half_life.max(0.0);
+        self.rotation_half_life = rotation_half_life.max(0.0);
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::FirstPerson => CameraMode::ThirdPerson,
+            CameraMode::ThirdPerson => CameraMode::FirstPerson,
+            CameraMode::TopDown => CameraMode::FirstPerson,
+        };
+    }
+
+    pub fn map_height(&self) -> f32 {
+        self.map_height
+    }
+
+    pub fn map_scale(&self) -> f32 {
+        self.map_scale
+    }
+
+    /// Sets how high above its position the top-down camera is, and how much
+    /// it shows: `scale` is half the height of the visible area. Both are in
+    /// world units.
+    pub fn set_map_view(&mut self, height: f32, scale: f32) {
+        self.map_height = height.max(0.0);
+        self.map_scale = scale.max(BLOCK_LENGTH);
+    }
+
+    /// Returns the scale the projection has to be orthographic with, or
+    /// `None` if it should be a perspective one.
+    pub fn orthographic_scale(&self) -> Option<f32> {
+        match self.mode {
+            CameraMode::TopDown => Some(self.map_scale),
+            _ => None,
         }
     }
 
+    /// Returns true if the block at the position overlaps the box around the
+    /// camera, so that placing a block there would put it inside the view.
+    pub fn touches_block(&self, at: BlockPos) -> bool {
+        let (min, max) = Self::touched_blocks(self.position);
+
+        (min.x..=max.x).contains(&at.x)
+            && (min.y..=max.y).contains(&at.y)
+            && (min.z..=max.z).contains(&at.z)
+    }
+
+    // The range of blocks the box around the position overlaps.
+    fn touched_blocks(at: Point3<f32>) -> (Point3<i32>, Point3<i32>) {
+        let to_block = |v: f32| (v / BLOCK_LENGTH).floor() as i32;
+        let min = at.map(|v| to_block(v - COLLISION_HALF_EXTENT));
+        let max = at.map(|v| to_block(v + COLLISION_HALF_EXTENT));
+        (min, max)
+    }
+
+    pub fn third_person_distance(&self) -> f32 {
+        self.third_person_distance
+    }
+
+    /// Sets how far (in world units) the third-person camera orbits from the
+    /// position of the camera.
+    pub fn set_third_person_distance(&mut self, distance: f32) {
+        self.third_person_distance = distance.max(0.0);
+    }
+
+    /// Returns where the camera was last moved to.
+    pub fn position(&self) -> Point3<f32> {
+        self.goal_position
+    }
+
+    pub fn yaw(&self) -> Rad<f32> {
+        Rad(self.goal_yaw)
+    }
+
+    pub fn pitch(&self) -> Rad<f32> {
+        Rad(self.goal_pitch)
+    }
+
     pub fn front(&self) -> Vector3<f32> {
-        self.target
+        self.orientation.rotate_vector(Vector3::unit_x())
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.orientation.rotate_vector(Vector3::unit_y())
+    }
+
+    pub fn orientation(&self) -> Quaternion<f32> {
+        self.orientation
     }
 
+    pub fn roll(&self) -> Rad<f32> {
+        Rad(self.goal_roll)
+    }
+
+    /// Tilts the camera around the direction it is looking at, e.g. for
+    /// cinematic shots. Mouse rotation keeps working relative to the roll.
+    pub fn set_roll<A: Into<Rad<f32>>>(&mut self, roll: A) {
+        self.goal_roll = roll.into().0;
+
+        if self.rotation_half_life == 0.0 {
+            self.roll = self.goal_roll;
+            self.update_orientation();
+        }
+    }
+
+    pub fn mouse_settings(&self) -> &MouseSettings {
+        &self.mouse
+    }
+
+    pub fn set_mouse_settings(&mut self, settings: MouseSettings) {
+        self.mouse = settings;
+    }
+
+    /// Returns the horizontal sensitivity.
     pub fn sensitivity(&self) -> f32 {
-        self.sensitivity
+        self.mouse.sensitivity_x
     }
 
+    /// Sets the horizontal sensitivity, scaling the vertical one along with
+    /// it.
     pub fn set_sensitivity(&mut self, s: f32) {
-        self.sensitivity = s;
+        let s = s.max(0.0);
+
+        self.mouse.sensitivity_y = match self.mouse.sensitivity_x {
+            x if x > 0.0 => self.mouse.sensitivity_y * s / x,
+            _ => s,
+        };
+        self.mouse.sensitivity_x = s;
     }
 
     pub fn rotate_by_mouse(&mut self, x: f32, y: f32, delta_time: f32) {
-        let x_angle =  delta_time * x * self.sensitivity;
-        let y_angle = -delta_time * y * self.sensitivity;
-        
-        self.yaw += x_angle;
-        self.pitch += y_angle;
-
-        let deg90 = Rad::from(Deg(89.9f32));
-        let deg90 = deg90.0;
-
-        // Prevent camera from flipping y
-        self.pitch = if self.pitch >= deg90 {
-            deg90
-        } else if self.pitch <= -deg90 {
-            -deg90
-        } else {
-            self.pitch
+        // Mouse deltas are already a displacement, so scaling them by the
+        // frame time is optional.
+        let scale = match self.mouse.frame_independent {
+            true => REFERENCE_FRAME_TIME,
+            false => delta_time,
         };
+        let y = if self.mouse.invert_y { -y } else { y };
+
+        let x_angle =  scale * x * self.mouse.sensitivity_x;
+        let y_angle = -scale * y * self.mouse.sensitivity_y;
+
+        self.rotate(Rad(x_angle), Rad(y_angle));
+    }
+
+    /// Turns the camera by the given angles. Positive pitch looks up.
+    pub fn rotate(&mut self, yaw: Rad<f32>, pitch: Rad<f32>) {
+        self.goal_yaw += yaw.0;
+        self.goal_pitch += pitch.0;
+
+        self.goal_pitch = clamp_pitch(self.goal_pitch);
 
-        self.target = Vector3::<f32>::new(
-            self.yaw.cos() * self.pitch.cos(),
-            self.pitch.sin(),
-            self.yaw.sin() * self.pitch.cos()
-        ).normalize();
+        if self.rotation_half_life == 0.0 {
+            self.yaw = self.goal_yaw;
+            self.pitch = self.goal_pitch;
+            self.update_orientation();
+        }
     }
 
     pub fn move_camera(&mut self, pos: Point3<f32>) {
-        self.position = pos;
+        self.goal_position = pos;
+
+        if self.position_half_life == 0.0 {
+            self.position = pos;
+        }
+    }
+
+    /// Shakes the view by up to `amplitude` world units, `frequency` times a
+    /// second, dying down over `duration` seconds. Shakes add up, and leave
+    /// the position and the orientation of the camera untouched.
+    pub fn add_shake(&mut self, amplitude: f32, frequency: f32, duration: f32) {
+        if duration <= 0.0 {
+            return
+        }
+
+        self.shakes.push(Shake {
+            amplitude,
+            frequency,
+            duration,
+            elapsed: 0.0,
+            seed: self.next_shake_seed,
+        });
+
+        self.next_shake_seed = self.next_shake_seed.wrapping_add(1);
+    }
+
+    /// Puts the camera at the given position and orientation right away,
+    /// regardless of smoothing.
+    pub fn snap_to(&mut self, position: Point3<f32>, yaw: Rad<f32>, pitch: Rad<f32>, roll: Rad<f32>) {
+        self.position = position;
+        self.goal_position = position;
+        self.yaw = yaw.0;
+        self.goal_yaw = yaw.0;
+        self.pitch = clamp_pitch(pitch.0);
+        self.goal_pitch = self.pitch;
+        self.roll = roll.0;
+        self.goal_roll = roll.0;
+        self.update_orientation();
+    }
+
+    /// Eases the camera towards where it was last moved and rotated to, and
+    /// the field of view towards its modifier.
+    pub fn update(&mut self, delta_time: f32) {
+        // The fraction of the remaining distance covered in this frame. This
+        // is independent of the frame rate: two frames of dt cover the same
+        // distance as one frame of 2 * dt.
+        let factor = |half_life: f32| match half_life {
+            h if h > 0.0 => 1.0 - 0.5f32.powf(delta_time / h),
+            _ => 1.0,
+        };
+
+        let p = factor(self.position_half_life);
+        self.position += (self.goal_position - self.position) * p;
+
+        let f = factor(FOV_HALF_LIFE);
+        self.fov_modifier += (self.goal_fov_modifier - self.fov_modifier) * f;
+
+        let r = factor(self.rotation_half_life);
+        self.yaw += (self.goal_yaw - self.yaw) * r;
+        self.pitch += (self.goal_pitch - self.pitch) * r;
+        self.roll += (self.goal_roll - self.roll) * r;
+        self.update_orientation();
+
+        for shake in self.shakes.iter_mut() {
+            shake.elapsed += delta_time;
+        }
+
+        self.shakes.retain(|s| s.elapsed < s.duration);
+    }
+
+    fn update_orientation(&mut self) {
+        // Yaw turns around the world's up axis, pitch around the camera's own
+        // right axis, and roll around the direction the camera faces. A
+        // positive yaw turns +X towards +Z, hence the negation.
+        let yaw = Quaternion::from_angle_y(Rad(-self.yaw));
+        let pitch = Quaternion::from_angle_z(Rad(self.pitch));
+        let roll = Quaternion::from_angle_x(Rad(self.roll));
+
+        self.orientation = (yaw * pitch * roll).normalize();
+    }
+
+    /// Returns the point the view is rendered from.
+    pub fn eye(&self) -> Point3<f32> {
+        self.eye_with(|_, _, _| None)
+    }
+
+    /// Like `eye()`, but pulls the third-person camera in front of whatever
+    /// obstructs it. `cast(origin, direction, max_distance)` returns the
+    /// distance to the first obstruction along the ray, if there is any.
+    pub fn eye_with<F>(&self, cast: F) -> Point3<f32>
+        where F: FnOnce(Point3<f32>, Vector3<f32>, f32) -> Option<f32>
+    {
+        match self.mode {
+            CameraMode::FirstPerson => self.position,
+            CameraMode::ThirdPerson => {
+                let backwards = -self.front();
+                let distance = cast(self.position, backwards, self.third_person_distance)
+                    .map(|hit| (hit - PULL_IN_MARGIN).max(0.0))
+                    .unwrap_or(self.third_person_distance)
+                    .min(self.third_person_distance);
+
+                self.position + backwards * distance
+            },
+            CameraMode::TopDown => self.position + Vector3::unit_y() * self.map_height,
+        }
     }
 
     pub fn generate_view(&self) -> Matrix4<f32> {
-        Matrix4::<f32>::look_at(
-            self.position, 
-            self.position + self.target, 
-            Vector3::<f32>::new(0.0, 1.0, 0.0)
-        )
+        self.view_from(self.eye())
+    }
+
+    /// Like `generate_view()`, but with the terrain-aware eye of `eye_with()`.
+    pub fn generate_view_with<F>(&self, cast: F) -> Matrix4<f32>
+        where F: FnOnce(Point3<f32>, Vector3<f32>, f32) -> Option<f32>
+    {
+        self.view_from(self.eye_with(cast))
+    }
+
+    /// Returns the volume seen through the camera with the given projection.
+    pub fn frustum(&self, projection: &Matrix4<f32>) -> Frustum {
+        Frustum::from_matrix(&(projection * self.generate_view()))
     }
+
+    fn view_from(&self, eye: Point3<f32>) -> Matrix4<f32> {
+        let view = match self.mode {
+            // The map is turned so that the direction the camera faces is up.
+            CameraMode::TopDown => {
+                let heading = Vector3::new(self.yaw.cos(), 0.0, self.yaw.sin());
+                Matrix4::<f32>::look_at(eye, eye - Vector3::unit_y(), heading)
+            },
+
+            // The third-person camera keeps looking in the same direction, so
+            // it always faces the position it orbits. The up vector comes from
+            // the orientation too, so looking straight up or down stays
+            // well-defined.
+            _ => Matrix4::<f32>::look_at(
+                eye, 
+                eye + self.front(), 
+                self.up()
+            ),
+        };
+
+        self.shake_matrix() * view
+    }
+
+    fn shake_matrix(&self) -> Matrix4<f32> {
+        let (offset, angles) = self.shakes
+            .iter()
+            .map(Shake::offset)
+            .fold((Vector3::zero(), Vector3::zero()), |(o, a), (offset, angles)| {
+                (o + offset, a + angles)
+            });
+
+        let rotation = Quaternion::from_angle_x(Rad(angles.x))
+            * Quaternion::from_angle_y(Rad(angles.y))
+            * Quaternion::from_angle_z(Rad(angles.z));
+
+        Matrix4::from(rotation) * Matrix4::from_translation(offset)
+    }
+}
+
+// Prevent camera from flipping over.
+fn clamp_pitch(pitch: f32) -> f32 {
+    let deg90 = Rad::from(Deg(90.0f32)).0;
+    pitch.max(-deg90).min(deg90)
+}
+
+// Smooth noise in [-1, 1], varying about once per unit of `t`.
+fn value_noise(seed: u32, t: f32) -> f32 {
+    let hash = |i: i32| {
+        let mut x = (i as u32) ^ seed.wrapping_mul(0x9E37_79B9);
+        x = (x ^ (x >> 16)).wrapping_mul(0x7FEB_352D);
+        x = (x ^ (x >> 15)).wrapping_mul(0x846C_A68B);
+        x ^= x >> 16;
+        x as f32 / u32::MAX as f32 * 2.0 - 1.0
+    };
+
+    let i = t.floor();
+    let f = t - i;
+    let f = f * f * (3.0 - 2.0 * f);
+
+    let (a, b) = (hash(i as i32), hash(i as i32 + 1));
+    a + (b - a) * f
 }
@@ -0,0 +1,211 @@
+use std::f32::consts::PI;
+use cgmath::{ EuclideanSpace, InnerSpace, Point3, Rad, Vector3 };
+use crate::camera::Camera;
+
+// How many samples every segment of a path is measured with, to play it back
+// at a constant speed.
+const SAMPLES_PER_SEGMENT: usize = 32;
+
+/// A recorded position and orientation of the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub position: Point3<f32>,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    pub roll: Rad<f32>,
+}
+
+impl Keyframe {
+    pub fn from_camera(camera: &Camera) -> Self {
+        Self {
+            position: camera.position(),
+            yaw: camera.yaw(),
+            pitch: camera.pitch(),
+            roll: camera.roll(),
+        }
+    }
+
+    // The orientation packed into a vector, so that it can be interpolated
+    // along with the position.
+    fn angles(&self) -> Vector3<f32> {
+        Vector3::new(self.yaw.0, self.pitch.0, self.roll.0)
+    }
+}
+
+/// A path through keyframes, which the camera follows along a Catmull-Rom
+/// spline. Useful for benchmark fly-throughs and trailer captures.
+#[derive(Clone, Debug, Default)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the current position and orientation of the camera.
+    pub fn record(&mut self, camera: &Camera) {
+        self.push(Keyframe::from_camera(camera))
+    }
+
+    pub fn push(&mut self, mut keyframe: Keyframe) {
+        // Angles wrap around, take the shorter way from the previous keyframe
+        // instead of spinning around.
+        if let Some(previous) = self.keyframes.last() {
+            keyframe.yaw = Rad(unwrap_angle(previous.yaw.0, keyframe.yaw.0));
+            keyframe.roll = Rad(unwrap_angle(previous.roll.0, keyframe.roll.0));
+        }
+
+        self.keyframes.push(keyframe);
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Returns the keyframe at `t` along the path, where each whole number is
+    /// one of the recorded keyframes.
+    pub fn sample(&self, t: f32) -> Keyframe {
+        assert!(!self.keyframes.is_empty(), "sampling an empty camera path");
+
+        let last = self.keyframes.len() - 1;
+        let t = t.max(0.0).min(last as f32);
+        let segment = (t.floor() as usize).min(last.saturating_sub(1));
+        let t = t - segment as f32;
+
+        // The ends are repeated, so the spline passes through every keyframe.
+        let at = |i: isize| {
+            let i = i.max(0).min(last as isize) as usize;
+            self.keyframes[i]
+        };
+
+        let i = segment as isize;
+        let (k0, k1, k2, k3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+
+        let position = catmull_rom(
+            k0.position.to_vec(),
+            k1.position.to_vec(),
+            k2.position.to_vec(),
+            k3.position.to_vec(),
+            t,
+        );
+        let angles = catmull_rom(k0.angles(), k1.angles(), k2.angles(), k3.angles(), t);
+
+        Keyframe {
+            position: Point3::from_vec(position),
+            yaw: Rad(angles.x),
+            pitch: Rad(angles.y),
+            roll: Rad(angles.z),
+        }
+    }
+
+    /// Starts playing the path back at `speed` world units per second.
+    /// Returns `None` if there are fewer than two keyframes.
+    pub fn play(&self, speed: f32) -> Option<PathPlayback> {
+        if self.keyframes.len() < 2 {
+            return None
+        }
+
+        let samples = (self.keyframes.len() - 1) * SAMPLES_PER_SEGMENT;
+        let mut lengths = Vec::with_capacity(samples + 1);
+        let mut length = 0.0;
+        let mut previous = self.keyframes[0].position;
+
+        lengths.push(0.0);
+
+        for i in 1..=samples {
+            let position = self.sample(i as f32 / SAMPLES_PER_SEGMENT as f32).position;
+            length += (position - previous).magnitude();
+            lengths.push(length);
+            previous = position;
+        }
+
+        Some(PathPlayback {
+            path: self.clone(),
+            speed,
+            distance: 0.0,
+            lengths,
+        })
+    }
+}
+
+/// A camera path being played back at a constant speed.
+pub struct PathPlayback {
+    path: CameraPath,
+    speed: f32,
+    distance: f32,
+    // The length of the path up to each sample.
+    lengths: Vec<f32>,
+}
+
+impl PathPlayback {
+    /// Advances the playback by `delta_time` seconds.
+    pub fn update(&mut self, delta_time: f32) {
+        self.distance = (self.distance + self.speed * delta_time).min(self.length());
+    }
+
+    /// Moves the camera to the current point of the path.
+    pub fn apply(&self, camera: &mut Camera) {
+        let keyframe = self.current();
+        camera.snap_to(keyframe.position, keyframe.yaw, keyframe.pitch, keyframe.roll);
+    }
+
+    pub fn current(&self) -> Keyframe {
+        // Find the samples the distance lies between, and interpolate between
+        // them linearly.
+        let i = match self.lengths.binary_search_by(|l| l.partial_cmp(&self.distance).unwrap()) {
+            Ok(i) => return self.path.sample(i as f32 / SAMPLES_PER_SEGMENT as f32),
+            Err(i) => i.max(1).min(self.lengths.len() - 1),
+        };
+
+        let (before, after) = (self.lengths[i - 1], self.lengths[i]);
+        let fraction = match after - before {
+            d if d > 0.0 => (self.distance - before) / d,
+            _ => 0.0,
+        };
+
+        self.path.sample((i - 1) as f32 / SAMPLES_PER_SEGMENT as f32 + fraction / SAMPLES_PER_SEGMENT as f32)
+    }
+
+    /// Returns the length of the whole path, in world units.
+    pub fn length(&self) -> f32 {
+        *self.lengths.last().unwrap()
+    }
+
+    /// Returns how far the playback is, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        match self.length() {
+            l if l > 0.0 => self.distance / l,
+            _ => 1.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.distance >= self.length()
+    }
+}
+
+fn catmull_rom(p0: Vector3<f32>, p1: Vector3<f32>, p2: Vector3<f32>, p3: Vector3<f32>, t: f32) -> Vector3<f32> {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+// Returns the angle equivalent to `angle` that is closest to `reference`.
+fn unwrap_angle(reference: f32, angle: f32) -> f32 {
+    let full = 2.0 * PI;
+    angle + ((reference - angle) / full).round() * full
+}
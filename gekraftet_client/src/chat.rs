@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use cgmath::Point2;
+use crate::RGBA;
+use crate::input::TextInput;
+use crate::mesh::MeshBuilder;
+use crate::renderer::{ add_quad, add_text, text_height, text_width, HudElement };
+
+/// The lines of the chat, newest last. A line is shown for a while after it
+/// arrived, and then only while the chat is open.
+pub struct Chat {
+    lines: VecDeque<ChatLine>,
+    // Whether the chat looks different since the last view, and what was
+    // being typed into it then.
+    dirty: bool,
+    last_input: Option<String>,
+}
+
+struct ChatLine {
+    text: String,
+    color: RGBA,
+    // How long ago the line arrived, in seconds.
+    age: f32,
+}
+
+impl Chat {
+    pub fn new() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            dirty: true,
+            last_input: None,
+        }
+    }
+
+    /// Adds what a player said, or, without a sender, what the game or the
+    /// server says.
+    pub fn push(&mut self, sender: Option<&str>, text: &str) {
+        let (text, color) = match sender {
+            Some(sender) => (format!("<{}> {}", sender, text), RGBA::new(1.0, 1.0, 1.0, 1.0)),
+            None => (text.to_owned(), RGBA::new(1.0, 0.9, 0.4, 1.0)),
+        };
+
+        self.lines.push_back(ChatLine { text, color, age: 0.0 });
+        if self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+
+        self.dirty = true;
+    }
+
+    /// Ages the lines by the frame, hiding those that have been shown long
+    /// enough.
+    pub fn update(&mut self, delta: f32) {
+        for line in self.lines.iter_mut() {
+            let shown = line.age < SHOW_DURATION;
+            line.age += delta;
+            self.dirty |= shown && line.age >= SHOW_DURATION;
+        }
+    }
+
+    /// Returns a snapshot of the chat, if it changed since the last one.
+    /// While text is being typed, every line is shown, along with the text.
+    pub fn view(&mut self, input: Option<&TextInput>) -> Option<ChatView> {
+        let input = input.map(|input| format!("{}{}_", input.text(), input.preedit()));
+        if !self.dirty && input == self.last_input {
+            return None
+        }
+
+        let lines = self.lines
+            .iter()
+            .filter(|line| input.is_some() || line.age < SHOW_DURATION)
+            .flat_map(|line| wrap(&line.text).into_iter().map(move |row| (row, line.color)))
+            .collect::<Vec<_>>();
+
+        self.dirty = false;
+        self.last_input = input.clone();
+
+        Some(ChatView {
+            lines: lines.into_iter().rev().take(MAX_SHOWN_ROWS).rev().collect(),
+            input,
+        })
+    }
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A snapshot of the chat, drawn on the HUD. It has to be put into a slot
+/// anchored at the bottom-left corner of the window.
+pub struct ChatView {
+    lines: Vec<(String, RGBA)>,
+    input: Option<String>,
+}
+
+impl HudElement for ChatView {
+    fn build(&self, mb: MeshBuilder, anchor: Point2<f32>) -> MeshBuilder {
+        let row = text_height(TEXT_SCALE) + ROW_GAP;
+        let width = text_width(&"W".repeat(ROW_CHARACTERS), TEXT_SCALE) + PADDING * 2.0;
+
+        // The text being typed goes at the bottom, and the lines above it,
+        // newest last.
+        let mut bottom = anchor.y;
+        let mut mb = mb;
+
+        if let Some(input) = self.input.as_ref() {
+            let visible = input.chars().count().saturating_sub(ROW_CHARACTERS);
+            let input = input.chars().skip(visible).collect::<String>();
+
+            mb = add_quad(
+                mb,
+                Point2::new(anchor.x, bottom - row - PADDING),
+                Point2::new(anchor.x + width, bottom),
+                RGBA::new(0.0, 0.0, 0.0, 0.6),
+            );
+            mb = add_text(mb, Point2::new(anchor.x + PADDING, bottom - row), &input, TEXT_SCALE, RGBA::new(1.0, 1.0, 1.0, 1.0));
+            bottom -= row + PADDING * 2.0;
+        }
+
+        if self.lines.is_empty() {
+            return mb
+        }
+
+        let top = bottom - self.lines.len() as f32 * row - PADDING;
+        mb = add_quad(
+            mb,
+            Point2::new(anchor.x, top),
+            Point2::new(anchor.x + width, bottom),
+            RGBA::new(0.0, 0.0, 0.0, 0.4),
+        );
+
+        self.lines.iter().enumerate().fold(mb, |mb, (i, (text, color))| {
+            let origin = Point2::new(anchor.x + PADDING, top + PADDING + i as f32 * row);
+            add_text(mb, origin, text, TEXT_SCALE, *color)
+        })
+    }
+}
+
+// Splits a line into rows that fit the width of the chat, breaking at spaces
+// where possible.
+fn wrap(text: &str) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+
+    for word in text.split(' ') {
+        let (row_length, word_length) = (row.chars().count(), word.chars().count());
+        if row_length > 0 && row_length + 1 + word_length > ROW_CHARACTERS {
+            rows.push(std::mem::take(&mut row));
+        }
+
+        if !row.is_empty() {
+            row.push(' ');
+        }
+        row.push_str(word);
+
+        // Words longer than a row are broken anywhere.
+        while row.chars().count() > ROW_CHARACTERS {
+            let split = row.char_indices().nth(ROW_CHARACTERS).map_or(row.len(), |(i, _)| i);
+            let rest = row.split_off(split);
+            rows.push(std::mem::replace(&mut row, rest));
+        }
+    }
+
+    rows.push(row);
+    rows
+}
+
+// How long a line is shown after it arrived, in seconds, and how many lines
+// are remembered at most.
+const SHOW_DURATION: f32 = 10.0;
+const MAX_LINES: usize = 100;
+
+// The layout of the chat, in logical pixels, except for the number of rows
+// and the characters on a row. The text is drawn with pixels this large.
+const MAX_SHOWN_ROWS: usize = 10;
+const ROW_CHARACTERS: usize = 60;
+const ROW_GAP: f32 = 4.0;
+const PADDING: f32 = 4.0;
+const TEXT_SCALE: f32 = 2.0;
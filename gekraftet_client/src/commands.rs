@@ -0,0 +1,56 @@
+use std::fmt;
+use gekraftet_core::player::GameMode;
+
+/// Something typed into the chat that starts with a slash, which is run
+/// instead of being sent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Makes the block the player stands in the spawn point.
+    SetSpawn,
+    /// Switches the player to another game mode.
+    GameMode(GameMode),
+    /// Picks the block to place by its name.
+    Block(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandError {
+    Unknown(String),
+    /// The command was given too few arguments, or one it does not accept.
+    Usage(&'static str),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Unknown(name) => write!(f, "unknown command /{}", name),
+            CommandError::Usage(usage) => write!(f, "usage: /{}", usage),
+        }
+    }
+}
+
+impl Command {
+    /// Parses the text, returning `None` if it is not a command at all but
+    /// a chat message.
+    pub fn parse(text: &str) -> Option<Result<Self, CommandError>> {
+        let text = text.trim().strip_prefix('/')?;
+        let mut words = text.split_whitespace();
+        let name = words.next().unwrap_or("");
+
+        let command = match name {
+            "setspawn" => Ok(Command::SetSpawn),
+            "gamemode" => words
+                .next()
+                .and_then(GameMode::from_name)
+                .map(Command::GameMode)
+                .ok_or(CommandError::Usage("gamemode <survival|creative>")),
+            "block" => words
+                .next()
+                .map(|name| Command::Block(name.to_owned()))
+                .ok_or(CommandError::Usage("block <name>")),
+            _ => Err(CommandError::Unknown(name.to_owned())),
+        };
+
+        Some(command)
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::{ fs, io };
+use std::path::Path;
+use cgmath::{ EuclideanSpace, Point3, Vector3 };
+use gekraftet_core::level::Level;
+use gekraftet_core::world::{ ChunkPos, CHUNK_LENGTH_X, CHUNK_LENGTH_Z };
+use serde_json::json;
+use crate::mesh::Mesh;
+use crate::world::{ ChunkMesh, GreedyCubeMesher, Mesher, BLOCK_LENGTH };
+
+/// The terrain of part of a world as a glTF scene, e.g. to be rendered in
+/// Blender. Blocks are drawn in a single color each, so the texture is a
+/// palette with a pixel for every color.
+pub struct GltfScene {
+    name: String,
+    opaque: Primitive,
+    translucent: Primitive,
+    // The colors of the palette, in the order of their pixels.
+    palette: Vec<[u8; 4]>,
+    palette_index: HashMap<[u8; 4], u32>,
+}
+
+// Triangles with the same material.
+#[derive(Default)]
+struct Primitive {
+    positions: Vec<[f32; 3]>,
+    // The pixel of the palette every vertex takes its color from.
+    colors: Vec<u32>,
+    indices: Vec<u32>,
+}
+
+impl GltfScene {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            opaque: Primitive::default(),
+            translucent: Primitive::default(),
+            palette: Vec::new(),
+            palette_index: HashMap::new(),
+        }
+    }
+
+    /// Generates the chunks within `radius` chunks of `center` (on either
+    /// axis) from the level and meshes them. One unit is one block, and the
+    /// origin is at the bottom of the center chunk.
+    pub fn from_level(level: &Level, center: ChunkPos, radius: i32) -> Self {
+        let mut scene = Self::new(level.name.clone());
+        let origin = Point3::new(
+            (center.x * CHUNK_LENGTH_X as i32) as f32,
+            0.0,
+            (center.z * CHUNK_LENGTH_Z as i32) as f32,
+        );
+
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                let chunk = level.generator.generate(ChunkPos::new(center.x + x, 0, center.z + z));
+                scene.add_chunk(&GreedyCubeMesher::from_chunk(&chunk).generate_mesh(), origin);
+            }
+        }
+
+        scene
+    }
+
+    /// Adds the meshes of a chunk, moved so that `origin` (in blocks) is at
+    /// the origin of the scene.
+    pub fn add_chunk(&mut self, mesh: &ChunkMesh, origin: Point3<f32>) {
+        let offset = origin.to_vec() * BLOCK_LENGTH;
+        self.add_mesh(&mesh.opaque, offset, true);
+        self.add_mesh(&mesh.translucent, offset, false);
+    }
+
+    fn add_mesh(&mut self, mesh: &Mesh, offset: Vector3<f32>, opaque: bool) {
+        let colors = mesh
+            .vertices()
+            .iter()
+            .map(|vertex| self.color_index(vertex.color.map(|c| (c.max(0.0).min(1.0) * 255.0).round() as u8).into()))
+            .collect::<Vec<_>>();
+
+        let primitive = match opaque {
+            true => &mut self.opaque,
+            false => &mut self.translucent,
+        };
+
+        let first = primitive.positions.len() as u32;
+        primitive.positions.extend(mesh.vertices().iter().map(|vertex| {
+            let position = (vertex.position - offset).map(|v| v / BLOCK_LENGTH);
+            [position.x, position.y, position.z]
+        }));
+        primitive.colors.extend(colors);
+        primitive.indices.extend(mesh.indices().iter().map(|index| first + index));
+    }
+
+    // The pixel of the palette with the color, which is added if there is
+    // none yet.
+    fn color_index(&mut self, color: [u8; 4]) -> u32 {
+        let palette = &mut self.palette;
+        *self.palette_index.entry(color).or_insert_with(|| {
+            palette.push(color);
+            palette.len() as u32 - 1
+        })
+    }
+
+    /// Writes the scene as binary glTF (`.glb`), with the palette embedded.
+    pub fn to_glb(&self) -> io::Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        let mut views = Vec::new();
+        let mut accessors = Vec::new();
+        let mut primitives = Vec::new();
+
+        // Every view starts at a multiple of 4, as the accessors require.
+        let mut push_view = |buffer: &mut Vec<u8>, bytes: &[u8], target: Option<u32>| {
+            let offset = buffer.len();
+            buffer.extend_from_slice(bytes);
+            buffer.resize((buffer.len() + 3) / 4 * 4, 0);
+
+            let mut view = json!({ "buffer": 0, "byteOffset": offset, "byteLength": bytes.len() });
+            if let Some(target) = target {
+                view["target"] = json!(target);
+            }
+            views.push(view);
+            views.len() - 1
+        };
+
+        let width = self.palette.len().max(1) as f32;
+        for (material, primitive) in [&self.opaque, &self.translucent].iter().enumerate() {
+            if primitive.indices.is_empty() {
+                continue
+            }
+
+            let (min, max) = primitive.bounds();
+            let texcoords = primitive
+                .colors
+                .iter()
+                .flat_map(|&i| [(i as f32 + 0.5) / width, 0.5])
+                .collect::<Vec<_>>();
+
+            let indices = push_view(&mut buffer, &to_bytes(primitive.indices.iter().map(|i| i.to_le_bytes())), Some(ELEMENT_ARRAY_BUFFER));
+            let positions = push_view(&mut buffer, &to_bytes(primitive.positions.iter().flatten().map(|v| v.to_le_bytes())), Some(ARRAY_BUFFER));
+            let texcoords = push_view(&mut buffer, &to_bytes(texcoords.iter().map(|v| v.to_le_bytes())), Some(ARRAY_BUFFER));
+
+            accessors.push(json!({ "bufferView": indices, "componentType": UNSIGNED_INT, "count": primitive.indices.len(), "type": "SCALAR" }));
+            accessors.push(json!({ "bufferView": positions, "componentType": FLOAT, "count": primitive.positions.len(), "type": "VEC3", "min": min, "max": max }));
+            accessors.push(json!({ "bufferView": texcoords, "componentType": FLOAT, "count": primitive.colors.len(), "type": "VEC2" }));
+
+            let first = accessors.len() - 3;
+            primitives.push(json!({
+                "attributes": { "POSITION": first + 1, "TEXCOORD_0": first + 2 },
+                "indices": first,
+                "material": material,
+            }));
+        }
+
+        if primitives.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "there is nothing to export"))
+        }
+
+        let image = push_view(&mut buffer, &self.palette_png()?, None);
+
+        let material = |alpha_mode: &str| json!({
+            "pbrMetallicRoughness": {
+                "baseColorTexture": { "index": 0 },
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0,
+            },
+            "alphaMode": alpha_mode,
+        });
+
+        let gltf = json!({
+            "asset": { "version": "2.0", "generator": "gekraftet" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0, "name": self.name }],
+            "meshes": [{ "primitives": primitives, "name": "terrain" }],
+            "materials": [material("OPAQUE"), material("BLEND")],
+            "textures": [{ "sampler": 0, "source": 0 }],
+            "samplers": [{ "magFilter": NEAREST, "minFilter": NEAREST, "wrapS": CLAMP_TO_EDGE, "wrapT": CLAMP_TO_EDGE }],
+            "images": [{ "bufferView": image, "mimeType": "image/png" }],
+            "accessors": accessors,
+            "bufferViews": views,
+            "buffers": [{ "byteLength": buffer.len() }],
+        });
+
+        let mut json = serde_json::to_vec(&gltf)?;
+        json.resize((json.len() + 3) / 4 * 4, b' ');
+
+        let length = 12 + 8 + json.len() + 8 + buffer.len();
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&buffer);
+
+        Ok(glb)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_glb()?)
+    }
+
+    // The palette as a PNG image a pixel high.
+    fn palette_png(&self) -> io::Result<Vec<u8>> {
+        let mut pixels = self.palette.iter().flatten().copied().collect::<Vec<_>>();
+        if pixels.is_empty() {
+            pixels = vec![0; 4];
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, pixels.len() as u32 / 4, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+
+        Ok(png)
+    }
+}
+
+impl Primitive {
+    // The smallest and largest position along every axis, which glTF wants
+    // to know.
+    fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+
+        for position in self.positions.iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(position[i]);
+                max[i] = max[i].max(position[i]);
+            }
+        }
+
+        (min, max)
+    }
+}
+
+fn to_bytes<I: Iterator<Item = [u8; 4]>>(values: I) -> Vec<u8> {
+    values.flatten().collect()
+}
+
+// The numbers glTF borrows from OpenGL.
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+const NEAREST: u32 = 9728;
+const CLAMP_TO_EDGE: u32 = 33071;
@@ -0,0 +1,77 @@
+use gekraftet_core::utils::FixedTimestep;
+use gekraftet_core::world::TickClock;
+use crate::windowing::{ FrameLimit, FramePacer };
+
+/// Runs the game a frame at a time. The frames are paced and measured by a
+/// `FramePacer`, and the time between them is split into fixed steps: the
+/// player moves in steps of `MOVEMENT_STEP`, and the world in ticks. What is
+/// simulated thus does not depend on the frame rate, and what is drawn is
+/// blended between the last two steps.
+pub struct GameLoop {
+    pacer: FramePacer,
+    movement: FixedTimestep,
+    ticks: TickClock,
+    delta: f32,
+}
+
+/// What has to be done during a frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    /// How long (in seconds) the frame is.
+    pub delta: f32,
+    /// How many times the player has to be moved, by `MOVEMENT_STEP` each.
+    pub movement_steps: u32,
+    /// How many times the world has to be ticked.
+    pub ticks: u32,
+}
+
+impl GameLoop {
+    pub fn new(limit: FrameLimit) -> Self {
+        Self {
+            pacer: FramePacer::new(limit),
+            movement: FixedTimestep::new(MOVEMENT_STEP),
+            ticks: TickClock::new(),
+            delta: 0.0,
+        }
+    }
+
+    /// Changes how often frames are presented, from the next frame on.
+    pub fn set_limit(&mut self, limit: FrameLimit) {
+        self.pacer.set_limit(limit);
+    }
+
+    /// How long (in seconds) the last frame was.
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// Splits a frame of `delta` seconds into steps. The delta is normally
+    /// `delta()`, but can be another (e.g. while replaying inputs).
+    pub fn begin_frame(&mut self, delta: f32) -> Frame {
+        Frame {
+            delta,
+            movement_steps: self.movement.advance(delta),
+            ticks: self.ticks.advance(delta),
+        }
+    }
+
+    /// How far (from 0 to 1) the frame is into the next movement step.
+    pub fn movement_alpha(&self) -> f32 {
+        self.movement.alpha()
+    }
+
+    /// How far (from 0 to 1) the frame is into the next tick.
+    pub fn tick_alpha(&self) -> f32 {
+        self.ticks.alpha()
+    }
+
+    /// Should be called once the frame has been presented. Waits out the
+    /// rest of the frame (if the frame rate is capped), and measures how long
+    /// it took.
+    pub fn end_frame(&mut self) {
+        self.delta = self.pacer.end_frame();
+    }
+}
+
+/// How long (in seconds) the player is moved at a time.
+pub const MOVEMENT_STEP: f32 = 1.0 / 60.0;
@@ -0,0 +1,189 @@
+use std::convert::TryFrom;
+use std::fmt;
+use serde::{ Deserialize, Serialize };
+use serde::de::IntoDeserializer;
+use super::{ GamepadButton, Key, MouseButton };
+
+/// Something the player does, which keys and mouse buttons are bound to.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    FlyUp,
+    FlyDown,
+    Sprint,
+    Crouch,
+    Walk,
+    Zoom,
+    Break,
+    Place,
+    PickBlock,
+    Inventory,
+    ReleaseCursor,
+    Pause,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    MenuSelect,
+    ToggleDebug,
+    ToggleFullscreen,
+    ToggleFly,
+    ToggleCollision,
+    ToggleCameraMode,
+    ToggleMap,
+    MapZoomIn,
+    MapZoomOut,
+    MinimapZoomIn,
+    MinimapZoomOut,
+    IncreaseSensitivity,
+    DecreaseSensitivity,
+    RecordKeyframe,
+    PlayPath,
+    ClearPath,
+    OpenChat,
+}
+
+impl Action {
+    pub const ALL: [Action; 38] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::FlyUp,
+        Action::FlyDown,
+        Action::Sprint,
+        Action::Crouch,
+        Action::Walk,
+        Action::Zoom,
+        Action::Break,
+        Action::Place,
+        Action::PickBlock,
+        Action::Inventory,
+        Action::ReleaseCursor,
+        Action::Pause,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+        Action::MenuSelect,
+        Action::ToggleDebug,
+        Action::ToggleFullscreen,
+        Action::ToggleFly,
+        Action::ToggleCollision,
+        Action::ToggleCameraMode,
+        Action::ToggleMap,
+        Action::MapZoomIn,
+        Action::MapZoomOut,
+        Action::MinimapZoomIn,
+        Action::MinimapZoomOut,
+        Action::IncreaseSensitivity,
+        Action::DecreaseSensitivity,
+        Action::RecordKeyframe,
+        Action::PlayPath,
+        Action::ClearPath,
+        Action::OpenChat,
+    ];
+}
+
+/// Whether an action is active while its binding is held, or is switched on
+/// and off by pressing its binding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActivationMode {
+    Hold,
+    Toggle,
+}
+
+/// A key or a mouse button an action is bound to. In the bindings file, keys
+/// are written by their names (e.g. `"W"`, `"LShift"`), and mouse buttons as
+/// `"MouseLeft"`, `"MouseMiddle"`, `"MouseRight"` and `"Mouse4"`. Gamepad
+/// buttons are prefixed with `Pad`, e.g. `"PadSouth"`, and physical keys
+/// (bound by their scancodes) with `Scan`, e.g. `"Scan17"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+    Scancode(u32),
+}
+
+impl From<Key> for Binding {
+    fn from(key: Key) -> Self {
+        Binding::Key(key)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(button: MouseButton) -> Self {
+        Binding::Mouse(button)
+    }
+}
+
+impl From<GamepadButton> for Binding {
+    fn from(button: GamepadButton) -> Self {
+        Binding::Gamepad(button)
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        let button = match name.as_str() {
+            "MouseLeft" => Some(MouseButton::Left),
+            "MouseMiddle" => Some(MouseButton::Middle),
+            "MouseRight" => Some(MouseButton::Right),
+            "Mouse4" => Some(MouseButton::Button4),
+            _ => None,
+        };
+
+        if let Some(button) = button {
+            return Ok(Binding::Mouse(button))
+        }
+
+        let pad = GamepadButton::ALL
+            .iter()
+            .find(|b| format!("Pad{:?}", b) == name);
+
+        if let Some(&button) = pad {
+            return Ok(Binding::Gamepad(button))
+        }
+
+        if let Some(scancode) = name.strip_prefix("Scan").and_then(|s| s.parse().ok()) {
+            return Ok(Binding::Scancode(scancode))
+        }
+
+        // Keys are (de)serialized by the names of their variants.
+        let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+            name.as_str().into_deserializer();
+
+        Key::deserialize(deserializer)
+            .map(Binding::Key)
+            .map_err(|_| format!("unknown key or mouse button `{}`", name))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(MouseButton::Left) => write!(f, "MouseLeft"),
+            Binding::Mouse(MouseButton::Middle) => write!(f, "MouseMiddle"),
+            Binding::Mouse(MouseButton::Right) => write!(f, "MouseRight"),
+            Binding::Mouse(MouseButton::Button4) => write!(f, "Mouse4"),
+            Binding::Gamepad(button) => write!(f, "Pad{:?}", button),
+            Binding::Scancode(scancode) => write!(f, "Scan{}", scancode),
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::{ fmt, fs, io };
+use std::path::Path;
+use serde::{ Deserialize, Serialize };
+use super::{ Action, ActivationMode, Binding, GamepadButton, Key, MouseButton };
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindingsError::Io(e) => write!(f, "unable to access the key bindings: {}", e),
+            BindingsError::Parse(e) => write!(f, "invalid key bindings: {}", e),
+            BindingsError::Serialize(e) => write!(f, "unable to serialize the key bindings: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for BindingsError {
+    fn from(e: io::Error) -> Self {
+        BindingsError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for BindingsError {
+    fn from(e: toml::de::Error) -> Self {
+        BindingsError::Parse(e)
+    }
+}
+
+impl From<toml::ser::Error> for BindingsError {
+    fn from(e: toml::ser::Error) -> Self {
+        BindingsError::Serialize(e)
+    }
+}
+
+/// What every action is bound to. These are stored in a TOML file, with one
+/// list of bindings per action, e.g. `move_forward = ["W", "Up"]`. Actions
+/// missing from the file keep their default bindings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_forward: Vec<Binding>,
+    pub move_backward: Vec<Binding>,
+    pub move_left: Vec<Binding>,
+    pub move_right: Vec<Binding>,
+    pub jump: Vec<Binding>,
+    pub fly_up: Vec<Binding>,
+    pub fly_down: Vec<Binding>,
+    pub sprint: Vec<Binding>,
+    pub crouch: Vec<Binding>,
+    pub walk: Vec<Binding>,
+    pub zoom: Vec<Binding>,
+    pub break_block: Vec<Binding>,
+    pub place_block: Vec<Binding>,
+    pub pick_block: Vec<Binding>,
+    pub inventory: Vec<Binding>,
+    pub release_cursor: Vec<Binding>,
+    pub pause: Vec<Binding>,
+    pub menu_up: Vec<Binding>,
+    pub menu_down: Vec<Binding>,
+    pub menu_left: Vec<Binding>,
+    pub menu_right: Vec<Binding>,
+    pub menu_select: Vec<Binding>,
+    pub toggle_debug: Vec<Binding>,
+    pub toggle_fullscreen: Vec<Binding>,
+    pub toggle_fly: Vec<Binding>,
+    pub toggle_collision: Vec<Binding>,
+    pub toggle_camera_mode: Vec<Binding>,
+    pub toggle_map: Vec<Binding>,
+    pub map_zoom_in: Vec<Binding>,
+    pub map_zoom_out: Vec<Binding>,
+    pub minimap_zoom_in: Vec<Binding>,
+    pub minimap_zoom_out: Vec<Binding>,
+    pub increase_sensitivity: Vec<Binding>,
+    pub decrease_sensitivity: Vec<Binding>,
+    pub record_keyframe: Vec<Binding>,
+    pub play_path: Vec<Binding>,
+    pub clear_path: Vec<Binding>,
+    pub open_chat: Vec<Binding>,
+    /// Actions that are switched on and off by pressing them, instead of
+    /// being active only while they are held.
+    pub toggled: Vec<Action>,
+    /// Pairs of actions, where tapping the first one twice in quick
+    /// succession activates the second one, e.g. `["MoveForward", "Sprint"]`.
+    /// Unless the second one is toggled, it stays active until the first one
+    /// is released.
+    pub double_tap: Vec<(Action, Action)>,
+    /// Binds keys by where they are on the keyboard rather than by what they
+    /// are labelled, as if the keyboard had a US QWERTY layout. This keeps
+    /// e.g. WASD movement in place on AZERTY or Dvorak keyboards.
+    pub physical_keys: bool,
+    /// Input devices whose events are thrown away (e.g. a drawing tablet
+    /// spamming motion events), by the names they are logged with when the
+    /// devices are listed.
+    pub ignored_devices: Vec<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            move_forward: vec![Key::W.into()],
+            move_backward: vec![Key::S.into()],
+            move_left: vec![Key::A.into()],
+            move_right: vec![Key::D.into()],
+            jump: vec![Key::Space.into(), GamepadButton::South.into()],
+            fly_up: vec![Key::Space.into(), GamepadButton::South.into()],
+            fly_down: vec![Key::Q.into(), GamepadButton::East.into()],
+            sprint: vec![Key::LShift.into(), GamepadButton::LeftStick.into()],
+            crouch: vec![Key::Z.into(), GamepadButton::RightStick.into()],
+            walk: vec![Key::LControl.into()],
+            zoom: vec![Key::C.into(), GamepadButton::LeftTrigger.into()],
+            break_block: vec![MouseButton::Left.into(), GamepadButton::RightTrigger.into()],
+            place_block: vec![MouseButton::Right.into(), GamepadButton::RightBumper.into()],
+            pick_block: vec![MouseButton::Middle.into()],
+            inventory: vec![Key::E.into(), GamepadButton::West.into()],
+            release_cursor: vec![Key::LAlt.into()],
+            pause: vec![Key::Escape.into(), GamepadButton::Start.into()],
+            menu_up: vec![Key::Up.into(), GamepadButton::DPadUp.into()],
+            menu_down: vec![Key::Down.into(), GamepadButton::DPadDown.into()],
+            menu_left: vec![Key::Left.into(), GamepadButton::DPadLeft.into()],
+            menu_right: vec![Key::Right.into(), GamepadButton::DPadRight.into()],
+            menu_select: vec![Key::Return.into(), GamepadButton::South.into()],
+            toggle_debug: vec![Key::F3.into()],
+            toggle_fullscreen: vec![Key::F11.into()],
+            toggle_fly: vec![Key::F.into()],
+            toggle_collision: vec![Key::N.into()],
+            toggle_camera_mode: vec![Key::F5.into(), GamepadButton::North.into()],
+            toggle_map: vec![Key::M.into(), GamepadButton::Select.into()],
+            map_zoom_in: vec![Key::PageUp.into()],
+            map_zoom_out: vec![Key::PageDown.into()],
+            minimap_zoom_in: vec![Key::RBracket.into()],
+            minimap_zoom_out: vec![Key::LBracket.into()],
+            increase_sensitivity: vec![Key::Equals.into()],
+            decrease_sensitivity: vec![Key::Minus.into()],
+            record_keyframe: vec![Key::K.into()],
+            play_path: vec![Key::P.into()],
+            clear_path: vec![Key::Back.into()],
+            open_chat: vec![Key::T.into()],
+            toggled: Vec::new(),
+            double_tap: vec![
+                (Action::MoveForward, Action::Sprint),
+                (Action::Jump, Action::ToggleFly),
+            ],
+            physical_keys: false,
+            ignored_devices: Vec::new(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Returns what the action is bound to.
+    pub fn get(&self, action: Action) -> &[Binding] {
+        match action {
+            Action::MoveForward => &self.move_forward,
+            Action::MoveBackward => &self.move_backward,
+            Action::MoveLeft => &self.move_left,
+            Action::MoveRight => &self.move_right,
+            Action::Jump => &self.jump,
+            Action::FlyUp => &self.fly_up,
+            Action::FlyDown => &self.fly_down,
+            Action::Sprint => &self.sprint,
+            Action::Crouch => &self.crouch,
+            Action::Walk => &self.walk,
+            Action::Zoom => &self.zoom,
+            Action::Break => &self.break_block,
+            Action::Place => &self.place_block,
+            Action::PickBlock => &self.pick_block,
+            Action::Inventory => &self.inventory,
+            Action::ReleaseCursor => &self.release_cursor,
+            Action::Pause => &self.pause,
+            Action::MenuUp => &self.menu_up,
+            Action::MenuDown => &self.menu_down,
+            Action::MenuLeft => &self.menu_left,
+            Action::MenuRight => &self.menu_right,
+            Action::MenuSelect => &self.menu_select,
+            Action::ToggleDebug => &self.toggle_debug,
+            Action::ToggleFullscreen => &self.toggle_fullscreen,
+            Action::ToggleFly => &self.toggle_fly,
+            Action::ToggleCollision => &self.toggle_collision,
+            Action::ToggleCameraMode => &self.toggle_camera_mode,
+            Action::ToggleMap => &self.toggle_map,
+            Action::MapZoomIn => &self.map_zoom_in,
+            Action::MapZoomOut => &self.map_zoom_out,
+            Action::MinimapZoomIn => &self.minimap_zoom_in,
+            Action::MinimapZoomOut => &self.minimap_zoom_out,
+            Action::IncreaseSensitivity => &self.increase_sensitivity,
+            Action::DecreaseSensitivity => &self.decrease_sensitivity,
+            Action::RecordKeyframe => &self.record_keyframe,
+            Action::PlayPath => &self.play_path,
+            Action::ClearPath => &self.clear_path,
+            Action::OpenChat => &self.open_chat,
+        }
+    }
+
+    pub fn get_mut(&mut self, action: Action) -> &mut Vec<Binding> {
+        match action {
+            Action::MoveForward => &mut self.move_forward,
+            Action::MoveBackward => &mut self.move_backward,
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::Jump => &mut self.jump,
+            Action::FlyUp => &mut self.fly_up,
+            Action::FlyDown => &mut self.fly_down,
+            Action::Sprint => &mut self.sprint,
+            Action::Crouch => &mut self.crouch,
+            Action::Walk => &mut self.walk,
+            Action::Zoom => &mut self.zoom,
+            Action::Break => &mut self.break_block,
+            Action::Place => &mut self.place_block,
+            Action::PickBlock => &mut self.pick_block,
+            Action::Inventory => &mut self.inventory,
+            Action::ReleaseCursor => &mut self.release_cursor,
+            Action::Pause => &mut self.pause,
+            Action::MenuUp => &mut self.menu_up,
+            Action::MenuDown => &mut self.menu_down,
+            Action::MenuLeft => &mut self.menu_left,
+            Action::MenuRight => &mut self.menu_right,
+            Action::MenuSelect => &mut self.menu_select,
+            Action::ToggleDebug => &mut self.toggle_debug,
+            Action::ToggleFullscreen => &mut self.toggle_fullscreen,
+            Action::ToggleFly => &mut self.toggle_fly,
+            Action::ToggleCollision => &mut self.toggle_collision,
+            Action::ToggleCameraMode => &mut self.toggle_camera_mode,
+            Action::ToggleMap => &mut self.toggle_map,
+            Action::MapZoomIn => &mut self.map_zoom_in,
+            Action::MapZoomOut => &mut self.map_zoom_out,
+            Action::MinimapZoomIn => &mut self.minimap_zoom_in,
+            Action::MinimapZoomOut => &mut self.minimap_zoom_out,
+            Action::IncreaseSensitivity => &mut self.increase_sensitivity,
+            Action::DecreaseSensitivity => &mut self.decrease_sensitivity,
+            Action::RecordKeyframe => &mut self.record_keyframe,
+            Action::PlayPath => &mut self.play_path,
+            Action::ClearPath => &mut self.clear_path,
+            Action::OpenChat => &mut self.open_chat,
+        }
+    }
+
+    pub fn mode(&self, action: Action) -> ActivationMode {
+        match self.toggled.contains(&action) {
+            true => ActivationMode::Toggle,
+            false => ActivationMode::Hold,
+        }
+    }
+
+    pub fn set_mode(&mut self, action: Action, mode: ActivationMode) {
+        self.toggled.retain(|&a| a != action);
+
+        if mode == ActivationMode::Toggle {
+            self.toggled.push(action);
+        }
+    }
+
+    /// Binds the action to only the given key or button, e.g. when it is
+    /// rebound in-game.
+    pub fn rebind<B: Into<Binding>>(&mut self, action: Action, binding: B) {
+        *self.get_mut(action) = vec![binding.into()];
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BindingsError> {
+        let text = fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// Loads the bindings from `path`, falling back to the defaults if that
+    /// fails. If there is no such file, the defaults are written to it, so
+    /// that they can be edited.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+
+        match Self::load(path) {
+            Ok(bindings) => bindings,
+
+            Err(BindingsError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+                let bindings = Self::default();
+
+                if let Err(e) = bindings.save(path) {
+                    log::warn!("{}", e);
+                }
+
+                bindings
+            },
+
+            Err(e) => {
+                log::warn!("{}, using the default ones", e);
+                Self::default()
+            },
+        }
+    }
+
+    /// Writes the bindings to `path`, e.g. after they were changed in-game.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BindingsError> {
+        let text = toml::to_string_pretty(self)?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
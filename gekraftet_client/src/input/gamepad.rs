@@ -0,0 +1,170 @@
+use cgmath::{ InnerSpace, Vector2, Zero };
+
+/// The buttons of a gamepad, named after their position on the pad.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 16] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftBumper,
+        GamepadButton::RightBumper,
+        GamepadButton::LeftTrigger,
+        GamepadButton::RightTrigger,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+/// The analog sticks of the gamepads, in [-1, 1] with +Y being up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GamepadAxes {
+    pub left: Vector2<f32>,
+    pub right: Vector2<f32>,
+}
+
+impl Default for GamepadAxes {
+    fn default() -> Self {
+        Self {
+            left: Vector2::zero(),
+            right: Vector2::zero(),
+        }
+    }
+}
+
+/// Applies a radial deadzone to a stick, rescaling what is left of its range
+/// so that the output still starts at zero.
+pub fn apply_deadzone(stick: Vector2<f32>, deadzone: f32) -> Vector2<f32> {
+    let magnitude = stick.magnitude();
+
+    if magnitude <= deadzone || deadzone >= 1.0 {
+        return Vector2::zero()
+    }
+
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+    stick * (scaled / magnitude)
+}
+
+// Reads the connected gamepads. Without the `gamepad` feature there are never
+// any, and all of the sticks and buttons stay at rest.
+#[cfg(feature = "gamepad")]
+pub(super) struct GamepadReader {
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadReader {
+    pub fn new() -> Self {
+        let gilrs = gilrs::Gilrs::new()
+            .map_err(|e| log::warn!("gamepads are unavailable: {}", e))
+            .ok();
+
+        Self { gilrs }
+    }
+
+    /// Calls `button` for every button pressed (true) or released (false)
+    /// since the last poll, and returns the current state of the sticks.
+    pub fn poll<F>(&mut self, mut button: F) -> GamepadAxes
+        where F: FnMut(GamepadButton, bool)
+    {
+        use gilrs::{ Axis, EventType };
+
+        let gilrs = match self.gilrs.as_mut() {
+            Some(gilrs) => gilrs,
+            None => return GamepadAxes::default(),
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(b, _) => {
+                    if let Some(b) = convert_button(b) {
+                        button(b, true)
+                    }
+                },
+                EventType::ButtonReleased(b, _) => {
+                    if let Some(b) = convert_button(b) {
+                        button(b, false)
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        // The sticks of all gamepads add up, so any of them can be used.
+        let mut axes = GamepadAxes::default();
+
+        for (_, pad) in gilrs.gamepads() {
+            axes.left += Vector2::new(pad.value(Axis::LeftStickX), pad.value(Axis::LeftStickY));
+            axes.right += Vector2::new(pad.value(Axis::RightStickX), pad.value(Axis::RightStickY));
+        }
+
+        axes
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn convert_button(button: gilrs::Button) -> Option<GamepadButton> {
+    use gilrs::Button;
+
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::West => GamepadButton::West,
+        Button::North => GamepadButton::North,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(not(feature = "gamepad"))]
+pub(super) struct GamepadReader;
+
+#[cfg(not(feature = "gamepad"))]
+impl GamepadReader {
+    pub fn new() -> Self {
+        GamepadReader
+    }
+
+    pub fn poll<F>(&mut self, _button: F) -> GamepadAxes
+        where F: FnMut(GamepadButton, bool)
+    {
+        GamepadAxes::default()
+    }
+}
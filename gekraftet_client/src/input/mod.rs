@@ -0,0 +1,773 @@
+mod actions;
+mod bindings;
+mod gamepad;
+mod replay;
+mod scancode;
+mod text;
+
+pub use actions::*;
+pub use bindings::*;
+pub use gamepad::{ apply_deadzone, GamepadAxes, GamepadButton };
+pub use replay::*;
+pub use scancode::qwerty_scancode;
+pub use text::{ TextInput, TextInputEvent };
+
+use cgmath::Vector2;
+use gamepad::GamepadReader;
+use crate::settings::GamepadSettings;
+
+use std::collections::{ BTreeMap, BTreeSet, HashMap };
+use std::io;
+use std::path::Path;
+use winit::event::{ 
+    ButtonId,
+    DeviceId, 
+    DeviceEvent,
+    ElementState,
+    Ime,
+    KeyboardInput,
+    MouseScrollDelta,
+};
+
+pub use winit::event::VirtualKeyCode as Key;
+
+pub type HeldKeysIter<'a> = std::collections::btree_set::Iter<'a, Key>;
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    Button4,
+}
+
+#[derive(Clone, Copy)]
+enum ButtonState {
+    Held,
+    Pressed,
+    Released,
+}
+
+// Which keys or buttons are held down, and which of them went down or up since
+// the last frame.
+struct ButtonStates<T> {
+    held: BTreeSet<T>,
+    pressed: BTreeSet<T>,
+    released: BTreeSet<T>,
+}
+
+impl<T: Copy + Ord> ButtonStates<T> {
+    fn new() -> Self {
+        Self {
+            held: BTreeSet::new(),
+            pressed: BTreeSet::new(),
+            released: BTreeSet::new(),
+        }
+    }
+
+    fn press(&mut self, button: T) {
+        // Auto-repeat sends more presses while the button is held, which do
+        // not count as pressing it again.
+        if self.held.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    fn release(&mut self, button: T) {
+        if self.held.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    fn next_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    fn is(&self, button: T, state: ButtonState) -> bool {
+        match state {
+            ButtonState::Held => self.held.contains(&button),
+            ButtonState::Pressed => self.pressed.contains(&button),
+            ButtonState::Released => self.released.contains(&button),
+        }
+    }
+}
+
+/// What is known about an input device, from the events it sent so far.
+#[derive(Clone, Debug, Default)]
+pub struct InputDevice {
+    pub keys: bool,
+    pub buttons: bool,
+    pub motion: bool,
+    /// How many events the device sent.
+    pub events: u64,
+    /// Whether the events of the device are thrown away.
+    pub ignored: bool,
+}
+
+// What an action did during the current and the previous frame.
+#[derive(Clone, Copy, Debug, Default)]
+struct ActionState {
+    active: bool,
+    was_active: bool,
+    // The state of a toggled action.
+    latched: bool,
+    // When a binding of the action was last pressed, to detect double taps.
+    last_press: Option<f32>,
+    // Whether a double tap activated the action, see `KeyBindings::double_tap`.
+    tapped: bool,
+}
+
+/// What happens to the input that is held down when it gets suspended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SuspendMode {
+    /// Held keys and buttons stay held until the next input event arrives.
+    Keep,
+    /// Held keys and buttons are released right away, and the first mouse
+    /// movement after unsuspending is ignored, as it usually is the cursor
+    /// jumping back into the window.
+    Release,
+}
+
+pub struct InputManager {
+    // Is the input suspended?
+    suspended: bool,
+    // Set by SuspendMode::Release, cleared by the first mouse movement.
+    skip_mouse_delta: bool,
+    
+    // Every device that sent an event, and whether it is listened to.
+    devices: HashMap<DeviceId, InputDevice>,
+
+    // Mouse input related
+    mouse_button: ButtonStates<MouseButton>,
+    mouse_delta: (f64, f64),
+    // * In lines, which is what most mice scroll by.
+    scroll_delta: (f64, f64),
+    // * Whether the mouse moves by the raw deltas of the device, or by how
+    //   far the cursor moved in the window.
+    raw_mouse: bool,
+
+    // Keyboard input related
+    // * Presses and releases are tracked per frame, so that is_key_just_pressed()
+    //   and friends give the same answer during the whole frame. See
+    //   next_frame().
+    pressed_keys: ButtonStates<Key>,
+    pressed_scancodes: ButtonStates<u32>,
+    // * The key each scancode was last seen producing, which depends on the
+    //   layout of the keyboard.
+    scancode_keys: HashMap<u32, Key>,
+
+    // Gamepad input related
+    gamepad: GamepadReader,
+    gamepad_buttons: ButtonStates<GamepadButton>,
+    gamepad_axes: GamepadAxes,
+    gamepad_settings: GamepadSettings,
+
+    bindings: KeyBindings,
+    actions: BTreeMap<Action, ActionState>,
+
+    // The time (in seconds) the input has been running for. It only advances
+    // with the frame deltas, so that replays time double taps like the
+    // original.
+    clock: f32,
+    recorder: Option<InputRecorder>,
+    replay: Option<InputReplay>,
+
+    // While text is being entered, the game gets no input at all.
+    text: Option<TextInput>,
+    text_events: Vec<TextInputEvent>,
+}
+
+impl InputManager {
+    pub fn new() -> Self {
+        Self {
+            suspended: true,
+            skip_mouse_delta: false,
+            devices: HashMap::new(),
+            mouse_button: ButtonStates::new(),
+            mouse_delta: (0.0, 0.0),
+            scroll_delta: (0.0, 0.0),
+            raw_mouse: true,
+            pressed_keys: ButtonStates::new(),
+            pressed_scancodes: ButtonStates::new(),
+            scancode_keys: HashMap::new(),
+            gamepad: GamepadReader::new(),
+            gamepad_buttons: ButtonStates::new(),
+            gamepad_axes: GamepadAxes::default(),
+            gamepad_settings: GamepadSettings::default(),
+            bindings: KeyBindings::default(),
+            actions: BTreeMap::new(),
+            clock: 0.0,
+            recorder: None,
+            replay: None,
+            text: None,
+            text_events: Vec::new(),
+        }
+    }
+
+    fn keyboard_input(&mut self, input: KeyboardInput) {
+        // Keys are tracked both by what they are labelled and by where they
+        // are, see `KeyBindings::physical_keys`.
+        let bindings = input.virtual_keycode
+            .map(Binding::Key)
+            .into_iter()
+            .chain(std::iter::once(Binding::Scancode(input.scancode)));
+
+        if let Some(key) = input.virtual_keycode {
+            self.scancode_keys.insert(input.scancode, key);
+        }
+
+        for binding in bindings {
+            if let ElementState::Pressed = input.state {
+                self.apply(InputEvent::Press(binding));
+            }
+            
+            if let ElementState::Released = input.state {
+                self.apply(InputEvent::Release(binding));
+            }
+        }
+    }
+
+    fn mouse_button_input(&mut self, button: ButtonId, state: ElementState) {
+        let button = match button {
+            1 => MouseButton::Left,
+            2 => MouseButton::Middle,
+            3 => MouseButton::Right,
+            4 => MouseButton::Button4,
+            _ => unimplemented!()
+        };
+        
+        if let ElementState::Pressed = state {
+            self.apply(InputEvent::Press(Binding::Mouse(button)));
+        }
+        
+        if let ElementState::Released = state {
+            self.apply(InputEvent::Release(Binding::Mouse(button)));
+        }
+    }
+
+    fn mouse_axis_input(&mut self, (x, y): (f64, f64)) {
+        if self.skip_mouse_delta {
+            self.skip_mouse_delta = false;
+            return
+        }
+
+        self.apply(InputEvent::MouseMotion(x, y));
+    }
+
+    fn mouse_wheel_input(&mut self, delta: MouseScrollDelta) {
+        let (x, y) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => (x as f64, y as f64),
+            // Touchpads scroll by pixels instead.
+            MouseScrollDelta::PixelDelta(p) => (p.x / PIXELS_PER_LINE, p.y / PIXELS_PER_LINE),
+        };
+
+        self.apply(InputEvent::Scroll(x, y));
+    }
+
+    // Every change of the input goes through here, so that it can be
+    // recorded.
+    fn apply(&mut self, event: InputEvent) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(event);
+        }
+
+        match event {
+            InputEvent::Press(Binding::Key(key)) => self.pressed_keys.press(key),
+            InputEvent::Press(Binding::Mouse(button)) => self.mouse_button.press(button),
+            InputEvent::Press(Binding::Gamepad(button)) => self.gamepad_buttons.press(button),
+            InputEvent::Press(Binding::Scancode(scancode)) => self.pressed_scancodes.press(scancode),
+            InputEvent::Release(Binding::Key(key)) => self.pressed_keys.release(key),
+            InputEvent::Release(Binding::Mouse(button)) => self.mouse_button.release(button),
+            InputEvent::Release(Binding::Gamepad(button)) => self.gamepad_buttons.release(button),
+            InputEvent::Release(Binding::Scancode(scancode)) => self.pressed_scancodes.release(scancode),
+            InputEvent::MouseMotion(x, y) => {
+                self.mouse_delta.0 += x;
+                self.mouse_delta.1 += y;
+            },
+            InputEvent::Scroll(x, y) => {
+                self.scroll_delta.0 += x;
+                self.scroll_delta.1 += y;
+            },
+        }
+    }
+
+    fn release_all(&mut self) {
+        let held = self.pressed_keys.held.iter().map(|&k| Binding::Key(k))
+            .chain(self.mouse_button.held.iter().map(|&b| Binding::Mouse(b)))
+            .chain(self.gamepad_buttons.held.iter().map(|&b| Binding::Gamepad(b)))
+            .chain(self.pressed_scancodes.held.iter().map(|&s| Binding::Scancode(s)))
+            .collect::<Vec<_>>();
+
+        for binding in held {
+            self.apply(InputEvent::Release(binding));
+        }
+    }
+
+    pub fn update_inputs(&mut self, id: DeviceId, event: DeviceEvent) {
+        // Devices are tracked even while the input is suspended, so that the
+        // list of them is complete.
+        if let DeviceEvent::Removed = event {
+            self.devices.remove(&id);
+            return
+        }
+
+        let ignored_devices = &self.bindings.ignored_devices;
+        let device = self.devices.entry(id).or_insert_with(|| {
+            log::debug!("found input device {:?}", id);
+            InputDevice {
+                ignored: ignored_devices.contains(&device_name(id)),
+                ..InputDevice::default()
+            }
+        });
+
+        device.events += 1;
+
+        match event {
+            DeviceEvent::Key(_) => device.keys = true,
+            DeviceEvent::Button { .. } | DeviceEvent::MouseWheel { .. } => device.buttons = true,
+            DeviceEvent::MouseMotion { .. } | DeviceEvent::Motion { .. } => device.motion = true,
+            _ => {},
+        }
+
+        if device.ignored {
+            return
+        }
+
+        // Replays ignore whatever the user does.
+        if self.replay.is_some() {
+            return
+        }
+
+        // Skip if suspended
+        if self.suspended || self.text.is_some() {
+            self.release_all();
+            return
+        }
+
+        match event {
+            DeviceEvent::Key(input) => self.keyboard_input(input),
+            DeviceEvent::MouseMotion { delta } if self.raw_mouse => self.mouse_axis_input(delta),
+            DeviceEvent::Button { button, state } => self.mouse_button_input(button, state),
+            DeviceEvent::MouseWheel { delta } => self.mouse_wheel_input(delta),
+
+            _ => {},
+        };
+    }
+
+    /// Returns the name of the key or button to show to the player, which
+    /// follows the layout of the keyboard for keys bound by their position.
+    pub fn binding_name(&self, binding: Binding) -> String {
+        let scancode = match binding {
+            Binding::Scancode(scancode) => scancode,
+            Binding::Key(key) if self.bindings.physical_keys => match qwerty_scancode(key) {
+                Some(scancode) => scancode,
+                None => return binding.to_string(),
+            },
+            _ => return binding.to_string(),
+        };
+
+        match self.scancode_keys.get(&scancode) {
+            Some(key) => format!("{:?}", key),
+            None => binding.to_string(),
+        }
+    }
+
+    /// Returns every device that sent an event so far.
+    pub fn devices(&self) -> impl Iterator<Item = (DeviceId, &InputDevice)> {
+        self.devices.iter().map(|(&id, device)| (id, device))
+    }
+
+    /// Throws away the events of a device (e.g. a drawing tablet spamming
+    /// motion events), or listens to them again.
+    pub fn set_device_ignored(&mut self, id: DeviceId, ignored: bool) {
+        self.devices.entry(id).or_default().ignored = ignored;
+
+        // Whatever the device was holding down is let go of.
+        if ignored {
+            self.release_all();
+        }
+    }
+
+    pub fn is_device_ignored(&self, id: DeviceId) -> bool {
+        self.devices.get(&id).map_or(false, |d| d.ignored)
+    }
+
+    /// Chooses between the raw mouse deltas of the device, and the motion of
+    /// the cursor (see `cursor_input()`). Some platforms do not report the
+    /// former, or report unusable ones.
+    pub fn set_raw_mouse(&mut self, raw: bool) {
+        self.raw_mouse = raw;
+    }
+
+    pub fn raw_mouse(&self) -> bool {
+        self.raw_mouse
+    }
+
+    /// Feeds a `WindowEvent::CursorMoved` when the raw mouse deltas are not
+    /// used. The cursor is expected to be put back at `center` afterwards.
+    pub fn cursor_input(&mut self, position: (f64, f64), center: (f64, f64)) {
+        if self.raw_mouse || self.replay.is_some() || self.suspended || self.text.is_some() {
+            return
+        }
+
+        self.mouse_axis_input((position.0 - center.0, position.1 - center.1));
+    }
+
+    // Reads the buttons and sticks of the connected gamepads.
+    fn poll_gamepads(&mut self) {
+        let mut events = Vec::new();
+        let suspended = self.suspended;
+
+        let axes = self.gamepad.poll(|button, pressed| {
+            match pressed {
+                true if !suspended => events.push(InputEvent::Press(Binding::Gamepad(button))),
+                _ => events.push(InputEvent::Release(Binding::Gamepad(button))),
+            }
+        });
+
+        for event in events {
+            self.apply(event);
+        }
+
+        if self.suspended {
+            self.release_all();
+        }
+
+        self.gamepad_axes = match self.suspended {
+            true => GamepadAxes::default(),
+            false => axes,
+        };
+    }
+
+    /// Polls the gamepads and works out which actions are active during this
+    /// frame. This has to be called at the start of every frame, before any
+    /// of the actions is queried. Returns the frame delta to use, which is the
+    /// recorded one while replaying.
+    pub fn begin_frame(&mut self, delta: f32) -> f32 {
+        let delta = match self.replay.as_mut().map(InputReplay::next_frame) {
+            Some(Some(frame)) => {
+                for event in frame.events {
+                    self.apply(event);
+                }
+
+                self.gamepad_axes = frame.sticks;
+                frame.delta
+            },
+
+            finished => {
+                if finished.is_some() {
+                    log::info!("input replay finished");
+                    self.replay = None;
+                }
+
+                self.poll_gamepads();
+                delta
+            },
+        };
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.write_frame(delta, self.gamepad_axes) {
+                log::warn!("stopped recording the input: {}", e);
+                self.recorder = None;
+            }
+        }
+
+        self.clock += delta;
+        self.update_actions(self.clock);
+
+        delta
+    }
+
+    /// Starts capturing typed text instead of passing the input on to the
+    /// game, until Enter or Escape is pressed.
+    pub fn start_text_input(&mut self) {
+        self.release_all();
+        self.text = Some(TextInput::new());
+    }
+
+    pub fn stop_text_input(&mut self) {
+        self.text = None;
+    }
+
+    pub fn is_entering_text(&self) -> bool {
+        self.text.is_some()
+    }
+
+    /// Returns the text being entered, if any.
+    pub fn text_input(&self) -> Option<&TextInput> {
+        self.text.as_ref()
+    }
+
+    /// Feeds a `WindowEvent::ReceivedCharacter` to the text being entered.
+    pub fn received_character(&mut self, c: char) {
+        let event = match self.text.as_mut() {
+            Some(text) => text.character(c),
+            None => return,
+        };
+
+        if let Some(event) = event {
+            self.text = None;
+            self.text_events.push(event);
+        }
+    }
+
+    /// Feeds a `WindowEvent::Ime` to the text being entered.
+    pub fn ime_input(&mut self, ime: Ime) {
+        if let Some(text) = self.text.as_mut() {
+            text.ime(ime);
+        }
+    }
+
+    /// Returns the texts submitted or cancelled since the last call.
+    pub fn take_text_events(&mut self) -> Vec<TextInputEvent> {
+        std::mem::take(&mut self.text_events)
+    }
+
+    /// Starts writing the input to a file, see `InputRecorder`.
+    pub fn start_recording<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.recorder = Some(InputRecorder::create(path)?);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        match self.recorder.take() {
+            Some(mut recorder) => recorder.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the input with the recorded one, until the recording ends.
+    pub fn start_replay(&mut self, replay: InputReplay) {
+        self.release_all();
+        self.replay = Some(replay);
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    fn update_actions(&mut self, now: f32) {
+        let raw = Action::ALL
+            .iter()
+            .map(|&a| (a, self.any_binding(a, ButtonState::Held), self.any_binding(a, ButtonState::Pressed)))
+            .collect::<Vec<_>>();
+
+        let is_raw_held = |action: Action| raw
+            .iter()
+            .any(|&(a, held, pressed)| a == action && (held || pressed));
+
+        // Double taps are looked for first, they may toggle other actions.
+        for &(action, ..) in raw.iter().filter(|&&(_, _, pressed)| pressed) {
+            let state = self.actions.entry(action).or_default();
+
+            match state.last_press {
+                Some(last) if now - last <= DOUBLE_TAP_WINDOW => {
+                    // A third tap starts over, instead of counting twice.
+                    state.last_press = None;
+
+                    for &(_, target) in self.bindings.double_tap.iter().filter(|t| t.0 == action) {
+                        let target_state = self.actions.entry(target).or_default();
+
+                        match self.bindings.mode(target) {
+                            ActivationMode::Toggle => target_state.latched = !target_state.latched,
+                            ActivationMode::Hold => target_state.tapped = true,
+                        }
+                    }
+                },
+                _ => state.last_press = Some(now),
+            }
+        }
+
+        for &(action, held, pressed) in raw.iter() {
+            let mode = self.bindings.mode(action);
+            let double_tap = &self.bindings.double_tap;
+            let state = self.actions.entry(action).or_default();
+
+            state.was_active = state.active;
+            state.active = match mode {
+                ActivationMode::Toggle => {
+                    if pressed {
+                        state.latched = !state.latched;
+                    }
+
+                    state.latched
+                },
+
+                ActivationMode::Hold => {
+                    // Pressing and releasing within one frame still counts.
+                    state.tapped = state.tapped && double_tap
+                        .iter()
+                        .any(|&(trigger, target)| target == action && is_raw_held(trigger));
+
+                    held || pressed || state.tapped
+                },
+            };
+        }
+    }
+
+    /// Forgets which keys and buttons were pressed and released during the
+    /// current frame. This has to be called at the end of every frame.
+    pub fn next_frame(&mut self) {
+        self.pressed_keys.next_frame();
+        self.mouse_button.next_frame();
+        self.gamepad_buttons.next_frame();
+        self.pressed_scancodes.next_frame();
+    }
+
+    pub fn set_gamepad_settings(&mut self, settings: GamepadSettings) {
+        self.gamepad_settings = settings;
+    }
+
+    /// Returns how far the left stick is pushed, past its deadzone.
+    pub fn movement_stick(&self) -> Vector2<f32> {
+        apply_deadzone(self.gamepad_axes.left, self.gamepad_settings.deadzone)
+    }
+
+    /// Returns how fast the right stick turns the camera, in radians per
+    /// second. Positive Y looks up.
+    pub fn look_stick(&self) -> Vector2<f32> {
+        let settings = &self.gamepad_settings;
+        let stick = apply_deadzone(self.gamepad_axes.right, settings.deadzone);
+        let y = if settings.invert_y { -stick.y } else { stick.y };
+
+        Vector2::new(stick.x * settings.look_sensitivity_x, y * settings.look_sensitivity_y)
+    }
+
+    pub fn get_mouse_delta(&mut self) -> (f64, f64) {
+        let temp = self.mouse_delta;
+        self.mouse_delta = (0.0, 0.0);
+        temp
+    }
+
+    /// Returns how far the wheel was scrolled since the last call, in lines.
+    /// Positive Y scrolls up (away from the user).
+    pub fn get_scroll_delta(&mut self) -> (f64, f64) {
+        let temp = self.scroll_delta;
+        self.scroll_delta = (0.0, 0.0);
+        temp
+    }
+
+    /// Whether the key went down during this frame. This is true for exactly
+    /// one frame per press, no matter how long the key is held.
+    pub fn is_key_just_pressed(&self, key: Key) -> bool {
+        self.pressed_keys.is(key, ButtonState::Pressed)
+    }
+
+    /// Whether the key went up during this frame.
+    pub fn is_key_just_released(&self, key: Key) -> bool {
+        self.pressed_keys.is(key, ButtonState::Released)
+    }
+
+    /// Returns a key or button that went down during this frame, if there is
+    /// one, e.g. to bind an action to it. Keys are returned by their
+    /// scancodes if `KeyBindings::physical_keys` is set.
+    pub fn pressed_binding(&self) -> Option<Binding> {
+        let key = match self.bindings.physical_keys {
+            true => self.pressed_scancodes.pressed.iter().next().map(|&s| Binding::Scancode(s)),
+            false => self.pressed_keys.pressed.iter().next().map(|&k| Binding::Key(k)),
+        };
+
+        key.or_else(|| self.mouse_button.pressed.iter().next().map(|&b| Binding::Mouse(b)))
+            .or_else(|| self.gamepad_buttons.pressed.iter().next().map(|&b| Binding::Gamepad(b)))
+    }
+
+    pub fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    /// Replaces the bindings, and ignores the devices they list, listening
+    /// to the rest again.
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+
+        let devices = self.devices.keys().copied().collect::<Vec<_>>();
+        for id in devices {
+            let ignored = self.bindings.ignored_devices.contains(&device_name(id));
+
+            if self.is_device_ignored(id) != ignored {
+                self.set_device_ignored(id, ignored);
+            }
+        }
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut KeyBindings {
+        &mut self.bindings
+    }
+
+    /// Whether the action is active: for held actions, whether any key or
+    /// button bound to it is held down (or it was double-tapped). Toggled
+    /// actions stay active from one press to the next.
+    pub fn is_action_held(&self, action: Action) -> bool {
+        self.actions.get(&action).map_or(false, |s| s.active)
+    }
+
+    /// Whether the action became active during this frame. Actions pressed
+    /// again while they are still active (e.g. through another binding)
+    /// activate once more.
+    pub fn just_activated(&self, action: Action) -> bool {
+        let became_active = self.actions
+            .get(&action)
+            .map_or(false, |s| s.active && !s.was_active);
+
+        match self.bindings.mode(action) {
+            ActivationMode::Hold => became_active || self.any_binding(action, ButtonState::Pressed),
+            ActivationMode::Toggle => became_active,
+        }
+    }
+
+    /// Whether the action stopped being active during this frame.
+    pub fn just_deactivated(&self, action: Action) -> bool {
+        self.actions
+            .get(&action)
+            .map_or(false, |s| !s.active && s.was_active)
+    }
+
+    pub fn is_key_held(&self, key: Key) -> bool {
+        self.pressed_keys.is(key, ButtonState::Held)
+    }
+
+    pub fn iterate_held_keys(&self) -> HeldKeysIter {
+        self.pressed_keys.held.iter()
+    }
+
+    fn any_binding(&self, action: Action, state: ButtonState) -> bool {
+        self.bindings.get(action).iter().any(|binding| match *binding {
+            Binding::Key(key) => match qwerty_scancode(key) {
+                Some(scancode) if self.bindings.physical_keys =>
+                    self.pressed_scancodes.is(scancode, state),
+                _ => self.pressed_keys.is(key, state),
+            },
+            Binding::Mouse(button) => self.mouse_button.is(button, state),
+            Binding::Gamepad(button) => self.gamepad_buttons.is(button, state),
+            Binding::Scancode(scancode) => self.pressed_scancodes.is(scancode, state),
+        })
+    }
+
+    pub fn suspend_input(&mut self, mode: SuspendMode) {
+        self.suspended = true;
+
+        if let SuspendMode::Release = mode {
+            self.release_all();
+            self.mouse_delta = (0.0, 0.0);
+            self.skip_mouse_delta = true;
+        }
+    }
+
+    pub fn unsuspend_input(&mut self) {
+        self.suspended = false
+    }
+}
+
+// The name a device is listed under in `KeyBindings::ignored_devices`, which
+// is how it is logged.
+fn device_name(id: DeviceId) -> String {
+    format!("{:?}", id)
+}
+
+// How many pixels of touchpad scrolling count as one line.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+// How quickly (in seconds) an action has to be pressed twice to count as a
+// double tap.
+const DOUBLE_TAP_WINDOW: f32 = 0.3;
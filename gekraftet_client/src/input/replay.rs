@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::{ self, BufRead, BufReader, BufWriter, Write };
+use std::path::Path;
+use cgmath::Vector2;
+use super::{ Binding, GamepadAxes };
+
+/// A single change of the input, as recorded and replayed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    Press(Binding),
+    Release(Binding),
+    MouseMotion(f64, f64),
+    Scroll(f64, f64),
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputEvent::Press(binding) => write!(f, "press {}", binding),
+            InputEvent::Release(binding) => write!(f, "release {}", binding),
+            InputEvent::MouseMotion(x, y) => write!(f, "motion {} {}", x, y),
+            InputEvent::Scroll(x, y) => write!(f, "scroll {} {}", x, y),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "unable to read the input recording: {}", e),
+            ReplayError::Parse { line, message } =>
+                write!(f, "invalid input recording at line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+/// Everything that happened to the input during one frame.
+#[derive(Clone, Debug)]
+pub struct RecordedFrame {
+    /// How long the frame took, in seconds.
+    pub delta: f32,
+    pub sticks: GamepadAxes,
+    pub events: Vec<InputEvent>,
+}
+
+/// Writes the input to a file, one frame after another. Every frame starts
+/// with a `frame <delta> <sticks>` line, followed by one line per event.
+pub struct InputRecorder {
+    out: BufWriter<File>,
+    events: Vec<InputEvent>,
+}
+
+impl InputRecorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+            events: Vec::new(),
+        })
+    }
+
+    pub fn record(&mut self, event: InputEvent) {
+        self.events.push(event);
+    }
+
+    /// Writes out a frame with the events recorded since the last one.
+    pub fn write_frame(&mut self, delta: f32, sticks: GamepadAxes) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "frame {} {} {} {} {}",
+            delta, sticks.left.x, sticks.left.y, sticks.right.x, sticks.right.y
+        )?;
+
+        for event in self.events.drain(..) {
+            writeln!(self.out, "{}", event)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// A recording made by `InputRecorder`, played back frame by frame.
+pub struct InputReplay {
+    frames: VecDeque<RecordedFrame>,
+}
+
+impl InputReplay {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut frames = VecDeque::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let error = |message: &str| ReplayError::Parse {
+                line: i + 1,
+                message: message.to_owned(),
+            };
+
+            let mut words = line.split_whitespace();
+            let (kind, args) = match words.next() {
+                Some(kind) => (kind, words.collect::<Vec<_>>()),
+                None => continue,
+            };
+
+            let numbers = || args
+                .iter()
+                .map(|a| a.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| error("expected numbers"));
+
+            let binding = || match args.as_slice() {
+                [name] => Binding::try_from(name.to_string()).map_err(|e| error(&e)),
+                _ => Err(error("expected a key or button")),
+            };
+
+            let event = match kind {
+                "frame" => {
+                    let n = numbers()?;
+
+                    if n.len() != 5 {
+                        return Err(error("expected a delta and four stick axes"))
+                    }
+
+                    frames.push_back(RecordedFrame {
+                        delta: n[0] as f32,
+                        sticks: GamepadAxes {
+                            left: Vector2::new(n[1] as f32, n[2] as f32),
+                            right: Vector2::new(n[3] as f32, n[4] as f32),
+                        },
+                        events: Vec::new(),
+                    });
+
+                    continue
+                },
+                "press" => InputEvent::Press(binding()?),
+                "release" => InputEvent::Release(binding()?),
+                "motion" | "scroll" => match numbers()?.as_slice() {
+                    &[x, y] if kind == "motion" => InputEvent::MouseMotion(x, y),
+                    &[x, y] => InputEvent::Scroll(x, y),
+                    _ => return Err(error("expected two numbers")),
+                },
+                _ => return Err(error("unknown event")),
+            };
+
+            match frames.back_mut() {
+                Some(frame) => frame.events.push(event),
+                None => return Err(error("event before the first frame")),
+            }
+        }
+
+        Ok(Self { frames })
+    }
+
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        self.frames.pop_front()
+    }
+
+    pub fn remaining_frames(&self) -> usize {
+        self.frames.len()
+    }
+}
@@ -0,0 +1,47 @@
+use super::Key;
+
+/// Returns the scancode of the physical key which is labelled `key` on a US
+/// QWERTY keyboard. Only keys that move around between layouts are listed,
+/// the others are better bound by their virtual keycodes.
+pub fn qwerty_scancode(key: Key) -> Option<u32> {
+    QWERTY_SCANCODES
+        .iter()
+        .find(|&&(k, _)| k == key)
+        .map(|&(_, scancode)| scancode)
+}
+
+// Windows and Linux both report set 1 scancodes (evdev codes, on Linux), which
+// agree on the main block of the keyboard.
+#[cfg(not(target_os = "macos"))]
+const QWERTY_SCANCODES: [(Key, u32); 47] = [
+    (Key::Key1, 2), (Key::Key2, 3), (Key::Key3, 4), (Key::Key4, 5), (Key::Key5, 6),
+    (Key::Key6, 7), (Key::Key7, 8), (Key::Key8, 9), (Key::Key9, 10), (Key::Key0, 11),
+    (Key::Minus, 12), (Key::Equals, 13),
+    (Key::Q, 16), (Key::W, 17), (Key::E, 18), (Key::R, 19), (Key::T, 20),
+    (Key::Y, 21), (Key::U, 22), (Key::I, 23), (Key::O, 24), (Key::P, 25),
+    (Key::LBracket, 26), (Key::RBracket, 27),
+    (Key::A, 30), (Key::S, 31), (Key::D, 32), (Key::F, 33), (Key::G, 34),
+    (Key::H, 35), (Key::J, 36), (Key::K, 37), (Key::L, 38),
+    (Key::Semicolon, 39), (Key::Apostrophe, 40), (Key::Grave, 41), (Key::Backslash, 43),
+    (Key::Z, 44), (Key::X, 45), (Key::C, 46), (Key::V, 47), (Key::B, 48),
+    (Key::N, 49), (Key::M, 50),
+    (Key::Comma, 51), (Key::Period, 52), (Key::Slash, 53),
+];
+
+// macOS reports its own virtual keycodes, which are tied to the physical keys
+// as well.
+#[cfg(target_os = "macos")]
+const QWERTY_SCANCODES: [(Key, u32); 47] = [
+    (Key::Key1, 0x12), (Key::Key2, 0x13), (Key::Key3, 0x14), (Key::Key4, 0x15), (Key::Key5, 0x17),
+    (Key::Key6, 0x16), (Key::Key7, 0x1A), (Key::Key8, 0x1C), (Key::Key9, 0x19), (Key::Key0, 0x1D),
+    (Key::Minus, 0x1B), (Key::Equals, 0x18),
+    (Key::Q, 0x0C), (Key::W, 0x0D), (Key::E, 0x0E), (Key::R, 0x0F), (Key::T, 0x11),
+    (Key::Y, 0x10), (Key::U, 0x20), (Key::I, 0x22), (Key::O, 0x1F), (Key::P, 0x23),
+    (Key::LBracket, 0x21), (Key::RBracket, 0x1E),
+    (Key::A, 0x00), (Key::S, 0x01), (Key::D, 0x02), (Key::F, 0x03), (Key::G, 0x05),
+    (Key::H, 0x04), (Key::J, 0x26), (Key::K, 0x28), (Key::L, 0x25),
+    (Key::Semicolon, 0x29), (Key::Apostrophe, 0x27), (Key::Grave, 0x32), (Key::Backslash, 0x2A),
+    (Key::Z, 0x06), (Key::X, 0x07), (Key::C, 0x08), (Key::V, 0x09), (Key::B, 0x0B),
+    (Key::N, 0x2D), (Key::M, 0x2E),
+    (Key::Comma, 0x2B), (Key::Period, 0x2F), (Key::Slash, 0x2C),
+];
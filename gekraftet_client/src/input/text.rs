@@ -0,0 +1,66 @@
+use winit::event::Ime;
+
+// Characters the keyboard sends for editing keys, rather than text.
+const BACKSPACE: char = '\u{8}';
+const DELETE: char = '\u{7f}';
+const ESCAPE: char = '\u{1b}';
+
+/// What happened to the text being entered.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextInputEvent {
+    /// Enter was pressed, with the text entered until then.
+    Submitted(String),
+    /// Escape was pressed, the text is discarded.
+    Cancelled,
+}
+
+/// A line of text being entered, e.g. into the chat or the console.
+#[derive(Clone, Debug, Default)]
+pub struct TextInput {
+    text: String,
+    // Text the input method is still composing. It is not part of `text`
+    // until it is committed.
+    preedit: String,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn preedit(&self) -> &str {
+        &self.preedit
+    }
+
+    /// Handles a character typed on the keyboard.
+    pub fn character(&mut self, c: char) -> Option<TextInputEvent> {
+        match c {
+            BACKSPACE | DELETE => {
+                self.text.pop();
+            },
+            '\r' | '\n' => return Some(TextInputEvent::Submitted(std::mem::take(&mut self.text))),
+            ESCAPE => return Some(TextInputEvent::Cancelled),
+            c if c.is_control() => {},
+            c => self.text.push(c),
+        }
+
+        None
+    }
+
+    /// Handles an event of the input method, which composes text that cannot
+    /// be typed directly (e.g. CJK).
+    pub fn ime(&mut self, ime: Ime) {
+        match ime {
+            Ime::Preedit(text, _) => self.preedit = text,
+            Ime::Commit(text) => {
+                self.preedit.clear();
+                self.text.push_str(&text);
+            },
+            Ime::Enabled | Ime::Disabled => self.preedit.clear(),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use cgmath::{ Point2, Vector2 };
+use gekraftet_core::inventory::{ Inventory, ItemStack };
+use gekraftet_core::world::Block;
+use crate::RGBA;
+use crate::mesh::MeshBuilder;
+use crate::renderer::{ add_quad, add_text, text_height, text_width, HudElement };
+use crate::world::block_color;
+
+/// The inventory screen, where stacks are moved around with the mouse. A
+/// stack picked up from a slot follows the cursor until it is put down.
+pub struct InventoryScreen {
+    held: Option<ItemStack>,
+}
+
+impl InventoryScreen {
+    pub fn new() -> Self {
+        Self { held: None }
+    }
+
+    /// Handles a click on the screen. Positions are in logical pixels, and
+    /// `screen` is the size of the window.
+    ///
+    /// A left click picks up the stack in a slot, or puts down (or swaps) the
+    /// held stack. A right click picks up half of a stack, or puts down a
+    /// single item of the held stack.
+    pub fn click(
+        &mut self,
+        inventory: &mut Inventory,
+        cursor: Point2<f32>,
+        screen: Vector2<f32>,
+        right: bool,
+    ) {
+        let anchor = Point2::new(screen.x * 0.5, screen.y * 0.5);
+        let slot = match slot_at(inventory.len(), cursor, anchor) {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        self.held = match (self.held.take(), right) {
+            (None, false) => inventory.take(slot),
+            (None, true) => inventory.split(slot),
+            (Some(held), false) => inventory.put(slot, held),
+            (Some(mut held), true) => {
+                let fits = inventory.slot(slot)
+                    .map_or(true, |s| s.id == held.id && !s.is_full());
+
+                if fits {
+                    inventory.put(slot, ItemStack::new(held.id, 1));
+                    held.count -= 1;
+                }
+
+                match held.count {
+                    0 => None,
+                    _ => Some(held),
+                }
+            },
+        };
+    }
+
+    /// Puts the held stack back into the inventory. Returns what did not fit,
+    /// which the caller should drop into the world.
+    pub fn close(&mut self, inventory: &mut Inventory) -> Option<ItemStack> {
+        self.held.take().and_then(|held| inventory.insert(held))
+    }
+
+    /// What the screen looks like right now, to be put onto the HUD.
+    pub fn view(&self, inventory: &Inventory, cursor: Point2<f32>) -> InventoryView {
+        InventoryView {
+            slots: inventory.slots().to_vec(),
+            held: self.held,
+            cursor,
+        }
+    }
+}
+
+impl Default for InventoryScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A snapshot of the inventory screen, drawn on the HUD. It has to be put into
+/// a slot anchored at the center of the window.
+pub struct InventoryView {
+    slots: Vec<Option<ItemStack>>,
+    held: Option<ItemStack>,
+    cursor: Point2<f32>,
+}
+
+impl HudElement for InventoryView {
+    fn build(&self, mb: MeshBuilder, anchor: Point2<f32>) -> MeshBuilder {
+        let mb = add_quad(
+            mb,
+            Point2::new(anchor.x - BACKDROP_SIZE, anchor.y - BACKDROP_SIZE),
+            Point2::new(anchor.x + BACKDROP_SIZE, anchor.y + BACKDROP_SIZE),
+            RGBA::new(0.0, 0.0, 0.0, 0.5),
+        );
+
+        let title = "Inventory";
+        let (first, _) = slot_bounds(0, self.slots.len(), anchor);
+        let title_origin = Point2::new(
+            anchor.x - text_width(title, TEXT_SCALE) * 0.5,
+            first.y - SLOT_GAP * 2.0 - text_height(TEXT_SCALE),
+        );
+        let mb = add_text(mb, title_origin, title, TEXT_SCALE, RGBA::new(1.0, 1.0, 1.0, 1.0));
+
+        let mb = self.slots.iter().enumerate().fold(mb, |mb, (i, slot)| {
+            let (min, max) = slot_bounds(i, self.slots.len(), anchor);
+            let mb = add_quad(mb, min, max, RGBA::new(0.15, 0.15, 0.15, 0.8));
+
+            match slot {
+                Some(stack) => add_stack(mb, *stack, min, max),
+                None => mb,
+            }
+        });
+
+        // The held stack goes last, on top of the slots.
+        match self.held {
+            Some(stack) => {
+                let half = Vector2::new(SLOT_SIZE, SLOT_SIZE) * 0.5;
+                add_stack(mb, stack, self.cursor - half, self.cursor + half)
+            },
+            None => mb,
+        }
+    }
+}
+
+// Draws a stack into the box: a swatch of the color of its block, with the
+// number of items in the corner.
+fn add_stack(mb: MeshBuilder, stack: ItemStack, min: Point2<f32>, max: Point2<f32>) -> MeshBuilder {
+    let inset = Vector2::new(ITEM_INSET, ITEM_INSET);
+    let mut color = block_color(&Block::new(stack.id));
+    color.w = 1.0;
+
+    let mb = add_quad(mb, min + inset, max - inset, color);
+
+    if stack.count <= 1 {
+        return mb
+    }
+
+    let count = stack.count.to_string();
+    let origin = Point2::new(
+        max.x - text_width(&count, COUNT_SCALE) - 2.0,
+        max.y - text_height(COUNT_SCALE) - 2.0,
+    );
+
+    add_text(mb, origin, &count, COUNT_SCALE, RGBA::new(1.0, 1.0, 1.0, 1.0))
+}
+
+// Where a slot is, in logical pixels. The slots are laid out in rows, centered
+// around the anchor.
+fn slot_bounds(index: usize, count: usize, anchor: Point2<f32>) -> (Point2<f32>, Point2<f32>) {
+    let rows = (count + COLUMNS - 1) / COLUMNS;
+    let pitch = SLOT_SIZE + SLOT_GAP;
+    let size = Vector2::new(COLUMNS as f32, rows as f32) * pitch - Vector2::new(SLOT_GAP, SLOT_GAP);
+
+    let (column, row) = (index % COLUMNS, index / COLUMNS);
+    let min = Point2::new(
+        anchor.x - size.x * 0.5 + column as f32 * pitch,
+        anchor.y - size.y * 0.5 + row as f32 * pitch,
+    );
+
+    (min, min + Vector2::new(SLOT_SIZE, SLOT_SIZE))
+}
+
+fn slot_at(count: usize, cursor: Point2<f32>, anchor: Point2<f32>) -> Option<usize> {
+    (0..count).find(|&i| {
+        let (min, max) = slot_bounds(i, count, anchor);
+        cursor.x >= min.x && cursor.x < max.x && cursor.y >= min.y && cursor.y < max.y
+    })
+}
+
+// The layout of the screen, in logical pixels. The text is drawn with pixels
+// this large.
+const COLUMNS: usize = 9;
+const SLOT_SIZE: f32 = 40.0;
+const SLOT_GAP: f32 = 4.0;
+const ITEM_INSET: f32 = 6.0;
+const TEXT_SCALE: f32 = 2.0;
+const COUNT_SCALE: f32 = 1.0;
+const BACKDROP_SIZE: f32 = 10000.0;
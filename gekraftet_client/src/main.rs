@@ -1,90 +1,342 @@
-use std::time::Instant;
-
+mod audio;
+mod breaking;
 mod camera;
+mod camera_path;
+mod chat;
+mod commands;
+mod export;
+mod game_loop;
 mod input;
+mod inventory_screen;
+mod menu;
+mod minimap;
 mod mesh;
+mod player;
+mod remote;
+mod resources;
 mod renderer;
+mod settings;
+mod weather;
 mod windowing;
 mod world;
 
+use audio::{ Audio, Sound, SoundKind };
+use breaking::BlockBreaking;
 use cgmath::*;
+use gekraftet_core::entity::*;
+use gekraftet_core::events::{ EventBus, EventKind, Interaction, WorldEvent };
+use gekraftet_core::inventory::{ Inventory, ItemStack };
+use gekraftet_core::level::Level;
+use gekraftet_core::movement;
+use gekraftet_core::player::{ GameMode, PlayerState };
+use gekraftet_core::saves::WorldManager;
+use gekraftet_core::utils::Ray;
 use gekraftet_core::world::*;
+use gekraftet_server::IntegratedServer;
+use gekraftet_server::console;
 use camera::*;
+use camera_path::*;
+use chat::Chat;
+use commands::Command;
+use game_loop::{ GameLoop, MOVEMENT_STEP };
 use input::*;
+use inventory_screen::*;
+use menu::*;
+use minimap::Minimap;
+use player::*;
+use remote::{ RemoteEvent, RemoteWorld };
+use resources::{ ResourceManager, ResourcePack, WINDOW_ICON };
 use renderer::*;
+use settings::{ FileWatcher, GamepadSettings, GraphicsSettings, MouseSettings, Msaa, SettingsFile };
+use weather::Rain;
 use windowing::*;
-use world::Mesher;
+use world::{ block_color, BLOCK_LENGTH, ChunkStreamer, Mesher };
+use std::cell::RefCell;
+use std::rc::Rc;
 
 pub type RGBA = cgmath::Vector4<f32>;
 
 fn main() {
-    let w = Window::create_window();
-    let mut r = GlRenderer::new(&w, 
-        cgmath::perspective(Deg(55.0), 16.0/9.0, 0.1, 500.0)
-    );
+    env_logger::init();
+    let args = std::env::args().collect::<Vec<_>>();
+    let arg_value = |name: &str| args
+        .iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1));
 
-    let (tx, rx) = std::sync::mpsc::channel::<(i32, i32, i32, mesh::Mesh)>();
-    let (bound0, bound1) = (-16i32, 16i32);
-
-    let world_minister = std::thread::spawn(move || {
-        let tx = tx;
-        
-        for x in bound0..bound1 {
-            for y in bound0..bound1 {
-                let tx = tx.clone();
-                let mut noise = Noise::<Perlin3D>::with_option(
-                    NoiseGenOption::new()
-                        .octaves(16)
-                        .amplitude(10.0)
-                        .persistance(0.5)
-                        .frequency(628.318530)
-                        .lacunarity(0.5),
-                    ((x << 6) ^ (y + 123456)) as u64,
-                );
-
-                std::thread::spawn(move || {
-                    let pos = Point3::<i32>::new(x, 0, y);
-                    let chunk = Chunk::new(pos, &mut noise);
-                    let mesher = world::GreedyCubeMesher::from_chunk(&chunk);
-                    let mesh = mesher.generate_mesh();
-                    tx.send((pos.x, pos.y, pos.z, mesh))
-                });
-            }
+    // Every `--resource-pack <path>` (a folder, or a zip archive of one) goes
+    // over the built-in resources, in the order they are given.
+    let mut resources = ResourceManager::new();
+    for path in args.windows(2).filter(|pair| pair[0] == "--resource-pack").map(|pair| &pair[1]) {
+        match ResourcePack::open(path) {
+            Ok(pack) => resources.push(pack),
+            Err(e) => log::warn!("unable to open the resource pack {}: {}", path, e),
         }
+    }
 
-        drop(tx);
+    // The blocks have to be defined before anything looks at them.
+    let mut blocks = BlockRegistry::load_or_create(BLOCKS_PATH).unwrap_or_else(|e| {
+        log::warn!("{}, using the built-in ones", e);
+        BlockRegistry::builtin()
     });
-    
-    let speed = 10.0;
+    resources.color_blocks(&mut blocks);
+    let _ = blocks.install();
+
+    // `--export-gltf <world> <file>` writes the terrain around the spawn point
+    // of a saved world to a binary glTF file (.glb) and quits, without opening
+    // a window. `--export-radius <chunks>` sets how much of it.
+    if let Some(folder) = arg_value("--export-gltf") {
+        let output = args.windows(3).find(|a| a[0] == "--export-gltf").map(|a| a[2].clone());
+        let radius = arg_value("--export-radius").and_then(|r| r.parse().ok()).unwrap_or(EXPORT_RADIUS);
+
+        match output {
+            Some(output) => export_world(&WorldManager::new(SAVES_PATH), folder, &output, radius),
+            None => log::error!("usage: --export-gltf <world> <file>"),
+        }
+        return
+    }
 
+    // The settings that are not in the file keep their defaults.
+    let settings = SettingsFile::load_or_default(SETTINGS_PATH);
+    let mut graphics = GraphicsSettings::default();
+    let mut mouse_settings = MouseSettings::default();
+    settings.apply(&mut graphics, &mut mouse_settings);
+
+    // `--monitor <index>` opens the window on another monitor than the one
+    // the platform picks.
+    let mut window_config = WindowConfig::from_settings(&graphics);
+    if let Some(index) = arg_value("--monitor").and_then(|i| i.parse().ok()) {
+        window_config = window_config.monitor(index);
+    }
+
+    let w = Window::create_window(window_config);
+    if let Err(e) = resources.read(WINDOW_ICON).map_or(Ok(()), |png| w.context().set_icon_png(&png)) {
+        log::warn!("unable to set the window icon: {}", e);
+    }
+    let mut r = GlRenderer::new(w.context(), &graphics);
+
+    // Chunks are generated around the player as it moves.
+    let mut streamer = ChunkStreamer::new(graphics.view_distance);
+
+    // How fast the player flies, which is changed by scrolling.
+    let mut fly_speed = 10.0;
     let mut mouse_locked = false;
-    let mut pos = Point3::<f32>::new(0.0, 200.0, 0.0);
 
-    let mut cam = Camera::new(pos, Vector3::<f32>::new(2.5, -200.0, 0.5));
+    // The game starts on the world selection screen. Until a world is
+    // picked, there is an empty level that is never saved.
+    let saves = WorldManager::new(SAVES_PATH);
+    let mut worlds = saved_worlds(&saves);
+    let mut level = Level::new(String::new(), WorldGenerator::default());
+    let mut folder: Option<String> = None;
+    let mut new_player = true;
+    let mut player = PlayerController::new(spawn_position(None));
+
+    let mut cam = Camera::new(player.eye(), Vector3::<f32>::new(2.5, -200.0, 0.5));
+    cam.set_fov(Deg(graphics.fov));
+    cam.set_mouse_settings(mouse_settings.clone());
+    cam.set_smoothing(0.0, mouse_settings.smoothing);
+
+    let mut world = World::new();
+    // Everything in the world besides blocks and the player, e.g. mobs.
+    let mut entities = Entities::new();
+    let mob_seed = rand::random::<u64>();
+    let mut schedule = Schedule::new()
+        .system(MobSpawner::new(mob_seed))
+        .system(WanderSystem::new(mob_seed))
+        .system(ItemDropSystem)
+        .system(MovementSystem);
+    let mut rain = Rain::new();
+    let mut minimap = Minimap::new();
+    let mut chat = Chat::new();
+    let audio = Rc::new(RefCell::new(Audio::new()));
+    audio.borrow_mut().set_sounds(resources.sounds());
+    audio.borrow_mut().set_volumes(&settings.audio);
+    // The chunks with blocks that changed since the last frame, which have to
+    // be remeshed.
+    let changed_chunks = Rc::new(RefCell::new(Vec::new()));
+    let mut events = EventBus::new();
+    events.subscribe(&[EventKind::BlockChanged], {
+        let changed_chunks = changed_chunks.clone();
+        move |event: &WorldEvent, _: &World| if let WorldEvent::BlockChanged { at, .. } = event {
+            let mut changed_chunks = changed_chunks.borrow_mut();
+            let at = ChunkPos::from(*at);
+            if !changed_chunks.contains(&at) {
+                changed_chunks.push(at);
+            }
+        }
+    });
+    events.subscribe(&[EventKind::PlayerInteracted], {
+        let audio = audio.clone();
+        move |event: &WorldEvent, _: &World| if let Some(sound) = interaction_sound(event) {
+            audio.borrow_mut().play(sound);
+        }
+    });
+    let mut inventory = Inventory::new(INVENTORY_SIZE);
+    let mut inventory_screen = InventoryScreen::new();
+    let mut path = CameraPath::new();
+    let mut playback: Option<PathPlayback> = None;
     let mut input_manager = InputManager::new();
+    input_manager.set_bindings(KeyBindings::load_or_default(KEY_BINDINGS_PATH));
+    input_manager.set_gamepad_settings(GamepadSettings::default());
+    input_manager.set_raw_mouse(mouse_settings.raw_input);
+    // Changes to the settings and the key bindings made while the game runs
+    // are picked up.
+    let mut settings_watcher = FileWatcher::new(SETTINGS_PATH);
+    let mut bindings_watcher = FileWatcher::new(KEY_BINDINGS_PATH);
+
+    // `--record-input <file>` writes the input to a file, which can be played
+    // back with `--replay-input <file>` to reproduce a session.
+    if let Some(path) = arg_value("--record-input") {
+        input_manager.start_recording(path).expect("unable to record the input");
+    }
+
+    if let Some(path) = arg_value("--replay-input") {
+        match InputReplay::load(path) {
+            Ok(replay) => input_manager.start_replay(replay),
+            Err(e) => log::error!("{}", e),
+        }
+    }
+
+    // `--connect <address>` plays on a server instead of in a saved world,
+    // as `--name <name>`. Servers in online mode also need `--token <token>`.
+    let name = arg_value("--name").map_or(DEFAULT_PLAYER_NAME.to_owned(), String::clone);
+    let token = arg_value("--token").cloned();
+    let mut remote = arg_value("--connect").and_then(|address| match RemoteWorld::connect(address.as_str(), &name, token) {
+        Ok(remote) => Some(remote),
+        Err(e) => {
+            log::error!("unable to connect to {}: {:?}", address, e);
+            None
+        },
+    });
+
+    // The worlds opened from the menu run on a server of their own, which the
+    // game talks to like to any other.
+    let mut integrated: Option<IntegratedServer> = None;
 
-    let mut last_time = Instant::now();
-    let mut delta = 0.0;
+    // `--map-window` opens a second window, showing the world around the
+    // camera from above.
+    let mut map = match args.iter().any(|a| a == "--map-window") {
+        true => Some(MapWindow::new(&w, &graphics, player.eye())),
+        false => None,
+    };
+
+    // The pause menu, which changes copies of the settings and the key
+    // bindings.
+    let mut menu = Menu::new(
+        graphics.clone(),
+        mouse_settings.clone(),
+        settings.audio.clone(),
+        input_manager.bindings().clone(),
+    );
+    menu.select_world(worlds.iter().map(|(_, name)| name.clone()).collect());
+    show_menu(&mut r, &menu);
+    let mut menu_event = None;
+    // Whether the menu was already waiting for a key to rebind an action to
+    // in the last frame, see below.
+    let mut rebind_armed = false;
+    // Where the cursor is, in logical pixels, for pointing at the menu.
+    let mut cursor = Point2::new(0.0, 0.0);
+
+    let mut show_debug = false;
+    // The block placed by the player, which can be changed by picking one.
+    let mut selected_block = Block::STONE;
+    // The block being broken by a player that cannot break blocks at once.
+    let mut breaking = BlockBreaking::new();
+
+    let mut game_loop = GameLoop::new(graphics.frame_limit);
+    // The frame rate shown in the title is averaged over a second.
+    let mut fps_frames = 0;
+    let mut fps_time = 0.0;
+    // The ticks per second and milliseconds per tick of the server, if it
+    // tells them.
+    let mut server_metrics: Option<(f32, f32)> = None;
     let mut time = 0.0;
 
     w.run(move |event, cl, context| {
-        use glutin::window::CursorGrabMode;
+        if let Some(map) = map.as_mut() {
+            map.window.handle_event(&event);
+        }
+
+        let map_id = map.as_ref().map(|m| m.window.id());
+
         match event {
-            Event::WindowEvent { event, .. } => {
+            Event::WindowEvent { window_id, event } if Some(window_id) == map_id => {
                 match event {
                     WindowEvent::CloseRequested => {
+                        // The renderer has to be dropped with its own context
+                        // current.
+                        if let Some(map) = map.take() {
+                            map.window.make_current();
+                            drop(map);
+                        }
+                        context.make_current();
+                    },
+
+                    WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } => {
+                        if let Some(map) = map.as_mut() {
+                            map.window.make_current();
+                            map.renderer.set_viewport(map.window.viewport());
+                            context.make_current();
+                        }
+                    },
+
+                    _ => {}
+                }
+            },
+
+            Event::WindowEvent { window_id, event } if window_id == context.id() => {
+                match event {
+                    WindowEvent::CloseRequested => {
+                        if let Err(e) = input_manager.stop_recording() {
+                            log::error!("unable to save the input recording: {}", e);
+                        }
+
+                        // The server saves the level as well, so it has to
+                        // be done with it first.
+                        if let Some(server) = integrated.take() {
+                            server.stop();
+                        }
+
+                        inventory_screen.close(&mut inventory);
+                        level.player = Some(player_state(&player, &cam, &inventory, selected_block));
+                        save_level(&saves, folder.as_deref(), &level);
                         *cl = ControlFlow::Exit;
                     },
                     
-                    WindowEvent::MouseInput { button, .. } => {
-                        use glutin::event::MouseButton;
-                        match button {
-                            MouseButton::Left => {
-                                context.window()
-                                    .set_cursor_grab(CursorGrabMode::Locked)
-                                    .expect("unable to grab cursor");
-                                context.window()
-                                    .set_cursor_visible(false);
+                    // Alt-tabbing away gives the cursor back and pauses the
+                    // game.
+                    WindowEvent::Focused(false) if mouse_locked => {
+                        ungrab_cursor(context);
+                        mouse_locked = false;
+                        input_manager.suspend_input(SuspendMode::Release);
+
+                        if menu.is_playing() {
+                            menu.set_mouse(cam.mouse_settings().clone());
+                            menu.pause();
+                            show_menu(&mut r, &menu);
+                        }
+                    },
+
+                    // The menu is navigated with the keyboard too.
+                    WindowEvent::Focused(true) if !menu.is_playing() => input_manager.unsuspend_input(),
+
+                    WindowEvent::MouseInput { button, state, .. } => {
+                        use winit::event::{ ElementState, MouseButton };
+                        match (button, state) {
+                            (MouseButton::Left, ElementState::Pressed) | (MouseButton::Right, ElementState::Pressed)
+                                if menu.state() == GameState::Inventory =>
+                            {
+                                let right = button == MouseButton::Right;
+                                inventory_screen.click(&mut inventory, cursor, logical_size(context.viewport()), right);
+                                show_inventory(&mut r, &inventory_screen, &inventory, cursor);
+                            },
+                            (MouseButton::Left, ElementState::Pressed) if !menu.is_playing() => {
+                                let clicked = menu.click(cursor, logical_size(context.viewport()));
+                                menu_event = clicked.or(menu_event);
+                                show_menu(&mut r, &menu);
+                            },
+                            (MouseButton::Left, _) if menu.is_playing() => {
+                                grab_cursor(context, input_manager.raw_mouse());
                                 mouse_locked = true;
                                 input_manager.unsuspend_input();
                             },
@@ -92,80 +344,838 @@ fn main() {
                         }
                     },
 
-                    WindowEvent::Resized(glutin::dpi::PhysicalSize::<u32> { width, height }) => 
-                        r.change_viewport(width, height),
+                    // Without raw mouse input, the cursor is kept at the
+                    // center of the window and every move away from it turns
+                    // the camera.
+                    WindowEvent::CursorMoved { position, .. } if mouse_locked && !input_manager.raw_mouse() => {
+                        let size = context.window().inner_size();
+                        let center = winit::dpi::PhysicalPosition::new(
+                            size.width as f64 / 2.0,
+                            size.height as f64 / 2.0,
+                        );
+
+                        input_manager.cursor_input((position.x, position.y), (center.x, center.y));
+
+                        if let Err(e) = context.window().set_cursor_position(center) {
+                            log::warn!("unable to center the cursor: {}", e);
+                        }
+                    },
+
+                    WindowEvent::CursorMoved { position, .. } => {
+                        let viewport = context.viewport();
+                        let position = position.to_logical::<f32>(viewport.scale_factor);
+                        cursor = Point2::new(position.x, position.y);
+
+                        // The held stack follows the cursor.
+                        if menu.state() == GameState::Inventory {
+                            show_inventory(&mut r, &inventory_screen, &inventory, cursor);
+                        } else if !menu.is_playing() && menu.hover(cursor, logical_size(viewport)) {
+                            show_menu(&mut r, &menu);
+                        }
+                    },
+
+                    WindowEvent::ReceivedCharacter(c) => input_manager.received_character(c),
+
+                    WindowEvent::Ime(ime) => input_manager.ime_input(ime),
+
+                    // The window has already resized its framebuffer.
+                    WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. } =>
+                        r.set_viewport(context.viewport()),
 
                     _ => {}
                 }
             },
 
             Event::MainEventsCleared => {
-                let mut new_speed = speed;
+                let frame = game_loop.begin_frame(input_manager.begin_frame(game_loop.delta()));
+                let delta = frame.delta;
+
+                if settings_watcher.poll(delta) {
+                    match SettingsFile::load(SETTINGS_PATH) {
+                        Ok(settings) => {
+                            let mut graphics = menu.graphics().clone();
+                            let mut mouse = menu.mouse().clone();
+                            if settings.graphics.msaa != graphics.msaa.samples() {
+                                log::info!("the msaa level takes effect after a restart");
+                            }
+                            if settings.graphics.render_path != graphics.render_path {
+                                log::info!("the render path takes effect after a restart");
+                            }
+                            settings.apply(&mut graphics, &mut mouse);
+                            menu.set_graphics(graphics);
+                            menu.set_mouse(mouse);
+                            menu.set_audio(settings.audio);
+
+                            apply_settings(&menu, &mut cam, &mut input_manager, &mut r, &mut streamer, &mut game_loop, context, &mut audio.borrow_mut());
+                            if !menu.is_playing() {
+                                show_menu(&mut r, &menu);
+                            }
+                            log::info!("reloaded the settings");
+                        },
+                        Err(e) => log::warn!("unable to reload the settings: {}", e),
+                    }
+                }
+
+                if bindings_watcher.poll(delta) {
+                    input_manager.set_bindings(KeyBindings::load_or_default(KEY_BINDINGS_PATH));
+                    menu.set_bindings(input_manager.bindings().clone());
+                    log::info!("reloaded the key bindings");
+                }
+
+                if menu.is_playing() {
+                    if input_manager.just_activated(Action::Pause) {
+                        ungrab_cursor(context);
+                        mouse_locked = false;
+                        menu.set_mouse(cam.mouse_settings().clone());
+                        menu.pause();
+                        show_menu(&mut r, &menu);
+                    } else if input_manager.just_activated(Action::Inventory) {
+                        ungrab_cursor(context);
+                        mouse_locked = false;
+                        menu.open_inventory();
+                        show_inventory(&mut r, &inventory_screen, &inventory, cursor);
+                    }
+                } else {
+                    // The key that opened the inventory closes it as well.
+                    if menu.state() == GameState::Inventory && input_manager.just_activated(Action::Inventory) {
+                        menu_event = menu.input(MenuInput::Back).or(menu_event);
+                    }
+
+                    let inputs = [
+                        (Action::MenuUp, MenuInput::Up),
+                        (Action::MenuDown, MenuInput::Down),
+                        (Action::MenuLeft, MenuInput::Left),
+                        (Action::MenuRight, MenuInput::Right),
+                        (Action::MenuSelect, MenuInput::Select),
+                        (Action::Pause, MenuInput::Back),
+                    ];
+
+                    // An action being rebound is bound to the next key or
+                    // button pressed, but not to the click or key that
+                    // started rebinding it. Pausing cancels it instead.
+                    let pressed = match rebind_armed && !input_manager.just_activated(Action::Pause) {
+                        true => input_manager.pressed_binding(),
+                        false => None,
+                    };
+
+                    match pressed {
+                        Some(binding) => {
+                            menu_event = menu.bind(binding).or(menu_event);
+                            show_menu(&mut r, &menu);
+                        },
+                        None => for &(action, input) in inputs.iter() {
+                            if input_manager.just_activated(action) {
+                                menu_event = menu.input(input).or(menu_event);
+                                show_menu(&mut r, &menu);
+                            }
+                        },
+                    }
+                }
+                rebind_armed = menu.rebinding().is_some();
+
+                // The level opened from the menu or joined on a server, and
+                // the folder it is saved in.
+                let mut opened: Option<(Option<String>, Level)> = None;
+
+                // The server is listened to whatever the menu is doing.
+                if let Some(connection) = remote.as_mut() {
+                    match connection.update(delta) {
+                        Ok(remote_events) => for event in remote_events {
+                            match event {
+                                // The level of an integrated server was opened
+                                // before it was joined.
+                                RemoteEvent::Joined(generator) => if integrated.is_none() {
+                                    opened = Some((None, Level::new(String::new(), generator)));
+                                },
+                                // Too much changed for the blocks to be changed
+                                // one by one.
+                                RemoteEvent::ChunkReplaced { at, blocks } => {
+                                    if world.chunk_mut(at).map_or(false, |chunk| chunk.replace_blocks(&blocks)) {
+                                        let mut changed_chunks = changed_chunks.borrow_mut();
+                                        if !changed_chunks.contains(&at) {
+                                            changed_chunks.push(at);
+                                        }
+                                    }
+                                },
+                                RemoteEvent::Rejected(reason) => {
+                                    log::error!("the server disconnected the player: {}", reason);
+                                    chat.push(None, &reason);
+                                    remote = None;
+                                    break
+                                },
+                                RemoteEvent::Chat { sender, text } => chat.push(sender.as_deref(), &text),
+                                RemoteEvent::Stats(stats) => {
+                                    menu.set_stats(stats.lines());
+                                    show_menu(&mut r, &menu);
+                                },
+                                RemoteEvent::Metrics { tps, mspt, parts } => {
+                                    for (name, millis) in parts {
+                                        log::debug!("server tick: {} took {:.3} ms", name, millis);
+                                    }
+                                    server_metrics = Some((tps, mspt));
+                                },
+                                // The local world follows the server, and the
+                                // chunks are remeshed like any other change.
+                                // Only solid blocks coming or going are heard.
+                                RemoteEvent::BlockChanged { at, block } => {
+                                    match world.set_block(at, block.clone()) {
+                                        Some(old) if old != block && (old.is_solid() || block.is_solid()) => {
+                                            let interaction = match block.is_air() {
+                                                true => Interaction::Break,
+                                                false => Interaction::Place,
+                                            };
+                                            events.publish(WorldEvent::PlayerInteracted { at, interaction, old, new: block });
+                                        },
+                                        _ => {},
+                                    }
+                                },
+                            }
+                        },
+                        Err(e) => {
+                            log::error!("lost the connection to the server: {:?}", e);
+                            remote = None;
+                        },
+                    }
+                }
+
+                // A saved world dropped onto the world selection screen is
+                // copied into the saves and opened. A schematic dropped while
+                // playing alone is pasted where the player stands, by the
+                // server.
+                for file in context.take_dropped_files() {
+                    match file {
+                        DroppedFile::World(_) if menu.state() == GameState::SelectingWorld => match saves.import(file.path()) {
+                            Ok((imported, imported_level)) => {
+                                let index = match worlds.iter().position(|(folder, _)| *folder == imported) {
+                                    Some(index) => index,
+                                    None => {
+                                        worlds.push((imported, imported_level.name));
+                                        worlds.len() - 1
+                                    },
+                                };
+
+                                menu.select_world(worlds.iter().map(|(_, name)| name.clone()).collect());
+                                menu_event = Some(MenuEvent::LoadWorld(index));
+                            },
+                            Err(e) => log::warn!("unable to import the world {}: {:?}", file.path().display(), e),
+                        },
+                        DroppedFile::World(_) =>
+                            log::warn!("worlds can only be opened from the world selection screen"),
+                        DroppedFile::Schematic(_) => match (integrated.is_some(), remote.as_mut()) {
+                            (true, Some(connection)) => match console::import_schematic(file.path()) {
+                                Ok(schematic) => {
+                                    let command = format!("/schem paste {} {}", schematic, name);
+                                    if let Err(e) = connection.send_chat(&command) {
+                                        log::error!("lost the connection to the server: {:?}", e);
+                                        remote = None;
+                                    }
+                                },
+                                Err(e) => log::warn!("unable to import the schematic {}: {}", file.path().display(), e),
+                            },
+                            _ => log::warn!("schematics can only be pasted into worlds played alone"),
+                        },
+                        DroppedFile::Other(_) =>
+                            log::info!("ignoring dropped file {}", file.path().display()),
+                    }
+                }
+
+                let resumed = match menu_event {
+                    Some(MenuEvent::Resume) | Some(MenuEvent::LoadWorld(_)) | Some(MenuEvent::CreateWorld) => true,
+                    _ => false,
+                };
+                match menu_event.take() {
+                    Some(MenuEvent::Resume) => {
+                        // Whatever is still held when the inventory closes is
+                        // dropped in front of the player.
+                        if let Some(left) = inventory_screen.close(&mut inventory) {
+                            let at = cam.eye().map(|v| v / BLOCK_LENGTH) + cam.front() * ITEM_THROW_DISTANCE;
+                            spawn_item_drop(&mut entities, at, left, cam.front() * ITEM_THROW_SPEED);
+                        }
+
+                        grab_cursor(context, input_manager.raw_mouse());
+                        mouse_locked = true;
+                        input_manager.unsuspend_input();
+                        show_menu(&mut r, &menu);
+                    },
+
+                    Some(MenuEvent::Quit) => {
+                        if let Err(e) = input_manager.stop_recording() {
+                            log::error!("unable to save the input recording: {}", e);
+                        }
+
+                        // The server saves the level as well, so it has to
+                        // be done with it first.
+                        if let Some(server) = integrated.take() {
+                            server.stop();
+                        }
+
+                        inventory_screen.close(&mut inventory);
+                        level.player = Some(player_state(&player, &cam, &inventory, selected_block));
+                        save_level(&saves, folder.as_deref(), &level);
+                        *cl = ControlFlow::Exit;
+                    },
+
+                    Some(event @ MenuEvent::LoadWorld(_)) | Some(event @ MenuEvent::CreateWorld) => {
+                        let loaded = match event {
+                            MenuEvent::LoadWorld(index) => {
+                                let folder = worlds[index].0.clone();
+                                saves.load(&folder).map(|level| (folder, level))
+                            },
+                            _ => {
+                                let name = format!("World {}", worlds.len() + 1);
+                                saves.create(&name, WorldGenerator::new(rand::random(), menu.preset()))
+                            },
+                        };
+
+                        let started = loaded.and_then(|(loaded_folder, loaded_level)| {
+                            let (server, connection) = IntegratedServer::start(saves.clone(), &loaded_folder, &name)?;
+                            Ok((loaded_folder, loaded_level, server, connection))
+                        });
+
+                        match started {
+                            Ok((loaded_folder, loaded_level, server, connection)) => match RemoteWorld::local(connection, &name) {
+                                Ok(connection) => {
+                                    remote = Some(connection);
+                                    integrated = Some(server);
+                                    opened = Some((Some(loaded_folder), loaded_level));
+                                },
+                                Err(e) => log::error!("unable to join the world: {:?}", e),
+                            },
+                            Err(e) => log::error!("unable to open the world: {:?}", e),
+                        }
+                    },
+
+                    // Only the server knows what the player did.
+                    Some(MenuEvent::StatisticsOpened) => {
+                        let requested = match remote.as_mut() {
+                            Some(connection) => connection.request_stats().unwrap_or_else(|e| {
+                                log::error!("unable to ask for the statistics: {:?}", e);
+                                false
+                            }),
+                            None => false,
+                        };
+
+                        if !requested {
+                            menu.set_stats(vec!["The server keeps no statistics".to_string()]);
+                        }
+                        show_menu(&mut r, &menu);
+                    },
+
+                    Some(MenuEvent::BindingsChanged) => {
+                        input_manager.set_bindings(menu.bindings().clone());
+
+                        if let Err(e) = menu.bindings().save(KEY_BINDINGS_PATH) {
+                            log::warn!("{}", e);
+                        }
+                        bindings_watcher.mark_seen();
+                    },
+
+                    Some(MenuEvent::SettingsChanged) => {
+                        apply_settings(&menu, &mut cam, &mut input_manager, &mut r, &mut streamer, &mut game_loop, context, &mut audio.borrow_mut());
+
+                        let settings = SettingsFile::new(menu.graphics(), menu.mouse(), menu.audio());
+                        if let Err(e) = settings.save(SETTINGS_PATH) {
+                            log::warn!("unable to save the settings: {}", e);
+                        }
+                        // The game wrote it, so there is nothing to reload.
+                        settings_watcher.mark_seen();
+                    },
+
+                    None => {},
+                }
+
+                if let Some((opened_folder, opened_level)) = opened {
+                    level = opened_level;
+                    new_player = enter_level(&mut level, &mut player, &mut cam, &mut inventory, &mut selected_block);
+                    streamer.set_generator(level.generator);
+                    world.set_border(level.generator.border());
+                    r.set_world_border(Some(level.generator.border().radius() as f32 * BLOCK_LENGTH));
+                    folder = opened_folder;
+                    context.set_title(&format!("{} - {}", WINDOW_TITLE, level.name));
+
+                    menu.resume();
+                    grab_cursor(context, input_manager.raw_mouse());
+                    mouse_locked = true;
+                    input_manager.unsuspend_input();
+                    show_menu(&mut r, &menu);
+                }
+
+                // The world stands still behind the menu. The frame the game
+                // resumes in is skipped as well, so that the click or key that
+                // resumed it does nothing else.
+                if !menu.is_playing() || resumed {
+                    input_manager.next_frame();
+                    context.window().request_redraw();
+                    return
+                }
+
+                let (_, scroll) = input_manager.get_scroll_delta();
+                fly_speed = (fly_speed * SPEED_SCROLL_FACTOR.powf(scroll as f32))
+                    .max(MIN_FLY_SPEED)
+                    .min(MAX_FLY_SPEED);
+
+                if input_manager.just_activated(Action::ToggleFly) {
+                    let flying = !player.flying();
+                    player.set_flying(flying);
+                }
+
+                if input_manager.just_activated(Action::ToggleCollision) {
+                    let noclip = !player.noclip();
+                    player.set_noclip(noclip);
+                }
+
+                let mut new_speed = match player.flying() {
+                    true => fly_speed,
+                    false => WALKING_SPEED,
+                };
                 let sensitivity = cam.sensitivity();
-                let up = Vector3::<f32>::new(0.0, 1.0, 0.0);
 
-                cam.move_camera(pos);
+                let feet = BlockPos(player.position().map(|v| (v / BLOCK_LENGTH).floor() as i32));
+                for position in streamer.update(ChunkPos::from(feet)) {
+                    world.remove_chunk(position);
+                    r.remove_chunk(position);
+                    minimap.remove_chunk(position);
+
+                    if let Some(connection) = remote.as_mut() {
+                        if let Err(e) = connection.unload_chunk(position) {
+                            log::error!("lost the connection to the server: {:?}", e);
+                            remote = None;
+                        }
+                    }
+
+                    if let Some(map) = map.as_mut() {
+                        map.window.make_current();
+                        map.renderer.remove_chunk(position);
+                        context.make_current();
+                    }
+                }
 
-                if let Ok((x, y , z, mesh)) = rx.recv() {
+                // Uploading is left to the renderer, which spreads it over
+                // several frames when many chunks arrive at once.
+                for (chunk, mesh) in streamer.poll() {
+                    let pos = chunk.position();
                     println!(
                         "chunk at ({}, {}, {}) has {} vertices and {} indices",
-                        x, y, z,
-                        mesh.vertices().len(),
-                        mesh.indices().len(),
+                        pos.x, pos.y, pos.z,
+                        mesh.opaque.vertices().len() + mesh.translucent.vertices().len(),
+                        mesh.opaque.indices().len() + mesh.translucent.indices().len(),
                     );
-                    r.render_mesh(mesh);
-                }
-
-                // Prioritise modifiers like LShift.
-                for key in input_manager.iterate_held_keys() {
-                    match key {
-                        &Key::LShift => new_speed *= 2.0,
-                        &Key::LControl => new_speed *= 0.2,
-                        _ => {}
-                    }
-                }
-
-                for key in input_manager.iterate_held_keys() {
-                    match key {
-                        &Key::W => pos += new_speed * delta * cam.front(),
-                        &Key::S => pos -= new_speed * delta * cam.front(),
-                        //&Key::W => pos += maths::Matrix3::rotate_y_axis(maths::Deg(-90.0)) * (new_speed * delta * cam.front().cross(up).normalize()),
-                        //&Key::S => pos -= maths::Matrix3::rotate_y_axis(maths::Deg(-90.0)) * (new_speed * delta * cam.front().cross(up).normalize()),
-                        &Key::A => pos -= new_speed * delta * cam.front().cross(up).normalize(),
-                        &Key::D => pos += new_speed * delta * cam.front().cross(up).normalize(),
-
-                        &Key::Escape => {
-                            context.window()
-                                .set_cursor_grab(CursorGrabMode::None)
-                                .expect("unable to ungrab cursor");
-                            context.window()
-                                .set_cursor_visible(true);
-                            mouse_locked = false;
+                    if let Some(map) = map.as_mut() {
+                        map.renderer.queue_chunk(pos, mesh.clone());
+                    }
+                    r.queue_chunk(pos, mesh);
+                    world.insert_chunk(chunk);
+                    minimap.update_chunk(&world, pos);
+
+                    // The server only tells about changes to the chunks the
+                    // client has.
+                    if let Some(connection) = remote.as_mut() {
+                        if let Err(e) = connection.load_chunk(pos) {
+                            log::error!("lost the connection to the server: {:?}", e);
+                            remote = None;
+                        }
+                    }
+                }
+
+                r.particles_mut().update(delta);
+
+                if input_manager.is_action_held(Action::Walk) {
+                    new_speed *= 0.2;
+                }
+
+                let held = |action| match input_manager.is_action_held(action) {
+                    true => 1.0,
+                    false => 0.0,
+                };
+
+                let stick = input_manager.movement_stick();
+                let movement = MovementInput {
+                    direction: Vector2::new(
+                        held(Action::MoveRight) - held(Action::MoveLeft),
+                        held(Action::MoveForward) - held(Action::MoveBackward),
+                    ) + stick,
+                    speed: new_speed / BLOCK_LENGTH,
+                    jump: input_manager.is_action_held(Action::Jump),
+                    sprint: input_manager.is_action_held(Action::Sprint),
+                    crouch: input_manager.is_action_held(Action::Crouch),
+                    vertical: held(Action::FlyUp) - held(Action::FlyDown),
+                };
+
+                // The player moves in steps of the same length, however fast
+                // the frames are, and is drawn between the last two.
+                for _ in 0..frame.movement_steps {
+                    player.begin_step();
+
+                    match remote.as_mut() {
+                        Some(connection) => if let Err(e) = connection.move_player(&mut player, &movement, cam.front(), MOVEMENT_STEP, &world) {
+                            log::error!("lost the connection to the server: {:?}", e);
+                            remote = None;
                         },
-                        _ => {}
+                        None => player.update(&movement, cam.front(), MOVEMENT_STEP, &world),
                     }
                 }
 
-                if input_manager.is_key_pressed(Key::Equals) {
+                // The spawn point is picked once the ground around the origin
+                // has been generated, and a new player is moved onto it.
+                if level.spawn.is_none() {
+                    level.spawn = world.find_spawn(0, 0, SPAWN_SEARCH_RADIUS);
+
+                    if level.spawn.is_some() && new_player {
+                        respawn(&mut player, &mut cam, level.spawn);
+                    }
+                }
+
+                if player.position().y < VOID_HEIGHT * BLOCK_LENGTH {
+                    respawn(&mut player, &mut cam, level.spawn);
+                }
+
+                if let Some(ground) = player.take_footstep() {
+                    audio.borrow_mut().play(Sound {
+                        kind: SoundKind::Footstep,
+                        material: ground.sound_material(),
+                        position: player.position(),
+                    });
+                }
+
+                // The world and its entities run at a fixed rate, however fast
+                // the frames are. Items are picked up around the middle of the
+                // body.
+                let reach_center = player.position().map(|v| v / BLOCK_LENGTH) + Vector3::unit_y() * PICKUP_HEIGHT;
+                for _ in 0..frame.ticks {
+                    world.tick();
+
+                    // On a server, the entities are run by the server.
+                    if remote.is_none() {
+                        pickup_items(&mut entities, reach_center, &mut inventory, TICK_LENGTH);
+                        schedule.run(&mut entities, &world, TICK_LENGTH);
+                    }
+                }
+
+                let instances = match remote.as_ref() {
+                    Some(connection) => connection.entity_instances(),
+                    None => entity_instances(&entities, game_loop.tick_alpha()),
+                };
+                r.set_entities(&instances);
+                cam.move_camera(player.interpolated_eye(game_loop.movement_alpha()));
+                audio.borrow_mut().set_listener(cam.eye());
+
+                let eye_block = BlockPos(cam.eye().map(|v| (v / BLOCK_LENGTH).floor() as i32));
+                let underwater = world.get_block(eye_block)
+                    .map(|block| block.is_fluid())
+                    .unwrap_or(false);
+                r.set_underwater(underwater);
+                r.set_point_lights(block_lights(&world, eye_block));
+
+                rain.update(&world, cam.eye(), delta, r.particles_mut());
+                r.set_rain(rain.intensity());
+
+                let mut fov_modifier = 1.0;
+
+                if player.sprinting() {
+                    fov_modifier *= SPRINT_FOV_MODIFIER;
+                }
+
+                if input_manager.is_action_held(Action::Zoom) {
+                    fov_modifier *= ZOOM_FOV_MODIFIER;
+                }
+
+                cam.set_fov_modifier(fov_modifier);
+
+                if input_manager.is_action_held(Action::ReleaseCursor) {
+                    ungrab_cursor(context);
+                    mouse_locked = false;
+                }
+
+                if input_manager.just_activated(Action::IncreaseSensitivity) {
                     cam.set_sensitivity(sensitivity + 0.05)
                 }
 
-                if input_manager.is_key_pressed(Key::Minus) {
+                if input_manager.just_activated(Action::DecreaseSensitivity) {
                     cam.set_sensitivity(sensitivity - 0.05)
                 }
 
-                if input_manager.is_key_pressed(Key::E) {
-                    println!("{:?}", pos * 4.0);
+                if input_manager.just_activated(Action::ToggleCameraMode) {
+                    cam.toggle_mode();
+                }
+
+                if input_manager.just_activated(Action::ToggleMap) {
+                    match cam.mode() {
+                        CameraMode::TopDown => cam.set_mode(CameraMode::FirstPerson),
+                        _ => cam.set_mode(CameraMode::TopDown),
+                    }
+                }
+
+                if input_manager.just_activated(Action::MapZoomIn) {
+                    cam.set_map_view(cam.map_height(), cam.map_scale() * 0.5);
+                }
+
+                if input_manager.just_activated(Action::MapZoomOut) {
+                    cam.set_map_view(cam.map_height(), cam.map_scale() * 2.0);
+                }
+
+                if input_manager.just_activated(Action::MinimapZoomIn) {
+                    minimap.zoom_in();
+                }
+
+                if input_manager.just_activated(Action::MinimapZoomOut) {
+                    minimap.zoom_out();
+                }
+
+                let feet = BlockPos(player.position().map(|v| (v / BLOCK_LENGTH).floor() as i32));
+                if let Some(view) = minimap.view(feet, cam.yaw().0) {
+                    r.hud_mut().set_element(HudSlot::Minimap, Box::new(view));
+                }
+
+                chat.update(delta);
+                if let Some(view) = chat.view(input_manager.text_input()) {
+                    r.hud_mut().set_element(HudSlot::Chat, Box::new(view));
+                }
+
+                if input_manager.just_activated(Action::RecordKeyframe) {
+                    path.record(&cam);
+                }
+
+                if input_manager.just_activated(Action::PlayPath) {
+                    playback = match playback {
+                        Some(_) => None,
+                        None => path.play(PATH_SPEED),
+                    };
+                }
+
+                if input_manager.just_activated(Action::ClearPath) {
+                    path.clear();
+                    playback = None;
+                }
+
+                if input_manager.just_activated(Action::OpenChat) {
+                    input_manager.start_text_input();
+                    context.window().set_ime_allowed(true);
+                }
+
+                // On a server, everything typed is sent to it, commands
+                // included. Otherwise the commands are run by the game, and
+                // the rest is only shown.
+                for event in input_manager.take_text_events() {
+                    context.window().set_ime_allowed(false);
+
+                    let text = match event {
+                        TextInputEvent::Submitted(text) if !text.trim().is_empty() => text,
+                        _ => continue,
+                    };
+
+                    if let Some(connection) = remote.as_mut() {
+                        if let Err(e) = connection.send_chat(&text) {
+                            log::error!("lost the connection to the server: {:?}", e);
+                            remote = None;
+                        }
+                        continue
+                    }
+
+                    match Command::parse(&text) {
+                        Some(Ok(Command::SetSpawn)) => {
+                            let at = BlockPos(player.position().map(|v| (v / BLOCK_LENGTH).floor() as i32));
+                            level.spawn = Some(at);
+                            chat.push(None, &format!("spawn point set to {} {} {}", at.x, at.y, at.z));
+                        },
+                        Some(Ok(Command::GameMode(game_mode))) => {
+                            player.set_game_mode(game_mode);
+                            chat.push(None, &format!("game mode set to {}", game_mode.name()));
+                        },
+                        Some(Ok(Command::Block(block_name))) => match BlockRegistry::global().find(&block_name) {
+                            Some(definition) if definition.id != Block::AIR => {
+                                selected_block = definition.id;
+                                chat.push(None, &format!("placing {}", definition.name));
+                            },
+                            _ => chat.push(None, &format!("there is no block called {}", block_name)),
+                        },
+                        Some(Err(e)) => chat.push(None, &e.to_string()),
+                        None => chat.push(Some(&name), text.trim()),
+                    }
+                }
+
+                if input_manager.just_activated(Action::ToggleDebug) {
+                    show_debug = !show_debug;
+                }
+
+                if input_manager.just_activated(Action::ToggleFullscreen) {
+                    let viewport = context.set_display_mode(context.display_mode().next());
+                    r.set_viewport(viewport);
+                }
+
+                // Debugging aids, these are not rebindable.
+                if input_manager.is_key_just_pressed(Key::H) {
+                    cam.add_shake(0.05, 12.0, 0.6);
+                }
+
+                // Lists the input devices, by the names the bindings file
+                // ignores them by.
+                if input_manager.is_key_just_pressed(Key::F9) {
+                    for (id, device) in input_manager.devices() {
+                        log::info!("{:?}: {:?}", id, device);
+                    }
+                }
+
+                if input_manager.is_key_just_pressed(Key::E) {
+                    println!("{:?}", player.position().map(|v| v / BLOCK_LENGTH));
                 }
 
                 if !mouse_locked {
-                    input_manager.suspend_input();
+                    input_manager.suspend_input(SuspendMode::Release);
                 }
 
                 let (delta_x, delta_y) = input_manager.get_mouse_delta(); {
                     cam.rotate_by_mouse(delta_x as f32, delta_y as f32, delta);
                 };
 
+                let look = input_manager.look_stick() * delta;
+                cam.rotate(Rad(look.x), Rad(look.y));
+
+                if let Some(p) = playback.as_mut() {
+                    p.update(delta);
+                    p.apply(&mut cam);
+                    player.set_eye(cam.position());
+
+                    if p.is_finished() {
+                        playback = None;
+                    }
+                }
+
+                cam.update(delta);
+                r.set_fov(cam.fov());
+
+                // The player can only reach blocks while the cursor is grabbed.
+                let eye = cam.eye().map(|v| v / BLOCK_LENGTH);
+                let hit = match mouse_locked {
+                    true => Ray::new(eye, cam.front()).and_then(|ray| world.raycast(&ray, REACH)),
+                    false => None,
+                };
+                r.set_targeted_block(hit.map(|h| h.block));
+
+                if hit.is_none() {
+                    breaking.reset();
+                }
+
+                if let Some(hit) = hit {
+                    // Unless blocks break at once, the same block has to be
+                    // held down for as long as it is hard, and letting go
+                    // starts over.
+                    let target = world.get_block(hit.block).cloned();
+                    let broken = match (player.game_mode().breaks_instantly(), target) {
+                        (true, _) => input_manager.just_activated(Action::Break),
+                        (false, Some(block)) if input_manager.is_action_held(Action::Break) =>
+                            breaking.hit(hit.block, &block, delta),
+                        (false, _) => {
+                            breaking.reset();
+                            false
+                        },
+                    };
+
+                    if broken {
+                        let air = Block::new(Block::AIR);
+
+                        // On a server, the block only breaks once the server
+                        // says so.
+                        match remote.as_mut() {
+                            Some(connection) => if let Err(e) = connection.break_block(hit.block) {
+                                log::error!("lost the connection to the server: {:?}", e);
+                                remote = None;
+                            },
+                            None => if let Some(old) = world.set_block(hit.block, air.clone()) {
+                                if player.game_mode().uses_items() {
+                                    let center = hit.block.0.map(|v| v as f32 + 0.5);
+                                    let stack = ItemStack::new(old.id, 1);
+                                    spawn_item_drop(&mut entities, center, stack, DROP_VELOCITY);
+                                }
+
+                                events.publish(WorldEvent::PlayerInteracted {
+                                    at: hit.block,
+                                    interaction: Interaction::Break,
+                                    old,
+                                    new: air,
+                                });
+                            },
+                        }
+                    } else if input_manager.just_activated(Action::Place) {
+                        // Blocks only replace what can be moved through, and
+                        // never trap the camera.
+                        let target = hit.face.neighbour(hit.block);
+                        let free = world.get_block(target)
+                            .map(|b| !b.is_solid())
+                            .unwrap_or(false);
+                        let uses_items = player.game_mode().uses_items();
+                        let carried = !uses_items || inventory.count(selected_block) > 0;
+
+                        let new = Block::new(selected_block);
+
+                        if free && carried && !cam.touches_block(target) {
+                            match remote.as_mut() {
+                                Some(connection) => match connection.place_block(target, new) {
+                                    Ok(()) => if uses_items {
+                                        inventory.remove(selected_block, 1);
+                                    },
+                                    Err(e) => {
+                                        log::error!("lost the connection to the server: {:?}", e);
+                                        remote = None;
+                                    },
+                                },
+                                None => if let Some(old) = world.set_block(target, new.clone()) {
+                                    if uses_items {
+                                        inventory.remove(selected_block, 1);
+                                    }
+
+                                    events.publish(WorldEvent::PlayerInteracted {
+                                        at: target,
+                                        interaction: Interaction::Place,
+                                        old,
+                                        new,
+                                    });
+                                },
+                            }
+                        }
+                    }
+
+                    if input_manager.just_activated(Action::PickBlock) {
+                        if let Some(block) = world.get_block(hit.block).cloned() {
+                            selected_block = block.id;
+
+                            events.publish(WorldEvent::PlayerInteracted {
+                                at: hit.block,
+                                interaction: Interaction::Pick,
+                                old: block.clone(),
+                                new: block,
+                            });
+                        }
+                    }
+                }
+
+                // Blocks changed by the player and by the world itself are
+                // remeshed alike, see the listeners above.
+                events.dispatch(&mut world);
+
+                for at in changed_chunks.borrow_mut().drain(..) {
+                    minimap.update_chunk(&world, at);
+
+                    if let Some(mesh) = remesh_chunk(&world, at) {
+                        if let Some(map) = map.as_mut() {
+                            map.renderer.queue_chunk(at, mesh.clone());
+                        }
+                        r.add_chunk(at, mesh);
+                    }
+                }
+
+                r.set_breaking_block(breaking.progress());
+
+                if show_debug {
+                    draw_debug_shapes(&cam, &path);
+                }
+                r.set_orthographic(cam.orthographic_scale());
+
+                if let Some(map) = map.as_mut() {
+                    map.camera.snap_to(cam.position(), cam.yaw(), Rad(0.0), Rad(0.0));
+                    map.window.window().request_redraw();
+                }
+
+                input_manager.next_frame();
                 context.window().request_redraw();
             },
 
@@ -173,15 +1183,40 @@ fn main() {
                 input_manager.update_inputs(device_id, event);
             }
 
-            Event::RedrawRequested(_id) => {
-                r.render(time, cam.generate_view());
+            Event::RedrawRequested(id) if Some(id) == map_id => {
+                if let Some(map) = map.as_mut() {
+                    map.render(time);
+                    context.make_current();
+                }
+            },
+
+            Event::RedrawRequested(id) if id == context.id() => {
+                // The third-person camera is pulled in front of the terrain
+                // behind the player.
+                r.render(time, cam.generate_view_with(|origin, direction, max_distance| {
+                    terrain_distance(&world, origin, direction, max_distance)
+                }));
 
                 time += 1.0;
-                std::thread::sleep(std::time::Duration::from_micros(4167/*16667*/));
                 context.swap_buffers().unwrap();
-                let now = Instant::now();
-                delta = (now - last_time).as_secs_f32();
-                last_time = now;
+                game_loop.end_frame();
+
+                fps_frames += 1;
+                fps_time += game_loop.delta();
+                if fps_time >= 1.0 {
+                    let fps = fps_frames as f32 / fps_time;
+                    match (menu.is_playing(), remote.is_some(), server_metrics) {
+                        // The world stands still behind the menu, so there is
+                        // nothing worth measuring.
+                        (false, _, _) => context.clear_status(),
+                        (true, true, Some((tps, mspt))) => context.set_status(
+                            &format!("{:.0} fps, server {:.1} tps {:.1} mspt", fps, tps, mspt)
+                        ),
+                        _ => context.set_status(&format!("{:.0} fps", fps)),
+                    }
+                    fps_frames = 0;
+                    fps_time = 0.0;
+                }
             },
 
             _ => {
@@ -190,3 +1225,423 @@ fn main() {
         };
     });
 }
+
+// Not every platform can lock the cursor in place, confining it works
+// everywhere.
+fn grab_cursor(context: &WindowContext, raw_mouse: bool) {
+    use winit::window::CursorGrabMode;
+
+    let window = context.window();
+    let grab = match raw_mouse {
+        true => CursorGrabMode::Locked,
+        false => CursorGrabMode::Confined,
+    };
+
+    window.set_cursor_grab(grab)
+        .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+        .expect("unable to grab cursor");
+    window.set_cursor_visible(false);
+}
+
+fn ungrab_cursor(context: &WindowContext) {
+    use winit::window::CursorGrabMode;
+
+    context.window()
+        .set_cursor_grab(CursorGrabMode::None)
+        .expect("unable to ungrab cursor");
+    context.window()
+        .set_cursor_visible(true);
+}
+
+// Puts the menu onto the HUD while it is open, and takes it off otherwise.
+// The inventory screen takes the place of the menu, see show_inventory().
+// Exports the terrain of a saved world, see `--export-gltf`.
+fn export_world(saves: &WorldManager, folder: &str, output: &str, radius: i32) {
+    let level = match saves.load(folder) {
+        Ok(level) => level,
+        Err(e) => return log::error!("unable to open the world {}: {:?}", folder, e),
+    };
+
+    let center = ChunkPos::from(level.spawn.unwrap_or(BlockPos::new(0, 0, 0)));
+    let scene = export::GltfScene::from_level(&level, center, radius);
+
+    match scene.save(output) {
+        Ok(()) => log::info!("exported {} chunks of {} to {}", (radius * 2 + 1).pow(2), level.name, output),
+        Err(e) => log::error!("unable to export the world: {}", e),
+    }
+}
+
+// Makes the game run with the settings shown in the menu. The MSAA level and
+// the render path are left as they are, as they cannot be changed without a
+// new renderer.
+fn apply_settings(
+    menu: &Menu,
+    cam: &mut Camera,
+    input_manager: &mut InputManager,
+    r: &mut GlRenderer,
+    streamer: &mut ChunkStreamer,
+    game_loop: &mut GameLoop,
+    context: &WindowContext,
+    audio: &mut Audio,
+) {
+    let graphics = menu.graphics();
+    cam.set_fov(Deg(graphics.fov));
+    cam.set_mouse_settings(menu.mouse().clone());
+    // The position already follows the player smoothly, so only turning the
+    // camera is smoothed.
+    cam.set_smoothing(0.0, menu.mouse().smoothing);
+    input_manager.set_raw_mouse(menu.mouse().raw_input);
+    r.set_view_distance(graphics.view_distance);
+    r.set_gamma(graphics.gamma);
+    if let Some(effects) = r.post_effects_mut() {
+        *effects = graphics.post_effects.clone();
+    }
+    streamer.set_radius(graphics.view_distance);
+    game_loop.set_limit(graphics.frame_limit);
+    context.set_vsync(graphics.effective_vsync());
+    audio.set_volumes(menu.audio());
+}
+
+fn show_menu(r: &mut GlRenderer, menu: &Menu) {
+    match menu.state() {
+        GameState::Playing => r.hud_mut().clear_element(HudSlot::Menu),
+        GameState::Inventory => {},
+        _ => r.hud_mut().set_element(HudSlot::Menu, Box::new(menu.view())),
+    }
+}
+
+fn show_inventory(r: &mut GlRenderer, screen: &InventoryScreen, inventory: &Inventory, cursor: Point2<f32>) {
+    r.hud_mut().set_element(HudSlot::Menu, Box::new(screen.view(inventory, cursor)));
+}
+
+// What is kept of the player in the level, with the position in blocks.
+fn player_state(player: &PlayerController, cam: &Camera, inventory: &Inventory, selected_block: u16) -> PlayerState {
+    PlayerState {
+        position: player.position().map(|v| v / BLOCK_LENGTH),
+        yaw: cam.yaw().0,
+        pitch: cam.pitch().0,
+        flying: player.flying(),
+        game_mode: player.game_mode(),
+        selected_block,
+        inventory: inventory.clone(),
+    }
+}
+
+// The folders of the saved worlds, and the names shown for them. Worlds that
+// cannot be read are left out.
+fn saved_worlds(saves: &WorldManager) -> Vec<(String, String)> {
+    let folders = match saves.list() {
+        Ok(folders) => folders,
+        Err(e) => {
+            log::error!("unable to list the worlds in {}: {:?}", saves.root().display(), e);
+            return Vec::new()
+        },
+    };
+
+    folders
+        .into_iter()
+        .filter_map(|folder| match saves.load(&folder) {
+            Ok(level) => Some((folder, level.name)),
+            Err(e) => {
+                log::warn!("unable to read the world {}: {:?}", folder, e);
+                None
+            },
+        })
+        .collect()
+}
+
+// Saves the level into the folder of the open world. Nothing is saved before
+// a world has been opened.
+fn save_level(saves: &WorldManager, folder: Option<&str>, level: &Level) {
+    if let Some(folder) = folder {
+        if let Err(e) = saves.save(folder, level) {
+            log::error!("unable to save the world {}: {:?}", folder, e);
+        }
+    }
+}
+
+// Puts the player where it was when it last left the level, or onto the spawn
+// point if it has never been in it. Returns whether the player is new.
+fn enter_level(
+    level: &mut Level,
+    player: &mut PlayerController,
+    cam: &mut Camera,
+    inventory: &mut Inventory,
+    selected_block: &mut u16,
+) -> bool {
+    match level.player.take() {
+        Some(state) => {
+            player.teleport(state.position * BLOCK_LENGTH);
+            player.set_game_mode(state.game_mode);
+            player.set_flying(state.flying);
+            cam.snap_to(player.eye(), Rad(state.yaw), Rad(state.pitch), Rad(0.0));
+            *inventory = state.inventory;
+            *selected_block = state.selected_block;
+            false
+        },
+        None => {
+            player.set_game_mode(GameMode::default());
+            respawn(player, cam, level.spawn);
+            true
+        },
+    }
+}
+
+// Where the feet of a (re)spawning player go, in world units: the middle of
+// the spawn point, or the top of the world above the origin if it has not
+// been picked yet.
+fn spawn_position(spawn: Option<BlockPos>) -> Point3<f32> {
+    let at = match spawn {
+        Some(at) => Point3::new(at.x as f32 + 0.5, at.y as f32, at.z as f32 + 0.5),
+        None => Point3::new(0.0, CHUNK_LENGTH_Y as f32, 0.0),
+    };
+
+    at * BLOCK_LENGTH
+}
+
+// Puts the player back at the spawn point, and the camera with it.
+fn respawn(player: &mut PlayerController, cam: &mut Camera, spawn: Option<BlockPos>) {
+    player.teleport(spawn_position(spawn));
+    cam.snap_to(player.eye(), cam.yaw(), cam.pitch(), cam.roll());
+}
+
+// How far (in world units) the ray from `origin` goes before it hits a solid
+// block, if it does so within `max_distance`.
+fn terrain_distance(world: &World, origin: Point3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<f32> {
+    Ray::new(origin.map(|v| v / BLOCK_LENGTH), direction)
+        .and_then(|ray| world.raycast(&ray, max_distance / BLOCK_LENGTH))
+        .map(|hit| hit.distance * BLOCK_LENGTH)
+}
+
+// The blocks around the camera that give off light, nearest first, as lights
+// of their color that reach as far as they shine.
+fn block_lights(world: &World, center: BlockPos) -> Vec<PointLight> {
+    let r = LIGHT_SEARCH_RADIUS;
+    let mut lights = Vec::new();
+
+    for x in -r..=r {
+        for y in -r..=r {
+            for z in -r..=r {
+                let at = BlockPos(center.0 + Vector3::new(x, y, z));
+                let block = match world.get_block(at) {
+                    Some(block) if block.light_emission() > 0 => block,
+                    _ => continue,
+                };
+
+                let color = block.definition().color;
+                lights.push((x * x + y * y + z * z, PointLight {
+                    position: at.0.map(|v| (v as f32 + 0.5) * BLOCK_LENGTH),
+                    color: Vector3::new(color[0], color[1], color[2]),
+                    radius: block.light_emission() as f32 * BLOCK_LENGTH,
+                }));
+            }
+        }
+    }
+
+    lights.sort_by_key(|&(distance, _)| distance);
+    lights.into_iter().map(|(_, light)| light).collect()
+}
+
+// The boxes drawn for every entity that has a position, a size and a color.
+// Entities only move every tick, so they are moved ahead by their velocity
+// for the part of a tick (`alpha`) that has passed since.
+fn entity_instances(entities: &Entities, alpha: f32) -> Vec<EntityInstance> {
+    let positions = entities.storage::<Position>();
+    let velocities = entities.storage::<Velocity>();
+    let boxes = entities.storage::<Aabb>();
+    let drops = entities.storage::<ItemDrop>();
+
+    entities.storage::<Render>()
+        .iter()
+        .filter_map(|(entity, render)| {
+            let ahead = velocities.get(entity).map_or(Vector3::zero(), |v| v.0 * alpha * TICK_LENGTH);
+            let (min, max) = boxes.get(entity)?.bounds(positions.get(entity)?.0 + ahead);
+
+            // Dropped items are small copies of their block, spinning slowly.
+            let color = match render.shape {
+                RenderShape::Cube => render.color,
+                RenderShape::Block(id) => block_color(&Block::new(id)).mul_element_wise(render.color),
+            };
+            let yaw = drops.get(entity).map_or(0.0, |d| d.age() * ITEM_SPIN_SPEED);
+
+            Some(EntityInstance {
+                min: min * BLOCK_LENGTH,
+                size: (max - min) * BLOCK_LENGTH,
+                color,
+                yaw,
+            })
+        })
+        .collect()
+}
+
+// The size of the viewport in logical pixels, which the HUD is laid out in.
+fn logical_size(viewport: Viewport) -> Vector2<f32> {
+    let scale = viewport.scale_factor as f32;
+    Vector2::new(viewport.width as f32 / scale, viewport.height as f32 / scale)
+}
+
+// Outlines the chunk the camera is in, and the recorded camera path.
+fn draw_debug_shapes(cam: &Camera, path: &CameraPath) {
+    let chunk_length = Vector3::new(
+        CHUNK_LENGTH_X as f32 * world::BLOCK_LENGTH,
+        CHUNK_LENGTH_Y as f32 * world::BLOCK_LENGTH,
+        CHUNK_LENGTH_Z as f32 * world::BLOCK_LENGTH,
+    );
+
+    let position = cam.position();
+    let min = Point3::new(
+        (position.x / chunk_length.x).floor() * chunk_length.x,
+        0.0,
+        (position.z / chunk_length.z).floor() * chunk_length.z,
+    );
+
+    let points = path.keyframes()
+        .iter()
+        .map(|k| k.position)
+        .collect::<Vec<_>>();
+
+    debug_draw()
+        .aabb(min, min + chunk_length, RGBA::new(1.0, 1.0, 0.0, 1.0))
+        .path(&points, RGBA::new(0.0, 1.0, 1.0, 1.0));
+}
+
+/// A second window showing the world from above, for debugging.
+struct MapWindow {
+    // Dropped before the window, whose context its objects belong to.
+    renderer: GlRenderer,
+    camera: Camera,
+    window: WindowContext,
+}
+
+impl MapWindow {
+    fn new(w: &Window, graphics: &GraphicsSettings, position: Point3<f32>) -> Self {
+        let config = WindowConfig::new()
+            .title("gecraftet map")
+            .size(MAP_WINDOW_SIZE, MAP_WINDOW_SIZE)
+            .resizable(false)
+            .vsync(false);
+        let window = w.create_secondary_window(config, true);
+
+        // The map is plain, so it skips the costlier parts of rendering.
+        let settings = GraphicsSettings {
+            msaa: Msaa::Off,
+            occlusion_culling: false,
+            post_processing: false,
+            ..graphics.clone()
+        };
+
+        window.make_current();
+        let mut renderer = GlRenderer::new(&window, &settings);
+        w.context().make_current();
+
+        let mut camera = Camera::new(position, Vector3::new(1.0, 0.0, 0.0));
+        camera.set_mode(CameraMode::TopDown);
+        camera.set_map_view(MAP_WINDOW_HEIGHT, MAP_WINDOW_SCALE);
+        renderer.set_orthographic(camera.orthographic_scale());
+
+        Self {
+            renderer,
+            camera,
+            window,
+        }
+    }
+
+    // Leaves the context of the map window current.
+    fn render(&mut self, time: f32) {
+        self.window.make_current();
+        self.renderer.render(time, self.camera.generate_view());
+
+        if let Err(e) = self.window.swap_buffers() {
+            log::error!("unable to present the map window: {}", e);
+        }
+    }
+}
+
+// The sound made by the player breaking or placing a block, in the middle of
+// the block.
+fn interaction_sound(event: &WorldEvent) -> Option<Sound> {
+    let (at, kind, block) = match event {
+        WorldEvent::PlayerInteracted { at, interaction: Interaction::Break, old, .. } =>
+            (at, SoundKind::Break, old),
+        WorldEvent::PlayerInteracted { at, interaction: Interaction::Place, new, .. } =>
+            (at, SoundKind::Place, new),
+        _ => return None,
+    };
+
+    Some(Sound {
+        kind,
+        material: block.sound_material(),
+        position: at.0.map(|v| (v as f32 + 0.5) * BLOCK_LENGTH),
+    })
+}
+
+// Meshes the chunk again after a block in it changed. Chunks are meshed on
+// their own, so the neighbouring chunks are not affected.
+fn remesh_chunk(world: &World, at: ChunkPos) -> Option<world::ChunkMesh> {
+    let chunk = world.chunk(at)?;
+    Some(world::GreedyCubeMesher::from_chunk(chunk).generate_mesh())
+}
+
+// How much the field of view widens while sprinting, and narrows while
+// zooming in.
+const SPRINT_FOV_MODIFIER: f32 = 1.15;
+const ZOOM_FOV_MODIFIER: f32 = 0.3;
+
+// How fast the player walks, in world units per second.
+const WALKING_SPEED: f32 = movement::WALKING_SPEED * BLOCK_LENGTH;
+
+// How much one line of scrolling changes the flying speed by, and the range it
+// is kept in.
+const SPEED_SCROLL_FACTOR: f32 = 1.1;
+const MIN_FLY_SPEED: f32 = 1.0;
+const MAX_FLY_SPEED: f32 = movement::MAX_FLYING_SPEED * BLOCK_LENGTH;
+
+// Where the settings, the key bindings and the blocks are loaded from, and
+// where the worlds are saved.
+const SETTINGS_PATH: &'static str = "settings.toml";
+const KEY_BINDINGS_PATH: &'static str = "keybindings.toml";
+const BLOCKS_PATH: &'static str = "blocks.ron";
+const SAVES_PATH: &'static str = "saves";
+
+// How many chunks around the spawn point (on either axis) are exported to
+// glTF, unless told otherwise.
+const EXPORT_RADIUS: i32 = 4;
+
+// The name the player logs in to servers with, unless another is given.
+const DEFAULT_PLAYER_NAME: &'static str = "player";
+
+// How fast recorded camera paths are played back, in world units per second.
+const PATH_SPEED: f32 = 4.0;
+
+// The size of the map window in logical pixels, and how high above the camera
+// it looks from and how much it shows, in world units.
+const MAP_WINDOW_SIZE: u32 = 512;
+const MAP_WINDOW_HEIGHT: f32 = 256.0;
+const MAP_WINDOW_SCALE: f32 = 128.0;
+
+// How far from the origin (in blocks) the spawn point may be, and how far
+// below the bottom of the world the player falls before respawning.
+const SPAWN_SEARCH_RADIUS: i32 = 32;
+const VOID_HEIGHT: f32 = -64.0;
+
+// How far away (in blocks) the player can break and place blocks.
+const REACH: f32 = 5.0;
+
+// How far from the camera (in blocks) blocks that give off light are looked
+// for every frame.
+const LIGHT_SEARCH_RADIUS: i32 = 8;
+
+// How many slots the inventory of the player has, and how high above the feet
+// (in blocks) items are pulled towards.
+const INVENTORY_SIZE: usize = 36;
+const PICKUP_HEIGHT: f32 = 0.9;
+
+// How items pop out of broken blocks (in blocks per second), and how fast they
+// spin on the ground (in radians per second).
+const DROP_VELOCITY: Vector3<f32> = Vector3 { x: 0.0, y: 4.0, z: 0.0 };
+const ITEM_SPIN_SPEED: f32 = 1.5;
+
+// How far in front of the eyes (in blocks) items are thrown from, and how fast
+// (in blocks per second).
+const ITEM_THROW_DISTANCE: f32 = 0.5;
+const ITEM_THROW_SPEED: f32 = 4.0;
@@ -0,0 +1,687 @@
+use cgmath::{ Point2, Vector2 };
+use gekraftet_core::world::WorldPreset;
+use crate::RGBA;
+use crate::input::{ Action, ActivationMode, Binding, KeyBindings };
+use crate::mesh::MeshBuilder;
+use crate::renderer::{ add_quad, add_text, text_height, text_width, HudElement };
+use crate::settings::{ AudioSettings, GraphicsSettings, MouseSettings, Msaa };
+use crate::windowing::FrameLimit;
+
+/// What the game is doing. The world only runs while playing, the other
+/// states show a menu on top of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    /// No world is open yet, one is picked or created first.
+    SelectingWorld,
+    Playing,
+    Paused,
+    Settings,
+    /// The graphics settings, reached through the settings screen.
+    Graphics,
+    /// The key bindings, reached through the settings screen.
+    Controls,
+    /// The statistics of the player, see `Menu::set_stats()`.
+    Statistics,
+    /// The inventory screen is open. It is not drawn by the menu, see
+    /// `InventoryScreen`.
+    Inventory,
+}
+
+/// The ways a menu is navigated, without pointing at it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+/// Something picked in the menu that the rest of the game has to act on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuEvent {
+    Resume,
+    Quit,
+    /// The settings were changed, see `Menu::graphics()`, `Menu::mouse()`
+    /// and `Menu::audio()`.
+    SettingsChanged,
+    /// The key bindings were changed, see `Menu::bindings()`.
+    BindingsChanged,
+    /// The world with the given index (see `Menu::select_world()`) was
+    /// picked.
+    LoadWorld(usize),
+    /// A new world should be created, with the preset `Menu::preset()`.
+    CreateWorld,
+    /// The statistics screen was opened, and waits for the statistics.
+    StatisticsOpened,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuItem {
+    Resume,
+    Settings,
+    Statistics,
+    Quit,
+    Fov,
+    ViewDistance,
+    Graphics,
+    Vsync,
+    FrameLimit,
+    Msaa,
+    Gamma,
+    Tonemap,
+    Bloom,
+    Vignette,
+    Sensitivity,
+    InvertMouse,
+    RawMouse,
+    Smoothing,
+    Volume,
+    Controls,
+    // Indices into REBINDABLE and TOGGLEABLE.
+    Bind(usize),
+    Mode(usize),
+    Done,
+    World(usize),
+    Preset,
+    NewWorld,
+    Stat(usize),
+}
+
+/// The pause menu, and the settings (along with the graphics and controls)
+/// and statistics screens reached through it, as well as
+/// the world selection screen shown before the game starts. The menu keeps
+/// its own copy of the settings it changes, which the game picks up whenever
+/// it reports `MenuEvent::SettingsChanged`, and likewise for the key bindings
+/// and `MenuEvent::BindingsChanged`.
+pub struct Menu {
+    state: GameState,
+    selected: usize,
+    graphics: GraphicsSettings,
+    mouse: MouseSettings,
+    audio: AudioSettings,
+    bindings: KeyBindings,
+    // The action waiting for a key or button to be bound to it.
+    rebinding: Option<Action>,
+    // The names of the worlds that can be picked, and the preset new worlds
+    // are created with.
+    worlds: Vec<String>,
+    preset: WorldPreset,
+    // The lines shown on the statistics screen.
+    stats: Vec<String>,
+}
+
+impl Menu {
+    pub fn new(graphics: GraphicsSettings, mouse: MouseSettings, audio: AudioSettings, bindings: KeyBindings) -> Self {
+        Self {
+            state: GameState::Playing,
+            selected: 0,
+            graphics,
+            mouse,
+            audio,
+            bindings,
+            rebinding: None,
+            worlds: Vec::new(),
+            preset: WorldPreset::Default,
+            stats: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == GameState::Playing
+    }
+
+    pub fn pause(&mut self) {
+        self.state = GameState::Paused;
+        self.selected = 0;
+    }
+
+    /// Opens the world selection screen, listing the worlds with the given
+    /// names.
+    pub fn select_world(&mut self, worlds: Vec<String>) {
+        self.worlds = worlds;
+        self.open(GameState::SelectingWorld);
+    }
+
+    pub fn preset(&self) -> WorldPreset {
+        self.preset
+    }
+
+    /// Shows the lines on the statistics screen. Only the first few fit.
+    pub fn set_stats(&mut self, lines: Vec<String>) {
+        self.stats = lines;
+        self.stats.truncate(MAX_STAT_ROWS);
+
+        if self.state == GameState::Statistics {
+            self.selected = self.items().len() - 1;
+        }
+    }
+
+    pub fn open_inventory(&mut self) {
+        self.state = GameState::Inventory;
+    }
+
+    pub fn resume(&mut self) {
+        self.state = GameState::Playing;
+    }
+
+    pub fn graphics(&self) -> &GraphicsSettings {
+        &self.graphics
+    }
+
+    pub fn mouse(&self) -> &MouseSettings {
+        &self.mouse
+    }
+
+    pub fn audio(&self) -> &AudioSettings {
+        &self.audio
+    }
+
+    /// Replaces the graphics settings shown in the menu, e.g. after the
+    /// settings file was changed.
+    pub fn set_graphics(&mut self, graphics: GraphicsSettings) {
+        self.graphics = graphics;
+    }
+
+    /// Replaces the mouse settings shown in the menu, e.g. after they were
+    /// changed in-game.
+    pub fn set_mouse(&mut self, mouse: MouseSettings) {
+        self.mouse = mouse;
+    }
+
+    pub fn set_audio(&mut self, audio: AudioSettings) {
+        self.audio = audio;
+    }
+
+    pub fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    /// Replaces the key bindings shown in the menu, e.g. after the bindings
+    /// file was changed.
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+    }
+
+    /// Returns the action waiting for the next key or button pressed to be
+    /// bound to it, see `bind()`.
+    pub fn rebinding(&self) -> Option<Action> {
+        self.rebinding
+    }
+
+    /// Binds the action being rebound to the key or button.
+    pub fn bind(&mut self, binding: Binding) -> Option<MenuEvent> {
+        let action = self.rebinding.take()?;
+        self.bindings.rebind(action, binding);
+        Some(MenuEvent::BindingsChanged)
+    }
+
+    pub fn input(&mut self, input: MenuInput) -> Option<MenuEvent> {
+        // The inventory screen has no items, it can only be left.
+        if self.state == GameState::Inventory {
+            return match input {
+                MenuInput::Back => self.activate(MenuItem::Resume),
+                _ => None,
+            }
+        }
+
+        // Rebinding waits for a key, and can only be cancelled.
+        if self.rebinding.is_some() {
+            if input == MenuInput::Back {
+                self.rebinding = None;
+            }
+            return None
+        }
+
+        let items = self.items();
+        let count = items.len();
+        let item = items[self.selected];
+
+        match input {
+            MenuInput::Up => {
+                self.selected = (self.selected + count - 1) % count;
+                None
+            },
+            MenuInput::Down => {
+                self.selected = (self.selected + 1) % count;
+                None
+            },
+            MenuInput::Left => self.adjust(item, -1),
+            MenuInput::Right => self.adjust(item, 1),
+            MenuInput::Select => self.activate(item),
+            MenuInput::Back => match self.state {
+                GameState::Graphics | GameState::Controls => self.open(GameState::Settings),
+                GameState::Settings | GameState::Statistics => self.open(GameState::Paused),
+                // There is nothing to go back to before a world is open.
+                GameState::SelectingWorld => None,
+                _ => self.activate(MenuItem::Resume),
+            },
+        }
+    }
+
+    /// Selects the item under the cursor, if there is one. Positions are in
+    /// logical pixels, and `screen` is the size of the window.
+    pub fn hover(&mut self, cursor: Point2<f32>, screen: Vector2<f32>) -> bool {
+        match self.item_at(cursor, screen) {
+            Some(index) if index != self.selected => {
+                self.selected = index;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Activates the item under the cursor. Clicking the left half of a
+    /// setting lowers it, clicking the right half raises it.
+    pub fn click(&mut self, cursor: Point2<f32>, screen: Vector2<f32>) -> Option<MenuEvent> {
+        if self.rebinding.is_some() {
+            return None
+        }
+
+        let index = self.item_at(cursor, screen)?;
+        self.selected = index;
+
+        let item = self.items()[index];
+        let center = Point2::new(screen.x * 0.5, screen.y * 0.5);
+
+        match self.value(item) {
+            Some(_) if cursor.x < center.x => self.adjust(item, -1),
+            Some(_) => self.adjust(item, 1),
+            None => self.activate(item),
+        }
+    }
+
+    /// What the menu looks like right now, to be put onto the HUD.
+    pub fn view(&self) -> MenuView {
+        let title = match self.state {
+            GameState::SelectingWorld => "Select world",
+            GameState::Settings => "Settings",
+            GameState::Graphics => "Graphics",
+            GameState::Controls => "Controls",
+            GameState::Statistics => "Statistics",
+            _ => "Paused",
+        };
+
+        MenuView {
+            title: title.to_string(),
+            labels: self.items().iter().map(|&item| self.label(item)).collect(),
+            selected: self.selected,
+        }
+    }
+
+    fn items(&self) -> Vec<MenuItem> {
+        match self.state {
+            GameState::SelectingWorld => (0..self.worlds.len())
+                .map(MenuItem::World)
+                .chain(vec![MenuItem::Preset, MenuItem::NewWorld, MenuItem::Quit])
+                .collect(),
+            GameState::Settings => vec![
+                MenuItem::Fov,
+                MenuItem::ViewDistance,
+                MenuItem::Sensitivity,
+                MenuItem::InvertMouse,
+                MenuItem::RawMouse,
+                MenuItem::Smoothing,
+                MenuItem::Volume,
+                MenuItem::Graphics,
+                MenuItem::Controls,
+                MenuItem::Done,
+            ],
+            GameState::Controls => (0..REBINDABLE.len())
+                .map(MenuItem::Bind)
+                .chain((0..TOGGLEABLE.len()).map(MenuItem::Mode))
+                .chain(std::iter::once(MenuItem::Done))
+                .collect(),
+            GameState::Graphics => vec![
+                MenuItem::Vsync,
+                MenuItem::FrameLimit,
+                MenuItem::Msaa,
+                MenuItem::Gamma,
+                MenuItem::Tonemap,
+                MenuItem::Bloom,
+                MenuItem::Vignette,
+                MenuItem::Done,
+            ],
+            GameState::Statistics => (0..self.stats.len())
+                .map(MenuItem::Stat)
+                .chain(std::iter::once(MenuItem::Done))
+                .collect(),
+            GameState::Inventory => Vec::new(),
+            _ => vec![MenuItem::Resume, MenuItem::Settings, MenuItem::Statistics, MenuItem::Quit],
+        }
+    }
+
+    fn item_at(&self, cursor: Point2<f32>, screen: Vector2<f32>) -> Option<usize> {
+        let anchor = Point2::new(screen.x * 0.5, screen.y * 0.5);
+        let count = self.items().len();
+
+        (0..count).find(|&i| {
+            let (min, max) = item_bounds(i, count, anchor);
+            cursor.x >= min.x && cursor.x < max.x && cursor.y >= min.y && cursor.y < max.y
+        })
+    }
+
+    fn open(&mut self, state: GameState) -> Option<MenuEvent> {
+        self.state = state;
+        self.selected = 0;
+        None
+    }
+
+    fn activate(&mut self, item: MenuItem) -> Option<MenuEvent> {
+        match item {
+            MenuItem::Resume => {
+                self.resume();
+                Some(MenuEvent::Resume)
+            },
+            MenuItem::Settings => self.open(GameState::Settings),
+            MenuItem::Statistics => {
+                self.stats = vec!["Loading...".to_string()];
+                self.open(GameState::Statistics);
+                Some(MenuEvent::StatisticsOpened)
+            },
+            MenuItem::Quit => Some(MenuEvent::Quit),
+            MenuItem::Graphics => self.open(GameState::Graphics),
+            MenuItem::Controls => self.open(GameState::Controls),
+            MenuItem::Bind(index) => {
+                self.rebinding = Some(REBINDABLE[index].0);
+                None
+            },
+            MenuItem::Done => match self.state {
+                GameState::Graphics | GameState::Controls => self.open(GameState::Settings),
+                _ => self.open(GameState::Paused),
+            },
+            MenuItem::World(index) => Some(MenuEvent::LoadWorld(index)),
+            MenuItem::NewWorld => Some(MenuEvent::CreateWorld),
+            // Toggles flip on select, like they do with left and right.
+            _ => self.adjust(item, 1),
+        }
+    }
+
+    // Changes a setting by a step, in the given direction.
+    fn adjust(&mut self, item: MenuItem, direction: i32) -> Option<MenuEvent> {
+        let step = direction as f32;
+
+        match item {
+            MenuItem::Fov => {
+                self.graphics.fov = (self.graphics.fov + step * FOV_STEP).max(MIN_FOV).min(MAX_FOV);
+            },
+            MenuItem::ViewDistance => {
+                let distance = self.graphics.view_distance as i32 + direction;
+                self.graphics.view_distance = distance.max(MIN_VIEW_DISTANCE).min(MAX_VIEW_DISTANCE) as u32;
+            },
+            MenuItem::Vsync => self.graphics.vsync = !self.graphics.vsync,
+            MenuItem::FrameLimit => {
+                let limits = FRAME_LIMITS.len() as i32;
+                let index = FRAME_LIMITS.iter().position(|&l| l == self.graphics.frame_limit).unwrap_or(0) as i32;
+                self.graphics.frame_limit = FRAME_LIMITS[(index + direction).rem_euclid(limits) as usize];
+            },
+            MenuItem::Msaa => {
+                let levels = MSAA_LEVELS.len() as i32;
+                let index = MSAA_LEVELS.iter().position(|&m| m == self.graphics.msaa).unwrap_or(0) as i32;
+                self.graphics.msaa = MSAA_LEVELS[(index + direction).rem_euclid(levels) as usize];
+            },
+            MenuItem::Gamma => {
+                self.graphics.gamma = (self.graphics.gamma + step * GAMMA_STEP).max(MIN_GAMMA).min(MAX_GAMMA);
+            },
+            MenuItem::Tonemap => self.graphics.post_effects.tonemap = !self.graphics.post_effects.tonemap,
+            MenuItem::Bloom => self.graphics.post_effects.bloom = !self.graphics.post_effects.bloom,
+            MenuItem::Vignette => self.graphics.post_effects.vignette = !self.graphics.post_effects.vignette,
+            MenuItem::Sensitivity => {
+                let old = self.mouse.sensitivity_x;
+                let new = (old + step * SENSITIVITY_STEP).max(SENSITIVITY_STEP).min(MAX_SENSITIVITY);
+
+                // The vertical sensitivity keeps its ratio to the horizontal.
+                self.mouse.sensitivity_y = match old > 0.0 {
+                    true => self.mouse.sensitivity_y * new / old,
+                    false => new,
+                };
+                self.mouse.sensitivity_x = new;
+            },
+            MenuItem::InvertMouse => self.mouse.invert_y = !self.mouse.invert_y,
+            MenuItem::RawMouse => self.mouse.raw_input = !self.mouse.raw_input,
+            MenuItem::Smoothing => {
+                // Kept on whole steps, so that it can get back to exactly off.
+                let steps = (self.mouse.smoothing / SMOOTHING_STEP).round() + step;
+                self.mouse.smoothing = (steps * SMOOTHING_STEP).max(0.0).min(MAX_SMOOTHING);
+            },
+            MenuItem::Volume => {
+                self.audio.master = (self.audio.master + step * VOLUME_STEP).max(0.0).min(1.0);
+            },
+            MenuItem::Mode(index) => {
+                let action = TOGGLEABLE[index].0;
+                let mode = match self.bindings.mode(action) {
+                    ActivationMode::Hold => ActivationMode::Toggle,
+                    ActivationMode::Toggle => ActivationMode::Hold,
+                };
+                self.bindings.set_mode(action, mode);
+                return Some(MenuEvent::BindingsChanged)
+            },
+            // The preset is not a setting, so nothing has to be picked up.
+            MenuItem::Preset => {
+                let presets = WorldPreset::ALL.len() as i32;
+                let index = WorldPreset::ALL.iter().position(|&p| p == self.preset).unwrap_or(0) as i32;
+                self.preset = WorldPreset::ALL[(index + direction).rem_euclid(presets) as usize];
+                return None
+            },
+            _ => return None,
+        }
+
+        Some(MenuEvent::SettingsChanged)
+    }
+
+    // The current value of a setting, or none if the item is not a setting.
+    fn value(&self, item: MenuItem) -> Option<String> {
+        let on_off = |on| match on {
+            true => "On".to_string(),
+            false => "Off".to_string(),
+        };
+
+        match item {
+            MenuItem::Fov => Some(format!("{:.0}", self.graphics.fov)),
+            MenuItem::ViewDistance => Some(format!("{} chunks", self.graphics.view_distance)),
+            MenuItem::Vsync => Some(on_off(self.graphics.vsync)),
+            MenuItem::FrameLimit => Some(match self.graphics.frame_limit {
+                FrameLimit::Uncapped => "Unlimited".to_string(),
+                FrameLimit::Capped(fps) => format!("{} FPS", fps),
+                FrameLimit::Benchmark => "Benchmark".to_string(),
+            }),
+            // Only the renderer created on the next start uses it.
+            MenuItem::Msaa => Some(match self.graphics.msaa {
+                Msaa::Off => "Off (restart)".to_string(),
+                msaa => format!("{}x (restart)", msaa.samples()),
+            }),
+            MenuItem::Gamma => Some(format!("{:.1}", self.graphics.gamma)),
+            MenuItem::Tonemap => Some(on_off(self.graphics.post_effects.tonemap)),
+            MenuItem::Bloom => Some(on_off(self.graphics.post_effects.bloom)),
+            MenuItem::Vignette => Some(on_off(self.graphics.post_effects.vignette)),
+            MenuItem::Sensitivity => Some(format!("{:.0}%", self.mouse.sensitivity_x * 100.0)),
+            MenuItem::InvertMouse => Some(on_off(self.mouse.invert_y)),
+            MenuItem::RawMouse => Some(on_off(self.mouse.raw_input)),
+            MenuItem::Smoothing => Some(match self.mouse.smoothing > 0.0 {
+                true => format!("{:.0} ms", self.mouse.smoothing * 1000.0),
+                false => "Off".to_string(),
+            }),
+            MenuItem::Volume => Some(format!("{:.0}%", self.audio.master * 100.0)),
+            MenuItem::Mode(index) => Some(match self.bindings.mode(TOGGLEABLE[index].0) {
+                ActivationMode::Hold => "Hold".to_string(),
+                ActivationMode::Toggle => "Toggle".to_string(),
+            }),
+            MenuItem::Preset => Some(self.preset.name().to_string()),
+            _ => None,
+        }
+    }
+
+    fn label(&self, item: MenuItem) -> String {
+        let name = match item {
+            MenuItem::Resume => "Resume",
+            MenuItem::Settings => "Settings",
+            MenuItem::Statistics => "Statistics",
+            MenuItem::Quit => "Quit",
+            MenuItem::Fov => "FOV",
+            MenuItem::ViewDistance => "Render distance",
+            MenuItem::Graphics => "Graphics",
+            MenuItem::Vsync => "Vsync",
+            MenuItem::FrameLimit => "Frame limit",
+            MenuItem::Msaa => "MSAA",
+            MenuItem::Gamma => "Gamma",
+            MenuItem::Tonemap => "Tone mapping",
+            MenuItem::Bloom => "Bloom",
+            MenuItem::Vignette => "Vignette",
+            MenuItem::Sensitivity => "Sensitivity",
+            MenuItem::InvertMouse => "Invert mouse",
+            MenuItem::RawMouse => "Raw mouse input",
+            MenuItem::Smoothing => "Camera smoothing",
+            MenuItem::Volume => "Volume",
+            MenuItem::Controls => "Controls",
+            MenuItem::Mode(index) => TOGGLEABLE[index].1,
+            // Bindings are picked rather than stepped through, so they are
+            // shown without arrows.
+            MenuItem::Bind(index) => {
+                let (action, name) = REBINDABLE[index];
+
+                return match self.rebinding == Some(action) {
+                    true => format!("{}: press a key", name),
+                    false => {
+                        let bound = self.bindings.get(action)
+                            .iter()
+                            .map(Binding::to_string)
+                            .collect::<Vec<_>>();
+                        format!("{}: {}", name, bound.join(", "))
+                    },
+                }
+            },
+            MenuItem::Done => "Done",
+            MenuItem::World(index) => return self.worlds[index].clone(),
+            MenuItem::Preset => "World type",
+            MenuItem::NewWorld => "Create new world",
+            MenuItem::Stat(index) => return self.stats[index].clone(),
+        };
+
+        match self.value(item) {
+            Some(value) => format!("< {}: {} >", name, value),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// A snapshot of the menu, drawn on the HUD. It has to be put into a slot
+/// anchored at the center of the window.
+pub struct MenuView {
+    title: String,
+    labels: Vec<String>,
+    selected: usize,
+}
+
+impl HudElement for MenuView {
+    fn build(&self, mb: MeshBuilder, anchor: Point2<f32>) -> MeshBuilder {
+        // Dims the whole world behind the menu, however large the window is.
+        let mb = add_quad(
+            mb,
+            Point2::new(anchor.x - BACKDROP_SIZE, anchor.y - BACKDROP_SIZE),
+            Point2::new(anchor.x + BACKDROP_SIZE, anchor.y + BACKDROP_SIZE),
+            RGBA::new(0.0, 0.0, 0.0, 0.5),
+        );
+
+        let count = self.labels.len();
+        let (first, _) = item_bounds(0, count, anchor);
+        let title_scale = TEXT_SCALE * 2.0;
+        let title_origin = Point2::new(
+            anchor.x - text_width(&self.title, title_scale) * 0.5,
+            first.y - ROW_HEIGHT - text_height(title_scale),
+        );
+        let mb = add_text(mb, title_origin, &self.title, title_scale, RGBA::new(1.0, 1.0, 1.0, 1.0));
+
+        self.labels.iter().enumerate().fold(mb, |mb, (i, label)| {
+            let (min, max) = item_bounds(i, count, anchor);
+            let background = match i == self.selected {
+                true => RGBA::new(0.45, 0.55, 0.75, 0.9),
+                false => RGBA::new(0.15, 0.15, 0.15, 0.8),
+            };
+
+            let mb = add_quad(mb, min, max, background);
+            let origin = Point2::new(
+                anchor.x - text_width(label, TEXT_SCALE) * 0.5,
+                (min.y + max.y - text_height(TEXT_SCALE)) * 0.5,
+            );
+
+            add_text(mb, origin, label, TEXT_SCALE, RGBA::new(1.0, 1.0, 1.0, 1.0))
+        })
+    }
+}
+
+// Where the button of an item is, in logical pixels. The buttons are stacked
+// vertically, centered around the anchor.
+fn item_bounds(index: usize, count: usize, anchor: Point2<f32>) -> (Point2<f32>, Point2<f32>) {
+    let top = anchor.y - count as f32 * ROW_HEIGHT * 0.5 + index as f32 * ROW_HEIGHT;
+    let half_width = BUTTON_WIDTH * 0.5;
+
+    (
+        Point2::new(anchor.x - half_width, top + ROW_GAP * 0.5),
+        Point2::new(anchor.x + half_width, top + ROW_HEIGHT - ROW_GAP * 0.5),
+    )
+}
+
+// The layout of the menu, in logical pixels. The text is drawn with pixels
+// this large.
+const BUTTON_WIDTH: f32 = 360.0;
+const ROW_HEIGHT: f32 = 40.0;
+const ROW_GAP: f32 = 8.0;
+const TEXT_SCALE: f32 = 2.0;
+const BACKDROP_SIZE: f32 = 10000.0;
+
+// How many lines of statistics fit on the screen.
+const MAX_STAT_ROWS: usize = 12;
+
+// How far the settings go, and by how much they change per step.
+const FOV_STEP: f32 = 5.0;
+const MIN_FOV: f32 = 30.0;
+const MAX_FOV: f32 = 110.0;
+const MIN_VIEW_DISTANCE: i32 = 2;
+const MAX_VIEW_DISTANCE: i32 = 32;
+const GAMMA_STEP: f32 = 0.1;
+const MIN_GAMMA: f32 = 1.0;
+const MAX_GAMMA: f32 = 3.0;
+const SENSITIVITY_STEP: f32 = 0.025;
+const MAX_SENSITIVITY: f32 = 2.0;
+const SMOOTHING_STEP: f32 = 0.01;
+const MAX_SMOOTHING: f32 = 0.1;
+const VOLUME_STEP: f32 = 0.1;
+
+// The frame limits the menu goes through.
+const FRAME_LIMITS: [FrameLimit; 7] = [
+    FrameLimit::Capped(30),
+    FrameLimit::Capped(60),
+    FrameLimit::Capped(120),
+    FrameLimit::Capped(144),
+    FrameLimit::Capped(240),
+    FrameLimit::Uncapped,
+    FrameLimit::Benchmark,
+];
+
+// The actions that can be rebound in the menu, and what they are called
+// there. The rest can only be rebound in the bindings file.
+const REBINDABLE: [(Action, &'static str); 8] = [
+    (Action::MoveForward, "Forward"),
+    (Action::MoveBackward, "Backward"),
+    (Action::MoveLeft, "Left"),
+    (Action::MoveRight, "Right"),
+    (Action::Jump, "Jump"),
+    (Action::Sprint, "Sprint"),
+    (Action::Crouch, "Crouch"),
+    (Action::Inventory, "Inventory"),
+];
+
+// The actions that can be switched between being held and toggled in the
+// menu.
+const TOGGLEABLE: [(Action, &'static str); 2] = [
+    (Action::Sprint, "Sprint mode"),
+    (Action::Crouch, "Crouch mode"),
+];
+
+// The MSAA levels the menu goes through.
+const MSAA_LEVELS: [Msaa; 4] = [Msaa::Off, Msaa::X2, Msaa::X4, Msaa::X8];
@@ -1,4 +1,5 @@
 use cgmath::{ Point3, Point2, Vector3 };
+use gekraftet_core::world::Direction;
 use crate::RGBA;
 use super::{ Face, Mesh, Texture, Vertex };
 //use rand::random;
@@ -39,6 +40,15 @@ impl MeshBuilder {
     }
 
     pub fn create_cuboid(length: Vector3<f32>, origin: Point3<f32>, faces: Face) -> Mesh {
+        Self::create_colored_cuboid(length, origin, faces, RGBA::new(0.9, 0.9, 0.9, 1.0))
+    }
+
+    pub fn create_colored_cuboid(
+        length: Vector3<f32>,
+        origin: Point3<f32>,
+        faces: Face,
+        color: RGBA
+    ) -> Mesh {
         if faces == Face::empty() {
             return MeshBuilder::new().build()
         };
@@ -50,12 +60,6 @@ impl MeshBuilder {
 
         let halved = length * 0.5;
         let create_vertex = |x, y, z, lighting| {
-            let color = {
-                //let origin = origin * 0.026315; // (1 / 38.0)
-                //let (x, y, z) = (origin.x(), origin.y(), origin.z());
-                RGBA::new(0.9, 0.9, 0.9, 1.0)
-            };
-
             Vertex::new(
                 Point3::<f32>::new(x + origin.x, y + origin.y, z + origin.z),
                 color,
@@ -87,27 +91,27 @@ impl MeshBuilder {
             }
         };
 
-        if faces.intersects(Face::BACK) {
+        if faces.contains(Direction::North) {
             add_face([1, 3, 0, 1, 2, 3]);
         };
 
-        if faces.intersects(Face::RIGHT) {
+        if faces.contains(Direction::East) {
             add_face([7, 3, 2, 6, 7, 2]);
         };
             
-        if faces.intersects(Face::TOP) {
+        if faces.contains(Direction::Up) {
             add_face([1, 5, 6, 2, 1, 6]);
         }
 
-        if faces.intersects(Face::FRONT) {
+        if faces.contains(Direction::South) {
             add_face([4, 7, 5, 7, 6, 5]);
         }
 
-        if faces.intersects(Face::LEFT) {
+        if faces.contains(Direction::West) {
             add_face([0, 4, 1, 4, 5, 1]);
         }
 
-        if faces.intersects(Face::BOTTOM) {
+        if faces.contains(Direction::Down) {
             add_face([3, 7, 4, 0, 3, 4]);
         }
 
@@ -128,6 +132,15 @@ impl MeshBuilder {
         )
     }
 
+    pub fn create_colored_cube(length: f32, origin: Point3<f32>, faces: Face, color: RGBA) -> Mesh {
+        Self::create_colored_cuboid(
+            Vector3::<f32>::new(length, length, length), 
+            origin,
+            faces,
+            color
+        )
+    }
+
     pub fn add_vertex(mut self, vert: Vertex) -> Self {
         self.vertices.push(vert);
         self
@@ -1,17 +1,15 @@
+use gekraftet_core::world::Direction;
+
+/// A set of the faces of a cuboid, each named by the `Direction` it faces,
+/// kept as the bits of the directions.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Face {
     enabled: u8,
 }
 
 impl Face {
-    pub const TOP:    u8 = 1;
-    pub const BOTTOM: u8 = 2;
-    pub const LEFT:   u8 = 4;
-    pub const RIGHT:  u8 = 8;
-    pub const FRONT:  u8 = 16;
-    pub const BACK:   u8 = 32;
-
     pub fn from_bitfield(enabled: u8) -> Self {
+        debug_assert!(enabled <= 0b11_1111);
         Self { enabled }
     }
 
@@ -31,25 +29,15 @@ impl Face {
         }
     }
 
-    pub fn with_faces(bitfield: u8) -> Self {
-        debug_assert!(bitfield <= 0b11_1111);
-
-        Self {
-            enabled: bitfield
-        }
-    }
-
-    pub fn enable(&mut self, bitfield: u8) {
-        debug_assert!(bitfield <= 0b11_1111);
-        self.enabled |= bitfield;
+    pub fn enable(&mut self, face: Direction) {
+        self.enabled |= face.bit();
     }
 
-    pub fn disable(&mut self, bitfield: u8) {
-        debug_assert!(bitfield <= 0b11_1111);
-        self.enabled &= !bitfield;
+    pub fn disable(&mut self, face: Direction) {
+        self.enabled &= !face.bit();
     }
 
-    pub fn intersects(&self, bitfield: u8) -> bool {
-        (self.enabled & bitfield) != 0
+    pub fn contains(&self, face: Direction) -> bool {
+        (self.enabled & face.bit()) != 0
     }
 }
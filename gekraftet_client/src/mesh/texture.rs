@@ -6,5 +6,7 @@ pub struct Texture {
 }
 
 impl Texture {
-
+    /// Textures are authored in sRGB. Storing them in an sRGB format lets the
+    /// GPU decode them into linear space whenever they are sampled.
+    pub const INTERNAL_FORMAT: GLenum = gl::SRGB8_ALPHA8;
 }
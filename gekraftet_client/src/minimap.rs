@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use cgmath::Point2;
+use gekraftet_core::world::{ BlockPos, ChunkPos, World, CHUNK_LENGTH_X, CHUNK_LENGTH_Z, SEA_LEVEL };
+use crate::RGBA;
+use crate::mesh::MeshBuilder;
+use crate::renderer::{ add_quad, add_text, text_width, HudElement };
+use crate::world::block_color;
+
+/// Keeps the color of the surface of every loaded column, taken from the
+/// heightmaps of the chunks, and draws the area around the player from it.
+/// North (negative Z) is always up on the map.
+pub struct Minimap {
+    // The surface colors of the loaded chunks, by column (x * 16 + z). Empty
+    // columns have no color.
+    chunks: HashMap<ChunkPos, Box<[Option<RGBA>; CHUNK_LENGTH_X * CHUNK_LENGTH_Z]>>,
+    zoom: usize,
+    // Whether the map has changed since the last view, and where that view
+    // was centered and which way it was heading.
+    dirty: bool,
+    last_view: Option<(BlockPos, i32)>,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            zoom: 0,
+            dirty: true,
+            last_view: None,
+        }
+    }
+
+    /// How many blocks every pixel of the map covers.
+    pub fn scale(&self) -> i32 {
+        ZOOM_LEVELS[self.zoom]
+    }
+
+    /// Shows a smaller area, in more detail.
+    pub fn zoom_in(&mut self) {
+        self.zoom = self.zoom.saturating_sub(1);
+        self.dirty = true;
+    }
+
+    /// Shows a larger area, in less detail.
+    pub fn zoom_out(&mut self) {
+        self.zoom = (self.zoom + 1).min(ZOOM_LEVELS.len() - 1);
+        self.dirty = true;
+    }
+
+    /// Reads the surface of the chunk again, after it was loaded or any of
+    /// its blocks changed.
+    pub fn update_chunk(&mut self, world: &World, at: ChunkPos) {
+        let chunk = match world.chunk(at) {
+            Some(chunk) => chunk,
+            None => return self.remove_chunk(at),
+        };
+
+        let mut colors = Box::new([None; CHUNK_LENGTH_X * CHUNK_LENGTH_Z]);
+        for x in 0..CHUNK_LENGTH_X {
+            for z in 0..CHUNK_LENGTH_Z {
+                let height = chunk.height(x, z).unwrap_or(0) as usize;
+
+                colors[x * CHUNK_LENGTH_Z + z] = height
+                    .checked_sub(1)
+                    .and_then(|y| chunk.block(x, y, z))
+                    .map(|block| surface_color(block_color(block), height as i32));
+            }
+        }
+
+        self.chunks.insert(at, colors);
+        self.dirty = true;
+    }
+
+    pub fn remove_chunk(&mut self, at: ChunkPos) {
+        if self.chunks.remove(&at).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Returns the map around `center` (with the player heading towards
+    /// `yaw`, in radians), unless it would look the same as the last one.
+    pub fn view(&mut self, center: BlockPos, yaw: f32) -> Option<MinimapView> {
+        let heading = (yaw / (2.0 * PI) * HEADINGS as f32).round() as i32;
+        let heading = heading.rem_euclid(HEADINGS);
+        let (x, z) = (center.x, center.z);
+        let center = BlockPos::new(x, 0, z);
+
+        if !self.dirty && self.last_view == Some((center, heading)) {
+            return None
+        }
+
+        self.dirty = false;
+        self.last_view = Some((center, heading));
+
+        let scale = self.scale();
+        let half = MINIMAP_PIXELS as i32 / 2;
+        let mut runs = Vec::new();
+
+        // Neighbouring pixels of the same color are drawn as a single quad.
+        for row in 0..MINIMAP_PIXELS {
+            let mut run: Option<(usize, RGBA)> = None;
+
+            for column in 0..=MINIMAP_PIXELS {
+                let color = match column < MINIMAP_PIXELS {
+                    true => self.color(
+                        x + (column as i32 - half) * scale,
+                        z + (row as i32 - half) * scale,
+                    ),
+                    false => None,
+                };
+
+                match run {
+                    Some((_, run_color)) if Some(run_color) == color => {},
+                    _ => {
+                        if let Some((start, run_color)) = run {
+                            runs.push((row, start, column, run_color));
+                        }
+                        run = color.map(|color| (column, color));
+                    },
+                }
+            }
+        }
+
+        Some(MinimapView {
+            runs,
+            heading: heading as f32 / HEADINGS as f32 * 2.0 * PI,
+        })
+    }
+
+    fn color(&self, x: i32, z: i32) -> Option<RGBA> {
+        let at = ChunkPos::from(BlockPos::new(x, 0, z));
+        let colors = self.chunks.get(&at)?;
+        let (x, z) = (x.rem_euclid(CHUNK_LENGTH_X as i32), z.rem_euclid(CHUNK_LENGTH_Z as i32));
+        colors[x as usize * CHUNK_LENGTH_Z + z as usize]
+    }
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A snapshot of the minimap, drawn on the HUD. It has to be put into a slot
+/// anchored at the top-right corner of the window.
+pub struct MinimapView {
+    // Horizontal runs of pixels: the row, the first column, the column after
+    // the last one, and the color.
+    runs: Vec<(usize, usize, usize, RGBA)>,
+    heading: f32,
+}
+
+impl HudElement for MinimapView {
+    fn build(&self, mb: MeshBuilder, anchor: Point2<f32>) -> MeshBuilder {
+        let size = MINIMAP_PIXELS as f32 * PIXEL_SIZE;
+        let min = Point2::new(anchor.x - size, anchor.y);
+        let max = Point2::new(anchor.x, anchor.y + size);
+
+        let mb = add_quad(
+            mb,
+            Point2::new(min.x - BORDER, min.y - BORDER),
+            Point2::new(max.x + BORDER, max.y + BORDER),
+            RGBA::new(0.0, 0.0, 0.0, 0.6),
+        );
+
+        let mb = self.runs.iter().fold(mb, |mb, &(row, start, end, color)| {
+            add_quad(
+                mb,
+                Point2::new(min.x + start as f32 * PIXEL_SIZE, min.y + row as f32 * PIXEL_SIZE),
+                Point2::new(min.x + end as f32 * PIXEL_SIZE, min.y + (row + 1) as f32 * PIXEL_SIZE),
+                color,
+            )
+        });
+
+        // The player is a dot in the middle, with a smaller one in front of
+        // it showing where it is heading.
+        let center = Point2::new((min.x + max.x) * 0.5, (min.y + max.y) * 0.5);
+        let white = RGBA::new(1.0, 1.0, 1.0, 1.0);
+        let square = |mb, at: Point2<f32>, half: f32| add_quad(
+            mb,
+            Point2::new(at.x - half, at.y - half),
+            Point2::new(at.x + half, at.y + half),
+            white,
+        );
+        let mb = square(mb, center, MARKER_SIZE);
+
+        let front = Point2::new(
+            center.x + self.heading.cos() * MARKER_SIZE * 3.0,
+            center.y + self.heading.sin() * MARKER_SIZE * 3.0,
+        );
+        let mb = square(mb, front, MARKER_SIZE * 0.5);
+
+        let north = Point2::new(center.x - text_width("N", TEXT_SCALE) * 0.5, min.y + BORDER);
+        add_text(mb, north, "N", TEXT_SCALE, RGBA::new(1.0, 0.3, 0.3, 1.0))
+    }
+}
+
+// The color of a column on the map, brighter the higher its surface is.
+fn surface_color(color: RGBA, height: i32) -> RGBA {
+    let shade = (1.0 + (height - SEA_LEVEL) as f32 / 96.0).max(0.4).min(1.4);
+    RGBA::new(color.x * shade, color.y * shade, color.z * shade, 1.0)
+}
+
+// How many blocks a pixel of the map covers at each zoom level.
+const ZOOM_LEVELS: [i32; 3] = [1, 2, 4];
+
+// How many pixels the map is across, and how large (in logical pixels) each
+// of them is drawn.
+const MINIMAP_PIXELS: usize = 64;
+const PIXEL_SIZE: f32 = 2.0;
+
+// The layout of the frame, the player and the north indicator, in logical
+// pixels. The letter is drawn with pixels this large.
+const BORDER: f32 = 2.0;
+const MARKER_SIZE: f32 = 2.0;
+const TEXT_SCALE: f32 = 1.0;
+
+// How many directions the heading of the player is rounded to, so that the
+// map is not rebuilt for every tiny turn.
+const HEADINGS: i32 = 16;
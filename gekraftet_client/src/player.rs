@@ -0,0 +1,73 @@
+use std::ops::{ Deref, DerefMut };
+use cgmath::Point3;
+use gekraftet_core::movement::PlayerBody;
+use crate::world::BLOCK_LENGTH;
+
+pub use gekraftet_core::movement::MovementInput;
+
+/// The body of the player, with its position in world units like everything
+/// else that is drawn. Everything else about it is as `PlayerBody` has it, in
+/// blocks.
+pub struct PlayerController {
+    body: PlayerBody,
+    // Where the eyes were before the last step, see interpolated_eye().
+    previous_eye: Point3<f32>,
+}
+
+impl PlayerController {
+    pub fn new(position: Point3<f32>) -> Self {
+        let body = PlayerBody::new(position / BLOCK_LENGTH);
+        let previous_eye = body.eye() * BLOCK_LENGTH;
+
+        Self {
+            body,
+            previous_eye,
+        }
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        self.body.position() * BLOCK_LENGTH
+    }
+
+    /// Where the eyes of the player are, which is where the camera goes.
+    pub fn eye(&self) -> Point3<f32> {
+        self.body.eye() * BLOCK_LENGTH
+    }
+
+    /// Where the eyes of the player are drawn, `alpha` (from 0 to 1) of the
+    /// way from where they were before the last step to where they are.
+    pub fn interpolated_eye(&self, alpha: f32) -> Point3<f32> {
+        self.previous_eye + (self.eye() - self.previous_eye) * alpha
+    }
+
+    /// Remembers where the eyes are, before the player is moved by a step.
+    pub fn begin_step(&mut self) {
+        self.previous_eye = self.eye();
+    }
+
+    /// Moves the feet of the player to the position, and stops it.
+    pub fn teleport(&mut self, position: Point3<f32>) {
+        self.body.teleport(position / BLOCK_LENGTH);
+        self.previous_eye = self.eye();
+    }
+
+    /// Moves the player so that its eyes are at the position, and stops it.
+    pub fn set_eye(&mut self, eye: Point3<f32>) {
+        self.body.set_eye(eye / BLOCK_LENGTH);
+        self.previous_eye = self.eye();
+    }
+}
+
+impl Deref for PlayerController {
+    type Target = PlayerBody;
+
+    fn deref(&self) -> &Self::Target {
+        &self.body
+    }
+}
+
+impl DerefMut for PlayerController {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.body
+    }
+}
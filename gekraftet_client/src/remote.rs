@@ -0,0 +1,489 @@
+use std::collections::{ HashMap, VecDeque };
+use std::net::{ Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs };
+use cgmath::{ ElementWise, EuclideanSpace, Point3, Vector3 };
+use gekraftet_core::entity::{ Entity, Render, RenderShape };
+use gekraftet_core::movement::{ MovementInput, MovementState, PlayerBody };
+use gekraftet_core::net::{
+    self, Capabilities, ClientMessage, Connection, DatagramSocket, ServerMessage, TcpTransport, PROTOCOL_VERSION,
+};
+use gekraftet_core::stats::PlayerStats;
+use gekraftet_core::world::{ Block, BlockPos, ChunkPos, World, WorldGenerator, TICKS_PER_SECOND };
+use crate::renderer::EntityInstance;
+use crate::world::{ block_color, BLOCK_LENGTH };
+
+/// What happened on the server that the rest of the game has to act on.
+#[derive(Clone, Debug)]
+pub enum RemoteEvent {
+    /// The server let the player in. Its world is generated with the
+    /// generator.
+    Joined(WorldGenerator),
+    /// The server did not let the player in, or kicked them out, for the
+    /// reason given. Nothing more is received after this.
+    Rejected(String),
+    /// A block was changed, and has to be changed in the local world too.
+    BlockChanged {
+        at: BlockPos,
+        block: Block,
+    },
+    /// Every block of the chunk has to be replaced, in the order
+    /// `Chunk::blocks()` returns them in.
+    ChunkReplaced {
+        at: ChunkPos,
+        blocks: Vec<Block>,
+    },
+    /// A line of the chat, without a sender if the server said it.
+    Chat {
+        sender: Option<String>,
+        text: String,
+    },
+    /// How the server is doing, which it only tells operators.
+    Metrics {
+        tps: f32,
+        mspt: f32,
+        parts: Vec<(String, f32)>,
+    },
+    /// The statistics of the player, as they were asked for.
+    Stats(PlayerStats),
+}
+
+/// A connection to a server, and the entities it has told about. Entities
+/// are moved by the server a few times a second, so they are drawn a little
+/// in the past, in between the positions the server sent.
+///
+/// The player is moved by the server too, but the client does not wait for
+/// it: it moves the player right away, and when the server tells where the
+/// player really is, it starts over from there with the inputs the server has
+/// not seen yet.
+pub struct RemoteWorld {
+    connection: Connection,
+    // Where the server is, unless it runs in the same process.
+    server: Option<SocketAddr>,
+    // Where entities moved to arrives over this, once the server offered it.
+    datagrams: Option<Datagrams>,
+    player: Option<Entity>,
+    // What the connection uses, once the server said.
+    capabilities: Capabilities,
+    // The inputs sent but not acknowledged yet, the sequence number of the
+    // next one, and where the server last said the player is.
+    inputs: VecDeque<SentInput>,
+    next_sequence: u32,
+    acknowledged: Option<(u32, MovementState)>,
+    entities: HashMap<Entity, RemoteEntity>,
+    // The chunks the server has been told the client has, and the revision
+    // of each.
+    chunks: HashMap<ChunkPos, u64>,
+    // The tick the server is guessed to be on, counting up with the frames
+    // in between the messages of the server.
+    clock: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SentInput {
+    sequence: u32,
+    delta: f32,
+    input: MovementInput,
+    forward: Vector3<f32>,
+    flying: bool,
+    noclip: bool,
+}
+
+impl SentInput {
+    // Moves the body like the server does.
+    fn apply(&self, body: &mut PlayerBody, world: &World) {
+        if self.flying != body.flying() {
+            body.set_flying(self.flying);
+        }
+        body.set_noclip(self.noclip);
+        body.update(&self.input, self.forward, self.delta, world);
+    }
+}
+
+struct Datagrams {
+    socket: DatagramSocket,
+    token: u64,
+    // Whether the server answered the hello, and how long ago it was sent.
+    confirmed: bool,
+    since_hello: f32,
+}
+
+struct RemoteEntity {
+    size: Vector3<f32>,
+    render: Render,
+    positions: InterpolationBuffer,
+}
+
+impl RemoteWorld {
+    /// Connects to the server at the address, and logs in with the name.
+    /// Without a token, the player asks to play in offline mode. This blocks
+    /// until the connection is made.
+    pub fn connect<A: ToSocketAddrs>(address: A, name: &str, token: Option<String>) -> net::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        let server = stream.peer_addr()?;
+        Self::log_in(Connection::new(TcpTransport::new(stream)?), Some(server), name, token)
+    }
+
+    /// Logs in with the name to a server running in the same process, over
+    /// the connection it gave, e.g. an `IntegratedServer`.
+    pub fn local(connection: Connection, name: &str) -> net::Result<Self> {
+        Self::log_in(connection, None, name, None)
+    }
+
+    fn log_in(mut connection: Connection, server: Option<SocketAddr>, name: &str, token: Option<String>) -> net::Result<Self> {
+        connection.send(&ClientMessage::Login {
+            version: PROTOCOL_VERSION,
+            capabilities: Capabilities::SUPPORTED,
+            name: name.to_owned(),
+            token,
+        })?;
+
+        Ok(Self {
+            connection,
+            server,
+            datagrams: None,
+            player: None,
+            capabilities: Capabilities::empty(),
+            inputs: VecDeque::new(),
+            next_sequence: 1,
+            acknowledged: None,
+            entities: HashMap::new(),
+            chunks: HashMap::new(),
+            clock: None,
+        })
+    }
+
+    /// Handles what the server sent since the last frame, `delta` seconds
+    /// ago.
+    pub fn update(&mut self, delta: f32) -> net::Result<Vec<RemoteEvent>> {
+        if let Some(clock) = self.clock.as_mut() {
+            *clock += (delta * TICKS_PER_SECOND as f32) as f64;
+        }
+
+        let mut events = Vec::new();
+        while let Some(message) = self.connection.receive::<ServerMessage>()? {
+            match message {
+                ServerMessage::Welcome { player, generator, capabilities } => {
+                    log::info!("joined the server, using {:?}", capabilities);
+                    self.connection.set_compression(capabilities.contains(Capabilities::COMPRESSION));
+                    self.player = Some(player);
+                    self.capabilities = capabilities;
+                    events.push(RemoteEvent::Joined(generator));
+                },
+                ServerMessage::EntitySpawned { entity, tick, position, size, render } => {
+                    self.sync_clock(tick);
+
+                    let mut positions = InterpolationBuffer::new();
+                    positions.push(tick, position);
+                    self.entities.insert(entity, RemoteEntity { size, render, positions });
+                },
+                ServerMessage::EntityMoved { entity, tick, position } => self.move_entity(entity, tick, position),
+                ServerMessage::EntityDespawned { entity } => {
+                    self.entities.remove(&entity);
+                },
+                ServerMessage::BlockChanged { at, block } => {
+                    events.push(RemoteEvent::BlockChanged { at, block });
+                },
+                ServerMessage::PlayerAcknowledged { sequence, state } => {
+                    self.acknowledged = Some((sequence, state));
+                },
+                ServerMessage::ChunkDelta { at, base, revision, changes } => {
+                    match self.chunks.get(&at).copied() {
+                        Some(known) if known == base => {
+                            self.chunks.insert(at, revision);
+                            events.extend(changes.into_iter().map(|(at, block)| RemoteEvent::BlockChanged { at, block }));
+                        },
+                        // The chunk was reloaded in the meantime, so the
+                        // server is told where it really is.
+                        Some(known) => self.connection.send(&ClientMessage::ChunkLoaded { at, revision: known })?,
+                        None => {},
+                    }
+                },
+                ServerMessage::ChunkData { at, revision, blocks } => {
+                    if self.chunks.contains_key(&at) {
+                        self.chunks.insert(at, revision);
+                        events.push(RemoteEvent::ChunkReplaced { at, blocks });
+                    }
+                },
+                ServerMessage::Rejected { reason } => {
+                    events.push(RemoteEvent::Rejected(reason));
+                    break
+                },
+                ServerMessage::Metrics { tps, mspt, parts } => {
+                    events.push(RemoteEvent::Metrics { tps, mspt, parts });
+                },
+                ServerMessage::Chat { sender, text } => {
+                    events.push(RemoteEvent::Chat { sender, text });
+                },
+                ServerMessage::Stats { stats } => {
+                    events.push(RemoteEvent::Stats(stats));
+                },
+                ServerMessage::DatagramSession { token } => {
+                    let unspecified = match self.server {
+                        Some(SocketAddr::V4(_)) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+                        Some(SocketAddr::V6(_)) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+                        None => continue,
+                    };
+
+                    match DatagramSocket::bind(unspecified) {
+                        Ok(socket) => self.datagrams = Some(Datagrams {
+                            socket,
+                            token,
+                            confirmed: false,
+                            since_hello: HELLO_INTERVAL,
+                        }),
+                        Err(e) => log::warn!("unable to receive datagrams, using tcp only: {}", e),
+                    }
+                },
+            }
+        }
+
+        if let Err(e) = self.receive_datagrams(delta) {
+            log::warn!("unable to receive datagrams, using tcp only: {:?}", e);
+            self.datagrams = None;
+        }
+
+        Ok(events)
+    }
+
+    // Says hello over UDP until the server answers, and takes the moves of
+    // entities that arrived over it. Only moves are taken, and only from the
+    // server.
+    fn receive_datagrams(&mut self, delta: f32) -> net::Result<()> {
+        let (datagrams, server) = match (self.datagrams.as_mut(), self.server) {
+            (Some(datagrams), Some(server)) => (datagrams, server),
+            _ => return Ok(()),
+        };
+
+        datagrams.since_hello += delta;
+        if !datagrams.confirmed && datagrams.since_hello >= HELLO_INTERVAL {
+            datagrams.socket.send_to(&ClientMessage::DatagramHello { token: datagrams.token }, server)?;
+            datagrams.since_hello = 0.0;
+        }
+
+        let mut moves = Vec::new();
+        while let Some((message, address)) = datagrams.socket.receive_from::<ServerMessage>()? {
+            match message {
+                _ if address != server => {},
+                ServerMessage::DatagramSession { token } if token == datagrams.token => datagrams.confirmed = true,
+                ServerMessage::EntityMoved { entity, tick, position } => moves.push((entity, tick, position)),
+                _ => {},
+            }
+        }
+
+        for (entity, tick, position) in moves {
+            self.move_entity(entity, tick, position);
+        }
+
+        Ok(())
+    }
+
+    fn move_entity(&mut self, entity: Entity, tick: u64, position: Point3<f32>) {
+        self.sync_clock(tick);
+
+        if let Some(remote) = self.entities.get_mut(&entity) {
+            remote.positions.push(tick, position);
+        }
+    }
+
+    /// Moves the player by a step, and sends the input to the server. If the
+    /// server said where the player is since the last frame, the player is
+    /// put there first, and moved again by the inputs it has not seen yet.
+    pub fn move_player(
+        &mut self,
+        body: &mut PlayerBody,
+        input: &MovementInput,
+        forward: Vector3<f32>,
+        delta: f32,
+        world: &World,
+    ) -> net::Result<()> {
+        if let Some((sequence, state)) = self.acknowledged.take() {
+            while self.inputs.front().map_or(false, |sent| sent.sequence <= sequence) {
+                self.inputs.pop_front();
+            }
+
+            body.set_state(state);
+            for sent in self.inputs.iter() {
+                sent.apply(body, world);
+            }
+
+            // The footsteps were heard the first time around.
+            body.take_footstep();
+        }
+
+        let sent = SentInput {
+            sequence: self.next_sequence,
+            delta,
+            input: *input,
+            forward,
+            flying: body.flying(),
+            noclip: body.noclip(),
+        };
+        sent.apply(body, world);
+        self.next_sequence += 1;
+
+        self.inputs.push_back(sent);
+        if self.inputs.len() > MAX_UNACKNOWLEDGED_INPUTS {
+            self.inputs.pop_front();
+        }
+
+        self.connection.send(&ClientMessage::PlayerInput {
+            sequence: sent.sequence,
+            delta: sent.delta,
+            input: sent.input,
+            forward: sent.forward,
+            flying: sent.flying,
+            noclip: sent.noclip,
+        })
+    }
+
+    /// Tells the server that the chunk was just generated, and that it
+    /// should send what changed in it.
+    pub fn load_chunk(&mut self, at: ChunkPos) -> net::Result<()> {
+        self.chunks.insert(at, 0);
+        self.connection.send(&ClientMessage::ChunkLoaded { at, revision: 0 })
+    }
+
+    /// Tells the server that the chunk is gone.
+    pub fn unload_chunk(&mut self, at: ChunkPos) -> net::Result<()> {
+        self.chunks.remove(&at);
+        self.connection.send(&ClientMessage::ChunkUnloaded { at })
+    }
+
+    /// Asks the server to break the block. It stays until the server
+    /// agrees.
+    pub fn break_block(&mut self, at: BlockPos) -> net::Result<()> {
+        self.connection.send(&ClientMessage::BreakBlock { at })
+    }
+
+    /// Asks the server to place the block. It appears once the server
+    /// agrees.
+    pub fn place_block(&mut self, at: BlockPos, block: Block) -> net::Result<()> {
+        self.connection.send(&ClientMessage::PlaceBlock { at, block })
+    }
+
+    /// Says something in the chat, or runs a command on the server if it
+    /// starts with a slash.
+    pub fn send_chat(&mut self, text: &str) -> net::Result<()> {
+        let text = text.chars().take(net::MAX_CHAT_LENGTH).collect();
+        self.connection.send(&ClientMessage::Chat { text })
+    }
+
+    /// Asks the server for the statistics of the player, which arrive as a
+    /// `RemoteEvent::Stats`. Returns whether the server keeps any.
+    pub fn request_stats(&mut self) -> net::Result<bool> {
+        match self.capabilities.contains(Capabilities::STATS) {
+            true => self.connection.send(&ClientMessage::RequestStats).map(|()| true),
+            false => Ok(false),
+        }
+    }
+
+    /// The entities of the server as they are drawn right now.
+    pub fn entity_instances(&self) -> Vec<EntityInstance> {
+        let time = self.clock.unwrap_or(0.0) - INTERPOLATION_DELAY;
+
+        self.entities
+            .iter()
+            .filter(|&(entity, _)| Some(*entity) != self.player)
+            .filter_map(|(_, remote)| {
+                let position = remote.positions.sample(time)?;
+                let half = Vector3::new(remote.size.x / 2.0, 0.0, remote.size.z / 2.0);
+
+                let color = match remote.render.shape {
+                    RenderShape::Cube => remote.render.color,
+                    RenderShape::Block(id) => block_color(&Block::new(id)).mul_element_wise(remote.render.color),
+                };
+
+                Some(EntityInstance {
+                    min: (position - half) * BLOCK_LENGTH,
+                    size: remote.size * BLOCK_LENGTH,
+                    color,
+                    yaw: 0.0,
+                })
+            })
+            .collect()
+    }
+
+    // Catches the clock up with the tick the server is on. A clock that has
+    // drifted too far off (e.g. after the server stalled) is reset instead.
+    fn sync_clock(&mut self, tick: u64) {
+        let tick = tick as f64;
+
+        self.clock = match self.clock {
+            Some(clock) if (clock - tick).abs() <= MAX_CLOCK_DRIFT => Some(clock.max(tick)),
+            _ => Some(tick),
+        };
+    }
+}
+
+// The positions an entity was at, by the tick of the server, which are
+// interpolated between.
+struct InterpolationBuffer {
+    positions: VecDeque<(u64, Point3<f32>)>,
+}
+
+impl InterpolationBuffer {
+    fn new() -> Self {
+        Self {
+            positions: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, tick: u64, position: Point3<f32>) {
+        // Positions sent over UDP may arrive late, after newer ones. Of those
+        // of the same tick, only the newest counts.
+        if self.positions.back().map_or(false, |&(t, _)| t > tick) {
+            return
+        }
+
+        while self.positions.back().map_or(false, |&(t, _)| t >= tick) {
+            self.positions.pop_back();
+        }
+
+        // The server only sends positions that changed, so an entity that
+        // stood still starts moving from the tick before.
+        if let Some(&(last_tick, last)) = self.positions.back() {
+            if last_tick + 1 < tick {
+                self.positions.push_back((tick - 1, last));
+            }
+        }
+
+        self.positions.push_back((tick, position));
+
+        if self.positions.len() > MAX_BUFFERED_POSITIONS {
+            self.positions.pop_front();
+        }
+    }
+
+    // Where the entity was at the (fractional) tick. Before the first
+    // position and after the last one, the entity stays where it was.
+    fn sample(&self, time: f64) -> Option<Point3<f32>> {
+        let after = self.positions.iter().position(|&(tick, _)| tick as f64 > time);
+
+        match after {
+            Some(0) => self.positions.front().map(|&(_, position)| position),
+            Some(i) => {
+                let (from_tick, from) = self.positions[i - 1];
+                let (to_tick, to) = self.positions[i];
+                let t = (time - from_tick as f64) / (to_tick - from_tick) as f64;
+                Some(Point3::from_vec(from.to_vec() + (to - from) * t as f32))
+            },
+            None => self.positions.back().map(|&(_, position)| position),
+        }
+    }
+}
+
+// How far behind the server (in ticks) entities are drawn, so that there is
+// usually a newer position to move towards, and how far the clock may drift
+// from the server before it is reset.
+const INTERPOLATION_DELAY: f64 = 2.0;
+const MAX_CLOCK_DRIFT: f64 = 10.0;
+
+// How many positions of an entity are kept, which is plenty for the delay.
+const MAX_BUFFERED_POSITIONS: usize = 16;
+
+// How often the client says hello over UDP until the server answers, in
+// seconds.
+const HELLO_INTERVAL: f32 = 1.0;
+
+// How many inputs are kept for the server to acknowledge. A server that falls
+// this far behind is not waited for.
+const MAX_UNACKNOWLEDGED_INPUTS: usize = 1024;
@@ -0,0 +1,153 @@
+use cgmath::{ Matrix4, Point3, Vector3 };
+use gl::types::*;
+use std::ptr;
+
+use crate::world::BLOCK_LENGTH;
+use super::shader::{ Linked, ShaderProgram };
+
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct WallVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+/// Draws the border of the world as a translucent wall, which fades in as the
+/// camera gets close to it. Only the part of the wall around the camera is
+/// drawn, fading out towards its edges.
+pub struct BorderRenderer {
+    program: ShaderProgram<Linked>,
+    vao: GLuint,
+    vbo: GLuint,
+    // How far the border is from the origin, in world units.
+    radius: Option<f32>,
+}
+
+impl BorderRenderer {
+    pub fn new() -> Self {
+        let program = ShaderProgram::new()
+            .compile_shader(BORDER_VS_SHADER, BORDER_FS_SHADER)
+            .unwrap();
+
+        let (vao, vbo) = unsafe {
+            let (mut vao, mut vbo) = (0, 0);
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            let stride = std::mem::size_of::<WallVertex>() as i32;
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, 12 as *const _);
+            gl::EnableVertexAttribArray(0);
+            gl::EnableVertexAttribArray(1);
+            (vao, vbo)
+        };
+
+        Self {
+            program,
+            vao,
+            vbo,
+            radius: None,
+        }
+    }
+
+    /// Sets how far (in world units) the border is from the origin, or hides
+    /// it.
+    pub fn set_radius(&mut self, radius: Option<f32>) {
+        self.radius = radius;
+    }
+
+    /// Draws the sides of the border near the camera. This has to be called
+    /// with blending on.
+    pub(super) fn render(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>, gamma: f32, camera: Point3<f32>) {
+        let radius = match self.radius {
+            Some(radius) => radius,
+            None => return,
+        };
+
+        let fade = WALL_FADE_DISTANCE * BLOCK_LENGTH;
+        let half = WALL_HALF_SIZE * BLOCK_LENGTH;
+        let mut vertices = Vec::new();
+
+        // Every side is a plane at ±radius along X or Z. The patch drawn on it
+        // is centered on the camera, opaque in the middle and clear at its
+        // edges.
+        for &(axis, sign) in [(0, 1.0), (0, -1.0), (2, 1.0), (2, -1.0)].iter() {
+            let distance = radius - sign * camera[axis];
+            if distance > fade {
+                continue
+            }
+
+            let opacity = WALL_OPACITY * (1.0 - distance.max(0.0) / fade);
+            let across = Vector3::new(
+                if axis == 0 { 0.0 } else { 1.0 },
+                0.0,
+                if axis == 0 { 1.0 } else { 0.0 },
+            );
+
+            let mut center = camera;
+            center[axis] = sign * radius;
+
+            let corner = |a: f32, b: f32| {
+                let p = center + across * a * half + Vector3::unit_y() * b * half;
+                WallVertex { position: [p.x, p.y, p.z], color: [WALL_COLOR[0], WALL_COLOR[1], WALL_COLOR[2], 0.0] }
+            };
+            let middle = WallVertex {
+                position: [center.x, center.y, center.z],
+                color: [WALL_COLOR[0], WALL_COLOR[1], WALL_COLOR[2], opacity],
+            };
+
+            let corners = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+            for i in 0..4 {
+                vertices.push(middle);
+                vertices.push(corners[i]);
+                vertices.push(corners[(i + 1) % 4]);
+            }
+        }
+
+        if vertices.is_empty() {
+            return
+        }
+
+        self.program.use_program();
+        self.program.use_uniform("projection", projection);
+        self.program.use_uniform("view", view);
+        self.program.use_uniform("gamma", &gamma);
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * std::mem::size_of::<WallVertex>()) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STREAM_DRAW
+            );
+
+            gl::DrawArrays(gl::TRIANGLES, 0, vertices.len() as i32);
+        }
+    }
+}
+
+impl Drop for BorderRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+// The wall is plain colored geometry, like the debug shapes.
+const BORDER_VS_SHADER: &'static str = include_str!("shaders/debug_vs.glsl");
+const BORDER_FS_SHADER: &'static str = include_str!("shaders/debug_fs.glsl");
+
+// How close (in blocks) the camera has to be for the wall to show, and how
+// large the patch of the wall drawn around it is.
+const WALL_FADE_DISTANCE: f32 = 16.0;
+const WALL_HALF_SIZE: f32 = 24.0;
+
+// What the wall looks like up close.
+const WALL_COLOR: [f32; 3] = [0.3, 0.6, 1.0];
+const WALL_OPACITY: f32 = 0.5;
@@ -0,0 +1,193 @@
+use cgmath::Point3;
+use gekraftet_core::world::{ self, ChunkPos };
+use gl::types::*;
+use std::ptr;
+use std::time::{ Duration, Instant };
+
+use crate::mesh::{ Mesh, Vertex };
+use crate::world::{ BLOCK_LENGTH, ChunkMesh };
+use super::memory::GpuMemory;
+
+/// A mesh that has been uploaded to the GPU.
+pub struct GpuMesh {
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+    count: i32,
+    memory: GpuMemory,
+}
+
+impl GpuMesh {
+    /// Uploads the mesh. Returns `None` if the mesh has nothing to draw.
+    pub fn upload(mesh: &Mesh) -> Option<Self> {
+        if mesh.indices().is_empty() {
+            return None
+        }
+
+        let vao = unsafe {
+            let mut vao_id: GLuint = 0;
+            gl::GenVertexArrays(1, &mut vao_id as *mut _);
+            gl::BindVertexArray(vao_id);
+            vao_id
+        };
+
+        let vbo = unsafe {
+            let size = std::mem::size_of::<Vertex>();
+            let mut vbo_id: GLuint = 0;
+            gl::GenBuffers(1, &mut vbo_id as *mut _);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
+            gl_check!(gl::BufferData(
+                gl::ARRAY_BUFFER, 
+                (mesh.vertices().len() * size) as isize,
+                mesh.vertices().as_ptr() as *const _, 
+                gl::STATIC_DRAW
+            ));
+            vbo_id
+        };
+
+        let ebo = unsafe {
+            let mut ebo_id: GLuint = 0;
+            gl::GenBuffers(1, &mut ebo_id as *mut _);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo_id);
+            gl_check!(gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER, 
+                mesh.indices().len() as isize * 4, 
+                mesh.indices().as_ptr() as *mut _, 
+                gl::STATIC_DRAW
+            ));
+            ebo_id
+        };
+
+        unsafe {
+            let stride = std::mem::size_of::<Vertex>() as i32;
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, 12 as *const _);
+            gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, stride, 28 as *const _);
+            gl::EnableVertexAttribArray(0);
+            gl::EnableVertexAttribArray(1);
+            gl::EnableVertexAttribArray(2);
+        };
+
+        Some(Self {
+            vao,
+            vbo,
+            ebo,
+            count: mesh.indices().len() as i32,
+            memory: GpuMemory {
+                vertex_buffers: mesh.vertices().len() * std::mem::size_of::<Vertex>(),
+                index_buffers: mesh.indices().len() * 4,
+                textures: 0,
+            },
+        })
+    }
+
+    pub fn memory(&self) -> GpuMemory {
+        self.memory
+    }
+
+    pub fn draw(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl_check!(gl::DrawElements(gl::TRIANGLES, self.count, gl::UNSIGNED_INT, ptr::null()));
+        }
+    }
+}
+
+impl Drop for GpuMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+/// The GPU side of a chunk: one mesh for each render pass.
+pub struct RenderedChunk {
+    pub position: ChunkPos,
+    pub opaque: Option<GpuMesh>,
+    pub translucent: Option<GpuMesh>,
+    /// The smallest box around both meshes, in world space.
+    pub bounds: (Point3<f32>, Point3<f32>),
+    /// The occlusion query of the chunk, see `OcclusionCuller`.
+    pub query: GLuint,
+    /// When the chunk first appeared, used to fade it in.
+    pub uploaded_at: Instant,
+}
+
+impl RenderedChunk {
+    pub fn new(position: ChunkPos, mesh: &ChunkMesh) -> Self {
+        let mut min = Point3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Point3::new(f32::MIN, f32::MIN, f32::MIN);
+
+        let vertices = mesh.opaque.vertices().iter()
+            .chain(mesh.translucent.vertices());
+
+        for v in vertices {
+            let p = v.position;
+            min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+
+        let query = unsafe {
+            let mut query_id: GLuint = 0;
+            gl::GenQueries(1, &mut query_id as *mut _);
+            query_id
+        };
+
+        Self {
+            position,
+            opaque: GpuMesh::upload(&mesh.opaque),
+            translucent: GpuMesh::upload(&mesh.translucent),
+            bounds: (min, max),
+            query,
+            uploaded_at: Instant::now(),
+        }
+    }
+
+    /// Returns how far the chunk is into its fade-in, from 0.0 to 1.0.
+    pub fn fade_in(&self, duration: Duration) -> f32 {
+        let elapsed = self.uploaded_at.elapsed().as_secs_f32();
+        (elapsed / duration.as_secs_f32()).min(1.0)
+    }
+
+    /// Returns the GPU memory taken by the meshes of the chunk.
+    pub fn memory(&self) -> GpuMemory {
+        let opaque = self.opaque.as_ref().map(GpuMesh::memory).unwrap_or_default();
+        let translucent = self.translucent.as_ref().map(GpuMesh::memory).unwrap_or_default();
+        opaque + translucent
+    }
+
+    /// Checks whether the point lies within `margin` of the bounds.
+    pub fn is_near(&self, point: Point3<f32>, margin: f32) -> bool {
+        let (min, max) = self.bounds;
+
+        point.x >= min.x - margin && point.x <= max.x + margin
+            && point.y >= min.y - margin && point.y <= max.y + margin
+            && point.z >= min.z - margin && point.z <= max.z + margin
+    }
+
+    /// Returns the center of the chunk in world space.
+    pub fn center(&self) -> Point3<f32> {
+        let length = Point3::new(
+            world::CHUNK_LENGTH_X as f32,
+            world::CHUNK_LENGTH_Y as f32,
+            world::CHUNK_LENGTH_Z as f32,
+        );
+
+        Point3::new(
+            (self.position.x as f32 + 0.5) * length.x,
+            (self.position.y as f32 + 0.5) * length.y,
+            (self.position.z as f32 + 0.5) * length.z,
+        ) * BLOCK_LENGTH
+    }
+}
+
+impl Drop for RenderedChunk {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteQueries(1, &self.query);
+        }
+    }
+}
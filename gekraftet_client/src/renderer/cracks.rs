@@ -0,0 +1,160 @@
+use cgmath::{ Matrix4, Vector3, Vector4 };
+use gekraftet_core::world::BlockPos;
+use gl::types::*;
+use std::ptr;
+
+use crate::world::BLOCK_LENGTH;
+use super::shader::{ Linked, ShaderProgram };
+
+/// Cracks drawn over the faces of the block being broken, more of them the
+/// further along it is.
+pub struct BlockCracks {
+    program: ShaderProgram<Linked>,
+    vao: GLuint,
+    vbo: GLuint,
+    color: Vector4<f32>,
+}
+
+impl BlockCracks {
+    pub fn new() -> Self {
+        let program = ShaderProgram::new()
+            .compile_shader(CRACKS_VS_SHADER, CRACKS_FS_SHADER)
+            .unwrap();
+
+        let vertices = crack_vertices();
+
+        let (vao, vbo) = unsafe {
+            let (mut vao, mut vbo) = (0, 0);
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * std::mem::size_of::<[f32; 3]>()) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW
+            );
+
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 12, ptr::null());
+            gl::EnableVertexAttribArray(0);
+            (vao, vbo)
+        };
+
+        Self {
+            program,
+            vao,
+            vbo,
+            color: Vector4::new(0.0, 0.0, 0.0, 0.6),
+        }
+    }
+
+    /// Draws the cracks of the block at the position, which is broken as far
+    /// as `progress` (from 0 to 1). This has to be called with blending on.
+    pub fn render(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>, at: BlockPos, progress: f32) {
+        let stage = ((progress * CRACK_STAGES as f32) as usize).min(CRACK_STAGES - 1);
+        let count = (stage + 1) * SEGMENTS_PER_STAGE * VERTICES_PER_SEGMENT;
+
+        let origin = at.cast::<f32>().unwrap() * BLOCK_LENGTH;
+        let model = Matrix4::from_translation(Vector3::new(origin.x, origin.y, origin.z))
+            * Matrix4::from_scale(BLOCK_LENGTH);
+
+        self.program.use_program();
+        self.program.use_uniform("projection", projection);
+        self.program.use_uniform("view", view);
+        self.program.use_uniform("model", &model);
+        self.program.use_uniform("depth_bias", &DEPTH_BIAS);
+        self.program.use_uniform("color", &self.color);
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, count as i32);
+        }
+    }
+}
+
+impl Drop for BlockCracks {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+// The cracks of every stage, on a unit cube. Every segment is a thin quad
+// on each of the six faces, and the segments of a stage follow the ones of
+// the stages before it, so that drawing a stage means drawing a prefix of
+// the vertices.
+fn crack_vertices() -> Vec<[f32; 3]> {
+    let mut vertices = Vec::new();
+    let mut seed = 0x1234_5678u32;
+
+    // Picks the next number from 0 to 1, the same ones every time.
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        seed as f32 / u32::max_value() as f32
+    };
+
+    for _ in 0..CRACK_STAGES * SEGMENTS_PER_STAGE {
+        for &(origin, u, v) in FACES.iter() {
+            let (start_u, start_v) = (0.1 + next() * 0.8, 0.1 + next() * 0.8);
+            let angle = next() * std::f32::consts::PI * 2.0;
+            let length = 0.15 + next() * 0.2;
+
+            let (du, dv) = (angle.cos(), angle.sin());
+            let (end_u, end_v) = (start_u + du * length, start_v + dv * length);
+            let (end_u, end_v) = (end_u.max(0.0).min(1.0), end_v.max(0.0).min(1.0));
+            let (side_u, side_v) = (-dv * CRACK_WIDTH, du * CRACK_WIDTH);
+
+            let point = |a: f32, b: f32| {
+                let origin = Vector3::from(origin);
+                let p = origin + Vector3::from(u) * a + Vector3::from(v) * b;
+                [p.x, p.y, p.z]
+            };
+
+            let corners = [
+                point(start_u - side_u, start_v - side_v),
+                point(end_u - side_u, end_v - side_v),
+                point(end_u + side_u, end_v + side_v),
+                point(start_u + side_u, start_v + side_v),
+            ];
+
+            vertices.extend_from_slice(&[corners[0], corners[1], corners[2]]);
+            vertices.extend_from_slice(&[corners[0], corners[2], corners[3]]);
+        }
+    }
+
+    vertices
+}
+
+// The cracks have no shading of their own, like the outline.
+const CRACKS_VS_SHADER: &'static str = include_str!("shaders/outline_vs.glsl");
+const CRACKS_FS_SHADER: &'static str = include_str!("shaders/outline_fs.glsl");
+
+// How many stages of cracks there are, and how many segments each stage adds
+// to every face. Every segment is drawn as two triangles on each of the six
+// faces.
+const CRACK_STAGES: usize = 10;
+const SEGMENTS_PER_STAGE: usize = 3;
+const VERTICES_PER_SEGMENT: usize = 6 * 6;
+
+// How wide a crack is, relative to the face.
+const CRACK_WIDTH: f32 = 0.015;
+
+// How far (in NDC depth units, scaled by w) the cracks are pulled towards the
+// camera, so that they win against the faces they lie on.
+const DEPTH_BIAS: f32 = 0.0005;
+
+// The faces of the unit cube: a corner, and the directions along the face
+// from it. The faces are pushed out a little to keep the cracks off them.
+const FACES: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    ([-0.002, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    ([1.002, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    ([0.0, -0.002, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, 1.002, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, 0.0, -0.002], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, 1.002], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+];
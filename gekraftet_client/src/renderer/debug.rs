@@ -0,0 +1,92 @@
+use gl::types::*;
+use log::{ debug, error, info, warn };
+use std::ffi::CStr;
+use std::ptr;
+
+/// Registers a `KHR_debug` callback which forwards the messages of the driver
+/// to the log. Returns `false` if the driver does not support it.
+pub fn install_debug_callback() -> bool {
+    if !gl::DebugMessageCallback::is_loaded() {
+        return false
+    }
+
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+
+        // Report the messages from within the offending call, so they show up
+        // next to the errors caught by check_error().
+        if cfg!(feature = "gl-debug") {
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+
+        gl::DebugMessageCallback(Some(debug_callback), ptr::null());
+    }
+
+    true
+}
+
+extern "system" fn debug_callback(
+    source: GLenum,
+    ty: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    _length: GLsizei,
+    message: *const GLchar,
+    _user: *mut GLvoid,
+) {
+    let message = unsafe { CStr::from_ptr(message).to_string_lossy() };
+    let source = match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    };
+    let ty = match ty {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        _ => "other",
+    };
+
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => error!("GL {} ({}, #{}): {}", ty, source, id, message),
+        gl::DEBUG_SEVERITY_MEDIUM => warn!("GL {} ({}, #{}): {}", ty, source, id, message),
+        gl::DEBUG_SEVERITY_LOW => info!("GL {} ({}, #{}): {}", ty, source, id, message),
+        _ => debug!("GL {} ({}, #{}): {}", ty, source, id, message),
+    }
+}
+
+/// Logs every error queued up by GL. Used by `gl_check!`.
+#[cfg(feature = "gl-debug")]
+pub fn check_error(call: &str, file: &str, line: u32) {
+    loop {
+        let error = unsafe { gl::GetError() };
+
+        let name = match error {
+            gl::NO_ERROR => break,
+            gl::INVALID_ENUM => "GL_INVALID_ENUM",
+            gl::INVALID_VALUE => "GL_INVALID_VALUE",
+            gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
+            gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+            gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+            _ => "unknown error",
+        };
+
+        error!("{} failed at {}:{} with {} (0x{:x})", call, file, line, name, error);
+    }
+}
+
+/// Runs a GL call, then checks glGetError when the `gl-debug` feature is
+/// enabled. Without the feature, this is just the call itself.
+macro_rules! gl_check {
+    ($call:expr) => {{
+        let result = $call;
+        #[cfg(feature = "gl-debug")]
+        $crate::renderer::debug::check_error(stringify!($call), file!(), line!());
+        result
+    }};
+}
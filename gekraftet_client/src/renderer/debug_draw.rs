@@ -0,0 +1,158 @@
+use cgmath::{ InnerSpace, Matrix4, Point3, Vector3 };
+use gl::types::*;
+use std::ptr;
+use std::sync::{ Mutex, MutexGuard };
+
+use crate::RGBA;
+use super::shader::{ Linked, ShaderProgram };
+
+// Shapes queued from anywhere in the client, drawn and cleared once a frame.
+static SHAPES: Mutex<DebugShapes> = Mutex::new(DebugShapes::new());
+
+/// Returns the queue of debug shapes. Shapes added to it are drawn in the
+/// next frame only, so they have to be added again every frame to stay.
+///
+/// ```ignore
+/// debug_draw().aabb(min, max, Vector4::new(1.0, 0.0, 0.0, 1.0));
+/// ```
+pub fn debug_draw() -> MutexGuard<'static, DebugShapes> {
+    // A panic while holding the lock cannot leave the queue in a bad state.
+    SHAPES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct LineVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+/// Lines in world space, waiting to be drawn.
+pub struct DebugShapes {
+    vertices: Vec<LineVertex>,
+}
+
+impl DebugShapes {
+    const fn new() -> Self {
+        Self { vertices: Vec::new() }
+    }
+
+    pub fn line(&mut self, a: Point3<f32>, b: Point3<f32>, color: RGBA) -> &mut Self {
+        let color = [color.x, color.y, color.z, color.w];
+        self.vertices.push(LineVertex { position: [a.x, a.y, a.z], color });
+        self.vertices.push(LineVertex { position: [b.x, b.y, b.z], color });
+        self
+    }
+
+    /// Draws the 12 edges of an axis-aligned box.
+    pub fn aabb(&mut self, min: Point3<f32>, max: Point3<f32>, color: RGBA) -> &mut Self {
+        let corner = |x: bool, y: bool, z: bool| Point3::new(
+            if x { max.x } else { min.x },
+            if y { max.y } else { min.y },
+            if z { max.z } else { min.z },
+        );
+
+        // For each axis, the 4 edges running along it.
+        for &a in [false, true].iter() {
+            for &b in [false, true].iter() {
+                self.line(corner(false, a, b), corner(true, a, b), color);
+                self.line(corner(a, false, b), corner(a, true, b), color);
+                self.line(corner(a, b, false), corner(a, b, true), color);
+            }
+        }
+
+        self
+    }
+
+    /// Draws a ray from `origin`, `length` units long.
+    pub fn ray(&mut self, origin: Point3<f32>, direction: Vector3<f32>, length: f32, color: RGBA) -> &mut Self {
+        let end = origin + direction.normalize() * length;
+        self.line(origin, end, color)
+    }
+
+    /// Draws a line through every point, in order.
+    pub fn path(&mut self, points: &[Point3<f32>], color: RGBA) -> &mut Self {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
+
+/// Draws the queued debug shapes.
+pub struct DebugDrawRenderer {
+    program: ShaderProgram<Linked>,
+    vao: GLuint,
+    vbo: GLuint,
+}
+
+impl DebugDrawRenderer {
+    pub fn new() -> Self {
+        let program = ShaderProgram::new()
+            .compile_shader(DEBUG_VS_SHADER, DEBUG_FS_SHADER)
+            .unwrap();
+
+        let (vao, vbo) = unsafe {
+            let (mut vao, mut vbo) = (0, 0);
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            let stride = std::mem::size_of::<LineVertex>() as i32;
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, 12 as *const _);
+            gl::EnableVertexAttribArray(0);
+            gl::EnableVertexAttribArray(1);
+            (vao, vbo)
+        };
+
+        Self { program, vao, vbo }
+    }
+
+    /// Draws everything queued since the last frame, then empties the queue.
+    pub fn flush(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>, gamma: f32) {
+        let mut shapes = debug_draw();
+
+        if shapes.vertices.is_empty() {
+            return
+        }
+
+        self.program.use_program();
+        self.program.use_uniform("projection", projection);
+        self.program.use_uniform("view", view);
+        self.program.use_uniform("gamma", &gamma);
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (shapes.vertices.len() * std::mem::size_of::<LineVertex>()) as isize,
+                shapes.vertices.as_ptr() as *const _,
+                gl::STREAM_DRAW
+            );
+
+            gl::DrawArrays(gl::LINES, 0, shapes.vertices.len() as i32);
+        }
+
+        shapes.clear();
+    }
+}
+
+impl Drop for DebugDrawRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+const DEBUG_VS_SHADER: &'static str = include_str!("shaders/debug_vs.glsl");
+const DEBUG_FS_SHADER: &'static str = include_str!("shaders/debug_fs.glsl");
@@ -0,0 +1,284 @@
+use cgmath::{ Matrix4, Point3, Vector3 };
+use gl::types::*;
+use std::ptr;
+
+use super::Fog;
+use super::memory::GpuMemory;
+use super::shader::{ Linked, ShaderProgram };
+
+/// The number of point lights the lighting pass can handle at once.
+pub const MAX_POINT_LIGHTS: usize = 32;
+
+/// A light that shines in every direction, fading out at `radius`.
+#[derive(Clone, Debug)]
+pub struct PointLight {
+    pub position: Point3<f32>,
+    pub color: Vector3<f32>,
+    pub radius: f32,
+}
+
+/// The render targets used by the deferred path.
+///
+/// The geometry pass writes the albedo, the normals and the depth of the
+/// opaque blocks into the G-buffer. The lighting pass then reads them, and
+/// writes the lit image into the composite target, which shares the depth of
+/// the G-buffer so forward passes (translucent blocks, outlines) can still be
+/// depth-tested against the world.
+struct GBuffer {
+    geometry_fbo: GLuint,
+    composite_fbo: GLuint,
+
+    albedo: GLuint,
+    normal: GLuint,
+    depth: GLuint,
+    composite: GLuint,
+
+    width: i32,
+    height: i32,
+}
+
+impl GBuffer {
+    fn new(width: u32, height: u32) -> Self {
+        let mut gbuffer = unsafe {
+            let mut fbos = [0; 2];
+            let mut textures = [0; 4];
+            gl::GenFramebuffers(2, fbos.as_mut_ptr());
+            gl::GenTextures(4, textures.as_mut_ptr());
+
+            Self {
+                geometry_fbo: fbos[0],
+                composite_fbo: fbos[1],
+                albedo: textures[0],
+                normal: textures[1],
+                depth: textures[2],
+                composite: textures[3],
+                width: 0,
+                height: 0,
+            }
+        };
+
+        gbuffer.resize(width, height);
+        gbuffer
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width.max(1) as i32;
+        self.height = height.max(1) as i32;
+
+        let allocate = |texture, internal: GLenum, format: GLenum, ty: GLenum| unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl_check!(gl::TexImage2D(
+                gl::TEXTURE_2D, 0, internal as i32, self.width, self.height, 0,
+                format, ty, ptr::null()
+            ));
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        };
+
+        allocate(self.albedo, gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE);
+        allocate(self.normal, gl::RGB16F, gl::RGB, gl::FLOAT);
+        allocate(self.depth, gl::DEPTH_COMPONENT24, gl::DEPTH_COMPONENT, gl::FLOAT);
+        // The composite may be post-processed, so it keeps values above 1.0.
+        allocate(self.composite, gl::RGBA16F, gl::RGBA, gl::FLOAT);
+
+        unsafe {
+            let attach = |attachment, texture| gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER, attachment, gl::TEXTURE_2D, texture, 0
+            );
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.geometry_fbo);
+            attach(gl::COLOR_ATTACHMENT0, self.albedo);
+            attach(gl::COLOR_ATTACHMENT1, self.normal);
+            attach(gl::DEPTH_ATTACHMENT, self.depth);
+            gl::DrawBuffers(2, [gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT1].as_ptr());
+            Self::check_status("G-buffer");
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.composite_fbo);
+            attach(gl::COLOR_ATTACHMENT0, self.composite);
+            attach(gl::DEPTH_ATTACHMENT, self.depth);
+            Self::check_status("composite target");
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    fn memory(&self) -> GpuMemory {
+        // albedo, normal (3 half floats), depth (padded to 4 bytes), composite
+        // (4 half floats)
+        let pixels = (self.width * self.height) as usize;
+
+        GpuMemory {
+            textures: pixels * (4 + 6 + 4 + 8),
+            ..GpuMemory::default()
+        }
+    }
+
+    fn check_status(name: &str) {
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            panic!("{} is incomplete - got status {}", name, status);
+        }
+    }
+}
+
+impl Drop for GBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(2, [self.geometry_fbo, self.composite_fbo].as_ptr());
+            gl::DeleteTextures(4, [self.albedo, self.normal, self.depth, self.composite].as_ptr());
+        }
+    }
+}
+
+/// The deferred shading path: a geometry pass filling the G-buffer, followed
+/// by a full-screen lighting pass.
+pub struct DeferredPipeline {
+    gbuffer: GBuffer,
+    geometry_program: ShaderProgram<Linked>,
+    lighting_program: ShaderProgram<Linked>,
+    // The full-screen triangle is generated in the vertex shader, but the core
+    // profile still needs a VAO to be bound when drawing.
+    empty_vao: GLuint,
+    lights: Vec<PointLight>,
+}
+
+impl DeferredPipeline {
+    pub fn new(width: u32, height: u32) -> Self {
+        let geometry_program = ShaderProgram::new()
+            .compile_shader(GEOMETRY_VS_SHADER, GEOMETRY_FS_SHADER)
+            .unwrap();
+        let lighting_program = ShaderProgram::new()
+            .compile_shader(LIGHTING_VS_SHADER, LIGHTING_FS_SHADER)
+            .unwrap();
+
+        let empty_vao = unsafe {
+            let mut vao = 0;
+            gl::GenVertexArrays(1, &mut vao);
+            vao
+        };
+
+        Self {
+            gbuffer: GBuffer::new(width, height),
+            geometry_program,
+            lighting_program,
+            empty_vao,
+            lights: Vec::new(),
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.gbuffer.resize(width, height);
+    }
+
+    pub fn memory(&self) -> GpuMemory {
+        self.gbuffer.memory()
+    }
+
+    /// Replaces the point lights. Only the first `MAX_POINT_LIGHTS` are used.
+    pub fn set_point_lights(&mut self, lights: Vec<PointLight>) {
+        self.lights = lights;
+    }
+
+    pub fn geometry_program(&self) -> &ShaderProgram<Linked> {
+        &self.geometry_program
+    }
+
+    /// Binds the G-buffer and the geometry program. Opaque meshes drawn
+    /// afterwards end up in the G-buffer.
+    pub fn begin_geometry(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>, model: &Matrix4<f32>) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.gbuffer.geometry_fbo);
+            // An albedo with zero alpha marks the sky.
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let p = &self.geometry_program;
+        p.use_program();
+        p.use_uniform("projection", projection);
+        p.use_uniform("view", view);
+        p.use_uniform("model", model);
+    }
+
+    /// Lights the G-buffer into the composite target, and leaves the target
+    /// bound so forward passes can draw on top of it.
+    pub fn light(
+        &self,
+        projection: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+        camera: Point3<f32>,
+        gamma: f32,
+        fog: Fog,
+    ) {
+        use cgmath::SquareMatrix;
+
+        let inverse = (projection * view).invert().unwrap_or(Matrix4::identity());
+
+        let mut light_data = [0.0f32; MAX_POINT_LIGHTS * 8];
+        let count = self.lights.len().min(MAX_POINT_LIGHTS);
+        for (i, light) in self.lights.iter().take(count).enumerate() {
+            light_data[i * 8..i * 8 + 8].copy_from_slice(&[
+                light.position.x, light.position.y, light.position.z, light.radius,
+                light.color.x, light.color.y, light.color.z, 0.0,
+            ]);
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.gbuffer.composite_fbo);
+            gl::Disable(gl::DEPTH_TEST);
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.gbuffer.albedo);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, self.gbuffer.normal);
+            gl::ActiveTexture(gl::TEXTURE2);
+            gl::BindTexture(gl::TEXTURE_2D, self.gbuffer.depth);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        let p = &self.lighting_program;
+        p.use_program();
+        p.use_uniform("inverse_view_projection", &inverse);
+        p.use_uniform("camera", &Vector3::new(camera.x, camera.y, camera.z));
+        p.use_uniform("gamma", &gamma);
+        p.use_uniform("fog_color", &fog.color);
+        p.use_uniform("fog_density", &fog.density);
+        p.use_uniform("light_count", &(count as f32));
+        p.use_uniform("light_data", &&light_data[..]);
+        p.use_uniform("albedo_tex", &0i32);
+        p.use_uniform("normal_tex", &1i32);
+        p.use_uniform("depth_tex", &2i32);
+
+        unsafe {
+            gl::BindVertexArray(self.empty_vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+
+    /// Copies the composite target into the `target` framebuffer, and binds it.
+    pub fn resolve(&self, target: GLuint) {
+        let (w, h) = (self.gbuffer.width, self.gbuffer.height);
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.gbuffer.composite_fbo);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target);
+            gl_check!(gl::BlitFramebuffer(0, 0, w, h, 0, 0, w, h, gl::COLOR_BUFFER_BIT, gl::NEAREST));
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target);
+        }
+    }
+}
+
+impl Drop for DeferredPipeline {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.empty_vao);
+        }
+    }
+}
+
+const GEOMETRY_VS_SHADER: &'static str = include_str!("shaders/gbuffer_vs.glsl");
+const GEOMETRY_FS_SHADER: &'static str = include_str!("shaders/gbuffer_fs.glsl");
+const LIGHTING_VS_SHADER: &'static str = include_str!("shaders/fullscreen_vs.glsl");
+const LIGHTING_FS_SHADER: &'static str = include_str!("shaders/lighting_fs.glsl");
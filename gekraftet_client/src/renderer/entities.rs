@@ -0,0 +1,222 @@
+use cgmath::{ Matrix4, Point2, Point3, Vector3 };
+use gl::types::*;
+use std::ptr;
+
+use crate::RGBA;
+use crate::mesh::Vertex;
+use super::Fog;
+use super::memory::GpuMemory;
+use super::shader::{ Linked, ShaderProgram };
+
+/// A box drawn for an entity, in world units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EntityInstance {
+    pub min: Point3<f32>,
+    pub size: Vector3<f32>,
+    pub color: RGBA,
+    /// The rotation around the vertical axis through the center of the box,
+    /// in radians.
+    pub yaw: f32,
+}
+
+// The layout of an instance in the instance buffer.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct InstanceData {
+    min: [f32; 3],
+    size: [f32; 3],
+    color: [f32; 4],
+    yaw: f32,
+}
+
+/// Draws entities as boxes. A single unit cube is drawn once per entity with
+/// instancing, so that many entities cost a single draw call.
+pub struct EntityRenderer {
+    program: ShaderProgram<Linked>,
+    vao: GLuint,
+    cube_vbo: GLuint,
+    cube_ebo: GLuint,
+    instance_vbo: GLuint,
+    instances: Vec<InstanceData>,
+    // How many instances the buffer has room for.
+    capacity: usize,
+}
+
+impl EntityRenderer {
+    pub fn new() -> Self {
+        let program = ShaderProgram::new()
+            .compile_shader(ENTITY_VS_SHADER, ENTITY_FS_SHADER)
+            .unwrap();
+
+        let (vertices, indices) = unit_cube();
+
+        unsafe {
+            let (mut vao, mut cube_vbo, mut cube_ebo, mut instance_vbo) = (0, 0, 0, 0);
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut cube_vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, cube_vbo);
+            gl_check!(gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * std::mem::size_of::<Vertex>()) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW
+            ));
+
+            let stride = std::mem::size_of::<Vertex>() as i32;
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, 12 as *const _);
+            gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, stride, 28 as *const _);
+            gl::EnableVertexAttribArray(0);
+            gl::EnableVertexAttribArray(1);
+            gl::EnableVertexAttribArray(2);
+
+            gl::GenBuffers(1, &mut cube_ebo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, cube_ebo);
+            gl_check!(gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                indices.len() as isize * 4,
+                indices.as_ptr() as *const _,
+                gl::STATIC_DRAW
+            ));
+
+            // The instance attributes advance once per cube, not per vertex.
+            gl::GenBuffers(1, &mut instance_vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, instance_vbo);
+
+            let stride = std::mem::size_of::<InstanceData>() as i32;
+            gl::VertexAttribPointer(3, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribPointer(4, 3, gl::FLOAT, gl::FALSE, stride, 12 as *const _);
+            gl::VertexAttribPointer(5, 4, gl::FLOAT, gl::FALSE, stride, 24 as *const _);
+            gl::VertexAttribPointer(6, 1, gl::FLOAT, gl::FALSE, stride, 40 as *const _);
+
+            for attribute in 3..=6 {
+                gl::EnableVertexAttribArray(attribute);
+                gl::VertexAttribDivisor(attribute, 1);
+            }
+
+            gl::BindVertexArray(0);
+
+            Self {
+                program,
+                vao,
+                cube_vbo,
+                cube_ebo,
+                instance_vbo,
+                instances: Vec::new(),
+                capacity: 0,
+            }
+        }
+    }
+
+    /// Replaces the entities drawn from the next frame on.
+    pub fn set_instances(&mut self, instances: &[EntityInstance]) {
+        self.instances.clear();
+        self.instances.extend(instances.iter().map(|i| InstanceData {
+            min: [i.min.x, i.min.y, i.min.z],
+            size: [i.size.x, i.size.y, i.size.z],
+            color: [i.color.x, i.color.y, i.color.z, i.color.w],
+            yaw: i.yaw,
+        }));
+    }
+
+    pub fn memory(&self) -> GpuMemory {
+        GpuMemory {
+            vertex_buffers: CUBE_VERTICES * std::mem::size_of::<Vertex>()
+                + self.capacity * std::mem::size_of::<InstanceData>(),
+            index_buffers: CUBE_INDICES * 4,
+            textures: 0,
+        }
+    }
+
+    pub(super) fn render(&mut self, projection: &Matrix4<f32>, view: &Matrix4<f32>, gamma: f32, fog: Fog) {
+        if self.instances.is_empty() {
+            return
+        }
+
+        self.program.use_program();
+        self.program.use_uniform("projection", projection);
+        self.program.use_uniform("view", view);
+        self.program.use_uniform("gamma", &gamma);
+        self.program.use_uniform("fog_color", &fog.color);
+        self.program.use_uniform("fog_density", &fog.density);
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_vbo);
+
+            // The buffer only grows, so that it is not reallocated every
+            // frame as entities come and go.
+            let size = (self.instances.len() * std::mem::size_of::<InstanceData>()) as isize;
+            if self.instances.len() > self.capacity {
+                self.capacity = self.instances.len().next_power_of_two();
+                gl_check!(gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    (self.capacity * std::mem::size_of::<InstanceData>()) as isize,
+                    ptr::null(),
+                    gl::STREAM_DRAW
+                ));
+            }
+            gl::BufferSubData(gl::ARRAY_BUFFER, 0, size, self.instances.as_ptr() as *const _);
+
+            gl_check!(gl::DrawElementsInstanced(
+                gl::TRIANGLES,
+                CUBE_INDICES as i32,
+                gl::UNSIGNED_INT,
+                ptr::null(),
+                self.instances.len() as i32
+            ));
+
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl Drop for EntityRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.cube_vbo);
+            gl::DeleteBuffers(1, &self.cube_ebo);
+            gl::DeleteBuffers(1, &self.instance_vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+// A cube from (0, 0, 0) to (1, 1, 1), with every face shaded like the faces
+// of blocks facing the same way.
+fn unit_cube() -> (Vec<Vertex>, Vec<u32>) {
+    let white = RGBA::new(1.0, 1.0, 1.0, 1.0);
+
+    // The corners of every face, counter-clockwise when seen from outside.
+    let faces: [([[f32; 3]; 4], f32); 6] = [
+        ([[0., 0., 0.], [0., 0., 1.], [0., 1., 1.], [0., 1., 0.]], 0.7),
+        ([[1., 0., 1.], [1., 0., 0.], [1., 1., 0.], [1., 1., 1.]], 0.7),
+        ([[0., 0., 0.], [1., 0., 0.], [1., 0., 1.], [0., 0., 1.]], 0.5),
+        ([[0., 1., 1.], [1., 1., 1.], [1., 1., 0.], [0., 1., 0.]], 1.0),
+        ([[1., 0., 0.], [0., 0., 0.], [0., 1., 0.], [1., 1., 0.]], 0.8),
+        ([[0., 0., 1.], [1., 0., 1.], [1., 1., 1.], [0., 1., 1.]], 0.8),
+    ];
+
+    let mut vertices = Vec::with_capacity(CUBE_VERTICES);
+    let mut indices = Vec::with_capacity(CUBE_INDICES);
+
+    for (corners, shade) in faces.iter() {
+        let base = vertices.len() as u32;
+
+        for c in corners.iter() {
+            vertices.push(Vertex::new(Point3::new(c[0], c[1], c[2]), white, Point2::new(*shade, 0.0)));
+        }
+
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    (vertices, indices)
+}
+
+const CUBE_VERTICES: usize = 24;
+const CUBE_INDICES: usize = 36;
+
+const ENTITY_VS_SHADER: &'static str = include_str!("shaders/entity_vs.glsl");
+const ENTITY_FS_SHADER: &'static str = include_str!("shaders/entity_fs.glsl");
@@ -0,0 +1,127 @@
+use gl::types::*;
+
+use super::memory::GpuMemory;
+
+/// An off-screen multisampled render target. The world is drawn into it, and
+/// then resolved into another framebuffer before the HUD is drawn.
+pub struct MultisampleTarget {
+    fbo: GLuint,
+    color: GLuint,
+    depth: GLuint,
+    format: GLenum,
+    samples: i32,
+    width: i32,
+    height: i32,
+}
+
+impl MultisampleTarget {
+    /// Resolving requires the same color format on both sides, so `format`
+    /// has to match the framebuffer the target is resolved into.
+    pub fn new(width: u32, height: u32, samples: u32, format: GLenum) -> Self {
+        let max_samples = unsafe {
+            let mut max = 0;
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut max);
+            max
+        };
+
+        let mut target = unsafe {
+            let (mut fbo, mut rbs) = (0, [0; 2]);
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::GenRenderbuffers(2, rbs.as_mut_ptr());
+
+            Self {
+                fbo,
+                color: rbs[0],
+                depth: rbs[1],
+                format,
+                samples: (samples as i32).min(max_samples),
+                width: 0,
+                height: 0,
+            }
+        };
+
+        target.resize(width, height);
+        target
+    }
+
+    /// Returns the memory taken by the renderbuffers. The depth is assumed to
+    /// be padded to 4 bytes, as most drivers do.
+    pub fn memory(&self) -> GpuMemory {
+        let pixels = (self.width * self.height * self.samples.max(1)) as usize;
+        let color = match self.format {
+            gl::RGBA16F => 8,
+            _ => 4,
+        };
+
+        GpuMemory {
+            textures: pixels * (color + 4),
+            ..GpuMemory::default()
+        }
+    }
+
+    /// Reallocates the storage of the target. The previous contents are lost.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        // Zero-sized renderbuffers are incomplete, which happens when the
+        // window gets minimized.
+        self.width = width.max(1) as i32;
+        self.height = height.max(1) as i32;
+
+        unsafe {
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.color);
+            gl_check!(gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER, self.samples, self.format, self.width, self.height
+            ));
+
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth);
+            gl_check!(gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER, self.samples, gl::DEPTH_COMPONENT24, self.width, self.height
+            ));
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, self.color
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, self.depth
+            );
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                panic!("multisampled framebuffer is incomplete - got status {}", status);
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Directs all subsequent draws into this target.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        }
+    }
+
+    /// Resolves the samples into the `target` framebuffer, and binds it.
+    pub fn resolve(&self, target: GLuint) {
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target);
+            gl_check!(gl::BlitFramebuffer(
+                0, 0, self.width, self.height,
+                0, 0, self.width, self.height,
+                gl::COLOR_BUFFER_BIT,
+                gl::NEAREST
+            ));
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target);
+        }
+    }
+}
+
+impl Drop for MultisampleTarget {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteRenderbuffers(2, [self.color, self.depth].as_ptr());
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use cgmath::{ Point2, Point3, Vector2 };
+use gl::types::*;
+use std::collections::BTreeMap;
+use std::ptr;
+
+use crate::RGBA;
+use crate::mesh::{ Mesh, MeshBuilder, Vertex };
+use super::shader::{ Linked, ShaderProgram };
+
+/// The places on the screen where HUD elements can live. Every slot is tied to
+/// an anchor point on the window, so elements stay in place when the window is
+/// resized.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
+pub enum HudSlot {
+    Crosshair,
+    Hotbar,
+    Health,
+    /// The top-right corner of the window.
+    Minimap,
+    /// The bottom-left corner of the window, above the hotbar.
+    Chat,
+    /// The center of the window, for menus drawn over everything else.
+    Menu,
+}
+
+impl HudSlot {
+    /// Returns the anchor point of the slot, in logical pixels.
+    fn anchor(self, size: Vector2<f32>) -> Point2<f32> {
+        match self {
+            HudSlot::Crosshair => Point2::new(size.x * 0.5, size.y * 0.5),
+            HudSlot::Hotbar => Point2::new(size.x * 0.5, size.y - 8.0),
+            HudSlot::Health => Point2::new(size.x * 0.5, size.y - 56.0),
+            HudSlot::Minimap => Point2::new(size.x - 8.0, 8.0),
+            HudSlot::Chat => Point2::new(8.0, size.y - 72.0),
+            HudSlot::Menu => Point2::new(size.x * 0.5, size.y * 0.5),
+        }
+    }
+}
+
+/// A 2D element drawn on the HUD.
+pub trait HudElement {
+    /// Appends the quads of this element into the builder. `anchor` is the
+    /// anchor point of the slot the element is in, in logical pixels.
+    fn build(&self, mb: MeshBuilder, anchor: Point2<f32>) -> MeshBuilder;
+}
+
+/// The default crosshair: two thin bars crossing at the center of the screen.
+pub struct Crosshair {
+    pub length: f32,
+    pub thickness: f32,
+    pub color: RGBA,
+}
+
+impl Default for Crosshair {
+    fn default() -> Self {
+        Self {
+            length: 16.0,
+            thickness: 2.0,
+            color: RGBA::new(1.0, 1.0, 1.0, 0.8),
+        }
+    }
+}
+
+impl HudElement for Crosshair {
+    fn build(&self, mb: MeshBuilder, anchor: Point2<f32>) -> MeshBuilder {
+        let (half_len, half_thick) = (self.length * 0.5, self.thickness * 0.5);
+
+        let mb = add_quad(
+            mb,
+            Point2::new(anchor.x - half_len, anchor.y - half_thick),
+            Point2::new(anchor.x + half_len, anchor.y + half_thick),
+            self.color,
+        );
+
+        // Skip the middle part, it has been covered by the horizontal bar.
+        let mb = add_quad(
+            mb,
+            Point2::new(anchor.x - half_thick, anchor.y - half_len),
+            Point2::new(anchor.x + half_thick, anchor.y - half_thick),
+            self.color,
+        );
+
+        add_quad(
+            mb,
+            Point2::new(anchor.x - half_thick, anchor.y + half_thick),
+            Point2::new(anchor.x + half_thick, anchor.y + half_len),
+            self.color,
+        )
+    }
+}
+
+/// Appends an axis-aligned rectangle spanning from `min` to `max` (in logical
+/// pixels, with the origin at the top-left corner of the window).
+pub fn add_quad(mb: MeshBuilder, min: Point2<f32>, max: Point2<f32>, color: RGBA) -> MeshBuilder {
+    let vertex = |x, y| Vertex::new(Point3::new(x, y, 0.0), color, Point2::new(1.0, 0.0));
+
+    let quad = MeshBuilder::new()
+        .add_vertex(vertex(min.x, min.y))
+        .add_vertex(vertex(min.x, max.y))
+        .add_vertex(vertex(max.x, max.y))
+        .add_vertex(vertex(max.x, min.y))
+        .extend_index(vec![0, 1, 2, 0, 2, 3])
+        .build();
+
+    mb.add_mesh(quad)
+}
+
+/// The 2D overlay drawn after the world. Everything on the HUD is specified in
+/// logical pixels, and scaled by the window's scale factor when drawn.
+pub struct Hud {
+    program: ShaderProgram<Linked>,
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+    index_count: i32,
+
+    // Physical size of the framebuffer and the DPI scale factor of the window
+    physical_size: Vector2<f32>,
+    scale_factor: f32,
+
+    elements: BTreeMap<HudSlot, Box<dyn HudElement>>,
+    dirty: bool,
+}
+
+impl Hud {
+    pub fn new(width: u32, height: u32, scale_factor: f64) -> Self {
+        let program = ShaderProgram::new()
+            .compile_shader(HUD_VS_SHADER, HUD_FS_SHADER)
+            .unwrap();
+
+        let (vao, vbo, ebo) = unsafe {
+            let (mut vao, mut vbo, mut ebo) = (0, 0, 0);
+            gl::GenVertexArrays(1, &mut vao as *mut _);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo as *mut _);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::GenBuffers(1, &mut ebo as *mut _);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+
+            let stride = std::mem::size_of::<Vertex>() as i32;
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, 12 as *const _);
+            gl::EnableVertexAttribArray(0);
+            gl::EnableVertexAttribArray(1);
+            (vao, vbo, ebo)
+        };
+
+        let mut elements = BTreeMap::<HudSlot, Box<dyn HudElement>>::new();
+        elements.insert(HudSlot::Crosshair, Box::new(Crosshair::default()));
+
+        Self {
+            program,
+            vao,
+            vbo,
+            ebo,
+            index_count: 0,
+            physical_size: Vector2::new(width as f32, height as f32),
+            scale_factor: scale_factor as f32,
+            elements,
+            dirty: true,
+        }
+    }
+
+    /// Puts an element into the slot, replacing the previous one.
+    pub fn set_element(&mut self, slot: HudSlot, element: Box<dyn HudElement>) {
+        self.elements.insert(slot, element);
+        self.dirty = true;
+    }
+
+    /// Removes the element in the slot, if there is any.
+    pub fn clear_element(&mut self, slot: HudSlot) {
+        self.elements.remove(&slot);
+        self.dirty = true;
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.physical_size = Vector2::new(width as f32, height as f32);
+        self.dirty = true;
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor as f32;
+        self.dirty = true;
+    }
+
+    fn logical_size(&self) -> Vector2<f32> {
+        self.physical_size / self.scale_factor
+    }
+
+    fn rebuild(&mut self) {
+        let size = self.logical_size();
+        let mesh = self.elements
+            .iter()
+            .fold(MeshBuilder::new(), |mb, (slot, element)| {
+                element.build(mb, slot.anchor(size))
+            })
+            .build();
+
+        self.upload(&mesh);
+        self.dirty = false;
+    }
+
+    fn upload(&mut self, mesh: &Mesh) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl_check!(gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (mesh.vertices().len() * std::mem::size_of::<Vertex>()) as isize,
+                mesh.vertices().as_ptr() as *const _,
+                gl::DYNAMIC_DRAW
+            ));
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+            gl_check!(gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                mesh.indices().len() as isize * 4,
+                mesh.indices().as_ptr() as *const _,
+                gl::DYNAMIC_DRAW
+            ));
+        }
+
+        self.index_count = mesh.indices().len() as i32;
+    }
+
+    pub fn render(&mut self) {
+        if self.dirty {
+            self.rebuild();
+        }
+
+        if self.index_count == 0 {
+            return
+        }
+
+        // Logical pixels, with the origin at the top-left corner.
+        let size = self.logical_size();
+        let projection = cgmath::ortho(0.0, size.x, size.y, 0.0, -1.0, 1.0);
+
+        self.program.use_program();
+        self.program.use_uniform("projection", &projection);
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+
+            gl::BindVertexArray(self.vao);
+            gl_check!(gl::DrawElements(gl::TRIANGLES, self.index_count, gl::UNSIGNED_INT, ptr::null()));
+
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
+
+impl Drop for Hud {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+const HUD_VS_SHADER: &'static str = include_str!("shaders/hud_vs.glsl");
+const HUD_FS_SHADER: &'static str = include_str!("shaders/hud_fs.glsl");
@@ -0,0 +1,52 @@
+use std::ops::{ Add, AddAssign, Sub, SubAssign };
+
+/// An amount of GPU memory held by the renderer, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GpuMemory {
+    pub vertex_buffers: usize,
+    pub index_buffers: usize,
+    /// Textures and renderbuffers, including render targets.
+    pub textures: usize,
+}
+
+impl GpuMemory {
+    pub fn total(&self) -> usize {
+        self.vertex_buffers + self.index_buffers + self.textures
+    }
+}
+
+impl Add for GpuMemory {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            vertex_buffers: self.vertex_buffers + other.vertex_buffers,
+            index_buffers: self.index_buffers + other.index_buffers,
+            textures: self.textures + other.textures,
+        }
+    }
+}
+
+impl AddAssign for GpuMemory {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for GpuMemory {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            vertex_buffers: self.vertex_buffers - other.vertex_buffers,
+            index_buffers: self.index_buffers - other.index_buffers,
+            textures: self.textures - other.textures,
+        }
+    }
+}
+
+impl SubAssign for GpuMemory {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
@@ -1,116 +1,665 @@
+#[macro_use]
+mod debug;
+
+mod border;
+mod chunk;
+mod cracks;
+mod debug_draw;
+mod deferred;
+mod entities;
 mod errors;
+mod font;
+mod framebuffer;
+mod hud;
+mod memory;
+mod occlusion;
+mod outline;
+mod particles;
+mod postprocess;
 mod shader;
 mod uniform;
 
-use crate::windowing::Window;
-use crate::mesh::Mesh;
-use gl::types::*;
+use crate::settings::{ GraphicsSettings, PostEffects, RenderPath };
+use crate::windowing::{ Viewport, WindowContext };
+use crate::world::{ BLOCK_LENGTH, ChunkMesh };
 use shader::{ Linked, ShaderProgram };
-use cgmath::Matrix4;
-use std::ptr;
+use std::collections::VecDeque;
+use std::time::{ Duration, Instant };
+use cgmath::{ Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector2, Vector3 };
+use gekraftet_core::utils::{ Aabb, Frustum };
+use gekraftet_core::world::{ self, BlockPos, ChunkPos };
+use border::BorderRenderer;
+use chunk::{ GpuMesh, RenderedChunk };
+use cracks::BlockCracks;
+use debug_draw::DebugDrawRenderer;
+use deferred::DeferredPipeline;
+use entities::EntityRenderer;
+use framebuffer::MultisampleTarget;
+use hud::Hud;
+use occlusion::OcclusionCuller;
+use outline::BlockOutline;
+use postprocess::PostProcessor;
 
+pub use debug_draw::{ debug_draw, DebugShapes };
+pub use deferred::PointLight;
+pub use entities::EntityInstance;
 pub use errors::RenderError;
+pub use font::{ add_text, text_height, text_width };
+pub use memory::GpuMemory;
+pub use particles::{ EmitterId, ParticleEmitter, ParticleSystem };
+pub use hud::{ add_quad, Crosshair, HudElement, HudSlot };
+
+// Fog over the world. The color is in linear space, and a density of zero
+// turns the fog off.
+#[derive(Clone, Copy, Debug)]
+struct Fog {
+    color: Vector3<f32>,
+    density: f32,
+}
 
 pub struct GlRenderer {
     projection: Matrix4<f32>,
-    programs: [ShaderProgram<Linked>; 1],
-    vaos: Vec<(GLuint, i32)>,
+    fov: Deg<f32>,
+    aspect_ratio: f32,
+    orthographic: Option<f32>,
+    chunk_program: ShaderProgram<Linked>,
+    chunks: Vec<RenderedChunk>,
+    // Meshes waiting to be uploaded, see queue_chunk().
+    pending_chunks: VecDeque<(ChunkPos, ChunkMesh)>,
+    upload_budget: Duration,
+    view_distance: u32,
+    outline: BlockOutline,
+    debug_shapes: DebugDrawRenderer,
+    targeted_block: Option<BlockPos>,
+    cracks: BlockCracks,
+    // The block being broken, and how far along it is.
+    breaking: Option<(BlockPos, f32)>,
+    underwater: bool,
+    // How heavy the rain is, see set_rain().
+    rain: f32,
+    hud: Hud,
+    particles: ParticleSystem,
+    entities: EntityRenderer,
+    border: BorderRenderer,
+    msaa: Option<MultisampleTarget>,
+    deferred: Option<DeferredPipeline>,
+    occlusion: Option<OcclusionCuller>,
+    post: Option<PostProcessor>,
+    gamma: f32,
+    // The memory of all chunks is kept up-to-date as they are added, so the
+    // budget can be checked without walking every chunk.
+    chunk_memory: GpuMemory,
+    memory_budget: usize,
+    over_budget: bool,
 }
 
 impl GlRenderer {
-    pub fn new(ctx: &Window, proj: Matrix4<f32>) -> Self {
-        gl::load_with(|s| ctx.context().get_proc_address(s) as *const _);
+    pub fn new(ctx: &WindowContext, settings: &GraphicsSettings) -> Self {
+        gl::load_with(|s| ctx.get_proc_address(s));
+
+        if !debug::install_debug_callback() {
+            log::info!("KHR_debug is unavailable, GL errors will not be reported by the driver");
+        }
 
         let prog = ShaderProgram::new();
         let prog = prog.compile_shader(VS_SHADER, FS_SHADER).unwrap();
 
+        let viewport = ctx.viewport();
+        let fov = Deg(settings.fov);
+        let aspect_ratio = viewport.width.max(1) as f32 / viewport.height.max(1) as f32;
+
         Self { 
-            projection: proj, 
-            programs: [prog],
-            vaos: Vec::new(),
+            projection: cgmath::perspective(fov, aspect_ratio, NEAR_PLANE, FAR_PLANE),
+            fov,
+            aspect_ratio,
+            orthographic: None,
+            chunk_program: prog,
+            chunks: Vec::new(),
+            pending_chunks: VecDeque::new(),
+            upload_budget: settings.upload_budget,
+            view_distance: settings.view_distance,
+            outline: BlockOutline::new(),
+            cracks: BlockCracks::new(),
+            breaking: None,
+            debug_shapes: DebugDrawRenderer::new(),
+            targeted_block: None,
+            underwater: false,
+            rain: 0.0,
+            hud: Hud::new(viewport.width, viewport.height, viewport.scale_factor),
+            particles: ParticleSystem::new(),
+            entities: EntityRenderer::new(),
+            border: BorderRenderer::new(),
+            msaa: match (settings.render_path, settings.msaa.samples()) {
+                (RenderPath::Deferred, _) | (_, 0) => None,
+                (_, samples) => {
+                    let format = if settings.post_processing { gl::RGBA16F } else { gl::RGBA8 };
+                    Some(MultisampleTarget::new(viewport.width, viewport.height, samples, format))
+                },
+            },
+            deferred: match settings.render_path {
+                RenderPath::Forward => None,
+                RenderPath::Deferred => Some(DeferredPipeline::new(viewport.width, viewport.height)),
+            },
+            occlusion: match settings.occlusion_culling {
+                true => Some(OcclusionCuller::new()),
+                false => None,
+            },
+            post: match settings.post_processing {
+                true => Some(PostProcessor::new(viewport.width, viewport.height, settings.post_effects.clone())),
+                false => None,
+            },
+            gamma: settings.gamma,
+            chunk_memory: GpuMemory::default(),
+            memory_budget: settings.gpu_memory_budget,
+            over_budget: false,
         }
     }
 
-    pub fn render_mesh(&mut self, mesh: Mesh) {
-        let vao = unsafe {
-            let mut vao_id: GLuint = 0;
-            gl::GenVertexArrays(1, &mut vao_id as *mut _);
-            gl::BindVertexArray(vao_id);
-            vao_id
-        };
+    /// Returns the GPU memory currently held by the renderer.
+    pub fn memory_usage(&self) -> GpuMemory {
+        let mut memory = self.chunk_memory + self.entities.memory();
 
-        let _vbo = unsafe {
-            let size = std::mem::size_of::<crate::mesh::Vertex>();
-            let mut vbo_id: GLuint = 0;
-            gl::GenBuffers(1, &mut vbo_id as *mut _);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
-            gl::BufferData(
-                gl::ARRAY_BUFFER, 
-                (mesh.vertices().len() * size) as isize,
-                mesh.vertices().as_ptr() as *const _, 
-                gl::STATIC_DRAW
-            );
-            vbo_id
-        };
+        if let Some(msaa) = self.msaa.as_ref() {
+            memory += msaa.memory();
+        }
 
-        let _ebo = unsafe {
-            let mut ebo_id: GLuint = 0;
-            gl::GenBuffers(1, &mut ebo_id as *mut _);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo_id);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER, 
-                mesh.indices().len() as isize * 4, 
-                mesh.indices().as_ptr() as *mut _, 
-                gl::STATIC_DRAW
-            );
-            ebo_id
-        };
+        if let Some(deferred) = self.deferred.as_ref() {
+            memory += deferred.memory();
+        }
 
-        let _va = unsafe {
-            let stride = std::mem::size_of::<crate::mesh::Vertex>() as i32;
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
-            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, 12 as *const _);
-            gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, stride, 28 as *const _);
-            gl::EnableVertexAttribArray(0);
-            gl::EnableVertexAttribArray(1);
-            gl::EnableVertexAttribArray(2);
-        };
+        if let Some(post) = self.post.as_ref() {
+            memory += post.memory();
+        }
+
+        memory
+    }
+
+    /// Returns the GPU memory held by a single chunk, if it is uploaded.
+    pub fn chunk_memory_usage(&self, position: ChunkPos) -> Option<GpuMemory> {
+        self.chunks.iter()
+            .find(|c| c.position == position)
+            .map(RenderedChunk::memory)
+    }
+
+    /// Sets how many bytes of GPU memory the renderer may use before it warns
+    /// about it.
+    pub fn set_memory_budget(&mut self, budget: usize) {
+        self.memory_budget = budget;
+        self.check_memory_budget();
+    }
+
+    /// Sets the vertical field of view.
+    pub fn set_fov(&mut self, fov: Deg<f32>) {
+        self.fov = fov;
+        self.update_projection();
+    }
+
+    pub fn fov(&self) -> Deg<f32> {
+        self.fov
+    }
+
+    /// Switches to an orthographic projection showing `scale` world units
+    /// above and below the center of the screen, or back to the perspective
+    /// one with `None`.
+    pub fn set_orthographic(&mut self, scale: Option<f32>) {
+        self.orthographic = scale;
+        self.update_projection();
+    }
+
+    /// Sets the gamma used to encode the rendered world for the display.
+    pub fn set_gamma(&mut self, gamma: f32) {
+        self.gamma = gamma.max(0.1);
+    }
+
+    /// Sets the point lights of the scene. They are ignored on the forward
+    /// path.
+    pub fn set_point_lights(&mut self, lights: Vec<PointLight>) {
+        if let Some(deferred) = self.deferred.as_mut() {
+            deferred.set_point_lights(lights);
+        }
+    }
+
+    /// Gives access to the post-process passes, to toggle or tweak them. Returns
+    /// `None` if post-processing is disabled.
+    pub fn post_effects_mut(&mut self) -> Option<&mut PostEffects> {
+        self.post.as_mut().map(PostProcessor::effects_mut)
+    }
+
+    pub fn hud_mut(&mut self) -> &mut Hud {
+        &mut self.hud
+    }
+
+    pub fn particles_mut(&mut self) -> &mut ParticleSystem {
+        &mut self.particles
+    }
+
+    /// Sets the entities drawn in the next frames.
+    pub fn set_entities(&mut self, instances: &[EntityInstance]) {
+        self.entities.set_instances(instances);
+    }
+
+    /// Sets the block that should be outlined in the next frames. Passing
+    /// `None` hides the outline.
+    pub fn set_targeted_block(&mut self, block: Option<BlockPos>) {
+        self.targeted_block = block;
+    }
 
-        self.vaos.push((vao, mesh.indices().len() as i32));
+    /// Sets how far (in world units) the border of the world is from the
+    /// origin. Passing `None` hides the border.
+    pub fn set_world_border(&mut self, radius: Option<f32>) {
+        self.border.set_radius(radius);
     }
 
-    pub fn change_viewport(&self, width: u32, height: u32) {
+    /// Sets the block being broken, and how far along it is (from 0 to 1),
+    /// which is shown by cracks over it. Passing `None` hides the cracks.
+    pub fn set_breaking_block(&mut self, breaking: Option<(BlockPos, f32)>) {
+        self.breaking = breaking;
+    }
+
+    /// Tints the world and fills it with fog while the camera is underwater.
+    pub fn set_underwater(&mut self, underwater: bool) {
+        self.underwater = underwater;
+    }
+
+    pub fn is_underwater(&self) -> bool {
+        self.underwater
+    }
+
+    /// Sets how heavy the rain is, from 0 (none) to 1 (a storm). Rain darkens
+    /// the sky and thickens the fog.
+    pub fn set_rain(&mut self, intensity: f32) {
+        self.rain = intensity.max(0.0).min(1.0);
+    }
+
+    /// Queues the meshes of a chunk for upload. Queued meshes are uploaded at
+    /// the start of the following frames, as many as fit in the upload budget.
+    /// Until then, the previous meshes of the chunk (if any) keep being drawn.
+    pub fn queue_chunk(&mut self, position: ChunkPos, mesh: ChunkMesh) {
+        // A newer mesh makes the queued one obsolete.
+        match self.pending_chunks.iter_mut().find(|(p, _)| *p == position) {
+            Some(pending) => pending.1 = mesh,
+            None => self.pending_chunks.push_back((position, mesh)),
+        }
+    }
+
+    /// Returns the number of chunks waiting to be uploaded.
+    pub fn pending_uploads(&self) -> usize {
+        self.pending_chunks.len()
+    }
+
+    /// Sets how much time may be spent uploading queued chunks every frame.
+    pub fn set_upload_budget(&mut self, budget: Duration) {
+        self.upload_budget = budget;
+    }
+
+    /// Sets how far away (in chunks) chunks are still drawn.
+    pub fn set_view_distance(&mut self, distance: u32) {
+        self.view_distance = distance;
+    }
+
+    pub fn view_distance(&self) -> u32 {
+        self.view_distance
+    }
+
+    /// Uploads the meshes of a chunk right away, replacing the ones previously
+    /// uploaded for the same chunk.
+    pub fn add_chunk(&mut self, position: ChunkPos, mesh: ChunkMesh) {
+        let chunk = RenderedChunk::new(position, &mesh);
+        self.chunk_memory += chunk.memory();
+
+        match self.chunks.iter_mut().find(|c| c.position == position) {
+            // A chunk that was already visible should not fade in again.
+            Some(old) => {
+                let mut chunk = chunk;
+                chunk.uploaded_at = old.uploaded_at;
+
+                self.chunk_memory -= old.memory();
+                *old = chunk;
+            },
+            None => self.chunks.push(chunk),
+        }
+
+        self.check_memory_budget();
+    }
+
+    /// Frees the meshes of a chunk, including queued ones that have not been
+    /// uploaded yet.
+    pub fn remove_chunk(&mut self, position: ChunkPos) {
+        self.pending_chunks.retain(|(p, _)| *p != position);
+
+        if let Some(index) = self.chunks.iter().position(|c| c.position == position) {
+            let chunk = self.chunks.swap_remove(index);
+            self.chunk_memory -= chunk.memory();
+        }
+    }
+
+    /// Adopts the new size and scale factor of the window.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.hud.set_scale_factor(viewport.scale_factor);
+        self.change_viewport(viewport.width, viewport.height);
+    }
+
+    fn change_viewport(&mut self, width: u32, height: u32) {
         unsafe {
             gl::Viewport(0, 0, width as i32, height as i32);
         }
+
+        self.aspect_ratio = width.max(1) as f32 / height.max(1) as f32;
+        self.update_projection();
+
+        self.hud.resize(width, height);
+
+        if let Some(msaa) = self.msaa.as_mut() {
+            msaa.resize(width, height);
+        }
+
+        if let Some(deferred) = self.deferred.as_mut() {
+            deferred.resize(width, height);
+        }
+
+        if let Some(post) = self.post.as_mut() {
+            post.resize(width, height);
+        }
+
+        self.check_memory_budget();
     }
 
-    pub fn render(&self, time: f32, view: Matrix4<f32>) {
-        unsafe {
-            let model = Matrix4::from_scale(1.0f32);
+    pub fn render(&mut self, time: f32, view: Matrix4<f32>) {
+        self.upload_pending_chunks();
+
+        let model = Matrix4::from_scale(1.0f32);
+        let camera = view.invert()
+            .map(|inv| Point3::new(inv.w.x, inv.w.y, inv.w.z))
+            .unwrap_or(Point3::new(0.0, 0.0, 0.0));
+
+        // With post-processing, the world is kept in linear space until the
+        // very last pass encodes it.
+        let (scene_gamma, target) = match self.post.as_ref() {
+            Some(post) => (1.0, post.target()),
+            None => (self.gamma, 0),
+        };
+        let fog = self.fog();
+
+        match self.msaa.as_ref() {
+            Some(msaa) => msaa.bind(),
+            None => unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, target) },
+        }
+
+        if let Some(occlusion) = self.occlusion.as_ref() {
+            occlusion.prepare(&self.projection, &view);
+        }
+
+        let chunk_length = Vector2::new(
+            world::CHUNK_LENGTH_X as f32 * BLOCK_LENGTH,
+            world::CHUNK_LENGTH_Z as f32 * BLOCK_LENGTH,
+        );
+        let camera_chunk = Vector2::new(
+            (camera.x / chunk_length.x).floor() as i32,
+            (camera.z / chunk_length.y).floor() as i32,
+        );
+        let distance = self.view_distance as i32;
+        let frustum = Frustum::from_matrix(&(self.projection * view));
+
+        // Front-to-back, so that the nearest chunks fill the depth buffer
+        // before the farther ones get tested against it.
+        let mut sorted = self.chunks
+            .iter()
+            .filter(|c| {
+                (c.position.x - camera_chunk.x).abs() <= distance
+                    && (c.position.z - camera_chunk.y).abs() <= distance
+                    && frustum.intersects_aabb(&Aabb::new(c.bounds.0, c.bounds.1))
+            })
+            .map(|c| ((c.center() - camera).magnitude2(), c))
+            .collect::<Vec<_>>();
+
+        sorted.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
 
+        let sorted = sorted.into_iter().map(|(_, c)| c).collect::<Vec<_>>();
+
+        unsafe {
             gl::ProvokingVertex(gl::LAST_VERTEX_CONVENTION);
+            gl::Enable(gl::DEPTH_TEST); 
+            gl::Enable(gl::CULL_FACE);
+
+            // Opaque pass: either lit right away, or written into the G-buffer
+            // and lit in a separate pass.
+            match self.deferred.as_ref() {
+                Some(deferred) => {
+                    deferred.begin_geometry(&self.projection, &view, &model);
+                    self.draw_opaque(&sorted, camera, deferred.geometry_program());
+                    deferred.light(&self.projection, &view, camera, scene_gamma, fog);
+                    self.use_chunk_program(time, &view, &model, scene_gamma, fog);
+                },
+
+                None => {
+                    // The sky color is authored in sRGB, it has to be decoded
+                    // when the world is kept in linear space. Underwater, the
+                    // fog hides the sky.
+                    let sky = self.sky_color();
+                    let (r, g, b) = match (self.underwater, self.post.is_some()) {
+                        (true, _) => {
+                            let c = fog.color.map(|c| c.powf(1.0 / scene_gamma));
+                            (c.x, c.y, c.z)
+                        },
+                        (false, true) => (sky.x.powf(2.2), sky.y.powf(2.2), sky.z.powf(2.2)),
+                        (false, false) => (sky.x, sky.y, sky.z),
+                    };
+
+                    gl::ClearColor(r, g, b, 1.0);
+                    gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                    self.use_chunk_program(time, &view, &model, scene_gamma, fog);
+                    self.draw_opaque(&sorted, camera, &self.chunk_program);
+                },
+            }
+
+            // Entities are drawn forward in both paths, over the depth of the
+            // opaque blocks.
+            self.entities.render(&self.projection, &view, scene_gamma, fog);
+
+            // Translucent pass: drawn back-to-front, blended over the opaque
+            // blocks, and without writing into the depth buffer so translucent
+            // faces do not hide each other.
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+            gl::Disable(gl::CULL_FACE);
+
+            for chunk in sorted.iter().rev() {
+                if let Some(mesh) = chunk.translucent.as_ref() {
+                    self.draw_chunk_mesh(chunk, mesh, camera, &self.chunk_program);
+                }
+            }
 
-            for p in &self.programs {
-                p.use_program();
-                p.use_uniform("time", &time);
-                p.use_uniform("projection", &self.projection);
-                p.use_uniform("view", &view);
-                p.use_uniform("model", &model);
+            // The border is a wall the camera can never be behind, so it is
+            // drawn after the world it encloses.
+            self.border.render(&self.projection, &view, scene_gamma, camera);
 
-                gl::Enable(gl::DEPTH_TEST); 
+            // Particles are not sorted, their blending is soft enough for it
+            // not to matter much.
+            self.particles.render(&self.projection, &view, scene_gamma);
+
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::CULL_FACE);
+
+            if let Some(block) = self.targeted_block {
+                self.outline.render(&self.projection, &view, block);
+            }
+
+            if let Some((block, progress)) = self.breaking {
+                gl::Enable(gl::BLEND);
+                gl::Disable(gl::CULL_FACE);
+                self.cracks.render(&self.projection, &view, block, progress);
                 gl::Enable(gl::CULL_FACE);
-                gl::ClearColor(0.45, 0.55, 0.75, 1.0);
-                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                gl::Disable(gl::BLEND);
+            }
+
+            self.debug_shapes.flush(&self.projection, &view, scene_gamma);
+
+            // The world is done, resolve it before the HUD goes on top of it.
+            if let Some(msaa) = self.msaa.as_ref() {
+                msaa.resolve(target);
+            }
 
-                for (vao, count) in &self.vaos {
-                    gl::BindVertexArray(*vao);
-                    gl::DrawElements(gl::TRIANGLES, *count as i32, gl::UNSIGNED_INT, ptr::null());
+            if let Some(deferred) = self.deferred.as_ref() {
+                deferred.resolve(target);
+            }
+
+            if let Some(post) = self.post.as_ref() {
+                post.apply(self.gamma);
+            }
+
+            // The HUD goes last, on top of everything in the world.
+            self.hud.render();
+        }
+    }
+
+    // Uploads queued chunks until the budget runs out. At least one chunk is
+    // uploaded every frame, so a tiny budget still makes progress.
+    fn upload_pending_chunks(&mut self) {
+        let start = Instant::now();
+
+        while let Some((position, mesh)) = self.pending_chunks.pop_front() {
+            self.add_chunk(position, mesh);
+
+            if start.elapsed() >= self.upload_budget {
+                break
+            }
+        }
+    }
+
+    /// Warns once whenever the memory usage goes over the budget.
+    fn check_memory_budget(&mut self) {
+        let used = self.memory_usage().total();
+        let over_budget = used > self.memory_budget;
+
+        if over_budget && !self.over_budget {
+            log::warn!(
+                "GPU memory usage ({} MiB) is over the budget ({} MiB)",
+                used >> 20,
+                self.memory_budget >> 20,
+            );
+        }
+
+        self.over_budget = over_budget;
+    }
+
+    fn update_projection(&mut self) {
+        self.projection = match self.orthographic {
+            Some(scale) => {
+                let width = scale * self.aspect_ratio;
+                cgmath::ortho(-width, width, -scale, scale, NEAR_PLANE, FAR_PLANE)
+            },
+            None => cgmath::perspective(self.fov, self.aspect_ratio, NEAR_PLANE, FAR_PLANE),
+        };
+    }
+
+    fn use_chunk_program(&self, time: f32, view: &Matrix4<f32>, model: &Matrix4<f32>, gamma: f32, fog: Fog) {
+        let p = &self.chunk_program;
+        p.use_program();
+        p.use_uniform("time", &time);
+        p.use_uniform("projection", &self.projection);
+        p.use_uniform("view", view);
+        p.use_uniform("model", model);
+        p.use_uniform("gamma", &gamma);
+        p.use_uniform("fog_color", &fog.color);
+        p.use_uniform("fog_density", &fog.density);
+    }
+
+    // Underwater, the world fades into the water. Otherwise the rain makes it
+    // fade into the sky.
+    fn fog(&self) -> Fog {
+        let (color, density) = match self.underwater {
+            true => (Vector3::from(UNDERWATER_COLOR), UNDERWATER_FOG_DENSITY),
+            false => (self.sky_color(), RAIN_FOG_DENSITY * self.rain),
+        };
+
+        Fog {
+            color: color.map(|c| c.powf(2.2)),
+            density,
+        }
+    }
+
+    // The color of the sky in sRGB, which gets darker the heavier it rains.
+    fn sky_color(&self) -> Vector3<f32> {
+        let clear = Vector3::from(SKY_COLOR);
+        let storm = Vector3::from(STORM_SKY_COLOR);
+        clear + (storm - clear) * self.rain
+    }
+
+    /// Draws the opaque meshes with the current program. The chunks must be
+    /// sorted front-to-back.
+    fn draw_opaque(
+        &self,
+        sorted: &[&RenderedChunk],
+        camera: Point3<f32>,
+        program: &ShaderProgram<Linked>,
+    ) {
+        for chunk in sorted {
+            // Chunks without an opaque mesh are still tested, their result is
+            // used in the translucent pass.
+            if let Some(occlusion) = self.occlusion.as_ref() {
+                if !chunk.is_near(camera, NEAR_MARGIN) {
+                    occlusion.test(chunk);
                 }
-            };
+            }
+
+            if let Some(mesh) = chunk.opaque.as_ref() {
+                self.draw_chunk_mesh(chunk, mesh, camera, program);
+            }
+        }
+    }
+
+    fn draw_chunk_mesh(
+        &self,
+        chunk: &RenderedChunk,
+        mesh: &GpuMesh,
+        camera: Point3<f32>,
+        program: &ShaderProgram<Linked>,
+    ) {
+        // New chunks grow out of their center instead of popping in.
+        let fade = chunk.fade_in(CHUNK_FADE_IN);
+        let scale = 1.0 - (1.0 - fade).powi(3);
+        let center = chunk.center().to_vec();
+        let model = Matrix4::from_translation(center)
+            * Matrix4::from_scale(scale)
+            * Matrix4::from_translation(-center);
+
+        program.use_uniform("model", &model);
+
+        // The bounding box of a chunk surrounding the camera might be clipped
+        // by the near plane, so its test cannot be trusted.
+        if self.occlusion.is_some() && !chunk.is_near(camera, NEAR_MARGIN) {
+            occlusion::draw_if_visible(chunk, || mesh.draw());
+        } else {
+            mesh.draw();
         }
     }
 }
 
+// The distances (in world units) of the clipping planes.
+const NEAR_PLANE: f32 = 0.1;
+const FAR_PLANE: f32 = 500.0;
+
+// The color of the sky, in sRGB, on a clear day and in a storm.
+const SKY_COLOR: (f32, f32, f32) = (0.45, 0.55, 0.75);
+const STORM_SKY_COLOR: (f32, f32, f32) = (0.3, 0.32, 0.36);
+
+// How quickly the fog thickens with the distance (in world units) in a storm.
+// Lighter rain has thinner fog.
+const RAIN_FOG_DENSITY: f32 = 0.03;
+
+// The color of the fog underwater, in sRGB, and how quickly it thickens with
+// the distance (in world units).
+const UNDERWATER_COLOR: (f32, f32, f32) = (0.1, 0.25, 0.55);
+const UNDERWATER_FOG_DENSITY: f32 = 0.6;
+
+// How long newly uploaded chunks take to grow to their full size.
+const CHUNK_FADE_IN: Duration = Duration::from_millis(300);
+
+// How close (in world units) the camera may get to the bounds of a chunk
+// before its occlusion test is skipped.
+const NEAR_MARGIN: f32 = 0.5;
+
 const VS_SHADER: &'static str = include_str!("shaders/vs.glsl");
 const FS_SHADER: &'static str = include_str!("shaders/fs.glsl");
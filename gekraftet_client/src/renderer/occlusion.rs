@@ -0,0 +1,137 @@
+use cgmath::{ Matrix4, Vector3 };
+use gl::types::*;
+use std::ptr;
+
+use super::chunk::RenderedChunk;
+use super::shader::{ Linked, ShaderProgram };
+
+// The 8 corners of an unit cube.
+const CORNERS: [[f32; 3]; 8] = [
+    [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 1.0], [0.0, 0.0, 1.0],
+    [0.0, 1.0, 0.0], [1.0, 1.0, 0.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0],
+];
+
+// The 12 triangles covering the faces of the cube. The winding does not
+// matter, as faces are not culled while testing.
+const TRIANGLES: [u32; 36] = [
+    0, 1, 2, 0, 2, 3, // bottom
+    4, 6, 5, 4, 7, 6, // top
+    0, 4, 5, 0, 5, 1, // north
+    3, 2, 6, 3, 6, 7, // south
+    0, 3, 7, 0, 7, 4, // west
+    1, 5, 6, 1, 6, 2, // east
+];
+
+/// Hides chunks that are fully covered by the terrain in front of them.
+///
+/// Chunks are expected to be visited front-to-back. Before a chunk is drawn,
+/// its bounding box is tested against the depth buffer with an occlusion
+/// query, and the chunk itself is then drawn conditionally on the result of
+/// that query - all on the GPU, so the CPU never waits for the results.
+pub struct OcclusionCuller {
+    program: ShaderProgram<Linked>,
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+}
+
+impl OcclusionCuller {
+    pub fn new() -> Self {
+        let program = ShaderProgram::new()
+            .compile_shader(OCCLUSION_VS_SHADER, OCCLUSION_FS_SHADER)
+            .unwrap();
+
+        let (vao, vbo, ebo) = unsafe {
+            let mut vao_id: GLuint = 0;
+            gl::GenVertexArrays(1, &mut vao_id as *mut _);
+            gl::BindVertexArray(vao_id);
+
+            let mut vbo_id: GLuint = 0;
+            gl::GenBuffers(1, &mut vbo_id as *mut _);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&CORNERS) as isize,
+                CORNERS.as_ptr() as *const _,
+                gl::STATIC_DRAW
+            );
+
+            let mut ebo_id: GLuint = 0;
+            gl::GenBuffers(1, &mut ebo_id as *mut _);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo_id);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                std::mem::size_of_val(&TRIANGLES) as isize,
+                TRIANGLES.as_ptr() as *const _,
+                gl::STATIC_DRAW
+            );
+
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 12, ptr::null());
+            gl::EnableVertexAttribArray(0);
+            (vao_id, vbo_id, ebo_id)
+        };
+
+        Self { program, vao, vbo, ebo }
+    }
+
+    /// Sets up the camera used for the tests in this frame.
+    pub fn prepare(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        self.program.use_program();
+        self.program.use_uniform("projection", projection);
+        self.program.use_uniform("view", view);
+    }
+
+    /// Tests the bounding box of the chunk against the current depth buffer.
+    /// The previously used program is restored afterwards.
+    pub fn test(&self, chunk: &RenderedChunk) {
+        let (min, max) = chunk.bounds;
+        let size = max - min;
+        let model = Matrix4::from_translation(Vector3::new(min.x, min.y, min.z))
+            * Matrix4::from_nonuniform_scale(size.x, size.y, size.z);
+
+        unsafe {
+            let mut previous = 0;
+            gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous);
+
+            self.program.use_program();
+            self.program.use_uniform("model", &model);
+
+            gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            gl::DepthMask(gl::FALSE);
+            gl::Disable(gl::CULL_FACE);
+
+            gl::BeginQuery(gl::ANY_SAMPLES_PASSED, chunk.query);
+            gl::BindVertexArray(self.vao);
+            gl::DrawElements(gl::TRIANGLES, TRIANGLES.len() as i32, gl::UNSIGNED_INT, ptr::null());
+            gl::EndQuery(gl::ANY_SAMPLES_PASSED);
+
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+            gl::DepthMask(gl::TRUE);
+            gl::Enable(gl::CULL_FACE);
+
+            gl::UseProgram(previous as GLuint);
+        }
+    }
+}
+
+impl Drop for OcclusionCuller {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+/// Runs `draw` only if the last test of the chunk found it visible.
+pub fn draw_if_visible<F: FnOnce()>(chunk: &RenderedChunk, draw: F) {
+    unsafe {
+        gl::BeginConditionalRender(chunk.query, gl::QUERY_WAIT);
+        draw();
+        gl::EndConditionalRender();
+    }
+}
+
+const OCCLUSION_VS_SHADER: &'static str = include_str!("shaders/occlusion_vs.glsl");
+const OCCLUSION_FS_SHADER: &'static str = include_str!("shaders/occlusion_fs.glsl");
@@ -0,0 +1,92 @@
+use cgmath::{ Matrix4, Vector3, Vector4 };
+use gekraftet_core::world::BlockPos;
+use gl::types::*;
+use std::ptr;
+
+use crate::world::BLOCK_LENGTH;
+use super::shader::{ Linked, ShaderProgram };
+
+// The 12 edges of an unit cube, as pairs of vertices fed to GL_LINES.
+const EDGES: [[f32; 3]; 24] = [
+    // bottom
+    [0.0, 0.0, 0.0], [1.0, 0.0, 0.0],
+    [1.0, 0.0, 0.0], [1.0, 0.0, 1.0],
+    [1.0, 0.0, 1.0], [0.0, 0.0, 1.0],
+    [0.0, 0.0, 1.0], [0.0, 0.0, 0.0],
+    // top
+    [0.0, 1.0, 0.0], [1.0, 1.0, 0.0],
+    [1.0, 1.0, 0.0], [1.0, 1.0, 1.0],
+    [1.0, 1.0, 1.0], [0.0, 1.0, 1.0],
+    [0.0, 1.0, 1.0], [0.0, 1.0, 0.0],
+    // pillars
+    [0.0, 0.0, 0.0], [0.0, 1.0, 0.0],
+    [1.0, 0.0, 0.0], [1.0, 1.0, 0.0],
+    [1.0, 0.0, 1.0], [1.0, 1.0, 1.0],
+    [0.0, 0.0, 1.0], [0.0, 1.0, 1.0],
+];
+
+// How far (in NDC depth units, scaled by w) the outline is pulled towards the
+// camera. This keeps the lines from z-fighting with the faces they lie on.
+const DEPTH_BIAS: f32 = 0.0005;
+
+/// A wireframe box drawn around the block currently targeted by the player.
+pub struct BlockOutline {
+    program: ShaderProgram<Linked>,
+    vao: GLuint,
+    color: Vector4<f32>,
+}
+
+impl BlockOutline {
+    pub fn new() -> Self {
+        let program = ShaderProgram::new()
+            .compile_shader(OUTLINE_VS_SHADER, OUTLINE_FS_SHADER)
+            .unwrap();
+
+        let vao = unsafe {
+            let mut vao_id: GLuint = 0;
+            gl::GenVertexArrays(1, &mut vao_id as *mut _);
+            gl::BindVertexArray(vao_id);
+
+            let mut vbo_id: GLuint = 0;
+            gl::GenBuffers(1, &mut vbo_id as *mut _);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_id);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&EDGES) as isize,
+                EDGES.as_ptr() as *const _,
+                gl::STATIC_DRAW
+            );
+
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 12, ptr::null());
+            gl::EnableVertexAttribArray(0);
+            vao_id
+        };
+
+        Self {
+            program,
+            vao,
+            color: Vector4::new(0.05, 0.05, 0.05, 1.0),
+        }
+    }
+
+    pub fn render(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>, at: BlockPos) {
+        let origin = at.cast::<f32>().unwrap() * BLOCK_LENGTH;
+        let model = Matrix4::from_translation(Vector3::new(origin.x, origin.y, origin.z))
+            * Matrix4::from_scale(BLOCK_LENGTH);
+
+        self.program.use_program();
+        self.program.use_uniform("projection", projection);
+        self.program.use_uniform("view", view);
+        self.program.use_uniform("model", &model);
+        self.program.use_uniform("depth_bias", &DEPTH_BIAS);
+        self.program.use_uniform("color", &self.color);
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::LINES, 0, EDGES.len() as i32);
+        }
+    }
+}
+
+const OUTLINE_VS_SHADER: &'static str = include_str!("shaders/outline_vs.glsl");
+const OUTLINE_FS_SHADER: &'static str = include_str!("shaders/outline_fs.glsl");
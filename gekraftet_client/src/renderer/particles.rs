@@ -0,0 +1,381 @@
+use cgmath::{ Matrix4, Point2, Point3, Vector3, Vector4 };
+use gekraftet_core::utils::Random;
+use gl::types::*;
+use rand::{ Rng, SeedableRng };
+use std::ptr;
+
+use crate::RGBA;
+use crate::mesh::Vertex;
+use super::shader::{ Linked, ShaderProgram };
+
+/// The most particles alive at once. Emitters stop spawning beyond this.
+pub const MAX_PARTICLES: usize = 8192;
+
+// The atlas is a single row of square frames.
+const ATLAS_FRAMES: u32 = 4;
+const ATLAS_FRAME_SIZE: usize = 16;
+
+// Downwards acceleration in world units per second squared, scaled by the
+// gravity of each emitter.
+const GRAVITY: f32 = 9.8;
+
+/// Describes how particles are spawned and how they behave afterwards.
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    position: Point3<f32>,
+    area: Vector3<f32>,
+    rate: f32,
+    lifetime: f32,
+    velocity: Vector3<f32>,
+    spread: f32,
+    gravity: f32,
+    size: f32,
+    color: RGBA,
+    frames: (u32, u32),
+    // Fractional particles left over from the previous updates.
+    pending: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(position: Point3<f32>) -> Self {
+        Self {
+            position,
+            area: Vector3::new(0.0, 0.0, 0.0),
+            rate: 10.0,
+            lifetime: 1.0,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            spread: 0.0,
+            gravity: 0.0,
+            size: 0.05,
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            frames: (0, ATLAS_FRAMES),
+            pending: 0.0,
+        }
+    }
+
+    pub fn set_position(&mut self, position: Point3<f32>) {
+        self.position = position;
+    }
+
+    /// Particles spawn anywhere within `area` around the position.
+    pub fn area(mut self, area: Vector3<f32>) -> Self {
+        self.area = area;
+        self
+    }
+
+    /// Particles spawned per second.
+    pub fn rate(mut self, rate: f32) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// How long each particle lives, in seconds.
+    pub fn lifetime(mut self, lifetime: f32) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    pub fn velocity(mut self, velocity: Vector3<f32>) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// The largest random change added to the velocity on each axis.
+    pub fn spread(mut self, spread: f32) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    /// How strongly the particles are pulled down. Negative values make them
+    /// rise, like smoke.
+    pub fn gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn color(mut self, color: RGBA) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// The atlas frames played over the lifetime of a particle.
+    pub fn frames(mut self, first: u32, count: u32) -> Self {
+        let first = first.min(ATLAS_FRAMES - 1);
+        self.frames = (first, count.max(1).min(ATLAS_FRAMES - first));
+        self
+    }
+
+    fn spawn(&self, random: &mut Random) -> Particle {
+        let mut offset = |extent: f32| match extent > 0.0 {
+            true => random.gen_range(-extent, extent),
+            false => 0.0,
+        };
+
+        let position = self.position + Vector3::new(
+            offset(self.area.x * 0.5),
+            offset(self.area.y * 0.5),
+            offset(self.area.z * 0.5),
+        );
+
+        let velocity = self.velocity + Vector3::new(
+            offset(self.spread),
+            offset(self.spread),
+            offset(self.spread),
+        );
+
+        Particle {
+            position,
+            velocity,
+            age: 0.0,
+            lifetime: self.lifetime,
+            gravity: self.gravity,
+            size: self.size,
+            color: self.color,
+            frames: self.frames,
+        }
+    }
+}
+
+/// Identifies an emitter added to a `ParticleSystem`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EmitterId(u32);
+
+#[derive(Clone, Debug)]
+struct Particle {
+    position: Point3<f32>,
+    velocity: Vector3<f32>,
+    age: f32,
+    lifetime: f32,
+    gravity: f32,
+    size: f32,
+    color: RGBA,
+    frames: (u32, u32),
+}
+
+/// Simulates particles on the CPU and draws them as camera-facing quads.
+pub struct ParticleSystem {
+    program: ShaderProgram<Linked>,
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+    atlas: GLuint,
+
+    emitters: Vec<(EmitterId, ParticleEmitter)>,
+    next_id: u32,
+    particles: Vec<Particle>,
+    random: Random,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        let program = ShaderProgram::new()
+            .compile_shader(PARTICLE_VS_SHADER, PARTICLE_FS_SHADER)
+            .unwrap();
+
+        let (vao, vbo, ebo) = unsafe {
+            let (mut vao, mut vbo, mut ebo) = (0, 0, 0);
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::GenBuffers(1, &mut ebo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+
+            let stride = std::mem::size_of::<Vertex>() as i32;
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, ptr::null());
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, 12 as *const _);
+            gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, stride, 28 as *const _);
+            gl::EnableVertexAttribArray(0);
+            gl::EnableVertexAttribArray(1);
+            gl::EnableVertexAttribArray(2);
+            (vao, vbo, ebo)
+        };
+
+        Self {
+            program,
+            vao,
+            vbo,
+            ebo,
+            atlas: create_atlas(),
+            emitters: Vec::new(),
+            next_id: 0,
+            particles: Vec::new(),
+            random: Random::seed_from_u64(0x9A27_1C1E),
+        }
+    }
+
+    pub fn add_emitter(&mut self, emitter: ParticleEmitter) -> EmitterId {
+        let id = EmitterId(self.next_id);
+        self.next_id += 1;
+        self.emitters.push((id, emitter));
+        id
+    }
+
+    /// Removes the emitter. Particles it already spawned live on.
+    pub fn remove_emitter(&mut self, id: EmitterId) -> Option<ParticleEmitter> {
+        let index = self.emitters.iter().position(|(i, _)| *i == id)?;
+        Some(self.emitters.remove(index).1)
+    }
+
+    pub fn emitter_mut(&mut self, id: EmitterId) -> Option<&mut ParticleEmitter> {
+        self.emitters.iter_mut()
+            .find(|(i, _)| *i == id)
+            .map(|(_, e)| e)
+    }
+
+    /// Spawns `count` particles at once, e.g. when a block breaks.
+    pub fn burst(&mut self, emitter: &ParticleEmitter, count: usize) {
+        let count = count.min(MAX_PARTICLES - self.particles.len());
+
+        for _ in 0..count {
+            let particle = emitter.spawn(&mut self.random);
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Advances the simulation by `delta` seconds.
+    pub fn update(&mut self, delta: f32) {
+        for p in self.particles.iter_mut() {
+            p.age += delta;
+            p.velocity.y -= GRAVITY * p.gravity * delta;
+            p.position += p.velocity * delta;
+        }
+
+        self.particles.retain(|p| p.age < p.lifetime);
+
+        for (_, emitter) in self.emitters.iter_mut() {
+            emitter.pending += emitter.rate * delta;
+
+            while emitter.pending >= 1.0 {
+                emitter.pending -= 1.0;
+
+                if self.particles.len() < MAX_PARTICLES {
+                    self.particles.push(emitter.spawn(&mut self.random));
+                }
+            }
+        }
+    }
+
+    /// Draws the particles. Blending and depth writes are left to the caller,
+    /// as particles are drawn within the translucent pass.
+    pub fn render(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>, gamma: f32) {
+        if self.particles.is_empty() {
+            return
+        }
+
+        // The first two rows of the view matrix are the right and up axes of
+        // the camera in world space.
+        let right = Vector3::new(view.x.x, view.y.x, view.z.x);
+        let up = Vector3::new(view.x.y, view.y.y, view.z.y);
+
+        let mut vertices = Vec::with_capacity(self.particles.len() * 4);
+        let mut indices = Vec::with_capacity(self.particles.len() * 6);
+
+        for p in self.particles.iter() {
+            let progress = (p.age / p.lifetime).min(0.999);
+            let frame = p.frames.0 + (progress * p.frames.1 as f32) as u32;
+            let u0 = frame as f32 / ATLAS_FRAMES as f32;
+            let u1 = (frame + 1) as f32 / ATLAS_FRAMES as f32;
+
+            let (r, u) = (right * p.size * 0.5, up * p.size * 0.5);
+            let base = vertices.len() as u32;
+
+            vertices.push(Vertex::new(p.position - r - u, p.color, Point2::new(u0, 1.0)));
+            vertices.push(Vertex::new(p.position + r - u, p.color, Point2::new(u1, 1.0)));
+            vertices.push(Vertex::new(p.position + r + u, p.color, Point2::new(u1, 0.0)));
+            vertices.push(Vertex::new(p.position - r + u, p.color, Point2::new(u0, 0.0)));
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+
+        self.program.use_program();
+        self.program.use_uniform("projection", projection);
+        self.program.use_uniform("view", view);
+        self.program.use_uniform("gamma", &gamma);
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.atlas);
+
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * std::mem::size_of::<Vertex>()) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STREAM_DRAW
+            );
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                indices.len() as isize * 4,
+                indices.as_ptr() as *const _,
+                gl::STREAM_DRAW
+            );
+
+            gl::DrawElements(gl::TRIANGLES, indices.len() as i32, gl::UNSIGNED_INT, ptr::null());
+        }
+    }
+}
+
+impl Drop for ParticleSystem {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteTextures(1, &self.atlas);
+        }
+    }
+}
+
+// Generates the particle atlas: soft white puffs, shrinking from one frame to
+// the next. The color of a particle comes from its emitter.
+fn create_atlas() -> GLuint {
+    let (width, height) = (ATLAS_FRAME_SIZE * ATLAS_FRAMES as usize, ATLAS_FRAME_SIZE);
+    let mut pixels = vec![0u8; width * height * 4];
+
+    for frame in 0..ATLAS_FRAMES as usize {
+        let radius = 1.0 - frame as f32 / (ATLAS_FRAMES as f32 + 1.0);
+
+        for y in 0..height {
+            for x in 0..ATLAS_FRAME_SIZE {
+                let center = ATLAS_FRAME_SIZE as f32 / 2.0;
+                let dx = (x as f32 + 0.5 - center) / center;
+                let dy = (y as f32 + 0.5 - center) / center;
+                let distance = (dx * dx + dy * dy).sqrt() / radius;
+                let alpha = (1.0 - distance).max(0.0).min(1.0).powf(0.5);
+
+                let i = (y * width + frame * ATLAS_FRAME_SIZE + x) * 4;
+                pixels[i..i + 4].copy_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+            }
+        }
+    }
+
+    unsafe {
+        let mut texture = 0;
+        gl::GenTextures(1, &mut texture);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+        gl::TexImage2D(
+            gl::TEXTURE_2D, 0, gl::RGBA8 as i32, width as i32, height as i32, 0,
+            gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_ptr() as *const _
+        );
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        texture
+    }
+}
+
+const PARTICLE_VS_SHADER: &'static str = include_str!("shaders/particle_vs.glsl");
+const PARTICLE_FS_SHADER: &'static str = include_str!("shaders/particle_fs.glsl");
@@ -0,0 +1,272 @@
+use gl::types::*;
+use std::ptr;
+
+use crate::settings::PostEffects;
+use super::memory::GpuMemory;
+use super::shader::{ Linked, ShaderProgram };
+
+// How many times the bright parts are blurred, horizontally and vertically.
+const BLUR_PASSES: usize = 3;
+
+// A color texture with its own framebuffer, optionally with a depth buffer.
+struct RenderTexture {
+    fbo: GLuint,
+    texture: GLuint,
+    depth: Option<GLuint>,
+    width: i32,
+    height: i32,
+}
+
+impl RenderTexture {
+    fn new(with_depth: bool) -> Self {
+        unsafe {
+            let (mut fbo, mut texture) = (0, 0);
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::GenTextures(1, &mut texture);
+
+            let depth = match with_depth {
+                true => {
+                    let mut depth = 0;
+                    gl::GenRenderbuffers(1, &mut depth);
+                    Some(depth)
+                },
+                false => None,
+            };
+
+            Self { fbo, texture, depth, width: 0, height: 0 }
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width.max(1) as i32;
+        self.height = height.max(1) as i32;
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl_check!(gl::TexImage2D(
+                gl::TEXTURE_2D, 0, gl::RGBA16F as i32, self.width, self.height, 0,
+                gl::RGBA, gl::FLOAT, ptr::null()
+            ));
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, self.texture, 0
+            );
+
+            if let Some(depth) = self.depth {
+                gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+                gl_check!(gl::RenderbufferStorage(
+                    gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, self.width, self.height
+                ));
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, depth
+                );
+            }
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                panic!("post-process target is incomplete - got status {}", status);
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    // Binds the framebuffer and sets the viewport to cover it.
+    fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    fn memory(&self) -> GpuMemory {
+        let pixels = (self.width * self.height) as usize;
+        let depth = if self.depth.is_some() { 4 } else { 0 };
+
+        GpuMemory {
+            textures: pixels * (8 + depth),
+            ..GpuMemory::default()
+        }
+    }
+}
+
+impl Drop for RenderTexture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.texture);
+
+            if let Some(depth) = self.depth {
+                gl::DeleteRenderbuffers(1, &depth);
+            }
+        }
+    }
+}
+
+/// Renders the world into an HDR target, then runs it through a chain of
+/// post-process passes before it reaches the screen.
+///
+/// The passes run in a fixed order: bloom is extracted and blurred at half
+/// resolution, then added back, tonemapped, vignetted and finally encoded
+/// with the display gamma. Each pass can be toggled through `effects_mut()`.
+pub struct PostProcessor {
+    hdr: RenderTexture,
+    // Ping-pong targets for the blur, at half resolution.
+    bloom: [RenderTexture; 2],
+    extract_program: ShaderProgram<Linked>,
+    blur_program: ShaderProgram<Linked>,
+    composite_program: ShaderProgram<Linked>,
+    empty_vao: GLuint,
+    effects: PostEffects,
+    width: u32,
+    height: u32,
+}
+
+impl PostProcessor {
+    pub fn new(width: u32, height: u32, effects: PostEffects) -> Self {
+        let compile = |fs| ShaderProgram::new()
+            .compile_shader(FULLSCREEN_VS_SHADER, fs)
+            .unwrap();
+
+        let empty_vao = unsafe {
+            let mut vao = 0;
+            gl::GenVertexArrays(1, &mut vao);
+            vao
+        };
+
+        let mut post = Self {
+            hdr: RenderTexture::new(true),
+            bloom: [RenderTexture::new(false), RenderTexture::new(false)],
+            extract_program: compile(BLOOM_EXTRACT_FS_SHADER),
+            blur_program: compile(BLUR_FS_SHADER),
+            composite_program: compile(POST_FS_SHADER),
+            empty_vao,
+            effects,
+            width: 0,
+            height: 0,
+        };
+
+        post.resize(width, height);
+        post
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.hdr.resize(width, height);
+
+        for target in self.bloom.iter_mut() {
+            target.resize(width / 2, height / 2);
+        }
+    }
+
+    pub fn effects_mut(&mut self) -> &mut PostEffects {
+        &mut self.effects
+    }
+
+    /// The framebuffer the world should be drawn (or resolved) into.
+    pub fn target(&self) -> GLuint {
+        self.hdr.fbo
+    }
+
+    pub fn memory(&self) -> GpuMemory {
+        self.hdr.memory() + self.bloom[0].memory() + self.bloom[1].memory()
+    }
+
+    /// Runs the passes over the HDR target, and writes the result into the
+    /// default framebuffer.
+    pub fn apply(&self, gamma: f32) {
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::BindVertexArray(self.empty_vao);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        if self.effects.bloom {
+            self.apply_bloom();
+        }
+
+        let p = &self.composite_program;
+        p.use_program();
+        p.use_uniform("scene_tex", &0i32);
+        p.use_uniform("bloom_tex", &1i32);
+        p.use_uniform("bloom_strength", &self.pass_value(self.effects.bloom, self.effects.bloom_strength));
+        p.use_uniform("tonemap", &self.pass_value(self.effects.tonemap, 1.0));
+        p.use_uniform("exposure", &self.effects.exposure);
+        p.use_uniform("vignette_strength", &self.pass_value(self.effects.vignette, self.effects.vignette_strength));
+        p.use_uniform("gamma", &gamma);
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, self.width as i32, self.height as i32);
+
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, self.bloom[0].texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.hdr.texture);
+
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+
+    fn apply_bloom(&self) {
+        // Keep only the parts brighter than the threshold...
+        self.bloom[0].bind();
+        let p = &self.extract_program;
+        p.use_program();
+        p.use_uniform("scene_tex", &0i32);
+        p.use_uniform("threshold", &self.effects.bloom_threshold);
+
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.hdr.texture);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+
+        // ...then blur them, back and forth between the two targets.
+        let p = &self.blur_program;
+        p.use_program();
+        p.use_uniform("source_tex", &0i32);
+
+        for _ in 0..BLUR_PASSES {
+            for (from, to, direction) in [(0, 1, (1.0, 0.0)), (1, 0, (0.0, 1.0))].iter() {
+                let texel = cgmath::Vector2::new(
+                    direction.0 / self.bloom[*from].width as f32,
+                    direction.1 / self.bloom[*from].height as f32,
+                );
+
+                self.bloom[*to].bind();
+                p.use_uniform("texel_step", &texel);
+
+                unsafe {
+                    gl::BindTexture(gl::TEXTURE_2D, self.bloom[*from].texture);
+                    gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                }
+            }
+        }
+    }
+
+    // Disabled passes are turned off in the shader by zeroing their value.
+    fn pass_value(&self, enabled: bool, value: f32) -> f32 {
+        if enabled { value } else { 0.0 }
+    }
+}
+
+impl Drop for PostProcessor {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.empty_vao);
+        }
+    }
+}
+
+const FULLSCREEN_VS_SHADER: &'static str = include_str!("shaders/fullscreen_vs.glsl");
+const BLOOM_EXTRACT_FS_SHADER: &'static str = include_str!("shaders/bloom_extract_fs.glsl");
+const BLUR_FS_SHADER: &'static str = include_str!("shaders/blur_fs.glsl");
+const POST_FS_SHADER: &'static str = include_str!("shaders/post_fs.glsl");
@@ -0,0 +1,18 @@
+#version 400 core
+
+uniform sampler2D scene_tex;
+uniform float threshold;
+
+in vec2 uv_vs;
+
+out vec4 frag_color;
+
+void main() {
+    vec3 color = texture(scene_tex, uv_vs).rgb;
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+
+    // Fade in above the threshold instead of cutting off, to avoid flicker.
+    float weight = clamp((luminance - threshold) / max(threshold, 0.0001), 0.0, 1.0);
+
+    frag_color = vec4(color * weight, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 400 core
+
+uniform sampler2D source_tex;
+// The distance between two samples, along the blur direction.
+uniform vec2 texel_step;
+
+in vec2 uv_vs;
+
+out vec4 frag_color;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec3 color = texture(source_tex, uv_vs).rgb * WEIGHTS[0];
+
+    for (int i = 1; i < 5; i++) {
+        color += texture(source_tex, uv_vs + texel_step * i).rgb * WEIGHTS[i];
+        color += texture(source_tex, uv_vs - texel_step * i).rgb * WEIGHTS[i];
+    }
+
+    frag_color = vec4(color, 1.0);
+}
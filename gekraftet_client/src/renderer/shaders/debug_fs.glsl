@@ -0,0 +1,11 @@
+#version 400 core
+
+uniform float gamma;
+
+in vec4 frag_color_vs;
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = vec4(pow(frag_color_vs.rgb, vec3(1.0 / gamma)), frag_color_vs.a);
+}
@@ -0,0 +1,19 @@
+#version 400 core
+
+uniform float gamma;
+// The fog color is in linear space. A density of zero turns the fog off.
+uniform vec3 fog_color;
+uniform float fog_density;
+
+in vec4 color_vs;
+flat in float color_mod_vs;
+in float view_distance_vs;
+
+out vec4 frag_color;
+
+void main() {
+    vec3 lit = color_vs.rgb * color_mod_vs;
+    lit = mix(fog_color, lit, exp(-fog_density * view_distance_vs));
+
+    frag_color = vec4(pow(lit, vec3(1.0 / gamma)), color_vs.a);
+}
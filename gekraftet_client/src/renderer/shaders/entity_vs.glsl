@@ -0,0 +1,44 @@
+#version 400 core
+
+uniform mat4 projection;
+uniform mat4 view;
+
+layout(location = 0) in vec3 pos;
+layout(location = 1) in vec4 color;
+layout(location = 2) in vec2 uv;
+
+// Every instance is a box, scaled, turned and moved into place, and tinted.
+layout(location = 3) in vec3 instance_min;
+layout(location = 4) in vec3 instance_size;
+layout(location = 5) in vec4 instance_color;
+layout(location = 6) in float instance_yaw;
+
+out vec4 color_vs;
+flat out float color_mod_vs;
+out float view_distance_vs;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(
+        c / 12.92,
+        pow((c + 0.055) / 1.055, vec3(2.4)),
+        step(0.04045, c)
+    );
+}
+
+void main() {
+    vec4 tint = color * instance_color;
+    color_vs = vec4(srgb_to_linear(tint.rgb), tint.a);
+    color_mod_vs = uv.x;
+
+    // The box turns around its own center.
+    vec3 local = (pos - vec3(0.5, 0.0, 0.5)) * instance_size;
+    float c = cos(instance_yaw);
+    float s = sin(instance_yaw);
+    local.xz = vec2(c * local.x - s * local.z, s * local.x + c * local.z);
+
+    vec3 center = instance_min + vec3(0.5, 0.0, 0.5) * instance_size;
+    vec4 view_pos = view * vec4(center + local, 1.0);
+    view_distance_vs = length(view_pos.xyz);
+
+    gl_Position = projection * view_pos;
+}
@@ -1,10 +1,21 @@
 #version 400 core
 
+uniform float gamma;
+// The fog color is in linear space. A density of zero turns the fog off.
+uniform vec3 fog_color;
+uniform float fog_density;
+
 in vec4 frag_color_vs;
 flat in float frag_color_mod;
+in float view_distance_vs;
 
 out vec4 frag_color;
 
 void main() {
-    frag_color = frag_color_vs * frag_color_mod;
+    // Lighting is done in linear space. Only the color is shaded, the alpha
+    // is left alone for translucent blocks.
+    vec3 lit = frag_color_vs.rgb * frag_color_mod;
+    lit = mix(fog_color, lit, exp(-fog_density * view_distance_vs));
+
+    frag_color = vec4(pow(lit, vec3(1.0 / gamma)), frag_color_vs.a);
 }
@@ -0,0 +1,12 @@
+#version 400 core
+
+out vec2 uv_vs;
+
+// A single triangle covering the whole screen, generated without any vertex
+// buffer.
+void main() {
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+
+    uv_vs = pos;
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}
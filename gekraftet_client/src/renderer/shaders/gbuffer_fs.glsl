@@ -0,0 +1,15 @@
+#version 400 core
+
+in vec4 albedo_vs;
+in vec3 world_pos_vs;
+
+layout(location = 0) out vec4 albedo;
+layout(location = 1) out vec3 normal;
+
+void main() {
+    albedo = albedo_vs;
+
+    // The vertices carry no normals, but every face of a block is flat, so the
+    // screen-space derivatives of the position are enough to recover them.
+    normal = normalize(cross(dFdx(world_pos_vs), dFdy(world_pos_vs)));
+}
@@ -0,0 +1,29 @@
+#version 400 core
+
+uniform mat4 model;
+uniform mat4 projection;
+uniform mat4 view;
+
+layout(location = 0) in vec3 pos;
+layout(location = 1) in vec4 color;
+layout(location = 2) in vec2 uv;
+
+out vec4 albedo_vs;
+out vec3 world_pos_vs;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(
+        c / 12.92,
+        pow((c + 0.055) / 1.055, vec3(2.4)),
+        step(0.04045, c)
+    );
+}
+
+void main() {
+    vec4 world_pos = model * vec4(pos, 1.0);
+
+    albedo_vs = vec4(srgb_to_linear(color.rgb), 1.0);
+    world_pos_vs = world_pos.xyz;
+
+    gl_Position = projection * view * world_pos;
+}
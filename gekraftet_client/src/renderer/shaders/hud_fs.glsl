@@ -0,0 +1,9 @@
+#version 400 core
+
+in vec4 frag_color_vs;
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = frag_color_vs;
+}
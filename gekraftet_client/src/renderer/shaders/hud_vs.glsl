@@ -0,0 +1,13 @@
+#version 400 core
+
+uniform mat4 projection;
+
+layout(location = 0) in vec3 pos;
+layout(location = 1) in vec4 color;
+
+out vec4 frag_color_vs;
+
+void main() {
+    frag_color_vs = color;
+    gl_Position = projection * vec4(pos, 1.0);
+}
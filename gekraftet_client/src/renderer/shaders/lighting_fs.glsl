@@ -0,0 +1,66 @@
+#version 400 core
+
+#define MAX_POINT_LIGHTS 32
+
+uniform sampler2D albedo_tex;
+uniform sampler2D normal_tex;
+uniform sampler2D depth_tex;
+
+uniform mat4 inverse_view_projection;
+uniform vec3 camera;
+uniform float gamma;
+// The fog color is in linear space. A density of zero turns the fog off.
+uniform vec3 fog_color;
+uniform float fog_density;
+
+// Every light takes 8 floats: position (xyz), radius, color (rgb), padding.
+uniform float light_count;
+uniform float light_data[MAX_POINT_LIGHTS * 8];
+
+in vec2 uv_vs;
+
+out vec4 frag_color;
+
+const vec3 SKY_COLOR = vec3(0.45, 0.55, 0.75);
+const vec3 SUN_DIRECTION = normalize(vec3(0.3, 1.0, 0.5));
+const vec3 SUN_COLOR = vec3(0.75);
+const vec3 AMBIENT_COLOR = vec3(0.3);
+
+void main() {
+    vec4 albedo = texture(albedo_tex, uv_vs);
+
+    // Nothing was drawn here. The sky color is authored in sRGB, so it goes
+    // through the same decoding and encoding as the blocks.
+    if (albedo.a == 0.0) {
+        vec3 sky = pow(SKY_COLOR, vec3(2.2));
+        sky = mix(sky, fog_color, step(0.0001, fog_density));
+        frag_color = vec4(pow(sky, vec3(1.0 / gamma)), 1.0);
+        return;
+    }
+
+    vec3 normal = texture(normal_tex, uv_vs).xyz;
+    float depth = texture(depth_tex, uv_vs).r;
+
+    vec4 ndc = vec4(uv_vs * 2.0 - 1.0, depth * 2.0 - 1.0, 1.0);
+    vec4 world = inverse_view_projection * ndc;
+    vec3 pos = world.xyz / world.w;
+
+    vec3 light = AMBIENT_COLOR + SUN_COLOR * max(dot(normal, SUN_DIRECTION), 0.0);
+
+    for (int i = 0; i < int(light_count); i++) {
+        int base = i * 8;
+        vec3 light_pos = vec3(light_data[base], light_data[base + 1], light_data[base + 2]);
+        float radius = light_data[base + 3];
+        vec3 color = vec3(light_data[base + 4], light_data[base + 5], light_data[base + 6]);
+
+        vec3 to_light = light_pos - pos;
+        float distance = length(to_light);
+        float falloff = clamp(1.0 - distance / radius, 0.0, 1.0);
+
+        light += color * falloff * falloff * max(dot(normal, to_light / distance), 0.0);
+    }
+
+    vec3 lit = albedo.rgb * light;
+    lit = mix(fog_color, lit, exp(-fog_density * distance(pos, camera)));
+    frag_color = vec4(pow(lit, vec3(1.0 / gamma)), 1.0);
+}
@@ -0,0 +1,5 @@
+#version 400 core
+
+// Only the depth test matters for occlusion queries, nothing is written.
+void main() {
+}
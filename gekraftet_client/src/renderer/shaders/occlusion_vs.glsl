@@ -0,0 +1,11 @@
+#version 400 core
+
+uniform mat4 model;
+uniform mat4 projection;
+uniform mat4 view;
+
+layout(location = 0) in vec3 pos;
+
+void main() {
+    gl_Position = projection * view * model * vec4(pos, 1.0);
+}
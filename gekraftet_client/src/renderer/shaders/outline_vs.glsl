@@ -0,0 +1,16 @@
+#version 400 core
+
+uniform mat4 model;
+uniform mat4 projection;
+uniform mat4 view;
+uniform float depth_bias;
+
+layout(location = 0) in vec3 pos;
+
+void main() {
+    gl_Position = projection * view * model * vec4(pos, 1.0);
+
+    // Pull the outline slightly towards the camera so that it wins the depth
+    // test against the faces of the block it surrounds.
+    gl_Position.z -= depth_bias * gl_Position.w;
+}
@@ -0,0 +1,19 @@
+#version 400 core
+
+uniform sampler2D atlas;
+uniform float gamma;
+
+in vec4 color_vs;
+in vec2 uv_vs;
+
+out vec4 frag_color;
+
+void main() {
+    vec4 color = color_vs * texture(atlas, uv_vs);
+
+    if (color.a < 0.01) {
+        discard;
+    }
+
+    frag_color = vec4(pow(color.rgb, vec3(1.0 / gamma)), color.a);
+}
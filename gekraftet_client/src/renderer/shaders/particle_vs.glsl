@@ -0,0 +1,26 @@
+#version 400 core
+
+uniform mat4 projection;
+uniform mat4 view;
+
+layout(location = 0) in vec3 pos;
+layout(location = 1) in vec4 color;
+layout(location = 2) in vec2 uv;
+
+out vec4 color_vs;
+out vec2 uv_vs;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(
+        c / 12.92,
+        pow((c + 0.055) / 1.055, vec3(2.4)),
+        step(0.04045, c)
+    );
+}
+
+void main() {
+    color_vs = vec4(srgb_to_linear(color.rgb), color.a);
+    uv_vs = uv;
+
+    gl_Position = projection * view * vec4(pos, 1.0);
+}
@@ -0,0 +1,39 @@
+#version 400 core
+
+uniform sampler2D scene_tex;
+uniform sampler2D bloom_tex;
+
+// Passes are disabled by setting these to zero.
+uniform float bloom_strength;
+uniform float tonemap;
+uniform float vignette_strength;
+
+uniform float exposure;
+uniform float gamma;
+
+in vec2 uv_vs;
+
+out vec4 frag_color;
+
+// The ACES filmic curve, as fitted by Krzysztof Narkowicz.
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec3 color = texture(scene_tex, uv_vs).rgb;
+
+    // The bloom texture is never written to while bloom is off.
+    if (bloom_strength > 0.0) {
+        color += texture(bloom_tex, uv_vs).rgb * bloom_strength;
+    }
+
+    if (tonemap > 0.0) {
+        color = aces(color * exposure);
+    }
+
+    vec2 centered = uv_vs - 0.5;
+    color *= 1.0 - vignette_strength * dot(centered, centered) * 2.0;
+
+    frag_color = vec4(pow(clamp(color, 0.0, 1.0), vec3(1.0 / gamma)), 1.0);
+}
@@ -11,10 +11,24 @@ layout(location = 2) in vec2 uv;
 
 out vec4 frag_color_vs;
 flat out float frag_color_mod;
+out float view_distance_vs;
+
+// Vertex colors are authored in sRGB, decode them into linear space so that
+// lighting can be done there.
+vec3 srgb_to_linear(vec3 c) {
+    return mix(
+        c / 12.92,
+        pow((c + 0.055) / 1.055, vec3(2.4)),
+        step(0.04045, c)
+    );
+}
 
 void main() {
-    frag_color_vs = color;
+    frag_color_vs = vec4(srgb_to_linear(color.rgb), color.a);
     frag_color_mod = uv.x;
 
-    gl_Position =  projection * view * model * vec4(pos, 1.0);
+    vec4 view_pos = view * model * vec4(pos, 1.0);
+    view_distance_vs = length(view_pos.xyz);
+
+    gl_Position =  projection * view_pos;
 }
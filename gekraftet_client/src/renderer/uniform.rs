@@ -160,3 +160,4 @@ impl_uniform_matrix!(Matrix4<f32>, gl::UniformMatrix4fv);
 
 impl_uniform_scalar!(f32, gl::Uniform1fv);
 impl_uniform_scalar!(f64, gl::Uniform1dv);
+impl_uniform_scalar!(i32, gl::Uniform1iv);
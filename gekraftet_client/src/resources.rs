@@ -0,0 +1,237 @@
+use std::{ fmt, fs, io };
+use std::fs::File;
+use std::io::Read;
+use std::path::{ Path, PathBuf };
+use gekraftet_core::world::{ BlockRegistry, SoundMaterial };
+use zip::ZipArchive;
+use zip::result::ZipError;
+use crate::audio::{ SoundBank, SoundKind };
+
+/// A set of resources (textures, sounds...), every one of them a file at a
+/// path like `textures/blocks/stone.png`.
+pub enum ResourcePack {
+    /// The resources the game comes with.
+    Builtin,
+    Directory(PathBuf),
+    /// A zip archive of a directory, with the resources at its root.
+    Zip(ZipArchive<File>),
+}
+
+#[derive(Debug)]
+pub enum ResourceError {
+    Io(io::Error),
+    Zip(ZipError),
+}
+
+impl fmt::Display for ResourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResourceError::Io(e) => write!(f, "{}", e),
+            ResourceError::Zip(e) => write!(f, "invalid zip archive: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ResourceError {
+    fn from(e: io::Error) -> Self {
+        ResourceError::Io(e)
+    }
+}
+
+impl From<ZipError> for ResourceError {
+    fn from(e: ZipError) -> Self {
+        ResourceError::Zip(e)
+    }
+}
+
+impl ResourcePack {
+    /// Opens the directory, or the zip archive if it is a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ResourceError> {
+        let path = path.as_ref();
+
+        match path.is_dir() {
+            true => Ok(ResourcePack::Directory(path.to_owned())),
+            false => Ok(ResourcePack::Zip(ZipArchive::new(File::open(path)?)?)),
+        }
+    }
+
+    /// Reads the resource at the path, or returns `None` if the pack does
+    /// not have it.
+    pub fn read(&mut self, path: &str) -> Result<Option<Vec<u8>>, ResourceError> {
+        match self {
+            ResourcePack::Builtin => Ok(BUILTIN
+                .iter()
+                .find(|&&(name, _)| name == path)
+                .map(|&(_, bytes)| bytes.to_vec())),
+
+            ResourcePack::Directory(root) => match fs::read(root.join(path)) {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            },
+
+            ResourcePack::Zip(archive) => {
+                let mut file = match archive.by_name(path) {
+                    Ok(file) => file,
+                    Err(ZipError::FileNotFound) => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                };
+
+                let mut bytes = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut bytes)?;
+                Ok(Some(bytes))
+            },
+        }
+    }
+}
+
+/// Resource packs stacked on top of each other. A resource is taken from the
+/// topmost pack that has it, so a pack only has to have the resources it
+/// changes. The built-in resources are always at the bottom.
+pub struct ResourceManager {
+    // From the bottom to the top.
+    packs: Vec<ResourcePack>,
+}
+
+impl ResourceManager {
+    pub fn new() -> Self {
+        Self {
+            packs: vec![ResourcePack::Builtin],
+        }
+    }
+
+    /// Puts the pack on top of the others.
+    pub fn push(&mut self, pack: ResourcePack) {
+        self.packs.push(pack);
+    }
+
+    /// Reads a resource from the topmost pack that has it. Packs that fail to
+    /// read it are skipped.
+    pub fn read(&mut self, path: &str) -> Option<Vec<u8>> {
+        for pack in self.packs.iter_mut().rev() {
+            match pack.read(path) {
+                Ok(Some(bytes)) => return Some(bytes),
+                Ok(None) => {},
+                Err(e) => log::warn!("unable to read the resource {}: {}", path, e),
+            }
+        }
+
+        None
+    }
+
+    /// Colors every block that has a texture, at `textures/blocks/<name>.png`,
+    /// by the average of its pixels. Blocks are drawn in a single color, so
+    /// that is all of the texture that shows.
+    pub fn color_blocks(&mut self, blocks: &mut BlockRegistry) {
+        for definition in blocks.definitions_mut() {
+            let path = format!("textures/blocks/{}.png", definition.name);
+            let png = match self.read(&path) {
+                Some(png) => png,
+                None => continue,
+            };
+
+            match decode_png(&png) {
+                Ok(image) => definition.color = image.average_color(),
+                Err(e) => log::warn!("unable to decode {}: {}", path, e),
+            }
+        }
+    }
+
+    /// Loads the sound of every kind of sound a material makes, at
+    /// `sounds/<kind>/<material>.ogg`, e.g. `sounds/break/glass.ogg`.
+    pub fn sounds(&mut self) -> SoundBank {
+        let mut bank = SoundBank::new();
+
+        for &kind in SoundKind::ALL.iter() {
+            for &material in SoundBank::MATERIALS.iter() {
+                let path = format!("sounds/{}/{}.ogg", kind.name(), material_name(material));
+                if let Some(bytes) = self.read(&path) {
+                    bank.insert(kind, material, path, bytes);
+                }
+            }
+        }
+
+        bank
+    }
+}
+
+/// An image, with 4 bytes (RGBA) per pixel, row by row.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// The average of every pixel, as RGBA from 0.0 to 1.0. The colors of
+    /// pixels are weighted by how opaque they are.
+    pub fn average_color(&self) -> [f32; 4] {
+        let mut sum = [0.0f32; 4];
+        for pixel in self.pixels.chunks(4) {
+            let alpha = pixel[3] as f32 / 255.0;
+            for i in 0..3 {
+                sum[i] += pixel[i] as f32 / 255.0 * alpha;
+            }
+            sum[3] += alpha;
+        }
+
+        let count = (self.pixels.len() / 4).max(1) as f32;
+        match sum[3] > 0.0 {
+            true => [sum[0] / sum[3], sum[1] / sum[3], sum[2] / sum[3], sum[3] / count],
+            false => [0.0; 4],
+        }
+    }
+}
+
+/// Decodes a PNG file into an RGBA image, whatever colors it has.
+pub fn decode_png(png: &[u8]) -> Result<Image, png::DecodingError> {
+    let mut decoder = png::Decoder::new(png);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    pixels.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => pixels,
+        png::ColorType::Rgb => pixels
+            .chunks(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels
+            .iter()
+            .flat_map(|&p| [p, p, p, 255])
+            .collect(),
+        // Expanded into RGB by normalize_to_color8().
+        png::ColorType::Indexed => unreachable!(),
+    };
+
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+// What the sound file of a material is called.
+fn material_name(material: SoundMaterial) -> &'static str {
+    match material {
+        SoundMaterial::Stone => "stone",
+        SoundMaterial::Glass => "glass",
+        SoundMaterial::Liquid => "liquid",
+        SoundMaterial::Silent => "silent",
+    }
+}
+
+/// Where the icon of the window is, in every resource pack.
+pub const WINDOW_ICON: &'static str = "textures/icon.png";
+
+// The resources that are built into the game, by path.
+const BUILTIN: &[(&str, &[u8])] = &[
+    (WINDOW_ICON, include_bytes!("icon.png")),
+];
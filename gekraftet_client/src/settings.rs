@@ -0,0 +1,498 @@
+use crate::windowing::{ DisplayMode, FrameLimit };
+use serde::{ Deserialize, Serialize };
+use std::{ fmt, fs, io };
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, SystemTime };
+
+/// Multisample anti-aliasing levels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Msaa {
+    Off,
+    X2,
+    X4,
+    X8,
+}
+
+impl Msaa {
+    pub fn samples(self) -> u32 {
+        match self {
+            Msaa::Off => 0,
+            Msaa::X2 => 2,
+            Msaa::X4 => 4,
+            Msaa::X8 => 8,
+        }
+    }
+
+    /// The level with the given number of samples, if there is one.
+    pub fn from_samples(samples: u32) -> Option<Self> {
+        match samples {
+            0 => Some(Msaa::Off),
+            2 => Some(Msaa::X2),
+            4 => Some(Msaa::X4),
+            8 => Some(Msaa::X8),
+            _ => None,
+        }
+    }
+}
+
+/// How the world is shaded. In the settings file, this is written by the
+/// name of the variant, e.g. `render_path = "Deferred"`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RenderPath {
+    /// Every block is lit while it is drawn.
+    Forward,
+    /// Opaque blocks are drawn into a G-buffer first, and lit afterwards in a
+    /// single full-screen pass. MSAA is not available on this path.
+    Deferred,
+}
+
+/// The post-process passes and their parameters. These can be changed at
+/// runtime through the renderer.
+#[derive(Clone, Debug)]
+pub struct PostEffects {
+    pub tonemap: bool,
+    pub exposure: f32,
+    pub bloom: bool,
+    /// The luminance above which parts of the image start to glow.
+    pub bloom_threshold: f32,
+    pub bloom_strength: f32,
+    pub vignette: bool,
+    pub vignette_strength: f32,
+}
+
+impl Default for PostEffects {
+    fn default() -> Self {
+        Self {
+            tonemap: true,
+            exposure: 1.2,
+            bloom: true,
+            bloom_threshold: 0.9,
+            bloom_strength: 0.3,
+            vignette: true,
+            vignette_strength: 0.35,
+        }
+    }
+}
+
+/// Graphics-related settings of the client.
+#[derive(Clone, Debug)]
+pub struct GraphicsSettings {
+    /// Synchronizes buffer swaps with the display refresh rate. This is
+    /// ignored in benchmark mode.
+    pub vsync: bool,
+    pub frame_limit: FrameLimit,
+    /// How the window is shown when the game starts. Toggling fullscreen
+    /// goes through the modes from there, see `DisplayMode::next()`.
+    pub display_mode: DisplayMode,
+    /// Only takes effect when the renderer is created.
+    pub msaa: Msaa,
+    /// Only takes effect when the renderer is created.
+    pub render_path: RenderPath,
+    /// Skips drawing chunks hidden behind other terrain. Only takes effect
+    /// when the renderer is created.
+    pub occlusion_culling: bool,
+    /// How many bytes of GPU memory the renderer may use before it warns.
+    pub gpu_memory_budget: usize,
+    /// How much time may be spent uploading chunk meshes every frame.
+    pub upload_budget: Duration,
+    /// The vertical field of view, in degrees.
+    pub fov: f32,
+    /// How far away (in chunks) the world is generated and drawn.
+    pub view_distance: u32,
+    /// Renders the world into an HDR target and post-processes it. Only takes
+    /// effect when the renderer is created.
+    pub post_processing: bool,
+    pub post_effects: PostEffects,
+    /// The gamma used to encode the final image for the display.
+    pub gamma: f32,
+}
+
+impl GraphicsSettings {
+    /// Whether vsync should actually be requested from the GL context.
+    pub fn effective_vsync(&self) -> bool {
+        self.vsync && self.frame_limit != FrameLimit::Benchmark
+    }
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            frame_limit: FrameLimit::Capped(240),
+            display_mode: DisplayMode::Windowed,
+            msaa: Msaa::X4,
+            render_path: RenderPath::Forward,
+            occlusion_culling: true,
+            gpu_memory_budget: 512 << 20,
+            upload_budget: Duration::from_millis(4),
+            fov: 55.0,
+            view_distance: 16,
+            post_processing: true,
+            post_effects: PostEffects::default(),
+            gamma: 2.2,
+        }
+    }
+}
+
+/// How the mouse turns the camera.
+#[derive(Clone, Debug)]
+pub struct MouseSettings {
+    /// How fast the camera turns left and right.
+    pub sensitivity_x: f32,
+    /// How fast the camera turns up and down.
+    pub sensitivity_y: f32,
+    /// Moving the mouse forwards makes the camera look down.
+    pub invert_y: bool,
+    /// Turns the camera only by how far the mouse moved, regardless of how
+    /// long the frame took. Otherwise, the rotation is scaled by the frame
+    /// time, so the same movement turns the camera further at low framerates.
+    pub frame_independent: bool,
+    /// Uses the raw motion reported by the mouse itself. Otherwise, the
+    /// motion of the cursor in the window is used, which works on platforms
+    /// that report no (or unusable) raw motion, but is subject to pointer
+    /// acceleration.
+    pub raw_input: bool,
+    /// How long (in seconds) the camera takes to turn halfway to where it was
+    /// turned. Zero turns it at once.
+    pub smoothing: f32,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity_x: 0.325,
+            sensitivity_y: 0.325,
+            invert_y: false,
+            frame_independent: false,
+            raw_input: true,
+            smoothing: 0.0,
+        }
+    }
+}
+
+/// How the analog sticks of gamepads move and turn the camera.
+#[derive(Clone, Debug)]
+pub struct GamepadSettings {
+    /// How far the sticks have to be pushed before they do anything, from 0.0
+    /// to 1.0.
+    pub deadzone: f32,
+    /// How fast the right stick turns the camera left and right, in radians
+    /// per second at full tilt.
+    pub look_sensitivity_x: f32,
+    /// How fast the right stick turns the camera up and down, in radians per
+    /// second at full tilt.
+    pub look_sensitivity_y: f32,
+    /// Pushing the right stick up makes the camera look down.
+    pub invert_y: bool,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.15,
+            look_sensitivity_x: 3.0,
+            look_sensitivity_y: 2.0,
+            invert_y: false,
+        }
+    }
+}
+
+/// How loud the game is. Every volume goes from 0.0 (silent) to 1.0, and the
+/// master volume scales all the others.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub footsteps: f32,
+    /// Blocks being broken and placed.
+    pub blocks: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            footsteps: 1.0,
+            blocks: 1.0,
+        }
+    }
+}
+
+/// The settings kept in the settings file, a TOML file with a table for the
+/// graphics, the controls and the audio, e.g.
+///
+/// ```toml
+/// [graphics]
+/// fov = 70.0
+/// msaa = 8
+/// ```
+///
+/// Settings missing from the file keep their defaults. The key bindings are
+/// kept in a file of their own, see `KeyBindings`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SettingsFile {
+    pub graphics: GraphicsSection,
+    pub controls: ControlsSection,
+    pub audio: AudioSettings,
+}
+
+/// The graphics settings in the settings file. MSAA is given as a number of
+/// samples. It only changes once the game is restarted, and so does the
+/// render path. The frame limit is the most frames per second, or 0 for no
+/// limit, and is ignored in benchmark mode. The post-process passes can only
+/// be switched on and off here, their parameters keep their defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSection {
+    pub view_distance: u32,
+    pub vsync: bool,
+    pub frame_limit: u32,
+    pub benchmark: bool,
+    pub fov: f32,
+    pub msaa: u32,
+    pub render_path: RenderPath,
+    pub gamma: f32,
+    pub tonemap: bool,
+    pub bloom: bool,
+    pub vignette: bool,
+}
+
+impl Default for GraphicsSection {
+    fn default() -> Self {
+        let graphics = GraphicsSettings::default();
+        let (frame_limit, benchmark) = frame_limit_to_file(graphics.frame_limit);
+
+        Self {
+            view_distance: graphics.view_distance,
+            vsync: graphics.vsync,
+            frame_limit,
+            benchmark,
+            fov: graphics.fov,
+            msaa: graphics.msaa.samples(),
+            render_path: graphics.render_path,
+            gamma: graphics.gamma,
+            tonemap: graphics.post_effects.tonemap,
+            bloom: graphics.post_effects.bloom,
+            vignette: graphics.post_effects.vignette,
+        }
+    }
+}
+
+/// The mouse settings in the settings file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlsSection {
+    pub sensitivity_x: f32,
+    pub sensitivity_y: f32,
+    pub invert_y: bool,
+    pub raw_input: bool,
+    pub smoothing: f32,
+}
+
+impl Default for ControlsSection {
+    fn default() -> Self {
+        let mouse = MouseSettings::default();
+
+        Self {
+            sensitivity_x: mouse.sensitivity_x,
+            sensitivity_y: mouse.sensitivity_y,
+            invert_y: mouse.invert_y,
+            raw_input: mouse.raw_input,
+            smoothing: mouse.smoothing,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "unable to access the settings: {}", e),
+            SettingsError::Parse(e) => write!(f, "invalid settings: {}", e),
+            SettingsError::Serialize(e) => write!(f, "unable to serialize the settings: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for SettingsError {
+    fn from(e: toml::de::Error) -> Self {
+        SettingsError::Parse(e)
+    }
+}
+
+impl From<toml::ser::Error> for SettingsError {
+    fn from(e: toml::ser::Error) -> Self {
+        SettingsError::Serialize(e)
+    }
+}
+
+impl SettingsFile {
+    /// Takes the settings that are kept in the file from the settings the
+    /// game runs with.
+    pub fn new(graphics: &GraphicsSettings, mouse: &MouseSettings, audio: &AudioSettings) -> Self {
+        let (frame_limit, benchmark) = frame_limit_to_file(graphics.frame_limit);
+
+        Self {
+            graphics: GraphicsSection {
+                view_distance: graphics.view_distance,
+                vsync: graphics.vsync,
+                frame_limit,
+                benchmark,
+                fov: graphics.fov,
+                msaa: graphics.msaa.samples(),
+                render_path: graphics.render_path,
+                gamma: graphics.gamma,
+                tonemap: graphics.post_effects.tonemap,
+                bloom: graphics.post_effects.bloom,
+                vignette: graphics.post_effects.vignette,
+            },
+            controls: ControlsSection {
+                sensitivity_x: mouse.sensitivity_x,
+                sensitivity_y: mouse.sensitivity_y,
+                invert_y: mouse.invert_y,
+                raw_input: mouse.raw_input,
+                smoothing: mouse.smoothing,
+            },
+            audio: audio.clone(),
+        }
+    }
+
+    /// Puts the settings from the file into the settings the game runs
+    /// with. Invalid MSAA levels leave it as it is.
+    pub fn apply(&self, graphics: &mut GraphicsSettings, mouse: &mut MouseSettings) {
+        graphics.view_distance = self.graphics.view_distance;
+        graphics.vsync = self.graphics.vsync;
+        graphics.frame_limit = match (self.graphics.benchmark, self.graphics.frame_limit) {
+            (true, _) => FrameLimit::Benchmark,
+            (false, 0) => FrameLimit::Uncapped,
+            (false, fps) => FrameLimit::Capped(fps),
+        };
+        graphics.fov = self.graphics.fov;
+        match Msaa::from_samples(self.graphics.msaa) {
+            Some(msaa) => graphics.msaa = msaa,
+            None => log::warn!("invalid msaa level {}, it can be 0, 2, 4 or 8", self.graphics.msaa),
+        }
+        graphics.render_path = self.graphics.render_path;
+        graphics.gamma = self.graphics.gamma;
+        graphics.post_effects.tonemap = self.graphics.tonemap;
+        graphics.post_effects.bloom = self.graphics.bloom;
+        graphics.post_effects.vignette = self.graphics.vignette;
+
+        mouse.sensitivity_x = self.controls.sensitivity_x;
+        mouse.sensitivity_y = self.controls.sensitivity_y;
+        mouse.invert_y = self.controls.invert_y;
+        mouse.raw_input = self.controls.raw_input;
+        mouse.smoothing = self.controls.smoothing;
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SettingsError> {
+        let text = fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// Loads the settings from `path`. If the file does not exist yet, it is
+    /// created with the default settings. If it cannot be read, the default
+    /// settings are used.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+
+        match Self::load(path) {
+            Ok(settings) => settings,
+
+            Err(SettingsError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+                let settings = Self::default();
+
+                if let Err(e) = settings.save(path) {
+                    log::warn!("{}", e);
+                }
+
+                settings
+            },
+
+            Err(e) => {
+                log::warn!("{}, using the default ones", e);
+                Self::default()
+            },
+        }
+    }
+
+    /// Writes the settings to `path`, e.g. after they were changed in the
+    /// menu.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SettingsError> {
+        let text = toml::to_string_pretty(self)?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Notices when a file is changed while the game runs, e.g. by hand, by
+/// checking when it was last modified every now and then.
+pub struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    // Seconds since the file was last checked.
+    since_check: f32,
+}
+
+impl FileWatcher {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+
+        Self {
+            modified: modified(&path),
+            path,
+            since_check: 0.0,
+        }
+    }
+
+    /// Returns true if the file was changed since it was last seen. It is
+    /// only checked every `WATCH_INTERVAL` seconds.
+    pub fn poll(&mut self, delta: f32) -> bool {
+        self.since_check += delta;
+        if self.since_check < WATCH_INTERVAL {
+            return false
+        }
+        self.since_check = 0.0;
+
+        let modified = modified(&self.path);
+        let changed = modified.is_some() && modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+
+    /// Takes the file as it is now as seen, e.g. after the game wrote it
+    /// itself.
+    pub fn mark_seen(&mut self) {
+        self.modified = modified(&self.path);
+    }
+}
+
+// The frame limit and whether benchmark mode is on, as they are written to
+// the settings file.
+fn frame_limit_to_file(limit: FrameLimit) -> (u32, bool) {
+    match limit {
+        FrameLimit::Uncapped => (0, false),
+        FrameLimit::Capped(fps) => (fps, false),
+        FrameLimit::Benchmark => (0, true),
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// How often (in seconds) watched files are checked for changes.
+const WATCH_INTERVAL: f32 = 1.0;
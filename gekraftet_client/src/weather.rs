@@ -0,0 +1,88 @@
+use cgmath::{ Point3, Vector3, Vector4 };
+use gekraftet_core::utils::Random;
+use gekraftet_core::world::World;
+use rand::{ Rng, SeedableRng };
+use crate::renderer::{ ParticleEmitter, ParticleSystem };
+use crate::world::BLOCK_LENGTH;
+
+/// Makes it rain around the camera while the weather calls for it. Drops only
+/// fall in columns open to the sky, and stop where they hit the ground.
+pub struct Rain {
+    random: Random,
+    // How heavy the rain is right now, which follows the weather slowly.
+    intensity: f32,
+    // Fractional drops left over from the previous frames.
+    pending: f32,
+}
+
+impl Rain {
+    pub fn new() -> Self {
+        Self {
+            random: Random::seed_from_u64(0x4A1_DA7E),
+            intensity: 0.0,
+            pending: 0.0,
+        }
+    }
+
+    /// How heavy the rain is, from 0 (none) to 1 (a storm).
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Spawns the drops falling in the next `delta` seconds around `eye`,
+    /// which is in world units.
+    pub fn update(&mut self, world: &World, eye: Point3<f32>, delta: f32, particles: &mut ParticleSystem) {
+        let target = world.weather().intensity();
+        let step = RAIN_FADE_SPEED * delta;
+        self.intensity += (target - self.intensity).max(-step).min(step);
+
+        self.pending += DROPS_PER_SECOND * self.intensity * delta;
+        let eye = eye.map(|v| v / BLOCK_LENGTH);
+        let top = eye.y + RAIN_HEIGHT;
+
+        while self.pending >= 1.0 {
+            self.pending -= 1.0;
+
+            let x = eye.x + self.random.gen_range(-RAIN_RADIUS, RAIN_RADIUS);
+            let z = eye.z + self.random.gen_range(-RAIN_RADIUS, RAIN_RADIUS);
+            let ground = match world.height(x.floor() as i32, z.floor() as i32) {
+                Some(height) => height as f32,
+                None => continue,
+            };
+
+            // The column is covered above where the drops start.
+            if ground >= top {
+                continue
+            }
+
+            let emitter = ParticleEmitter::new(Point3::new(x, top, z) * BLOCK_LENGTH)
+                .velocity(Vector3::new(0.0, -RAIN_SPEED, 0.0) * BLOCK_LENGTH)
+                .lifetime((top - ground) / RAIN_SPEED)
+                .size(DROP_SIZE * BLOCK_LENGTH)
+                .color(Vector4::new(0.6, 0.7, 0.9, 0.6))
+                .frames(0, 1);
+
+            particles.burst(&emitter, 1);
+        }
+    }
+}
+
+impl Default for Rain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// How many drops fall per second in a storm, and how quickly (per second) the
+// rain follows changes of the weather.
+const DROPS_PER_SECOND: f32 = 1500.0;
+const RAIN_FADE_SPEED: f32 = 0.1;
+
+// Where the drops start, in blocks around and above the camera, and how fast
+// they fall in blocks per second.
+const RAIN_RADIUS: f32 = 16.0;
+const RAIN_HEIGHT: f32 = 12.0;
+const RAIN_SPEED: f32 = 20.0;
+
+// How large the drops are, in blocks.
+const DROP_SIZE: f32 = 0.15;
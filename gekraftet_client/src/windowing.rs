@@ -1,59 +1,638 @@
-use glutin::{ Api as GlApi, GlProfile, GlRequest };
-use glutin::{ Context, ContextBuilder, PossiblyCurrent };
-use glutin::window::WindowBuilder;
+use glutin::config::{ Config, ConfigTemplateBuilder };
+use glutin::context::{ ContextApi, ContextAttributesBuilder, GlProfile };
+use glutin::context::{ PossiblyCurrentContext, Version };
+use glutin::display::GetGlDisplay;
+use glutin::prelude::*;
+use glutin::surface::{ Surface, SurfaceAttributesBuilder, SwapInterval, WindowSurface };
+use glutin_winit::{ DisplayBuilder, GlWindow };
+use raw_window_handle::HasRawWindowHandle;
+use winit::dpi::{ LogicalSize, PhysicalPosition, PhysicalSize };
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::{ BadIcon, Fullscreen, Icon, WindowBuilder, WindowId };
+use std::ffi::{ c_void, CString };
+use std::fmt;
+use std::num::NonZeroU32;
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, Instant };
 
-pub use glutin::event::{ Event, WindowEvent };
-pub use glutin::event_loop::{ ControlFlow, EventLoop };
-pub type CraftContext = glutin::WindowedContext<PossiblyCurrent>;
+use crate::resources::decode_png;
+use crate::settings::GraphicsSettings;
+
+pub use winit::event::{ Event, WindowEvent };
+pub use winit::event_loop::{ ControlFlow, EventLoop };
 
 pub struct Window {
     event_loop: EventLoop<()>,
-    context: CraftContext,
+    // The framebuffer configuration of the main window, which secondary
+    // windows use too.
+    gl_config: Config,
+    context: WindowContext,
+}
+
+/// How the window is shown on the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisplayMode {
+    Windowed,
+    /// A borderless window covering the whole monitor.
+    Borderless,
+    /// Takes the monitor over, using its largest video mode. Switching to and
+    /// from this may take a moment, or make the screen blink.
+    Exclusive,
+}
+
+impl DisplayMode {
+    /// The mode toggling fullscreen switches to, going from windowed to
+    /// borderless to exclusive and back to windowed.
+    pub fn next(self) -> Self {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Exclusive,
+            DisplayMode::Exclusive => DisplayMode::Windowed,
+        }
+    }
+}
+
+/// The size of the window's framebuffer, and the scale factor of the
+/// monitor it is on. Everything drawn in logical pixels (like the HUD) is
+/// multiplied by the scale factor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+/// A file or folder dropped onto a window, sorted by what it looks like.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DroppedFile {
+    /// A folder, which may contain a saved world.
+    World(PathBuf),
+    /// A `.schematic` or `.schem` file.
+    Schematic(PathBuf),
+    Other(PathBuf),
+}
+
+impl DroppedFile {
+    fn new(path: PathBuf) -> Self {
+        let extension = path.extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            _ if path.is_dir() => DroppedFile::World(path),
+            Some("schematic") | Some("schem") => DroppedFile::Schematic(path),
+            _ => DroppedFile::Other(path),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            DroppedFile::World(path) => path,
+            DroppedFile::Schematic(path) => path,
+            DroppedFile::Other(path) => path,
+        }
+    }
+}
+
+/// How a window is created. Settings are chained onto `WindowConfig::new()`,
+/// e.g. `WindowConfig::new().title("viewer").size(640, 480)`. Every window
+/// gets a core profile context of `GL_VERSION`, and a framebuffer without
+/// multisampling, as the renderer does that in its own framebuffers.
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    title: String,
+    // In logical pixels.
+    size: (u32, u32),
+    // The index of the monitor (in the order the platform lists them) to
+    // open the window on. The platform picks one if this is None.
+    monitor: Option<usize>,
+    display_mode: DisplayMode,
+    vsync: bool,
+    resizable: bool,
+}
+
+impl WindowConfig {
+    pub fn new() -> Self {
+        Self {
+            title: String::from(WINDOW_TITLE),
+            size: (1024, 576),
+            monitor: None,
+            display_mode: DisplayMode::Windowed,
+            vsync: true,
+            resizable: true,
+        }
+    }
+
+    /// The configuration of the game window.
+    pub fn from_settings(settings: &GraphicsSettings) -> Self {
+        Self::new()
+            .vsync(settings.effective_vsync())
+            .display_mode(settings.display_mode)
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// The size of the window when it is windowed, in logical pixels.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = (width, height);
+        self
+    }
+
+    /// Opens the window on the monitor with the index, in the order the
+    /// platform lists them.
+    pub fn monitor(mut self, index: usize) -> Self {
+        self.monitor = Some(index);
+        self
+    }
+
+    pub fn display_mode(mut self, mode: DisplayMode) -> Self {
+        self.display_mode = mode;
+        self
+    }
+
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    fn window_builder(&self, target: &EventLoopWindowTarget<()>) -> WindowBuilder {
+        let (width, height) = self.size;
+        let mut win = WindowBuilder::new()
+            .with_inner_size(LogicalSize { width, height })
+            .with_resizable(self.resizable)
+            .with_title(&self.title);
+
+        let monitor = self.monitor
+            .and_then(|i| target.available_monitors().nth(i));
+
+        if let Some(monitor) = monitor {
+            // Center the window on the monitor.
+            let size = LogicalSize::new(width, height)
+                .to_physical::<i32>(monitor.scale_factor());
+            let position = PhysicalPosition::new(
+                monitor.position().x + (monitor.size().width as i32 - size.width) / 2,
+                monitor.position().y + (monitor.size().height as i32 - size.height) / 2,
+            );
+            win = win.with_position(position);
+        }
+
+        win
+    }
+}
+
+/// The window, its GL surface and context, along with what is needed to
+/// switch the window between display modes while the game is running.
+pub struct WindowContext {
+    window: winit::window::Window,
+    surface: Surface<WindowSurface>,
+    context: PossiblyCurrentContext,
+    // The title, without the status appended to it.
+    title: String,
+    mode: DisplayMode,
+    viewport: Viewport,
+    // The size and position of the window before it went fullscreen, so that
+    // they can be restored afterwards.
+    windowed_size: PhysicalSize<u32>,
+    windowed_position: Option<PhysicalPosition<i32>>,
+    // Files dropped onto the window since they were last taken, and whether
+    // any are being dragged over it right now.
+    dropped_files: Vec<DroppedFile>,
+    hovering_files: bool,
 }
 
 impl Window {
-    pub fn create_window() -> Self {
+    pub fn create_window(config: WindowConfig) -> Self {
         let el = EventLoop::new();
+        let win = config.window_builder(&el);
+        let template = ConfigTemplateBuilder::new();
 
-        let win = WindowBuilder::new()
-            .with_inner_size(glutin::dpi::LogicalSize { width: 1024, height: 576 })
-            .with_title("gecraftet");
-
-        let ctx = ContextBuilder::new()
-            .with_gl(GlRequest::Specific(GlApi::OpenGl, (4, 0)))
-            .with_gl_profile(GlProfile::Core)
-            .build_windowed(win, &el)
-            .map_err(|e| panic!("context creation failed due to {}", e))
+        // The configs are listed from the most to the least suitable one.
+        let (window, gl_config) = DisplayBuilder::new()
+            .with_window_builder(Some(win))
+            .build(&el, template, |mut configs| configs.next().unwrap())
+            .map_err(|e| panic!("window creation failed due to {}", e))
             .unwrap();
-        
-        let ctx = unsafe {
-            ctx.make_current()
-                .map_err(|(_, e)| panic!("unable to make context current due to {}", e))
-                .unwrap()
-        };
-        
+
+        let context = WindowContext::new(window.unwrap(), &gl_config, config, None);
+
         Self {
             event_loop: el,
-            context: ctx,
+            gl_config,
+            context,
         }
     }
 
-    pub fn context(&self) -> &Context<PossiblyCurrent> {
-        self.context.context()
+    pub fn context(&self) -> &WindowContext {
+        &self.context
+    }
+
+    /// Opens another window (e.g. for debug views) with a GL context of its
+    /// own. If `shared` is set, the context shares buffers, textures and
+    /// shaders with the one of the main window. Vertex arrays are never
+    /// shared, so a renderer for the window still has to set up its own.
+    ///
+    /// Events for the window arrive in the same callback as those of the main
+    /// window, and have to be passed to its `handle_event()`.
+    pub fn create_secondary_window(&self, config: WindowConfig, shared: bool) -> WindowContext {
+        let win = config.window_builder(&self.event_loop);
+        let window = glutin_winit::finalize_window(&self.event_loop, win, &self.gl_config)
+            .map_err(|e| panic!("window creation failed due to {}", e))
+            .unwrap();
+
+        let share = match shared {
+            true => Some(&self.context.context),
+            false => None,
+        };
+        let context = WindowContext::new(window, &self.gl_config, config, share);
+
+        // Creating the context made it current.
+        self.context.make_current();
+        context
     }
 
     pub fn run<F>(self, mut callback: F)
         where F: 'static + FnMut(
             Event<()>,
             &mut ControlFlow,
-            &CraftContext
+            &mut WindowContext
         )
     {
-        let Self { event_loop, context } = self;
+        let Self { event_loop, mut context, .. } = self;
 
         event_loop.run(move |event, _, cl| {
             *cl = ControlFlow::Poll;
-            callback(event, cl, &context);
+
+            // The framebuffer follows the size of the window before anyone
+            // else learns of the change, so that context.viewport() is
+            // already up to date in the callback.
+            context.handle_event(&event);
+
+            callback(event, cl, &mut context);
         });
     }
 }
+
+impl WindowContext {
+    fn new(
+        window: winit::window::Window,
+        gl_config: &Config,
+        config: WindowConfig,
+        share: Option<&PossiblyCurrentContext>,
+    ) -> Self {
+        let (major, minor) = GL_VERSION;
+        let mut context_attributes = ContextAttributesBuilder::new()
+            .with_context_api(ContextApi::OpenGl(Some(Version::new(major, minor))))
+            .with_profile(GlProfile::Core)
+            .with_debug(cfg!(feature = "gl-debug"));
+
+        if let Some(share) = share {
+            context_attributes = context_attributes.with_sharing(share);
+        }
+
+        let context_attributes = context_attributes.build(Some(window.raw_window_handle()));
+        let display = gl_config.display();
+        let surface_attributes = window.build_surface_attributes(SurfaceAttributesBuilder::new());
+
+        let (surface, context) = unsafe {
+            let surface = display.create_window_surface(gl_config, &surface_attributes)
+                .map_err(|e| panic!("surface creation failed due to {}", e))
+                .unwrap();
+            let context = display.create_context(gl_config, &context_attributes)
+                .map_err(|e| panic!("context creation failed due to {}", e))
+                .unwrap()
+                .make_current(&surface)
+                .map_err(|e| panic!("unable to make context current due to {}", e))
+                .unwrap();
+            (surface, context)
+        };
+
+        let windowed_size = window.inner_size();
+        let windowed_position = window.outer_position().ok();
+        let scale_factor = window.scale_factor();
+
+        let mut context = Self {
+            window,
+            surface,
+            context,
+            title: config.title,
+            mode: DisplayMode::Windowed,
+            viewport: Viewport {
+                width: windowed_size.width,
+                height: windowed_size.height,
+                scale_factor,
+            },
+            windowed_size,
+            windowed_position,
+            dropped_files: Vec::new(),
+            hovering_files: false,
+        };
+        context.set_display_mode(config.display_mode);
+        context.set_vsync(config.vsync);
+
+        context
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    pub fn window(&self) -> &winit::window::Window {
+        &self.window
+    }
+
+    /// Looks up a GL function, for loading them.
+    pub fn get_proc_address(&self, name: &str) -> *const c_void {
+        let name = CString::new(name).unwrap();
+        self.context.display().get_proc_address(&name)
+    }
+
+    /// Makes GL calls go to this window. Only needed when there is more than
+    /// one window.
+    pub fn make_current(&self) {
+        if let Err(e) = self.context.make_current(&self.surface) {
+            log::error!("unable to make context current due to {}", e);
+        }
+    }
+
+    /// Keeps the framebuffer in line with the size of the window, and collects
+    /// the files dropped onto it. Events for other windows are ignored.
+    pub fn handle_event(&mut self, event: &Event<()>) {
+        let event = match event {
+            Event::WindowEvent { window_id, event } if *window_id == self.id() => event,
+            _ => return,
+        };
+
+        match event {
+            WindowEvent::Resized(size) => self.resize(*size, None),
+            WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } =>
+                self.resize(**new_inner_size, Some(*scale_factor)),
+            WindowEvent::HoveredFile(_) => self.hovering_files = true,
+            WindowEvent::HoveredFileCancelled => self.hovering_files = false,
+            WindowEvent::DroppedFile(path) => {
+                self.hovering_files = false;
+                self.dropped_files.push(DroppedFile::new(path.clone()));
+            },
+            _ => {},
+        }
+    }
+
+    /// Returns the files dropped onto the window since the last call.
+    pub fn take_dropped_files(&mut self) -> Vec<DroppedFile> {
+        std::mem::take(&mut self.dropped_files)
+    }
+
+    /// Whether files are being dragged over the window.
+    pub fn is_hovering_files(&self) -> bool {
+        self.hovering_files
+    }
+
+    /// Presents what has been rendered into the window.
+    pub fn swap_buffers(&self) -> Result<(), glutin::error::Error> {
+        self.surface.swap_buffers(&self.context)
+    }
+
+    /// Synchronizes buffer swaps with the display refresh rate, or stops
+    /// doing so. The context of this window has to be current.
+    pub fn set_vsync(&self, vsync: bool) {
+        let interval = match vsync {
+            true => SwapInterval::Wait(NonZeroU32::new(1).unwrap()),
+            false => SwapInterval::DontWait,
+        };
+
+        if let Err(e) = self.surface.set_swap_interval(&self.context, interval) {
+            log::warn!("unable to set the swap interval: {}", e);
+        }
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+        self.window.set_title(title);
+    }
+
+    /// Shows a status (e.g. the frame rate) after the title of the window.
+    pub fn set_status(&self, status: &str) {
+        let title = format!("{} - {}", self.title, status);
+        self.window.set_title(&title);
+    }
+
+    pub fn clear_status(&self) {
+        self.window.set_title(&self.title);
+    }
+
+    /// Sets the icon of the window from the contents of a PNG file.
+    pub fn set_icon_png(&self, png: &[u8]) -> Result<(), IconError> {
+        let image = decode_png(png)?;
+        let icon = Icon::from_rgba(image.pixels, image.width, image.height)?;
+        self.window.set_window_icon(Some(icon));
+        Ok(())
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>, scale_factor: Option<f64>) {
+        // Minimized windows have no size, and keep their old surface.
+        if let (Some(width), Some(height)) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height)) {
+            self.surface.resize(&self.context, width, height);
+        }
+
+        self.viewport.width = size.width;
+        self.viewport.height = size.height;
+
+        if let Some(scale_factor) = scale_factor {
+            self.viewport.scale_factor = scale_factor;
+        }
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    /// Switches the window to another display mode, and returns the viewport
+    /// it is going to have, which the renderer should adopt. Leaving
+    /// fullscreen restores the size and position the window had before
+    /// entering it.
+    pub fn set_display_mode(&mut self, mode: DisplayMode) -> Viewport {
+        let window = &self.window;
+
+        if self.mode == mode {
+            return self.viewport
+        }
+
+        if self.mode == DisplayMode::Windowed {
+            self.windowed_size = window.inner_size();
+            self.windowed_position = window.outer_position().ok();
+        }
+
+        let monitor = window.current_monitor();
+        let fullscreen = match mode {
+            DisplayMode::Windowed => None,
+            DisplayMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            DisplayMode::Exclusive => monitor
+                .and_then(|m| m.video_modes().max_by_key(|v| {
+                    let size = v.size();
+                    (size.width * size.height, v.refresh_rate_millihertz())
+                }))
+                .map(Fullscreen::Exclusive)
+                // Without a usable video mode, covering the monitor with a
+                // borderless window is the next best thing.
+                .or_else(|| Some(Fullscreen::Borderless(None))),
+        };
+
+        window.set_fullscreen(fullscreen);
+        self.mode = mode;
+
+        let size = match mode {
+            DisplayMode::Windowed => {
+                window.set_inner_size(self.windowed_size);
+                if let Some(position) = self.windowed_position {
+                    window.set_outer_position(position);
+                }
+                self.windowed_size
+            },
+            DisplayMode::Borderless | DisplayMode::Exclusive => window.inner_size(),
+        };
+
+        self.resize(size, None);
+        self.viewport
+    }
+}
+
+#[derive(Debug)]
+pub enum IconError {
+    Decode(png::DecodingError),
+    Icon(BadIcon),
+}
+
+impl fmt::Display for IconError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IconError::Decode(e) => write!(f, "unable to decode the icon: {}", e),
+            IconError::Icon(e) => write!(f, "invalid icon: {}", e),
+        }
+    }
+}
+
+impl From<png::DecodingError> for IconError {
+    fn from(e: png::DecodingError) -> Self {
+        IconError::Decode(e)
+    }
+}
+
+impl From<BadIcon> for IconError {
+    fn from(e: BadIcon) -> Self {
+        IconError::Icon(e)
+    }
+}
+
+/// Limits how often frames are presented.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameLimit {
+    /// Render as fast as possible (or as fast as vsync allows).
+    Uncapped,
+    /// Render at most this many frames per second.
+    Capped(u32),
+    /// Render as fast as possible with vsync disabled, and periodically print
+    /// frame time statistics.
+    Benchmark,
+}
+
+/// Paces the frames according to a `FrameLimit`, and measures the time taken
+/// by each frame.
+pub struct FramePacer {
+    limit: FrameLimit,
+    last_frame: Instant,
+
+    // Benchmark statistics, reset every BENCHMARK_INTERVAL
+    stats_start: Instant,
+    stats_frames: u32,
+    stats_min: Duration,
+    stats_max: Duration,
+}
+
+const BENCHMARK_INTERVAL: Duration = Duration::from_secs(5);
+
+impl FramePacer {
+    pub fn new(limit: FrameLimit) -> Self {
+        let now = Instant::now();
+
+        Self {
+            limit,
+            last_frame: now,
+            stats_start: now,
+            stats_frames: 0,
+            stats_min: Duration::from_secs(u64::MAX),
+            stats_max: Duration::from_secs(0),
+        }
+    }
+
+    pub fn limit(&self) -> FrameLimit {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: FrameLimit) {
+        self.limit = limit;
+    }
+
+    /// Should be called once the frame has been presented. Sleeps for the rest
+    /// of the frame budget (if the frame rate is capped), then returns the time
+    /// elapsed since the previous frame, in seconds.
+    pub fn end_frame(&mut self) -> f32 {
+        if let FrameLimit::Capped(fps) = self.limit {
+            let budget = Duration::from_secs(1) / fps.max(1);
+            let elapsed = self.last_frame.elapsed();
+
+            if elapsed < budget {
+                std::thread::sleep(budget - elapsed);
+            }
+        }
+
+        let now = Instant::now();
+        let frame_time = now - self.last_frame;
+        self.last_frame = now;
+
+        if self.limit == FrameLimit::Benchmark {
+            self.record(now, frame_time);
+        }
+
+        frame_time.as_secs_f32()
+    }
+
+    fn record(&mut self, now: Instant, frame_time: Duration) {
+        self.stats_frames += 1;
+        self.stats_min = self.stats_min.min(frame_time);
+        self.stats_max = self.stats_max.max(frame_time);
+
+        let elapsed = now - self.stats_start;
+        if elapsed >= BENCHMARK_INTERVAL {
+            println!(
+                "benchmark: {} frames in {:.2}s, {:.1} fps (frame time min {:.2}ms, max {:.2}ms)",
+                self.stats_frames,
+                elapsed.as_secs_f32(),
+                self.stats_frames as f32 / elapsed.as_secs_f32(),
+                self.stats_min.as_secs_f32() * 1000.0,
+                self.stats_max.as_secs_f32() * 1000.0,
+            );
+
+            self.stats_start = now;
+            self.stats_frames = 0;
+            self.stats_min = Duration::from_secs(u64::MAX);
+            self.stats_max = Duration::from_secs(0);
+        }
+    }
+}
+
+/// The title windows get unless they are given another.
+pub const WINDOW_TITLE: &'static str = "gecraftet";
+
+/// The OpenGL version the shaders are written for.
+pub const GL_VERSION: (u8, u8) = (4, 0);
@@ -1,15 +1,16 @@
-use cgmath::Point3;
-use gekraftet_core::world::{ self, Chunk };
-use crate::mesh::{ Face, Mesh, MeshBuilder };
-use super::{ Mesher, BLOCK_LENGTH };
+use cgmath::{ Point3, Vector3 };
+use gekraftet_core::world::{ self, Chunk, Direction };
+use crate::mesh::{ Face, MeshBuilder };
+use super::{ block_color, is_face_hidden, ChunkMesh, Mesher, BLOCK_LENGTH };
 
 pub struct BasicFaceMesher<'a> {
     chunk: &'a Chunk,
 }
 
 impl<'a> BasicFaceMesher<'a> {
-    fn intrasection_cull(&self) -> Mesh {
-        let mut mb = MeshBuilder::new();
+    fn intrasection_cull(&self) -> ChunkMesh {
+        let mut opaque = MeshBuilder::new();
+        let mut translucent = MeshBuilder::new();
         
         for (i, sec) in self.chunk.sections().iter().enumerate() {
             let range = (0..world::SECTION_LENGTH_X)
@@ -44,24 +45,39 @@ impl<'a> BasicFaceMesher<'a> {
                     z + self.chunk.position().z * world::CHUNK_LENGTH_Z as i32
                 );
 
-                let origin = pos.cast::<f32>().unwrap() * BLOCK_LENGTH;
+                // create_cube() expects the center of the cube.
+                let origin = (pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.5, 0.5)) * BLOCK_LENGTH;
 
                 // basic culling
+                let hidden = |b: &world::Block| is_face_hidden(block, b);
                 let mut faces = Face::all();
-                if block_left.map_or(false, |b| b.id > 0) { faces.disable(Face::LEFT) };
-                if block_right.map_or(false, |b| b.id > 0) { faces.disable(Face::RIGHT) };
-                if block_top.map_or(false, |b| b.id > 0) { faces.disable(Face::TOP) };
-                if block_bottom.map_or(false, |b| b.id > 0) { faces.disable(Face::BOTTOM) };
-                if block_front.map_or(false, |b| b.id > 0) { faces.disable(Face::FRONT) };
-                if block_back.map_or(false, |b| b.id > 0) { faces.disable(Face::BACK) };
+                if block_left.map_or(false, hidden) { faces.disable(Direction::West) };
+                if block_right.map_or(false, hidden) { faces.disable(Direction::East) };
+                if block_top.map_or(false, hidden) { faces.disable(Direction::Up) };
+                if block_bottom.map_or(false, hidden) { faces.disable(Direction::Down) };
+                if block_front.map_or(false, hidden) { faces.disable(Direction::South) };
+                if block_back.map_or(false, hidden) { faces.disable(Direction::North) };
 
-                if block.id > 0 {
-                    mb = mb.add_mesh(MeshBuilder::create_cube(BLOCK_LENGTH, origin, faces));
+                if block.is_air() {
+                    continue
+                }
+
+                let cube = MeshBuilder::create_colored_cube(
+                    BLOCK_LENGTH, origin, faces, block_color(block)
+                );
+
+                if block.is_translucent() {
+                    translucent = translucent.add_mesh(cube);
+                } else {
+                    opaque = opaque.add_mesh(cube);
                 }
             };
         }
 
-        mb.build()
+        ChunkMesh {
+            opaque: opaque.build(),
+            translucent: translucent.build(),
+        }
     }
 }
 
@@ -72,7 +88,7 @@ impl<'a> Mesher<'a> for BasicFaceMesher<'a> {
         }
     }
 
-    fn generate_mesh(&self) -> Mesh {
+    fn generate_mesh(&self) -> ChunkMesh {
         self.intrasection_cull()
     }
 }
@@ -1,14 +1,13 @@
 use cgmath::{ Point3, Vector3 };
-use gekraftet_core::world::{ self, Chunk, Section, SectionPos };
-use gekraftet_core::utils::PartialArray;
-use crate::mesh::{ Face, Mesh, MeshBuilder };
-use super::{ Mesher, BLOCK_LENGTH };
+use gekraftet_core::world::{ self, Chunk, Direction, Section, SectionOrder, SectionPos, SECTION_VOLUME };
+use crate::mesh::{ Face, MeshBuilder };
+use super::{ block_color, is_face_hidden, ChunkMesh, Mesher, BLOCK_LENGTH };
 
 pub struct GreedyCubeMesher<'a> {
     chunk: &'a Chunk,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default)]
 struct GroupedBlock {
     // This bitfield is filled with the following information:
     // - block extent: (x, y, z) = 12 bits (4 bits * 3) (see note 1)
@@ -87,13 +86,13 @@ impl<'a> GreedyCubeMesher<'a> {
         &self,
         section_pos: SectionPos,
         section: &Section,
-    ) -> Mesh 
+    ) -> ChunkMesh 
     {
         let block_pos = *section_pos * 16;
 
         let mut blocks = Vec::with_capacity(16);
-        let mut groups: [GroupedBlock; 4096] = {
-            let mut g = PartialArray::<GroupedBlock, 4096>::new();
+        let mut groups = {
+            let mut g = [GroupedBlock::default(); SECTION_VOLUME];
 
             let range = 
                 (0..16)
@@ -118,11 +117,10 @@ impl<'a> GreedyCubeMesher<'a> {
                     let mut group = GroupedBlock::new(block_id);
 
                     if y > 0 {
-                        let b = g.get_mut(x * 256 + z * 16 + y - 1).unwrap();
+                        let b = &mut g[GROUP_ORDER.index(x, y - 1, z)];
                         
-                        let can_disable_face =
-                            blocks[b.block_id()].id != 0 && 
-                            blocks[group.block_id()].id != 0;
+                        let this = blocks[group.block_id()];
+                        let below = blocks[b.block_id()];
 
                         let mut face1 = group.faces();
                         let mut face2 = b.faces();
@@ -130,19 +128,24 @@ impl<'a> GreedyCubeMesher<'a> {
                         if b.block_id() == group.block_id() {
                             group.extend_to(1, 1 + b.extent().y as usize, 1);
                             b.toggle_group();
-                        } else if can_disable_face {
-                            face1.disable(Face::BOTTOM);
-                            face2.disable(Face::TOP);
-                            group.set_faces(face1);
-                            b.set_faces(face2);
+                        } else {
+                            if is_face_hidden(this, below) {
+                                face1.disable(Direction::Down);
+                                group.set_faces(face1);
+                            }
+
+                            if is_face_hidden(below, this) {
+                                face2.disable(Direction::Up);
+                                b.set_faces(face2);
+                            }
                         }
                     };
 
-                    g.push(group).unwrap();
+                    g[GROUP_ORDER.index(x, y, z)] = group;
                 }
             };
 
-            g.into_full_array().unwrap()
+            g
         };
 
         // marking along z-axis
@@ -151,22 +154,24 @@ impl<'a> GreedyCubeMesher<'a> {
                 for y in 0..16 {
                     if z == 0 { continue };
         
-                    let idx = x * 256 + z * 16 + y;
-                    let idx2 = idx - 16;
+                    let idx = GROUP_ORDER.index(x, y, z);
+                    let idx2 = GROUP_ORDER.index(x, y, z - 1);
         
                     if groups[idx].is_in_group() {
                         continue
                     };
 
+                    let this = blocks[groups[idx].block_id()];
+                    let behind = blocks[groups[idx2].block_id()];
+
                     let can_disable_face =
-                        blocks[groups[idx].block_id()].id != 0 && 
-                        blocks[groups[idx2].block_id()].id != 0 &&
+                        is_face_hidden(this, behind) &&
                         groups[idx2].extent().y >= groups[idx].extent().y;
 
                     if groups[idx2].is_in_group() {
                         if can_disable_face {
                             let mut face = groups[idx].faces();
-                            face.disable(Face::BACK);
+                            face.disable(Direction::North);
                             groups[idx].set_faces(face);
                         }
                         continue
@@ -185,11 +190,16 @@ impl<'a> GreedyCubeMesher<'a> {
                                 orig_ext.y as usize,
                                 (orig_ext.z + groups[idx2].extent().z) as usize,
                             );
-                        } else if can_disable_face {
-                            face1.disable(Face::BACK);
-                            face2.disable(Face::FRONT);
-                            groups[idx].set_faces(face1);
-                            groups[idx2].set_faces(face2);
+                        } else {
+                            if can_disable_face {
+                                face1.disable(Direction::North);
+                                groups[idx].set_faces(face1);
+                            }
+
+                            if is_face_hidden(behind, this) {
+                                face2.disable(Direction::South);
+                                groups[idx2].set_faces(face2);
+                            }
                         }
                     }
                 }
@@ -202,23 +212,25 @@ impl<'a> GreedyCubeMesher<'a> {
                 for y in 0..16 {
                     if x == 0 { continue };
         
-                    let idx = x * 256 + z * 16 + y;
-                    let idx2 = idx - 256;
+                    let idx = GROUP_ORDER.index(x, y, z);
+                    let idx2 = GROUP_ORDER.index(x - 1, y, z);
         
                     if groups[idx].is_in_group() {
                         continue
                     };
 
+                    let this = blocks[groups[idx].block_id()];
+                    let left = blocks[groups[idx2].block_id()];
+
                     let can_disable_face =
-                        blocks[groups[idx].block_id()].id != 0 && 
-                        blocks[groups[idx2].block_id()].id != 0 &&
+                        is_face_hidden(this, left) &&
                         groups[idx2].extent().y >= groups[idx].extent().y &&
                         groups[idx2].extent().z >= groups[idx].extent().z;
 
                     if groups[idx2].is_in_group() {
                         if can_disable_face {
                             let mut face = groups[idx].faces();
-                            face.disable(Face::LEFT);
+                            face.disable(Direction::West);
                             groups[idx].set_faces(face);
                         }
                         continue
@@ -240,46 +252,65 @@ impl<'a> GreedyCubeMesher<'a> {
                                 orig_ext.y as usize,
                                 orig_ext.z as usize,
                             );
-                        } else if can_disable_face {
-                            face1.disable(Face::LEFT);
-                            face2.disable(Face::RIGHT);
-                            groups[idx].set_faces(face1);
-                            groups[idx2].set_faces(face2);
+                        } else {
+                            if can_disable_face {
+                                face1.disable(Direction::West);
+                                groups[idx].set_faces(face1);
+                            }
+
+                            if is_face_hidden(left, this) {
+                                face2.disable(Direction::East);
+                                groups[idx2].set_faces(face2);
+                            }
                         }
                     }
                 }
             }
         }
 
-        let mut mb = MeshBuilder::new();
+        let mut opaque = MeshBuilder::new();
+        let mut translucent = MeshBuilder::new();
         
         for (pos, grp) in groups.iter().enumerate() {
             if grp.is_in_group() { 
                 continue 
             };
 
-            if blocks[grp.block_id()].id == 0 {
+            let block = blocks[grp.block_id()];
+            if block.is_air() {
                 continue
             };
 
-            let x = ((pos >> 8) & 0xF) as i32;
-            let z = ((pos >> 4) & 0xF) as i32;
-            let y = ((pos >> 0) & 0xF) as i32;
+            let (x, y, z) = GROUP_ORDER.position(pos);
+            let (x, y, z) = (x as i32, y as i32, z as i32);
             let extent = grp.extent().cast::<f32>().unwrap();
+
+            // The group is stored at its last block, so the first block of the
+            // group sits (extent - 1) blocks before it. A block at (x, y, z)
+            // occupies [x, x + 1) in block space.
             let origin = Point3::<i32>::new(x, y, z)
                 + block_pos.to_homogeneous().truncate()
-                - grp.extent();
+                - grp.extent()
+                + Vector3::<i32>::new(1, 1, 1);
 
-            let mesh = MeshBuilder::create_cuboid(
+            let mesh = MeshBuilder::create_colored_cuboid(
                 extent * BLOCK_LENGTH, 
                 (origin.cast::<f32>().unwrap() + 0.5 * extent) * BLOCK_LENGTH,
-                grp.faces()
+                grp.faces(),
+                block_color(block)
             );
             
-            mb = mb.add_mesh(mesh);
+            if block.is_translucent() {
+                translucent = translucent.add_mesh(mesh);
+            } else {
+                opaque = opaque.add_mesh(mesh);
+            }
         }
 
-        mb.build()
+        ChunkMesh {
+            opaque: opaque.build(),
+            translucent: translucent.build(),
+        }
     }
 }
 
@@ -297,16 +328,29 @@ impl<'a> Mesher<'a> for GreedyCubeMesher<'a> {
         }
     }
 
-    fn generate_mesh(&self) -> Mesh {
-        let mut meshes = MeshBuilder::new();
+    fn generate_mesh(&self) -> ChunkMesh {
+        let mut opaque = MeshBuilder::new();
+        let mut translucent = MeshBuilder::new();
+
         for (i, sect) in self.chunk.sections().iter().enumerate() {
             let sect_pos = SectionPos::new(
                 self.chunk.position().x,
                 self.chunk.position().y + i as i32,
                 self.chunk.position().z,
             );
-            meshes = meshes.add_mesh(self.intrasection_cull(sect_pos, sect));
+            let meshes = self.intrasection_cull(sect_pos, sect);
+            opaque = opaque.add_mesh(meshes.opaque);
+            translucent = translucent.add_mesh(meshes.translucent);
         };
-        meshes.build()
+
+        ChunkMesh {
+            opaque: opaque.build(),
+            translucent: translucent.build(),
+        }
     }
 }
+
+// How the groups of a section are laid out. Along a Morton curve, the blocks
+// next to a block in every direction are mostly close to it in memory, which
+// is where merging looks.
+const GROUP_ORDER: SectionOrder = SectionOrder::Morton;
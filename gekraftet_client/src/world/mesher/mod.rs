@@ -1,17 +1,36 @@
 mod basic_face;
 mod greedy_cube;
 
+use crate::RGBA;
 use crate::mesh::Mesh;
-use gekraftet_core::world::Chunk;
+use gekraftet_core::world::{ Block, Chunk };
 
 pub use basic_face::BasicFaceMesher;
 pub use greedy_cube::GreedyCubeMesher;
 
 pub const BLOCK_LENGTH: f32 = 0.25;
 
+/// The meshes generated from a chunk. Translucent blocks are kept apart from
+/// the opaque ones, since they are drawn in a separate pass.
+#[derive(Clone, Debug)]
+pub struct ChunkMesh {
+    pub opaque: Mesh,
+    pub translucent: Mesh,
+}
+
 /// A trait implemented by mesh generators.
 pub trait Mesher<'a> {
     fn from_chunk(chunk: &'a Chunk) -> Self;
 
-    fn generate_mesh(&self) -> Mesh;
+    fn generate_mesh(&self) -> ChunkMesh;
+}
+
+/// Returns the color a block is drawn with.
+pub fn block_color(block: &Block) -> RGBA {
+    RGBA::from(block.definition().color)
+}
+
+/// Returns true if the face of `block` that touches `neighbour` can be hidden.
+fn is_face_hidden(block: &Block, neighbour: &Block) -> bool {
+    neighbour.is_opaque() || (block.is_translucent() && block.id == neighbour.id)
 }
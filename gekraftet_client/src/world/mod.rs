@@ -1,3 +1,5 @@
 mod mesher;
+mod streaming;
 
-pub use mesher::*;
\ No newline at end of file
+pub use mesher::*;
+pub use streaming::*;
\ No newline at end of file
@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use std::sync::{ Arc, Mutex };
+use std::sync::mpsc::{ self, Receiver, Sender };
+use std::thread;
+use gekraftet_core::world::{ Chunk, ChunkPos, WorldGenerator };
+use super::{ ChunkMesh, GreedyCubeMesher, Mesher };
+
+/// Keeps the chunks within a radius around the player loaded. Chunks that
+/// come into range are generated and meshed on worker threads, nearest
+/// first, and the ones that go out of range are handed back for unloading.
+pub struct ChunkStreamer {
+    radius: i32,
+    generator: WorldGenerator,
+    center: Option<ChunkPos>,
+    // Chunks that were handed out by `poll`, and have not been unloaded.
+    loaded: HashSet<ChunkPos>,
+    // Chunks sent to the workers whose results are still wanted.
+    requested: HashSet<ChunkPos>,
+    // Chunks waiting to be sent to the workers, the nearest one last.
+    queue: Vec<ChunkPos>,
+    // How many chunks the workers are busy with, including unwanted ones.
+    in_flight: usize,
+    jobs: Sender<(ChunkPos, WorldGenerator)>,
+    results: Receiver<(Chunk, ChunkMesh)>,
+}
+
+impl ChunkStreamer {
+    /// Starts the worker threads. `radius` is in chunks, as the view
+    /// distance is.
+    pub fn new(radius: u32) -> Self {
+        let (jobs, job_rx) = mpsc::channel::<(ChunkPos, WorldGenerator)>();
+        let (result_tx, results) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let workers = thread::available_parallelism()
+            .map(|n| n.get().saturating_sub(1).max(1))
+            .unwrap_or(1);
+
+        for _ in 0..workers {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+
+            thread::spawn(move || loop {
+                let (position, generator) = match job_rx.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    Err(_) => break,
+                };
+
+                if result_tx.send(generate_chunk(position, &generator)).is_err() {
+                    break
+                }
+            });
+        }
+
+        Self {
+            radius: radius as i32,
+            generator: WorldGenerator::default(),
+            center: None,
+            loaded: HashSet::new(),
+            requested: HashSet::new(),
+            queue: Vec::new(),
+            in_flight: 0,
+            jobs,
+            results,
+        }
+    }
+
+    pub fn radius(&self) -> u32 {
+        self.radius as u32
+    }
+
+    /// Changes how far around the player chunks are kept. This takes effect
+    /// on the next `update`.
+    pub fn set_radius(&mut self, radius: u32) {
+        self.radius = radius as i32;
+        self.center = None;
+    }
+
+    /// Changes what chunks are generated with, e.g. when another world is
+    /// opened. Every chunk handed out so far is forgotten, so the world they
+    /// were put into has to be emptied as well.
+    pub fn set_generator(&mut self, generator: WorldGenerator) {
+        self.generator = generator;
+        self.center = None;
+        self.loaded.clear();
+        self.requested.clear();
+        self.queue.clear();
+    }
+
+    /// Moves the center of the loaded area to the chunk the player is in,
+    /// and returns the chunks that should be unloaded.
+    pub fn update(&mut self, center: ChunkPos) -> Vec<ChunkPos> {
+        let center = ChunkPos::new(center.x, 0, center.z);
+        if self.center == Some(center) {
+            self.dispatch();
+            return Vec::new()
+        }
+        self.center = Some(center);
+
+        let radius = self.radius;
+        let distance = |p: &ChunkPos| (p.x - center.x).abs().max((p.z - center.z).abs());
+
+        // Chunks are kept a little beyond the radius, so that walking back
+        // and forth across a chunk border does not reload them every time.
+        let unloaded = self.loaded
+            .iter()
+            .filter(|p| distance(p) > radius + UNLOAD_MARGIN)
+            .copied()
+            .collect::<Vec<_>>();
+
+        for position in unloaded.iter() {
+            self.loaded.remove(position);
+        }
+        self.requested.retain(|p| distance(p) <= radius + UNLOAD_MARGIN);
+
+        let mut queue = Vec::new();
+        for x in -radius..=radius {
+            for z in -radius..=radius {
+                let position = ChunkPos::new(center.x + x, 0, center.z + z);
+
+                // Nothing is generated beyond the border of the world.
+                if !self.generator.border().intersects_chunk(position) {
+                    continue
+                }
+
+                if !self.loaded.contains(&position) && !self.requested.contains(&position) {
+                    queue.push(position);
+                }
+            }
+        }
+
+        let distance2 = |p: &ChunkPos| {
+            let (x, z) = (p.x - center.x, p.z - center.z);
+            x * x + z * z
+        };
+        queue.sort_by_key(|p| -distance2(p));
+        self.queue = queue;
+
+        self.dispatch();
+        unloaded
+    }
+
+    /// Returns the chunks that have finished generating and meshing since
+    /// the last call. Chunks that went out of range in the meantime are
+    /// thrown away.
+    pub fn poll(&mut self) -> Vec<(Chunk, ChunkMesh)> {
+        let mut finished = Vec::new();
+
+        while let Ok((chunk, mesh)) = self.results.try_recv() {
+            self.in_flight -= 1;
+
+            if self.requested.remove(&chunk.position()) {
+                self.loaded.insert(chunk.position());
+                finished.push((chunk, mesh));
+            }
+        }
+
+        self.dispatch();
+        finished
+    }
+
+    /// How many chunks are waiting to be generated, or being generated.
+    pub fn pending(&self) -> usize {
+        self.queue.len() + self.requested.len()
+    }
+
+    // Only a few chunks are handed to the workers at a time, so that when the
+    // player moves, the chunks nearest to its new position are not stuck
+    // behind a long line of chunks it already left behind.
+    fn dispatch(&mut self) {
+        while self.in_flight < MAX_IN_FLIGHT {
+            let position = match self.queue.pop() {
+                Some(position) => position,
+                None => break,
+            };
+
+            if self.jobs.send((position, self.generator)).is_err() {
+                break
+            }
+
+            self.requested.insert(position);
+            self.in_flight += 1;
+        }
+    }
+}
+
+// Generates the terrain of a chunk and meshes it.
+fn generate_chunk(position: ChunkPos, generator: &WorldGenerator) -> (Chunk, ChunkMesh) {
+    let chunk = generator.generate(position);
+    let mesh = GreedyCubeMesher::from_chunk(&chunk).generate_mesh();
+    (chunk, mesh)
+}
+
+// How many chunks past the radius a chunk has to be before it is unloaded.
+const UNLOAD_MARGIN: i32 = 2;
+
+// How many chunks the workers are given at once.
+const MAX_IN_FLIGHT: usize = 16;
@@ -0,0 +1,211 @@
+use std::{ fs, io };
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+use crate::level::Level;
+use crate::region::RegionStore;
+use crate::saves::{ self, WorldManager };
+use crate::world::{ BlockPos, Chunk, World, CHUNK_LENGTH_X, CHUNK_LENGTH_Y, CHUNK_LENGTH_Z };
+
+/// Snapshots of worlds, so that they can be put back the way they were. Every
+/// world has a folder of snapshots, and every snapshot a folder of its own,
+/// named after when it was taken.
+///
+/// The blocks of a world are not saved with it, only its level and the
+/// chunks that were generated ahead of time. A snapshot holds a copy of the
+/// save together with every chunk that is loaded, so that the blocks are put
+/// back as well.
+#[derive(Clone, Debug)]
+pub struct Backups {
+    root: PathBuf,
+}
+
+/// A snapshot of a world.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub name: String,
+    /// When it was taken, in seconds since the Unix epoch.
+    pub taken: u64,
+}
+
+impl Backups {
+    /// Keeps the snapshots in the folder, which is created once the first
+    /// one is taken.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+        }
+    }
+
+    /// Takes a snapshot of the world saved in the folder, whose loaded
+    /// blocks are those of `world`. Returns it.
+    pub fn snapshot(&self, saves: &WorldManager, folder: &str, world: &World) -> saves::Result<Snapshot> {
+        let taken = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        // Names sort in the order the snapshots were taken, even if two are
+        // taken in the same second.
+        let name = (1..)
+            .map(|i| match i {
+                1 => format!("{}", taken),
+                i => format!("{}-{}", taken, i),
+            })
+            .find(|name| !self.root.join(folder).join(name).exists())
+            .unwrap();
+
+        let path = self.root.join(folder).join(&name);
+        copy_folder(&saves.root().join(folder), &path.join(SAVE_FOLDER))?;
+        RegionStore::new(path.join(CHUNKS_FOLDER)).save(world.chunks())?;
+
+        Ok(Snapshot { name, taken })
+    }
+
+    /// Returns the snapshots of the world, oldest first. A world without a
+    /// folder of snapshots has none.
+    pub fn list(&self, folder: &str) -> io::Result<Vec<Snapshot>> {
+        let entries = match fs::read_dir(self.root.join(folder)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+
+            if !entry.path().join(SAVE_FOLDER).is_dir() {
+                continue
+            }
+
+            let name = match entry.file_name().to_str() {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+
+            if let Some(taken) = name.split('-').next().and_then(|t| t.parse().ok()) {
+                snapshots.push(Snapshot { name, taken });
+            }
+        }
+
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(snapshots)
+    }
+
+    /// Deletes all but the latest `keep` snapshots of the world. Returns the
+    /// ones that were deleted.
+    pub fn prune(&self, folder: &str, keep: usize) -> io::Result<Vec<Snapshot>> {
+        let mut snapshots = self.list(folder)?;
+        let excess = snapshots.len().saturating_sub(keep);
+        snapshots.truncate(excess);
+
+        for snapshot in snapshots.iter() {
+            fs::remove_dir_all(self.root.join(folder).join(&snapshot.name))?;
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Puts the world back the way it was when the snapshot was taken: its
+    /// save is replaced by the one of the snapshot, and every block of
+    /// `world` that changed since is changed back. Chunks that were not
+    /// loaded back then are put back the way they were generated. Returns
+    /// the level of the snapshot, and how many blocks were changed back.
+    pub fn restore(&self, saves: &WorldManager, folder: &str, name: &str, world: &mut World) -> saves::Result<(Level, usize)> {
+        let snapshot = self.root.join(folder).join(name);
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_digit() || c == '-');
+
+        if !valid || !snapshot.join(SAVE_FOLDER).is_dir() {
+            let e = io::Error::new(io::ErrorKind::NotFound, format!("there is no snapshot {}", name));
+            return Err(e.into())
+        }
+
+        // The save is copied next to the world first, so that the world is
+        // left alone if the copy fails halfway.
+        let target = saves.root().join(folder);
+        let copy = saves.root().join(format!("{}{}", folder, RESTORING_SUFFIX));
+        if copy.exists() {
+            fs::remove_dir_all(&copy)?;
+        }
+        copy_folder(&snapshot.join(SAVE_FOLDER), &copy)?;
+
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(&copy, &target)?;
+
+        let level = saves.load(folder)?;
+        let chunks = RegionStore::new(snapshot.join(CHUNKS_FOLDER));
+        let regions = saves.regions(folder);
+
+        let loaded = world.chunks().map(Chunk::position).collect::<Vec<_>>();
+        let mut changed = 0;
+        for at in loaded {
+            let old = match chunks.load(at)? {
+                Some(chunk) => chunk,
+                None => regions.load(at)?.unwrap_or_else(|| level.generator.generate(at)),
+            };
+
+            changed += revert_chunk(world, &old);
+        }
+
+        Ok((level, changed))
+    }
+}
+
+// Changes every block of the loaded chunk at the position of `old` that
+// differs from `old` back. Returns how many were changed.
+fn revert_chunk(world: &mut World, old: &Chunk) -> usize {
+    let at = old.position();
+    let mut differing = Vec::new();
+
+    if let Some(chunk) = world.chunk(at) {
+        for x in 0..CHUNK_LENGTH_X {
+            for z in 0..CHUNK_LENGTH_Z {
+                for y in 0..CHUNK_LENGTH_Y {
+                    match (chunk.block(x, y, z), old.block(x, y, z)) {
+                        (Some(block), Some(old)) if block != old => differing.push(((x, y, z), old.clone())),
+                        _ => {},
+                    }
+                }
+            }
+        }
+    }
+
+    differing
+        .into_iter()
+        .filter_map(|((x, y, z), block)| {
+            let position = BlockPos::new(
+                at.x * CHUNK_LENGTH_X as i32 + x as i32,
+                y as i32,
+                at.z * CHUNK_LENGTH_Z as i32 + z as i32,
+            );
+            world.set_block(position, block)
+        })
+        .count()
+}
+
+// Copies the folder and everything in it.
+fn copy_folder(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+
+        match entry.file_type()?.is_dir() {
+            true => copy_folder(&entry.path(), &target)?,
+            false => { fs::copy(entry.path(), target)?; },
+        }
+    }
+
+    Ok(())
+}
+
+// The folders in a snapshot that hold the copy of the save and the chunks
+// that were loaded.
+const SAVE_FOLDER: &'static str = "save";
+const CHUNKS_FOLDER: &'static str = "chunks";
+
+// Added to the folder of a world for the copy of the save being restored.
+const RESTORING_SUFFIX: &'static str = ".restoring";
@@ -0,0 +1,70 @@
+use cgmath::{ Point3, Vector3, Vector4 };
+use crate::utils;
+use super::Component;
+
+/// Where an entity is, in blocks. For entities with an `Aabb`, this is the
+/// center of the bottom of the box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position(pub Point3<f32>);
+
+/// How fast an entity moves, in blocks per second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Velocity(pub Vector3<f32>);
+
+/// The box an entity takes up, which keeps it from moving into solid blocks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// The size of the box, in blocks.
+    pub size: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            size: Vector3::new(width, height, width),
+        }
+    }
+
+    /// Returns the corners of the box of an entity at `position`.
+    pub fn bounds(&self, position: Point3<f32>) -> (Point3<f32>, Point3<f32>) {
+        let half = Vector3::new(self.size.x / 2.0, 0.0, self.size.z / 2.0);
+        (position - half, position + half + Vector3::unit_y() * self.size.y)
+    }
+
+    /// The box of an entity at `position`, to test it against other boxes.
+    pub fn at(&self, position: Point3<f32>) -> utils::Aabb {
+        let (min, max) = self.bounds(position);
+        utils::Aabb { min, max }
+    }
+}
+
+/// Makes an entity fall, in blocks per second squared.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gravity(pub f32);
+
+/// Whether an entity is standing on a solid block. This is kept up-to-date
+/// for entities that have it, see `MovementSystem`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Grounded(pub bool);
+
+/// How an entity looks. The client decides how each shape is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Render {
+    pub shape: RenderShape,
+    pub color: Vector4<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderShape {
+    /// A box filling the `Aabb` of the entity.
+    Cube,
+    /// A small copy of a block, e.g. for dropped items.
+    Block(u16),
+}
+
+impl Component for Position {}
+impl Component for Velocity {}
+impl Component for Aabb {}
+impl Component for Gravity {}
+impl Component for Grounded {}
+impl Component for Render {}
@@ -0,0 +1,201 @@
+use cgmath::{ InnerSpace, Point3, Vector3, Vector4 };
+use crate::inventory::{ Inventory, ItemStack };
+use crate::world::World;
+use super::*;
+
+/// Marks an entity as items lying around in the world, e.g. dropped from a
+/// broken block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ItemDrop {
+    stack: ItemStack,
+    // How long (in seconds) the drop has been lying around.
+    age: f32,
+}
+
+impl ItemDrop {
+    pub fn new(stack: ItemStack) -> Self {
+        Self { stack, age: 0.0 }
+    }
+
+    pub fn stack(&self) -> ItemStack {
+        self.stack
+    }
+
+    /// How long the drop has existed, in seconds.
+    pub fn age(&self) -> f32 {
+        self.age
+    }
+}
+
+impl Component for ItemDrop {}
+
+/// Spawns a drop of the items centered on `position`, thrown with `velocity`.
+pub fn spawn_item_drop(
+    entities: &mut Entities,
+    position: Point3<f32>,
+    stack: ItemStack,
+    velocity: Vector3<f32>,
+) -> Entity {
+    let feet = position - Vector3::unit_y() * ITEM_SIZE / 2.0;
+
+    let drop = entities.spawn();
+    entities.insert(drop, Position(feet));
+    entities.insert(drop, Velocity(velocity));
+    entities.insert(drop, Aabb::new(ITEM_SIZE, ITEM_SIZE));
+    entities.insert(drop, Gravity(ITEM_GRAVITY));
+    entities.insert(drop, Grounded(false));
+    entities.insert(drop, Render {
+        shape: RenderShape::Block(stack.id),
+        color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+    });
+    entities.insert(drop, ItemDrop::new(stack));
+    drop
+}
+
+/// Ages item drops, despawns the ones that have been lying around for too
+/// long, and merges drops of the same kind that are close to each other.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ItemDropSystem;
+
+impl System for ItemDropSystem {
+    fn run(&mut self, entities: &mut Entities, _world: &World, delta: f32) {
+        let mut expired = Vec::new();
+
+        {
+            let mut drops = entities.storage_mut::<ItemDrop>();
+            let positions = entities.storage::<Position>();
+
+            for (entity, drop) in drops.iter_mut() {
+                drop.age += delta;
+
+                if drop.age >= ITEM_LIFETIME || drop.stack.count == 0 {
+                    expired.push(entity);
+                }
+            }
+
+            // The older drop takes in the younger one, so that a pile of drops
+            // ends up where the first of them landed.
+            let nearby = drops.iter()
+                .filter(|(_, d)| d.age < ITEM_LIFETIME)
+                .filter_map(|(e, d)| Some((e, positions.get(e)?.0, d.stack.id, d.age)))
+                .collect::<Vec<_>>();
+
+            for (i, &(a, position_a, id_a, age_a)) in nearby.iter().enumerate() {
+                for &(b, position_b, id_b, age_b) in nearby[i + 1..].iter() {
+                    if id_a != id_b || (position_a - position_b).magnitude2() > MERGE_RADIUS * MERGE_RADIUS {
+                        continue
+                    }
+
+                    let (into, from) = match age_a >= age_b {
+                        true => (a, b),
+                        false => (b, a),
+                    };
+
+                    // Either drop may have been emptied by an earlier merge.
+                    let mut taken = match (drops.get(into), drops.get(from)) {
+                        (Some(i), Some(f)) if i.stack.count > 0 && f.stack.count > 0 => f.stack,
+                        _ => continue,
+                    };
+
+                    if let Some(drop) = drops.get_mut(into) {
+                        drop.stack.merge(&mut taken);
+                    }
+
+                    if let Some(drop) = drops.get_mut(from) {
+                        drop.stack = taken;
+                    }
+
+                    if taken.count == 0 {
+                        expired.push(from);
+                    }
+                }
+            }
+        }
+
+        for entity in expired {
+            entities.despawn(entity);
+        }
+    }
+}
+
+/// Pulls the item drops near `target` towards it, and moves the ones that
+/// reach it into the inventory. Drops that do not fit stay in the world.
+///
+/// Returns the number of items picked up.
+pub fn pickup_items(
+    entities: &mut Entities,
+    target: Point3<f32>,
+    inventory: &mut Inventory,
+    delta: f32,
+) -> u32 {
+    let mut picked_up = 0;
+    let mut collected = Vec::new();
+
+    {
+        let mut drops = entities.storage_mut::<ItemDrop>();
+        let positions = entities.storage::<Position>();
+        let mut velocities = entities.storage_mut::<Velocity>();
+
+        for (entity, drop) in drops.iter_mut() {
+            if drop.age < PICKUP_DELAY {
+                continue
+            }
+
+            let center = match positions.get(entity) {
+                Some(position) => position.0 + Vector3::unit_y() * ITEM_SIZE / 2.0,
+                None => continue,
+            };
+
+            let offset = target - center;
+            let distance = offset.magnitude();
+
+            if distance <= PICKUP_RADIUS {
+                let count = drop.stack.count;
+
+                match inventory.insert(drop.stack) {
+                    Some(left) => drop.stack = left,
+                    None => {
+                        drop.stack.count = 0;
+                        collected.push(entity);
+                    },
+                }
+
+                picked_up += count - drop.stack.count;
+            } else if distance <= MAGNET_RADIUS {
+                // The closer the drop, the faster it is pulled in.
+                if let Some(velocity) = velocities.get_mut(entity) {
+                    let pull = 1.0 - distance / MAGNET_RADIUS;
+                    let wanted = offset / distance * MAGNET_SPEED * (0.25 + pull);
+                    velocity.0 += (wanted - velocity.0) * (MAGNET_RESPONSE * delta).min(1.0);
+                }
+            }
+        }
+    }
+
+    for entity in collected {
+        entities.despawn(entity);
+    }
+
+    picked_up
+}
+
+// The size of item drops in blocks, and how fast they fall in blocks per
+// second squared.
+const ITEM_SIZE: f32 = 0.25;
+const ITEM_GRAVITY: f32 = 24.0;
+
+// How long (in seconds) drops lie around before they vanish, and before they
+// can be picked up.
+const ITEM_LIFETIME: f32 = 300.0;
+const PICKUP_DELAY: f32 = 0.5;
+
+// How close (in blocks) drops have to be to merge, to be pulled in, and to be
+// picked up.
+const MERGE_RADIUS: f32 = 0.75;
+const MAGNET_RADIUS: f32 = 2.5;
+const PICKUP_RADIUS: f32 = 0.75;
+
+// How fast (in blocks per second) drops are pulled in at most, and how quickly
+// they turn towards the target.
+const MAGNET_SPEED: f32 = 10.0;
+const MAGNET_RESPONSE: f32 = 12.0;
@@ -0,0 +1,270 @@
+use cgmath::{ InnerSpace, Point3, Vector2, Vector3, Vector4 };
+use rand::{ Rng, SeedableRng };
+use crate::utils::Random;
+use crate::world::{ BlockPos, ChunkPos, World, CHUNK_LENGTH_X, CHUNK_LENGTH_Y, CHUNK_LENGTH_Z, SEA_LEVEL };
+use super::*;
+
+/// Makes an entity wander around aimlessly, resting every now and then. This
+/// is what marks an entity as a mob.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wander {
+    walking: bool,
+    heading: Vector2<f32>,
+    // How long (in seconds) until the mob switches between resting and
+    // walking.
+    timer: f32,
+}
+
+impl Wander {
+    pub fn new() -> Self {
+        Self {
+            walking: false,
+            heading: Vector2::new(1.0, 0.0),
+            timer: 0.0,
+        }
+    }
+
+    pub fn is_walking(&self) -> bool {
+        self.walking
+    }
+
+    /// The direction the mob walks (or last walked) in, on the XZ plane.
+    pub fn heading(&self) -> Vector2<f32> {
+        self.heading
+    }
+}
+
+impl Default for Wander {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Component for Wander {}
+
+/// Spawns the test mob, a cube creature, with its feet at `position`.
+pub fn spawn_cube_mob(entities: &mut Entities, position: Point3<f32>) -> Entity {
+    let mob = entities.spawn();
+    entities.insert(mob, Position(position));
+    entities.insert(mob, Velocity(Vector3::new(0.0, 0.0, 0.0)));
+    entities.insert(mob, Aabb::new(MOB_SIZE, MOB_SIZE));
+    entities.insert(mob, Gravity(MOB_GRAVITY));
+    entities.insert(mob, Grounded(false));
+    entities.insert(mob, Render {
+        shape: RenderShape::Cube,
+        color: Vector4::new(0.85, 0.45, 0.3, 1.0),
+    });
+    entities.insert(mob, Wander::new());
+    mob
+}
+
+/// Returns where in the column of blocks at (x, z) a mob may spawn, if it may
+/// spawn there at all. Mobs spawn on the highest solid block of the column,
+/// as long as it is dry land at or above the sea level.
+///
+/// There are no biomes or light levels yet. Spawning only under the open sky
+/// stands in for needing daylight, and only on dry land for the biome.
+pub fn mob_spawn_point(world: &World, x: i32, z: i32) -> Option<Point3<f32>> {
+    let top = (0..CHUNK_LENGTH_Y as i32)
+        .rev()
+        .find(|&y| world.get_block(BlockPos::new(x, y, z)).map_or(false, |b| !b.is_air()))?;
+
+    let ground = world.get_block(BlockPos::new(x, top, z))?;
+    if !ground.is_solid() || top + 1 < SEA_LEVEL {
+        return None
+    }
+
+    Some(Point3::new(x as f32 + 0.5, (top + 1) as f32, z as f32 + 0.5))
+}
+
+/// Spawns mobs in the loaded chunks, following `mob_spawn_point`, and
+/// despawns mobs once the chunk they are in is unloaded.
+pub struct MobSpawner {
+    random: Random,
+    max_mobs: usize,
+    // How long (in seconds) until the next attempt to spawn a mob.
+    cooldown: f32,
+}
+
+impl MobSpawner {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            random: Random::seed_from_u64(seed),
+            max_mobs: DEFAULT_MAX_MOBS,
+            cooldown: 0.0,
+        }
+    }
+
+    /// How many mobs may be alive at once.
+    pub fn max_mobs(mut self, max_mobs: usize) -> Self {
+        self.max_mobs = max_mobs;
+        self
+    }
+}
+
+impl System for MobSpawner {
+    fn run(&mut self, entities: &mut Entities, world: &World, delta: f32) {
+        let unloaded = {
+            let mobs = entities.storage::<Wander>();
+            let positions = entities.storage::<Position>();
+
+            mobs.iter()
+                .filter(|(mob, _)| {
+                    positions.get(*mob).map_or(true, |p| !is_loaded(world, p.0))
+                })
+                .map(|(mob, _)| mob)
+                .collect::<Vec<_>>()
+        };
+
+        for mob in unloaded {
+            entities.despawn(mob);
+        }
+
+        self.cooldown -= delta;
+        if self.cooldown > 0.0 || entities.storage::<Wander>().len() >= self.max_mobs {
+            return
+        }
+        self.cooldown = SPAWN_INTERVAL;
+
+        let chunk_count = world.chunks().count();
+        if chunk_count == 0 {
+            return
+        }
+
+        let chunk = world.chunks().nth(self.random.gen_range(0, chunk_count)).unwrap();
+        let origin = chunk.position();
+        let x = origin.x * CHUNK_LENGTH_X as i32 + self.random.gen_range(0, CHUNK_LENGTH_X as i32);
+        let z = origin.z * CHUNK_LENGTH_Z as i32 + self.random.gen_range(0, CHUNK_LENGTH_Z as i32);
+
+        if let Some(position) = mob_spawn_point(world, x, z) {
+            spawn_cube_mob(entities, position);
+        }
+    }
+}
+
+/// Runs the AI of wandering mobs. Mobs hop up single blocks in their way,
+/// and turn back instead of walking off cliffs or into water.
+pub struct WanderSystem {
+    random: Random,
+}
+
+impl WanderSystem {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            random: Random::seed_from_u64(seed),
+        }
+    }
+}
+
+impl System for WanderSystem {
+    fn run(&mut self, entities: &mut Entities, world: &World, delta: f32) {
+        let mut mobs = entities.storage_mut::<Wander>();
+        let positions = entities.storage::<Position>();
+        let mut velocities = entities.storage_mut::<Velocity>();
+        let grounded = entities.storage::<Grounded>();
+        let boxes = entities.storage::<Aabb>();
+
+        for (mob, wander) in mobs.iter_mut() {
+            let (position, velocity) = match (positions.get(mob), velocities.get_mut(mob)) {
+                (Some(position), Some(velocity)) => (position.0, velocity),
+                _ => continue,
+            };
+            let on_ground = grounded.get(mob).map_or(true, |g| g.0);
+            let reach = boxes.get(mob).map_or(0.5, |b| b.size.x / 2.0) + LOOK_AHEAD;
+
+            wander.timer -= delta;
+            if wander.timer <= 0.0 {
+                wander.walking = !wander.walking;
+
+                if wander.walking {
+                    let angle = self.random.gen_range(0.0, std::f32::consts::PI * 2.0);
+                    wander.heading = Vector2::new(angle.cos(), angle.sin());
+                    wander.timer = self.random.gen_range(MIN_WALK_TIME, MAX_WALK_TIME);
+                } else {
+                    wander.timer = self.random.gen_range(MIN_REST_TIME, MAX_REST_TIME);
+                }
+            }
+
+            if !wander.walking {
+                velocity.0.x = 0.0;
+                velocity.0.z = 0.0;
+                continue
+            }
+
+            if on_ground {
+                match look_ahead(world, position, wander.heading * reach) {
+                    Terrain::Clear => {},
+                    Terrain::Step => velocity.0.y = MOB_JUMP_SPEED,
+                    Terrain::Blocked => wander.heading = -wander.heading,
+                }
+            }
+
+            let walk = wander.heading.normalize() * MOB_SPEED;
+            velocity.0.x = walk.x;
+            velocity.0.z = walk.y;
+        }
+    }
+}
+
+// What is in front of a walking mob.
+enum Terrain {
+    Clear,
+    // A single block, which can be hopped onto.
+    Step,
+    // A wall, a cliff or water.
+    Blocked,
+}
+
+fn look_ahead(world: &World, position: Point3<f32>, offset: Vector2<f32>) -> Terrain {
+    let x = (position.x + offset.x).floor() as i32;
+    let z = (position.z + offset.y).floor() as i32;
+    let y = position.y.floor() as i32;
+
+    let block = |dy: i32| world.get_block(BlockPos::new(x, y + dy, z));
+    let solid = |dy: i32| block(dy).map_or(true, |b| b.is_solid());
+    let fluid = |dy: i32| block(dy).map_or(false, |b| b.is_fluid());
+
+    if solid(0) {
+        return match !solid(1) && !solid(2) {
+            true => Terrain::Step,
+            false => Terrain::Blocked,
+        }
+    }
+
+    // The first block below the feet that is not air decides whether it is
+    // safe to walk on.
+    let below = (1..=MAX_DROP).find(|&dy| solid(-dy) || fluid(-dy));
+    match below {
+        Some(dy) if solid(-dy) => Terrain::Clear,
+        _ => Terrain::Blocked,
+    }
+}
+
+fn is_loaded(world: &World, position: Point3<f32>) -> bool {
+    let mut column = position.map(|v| v.floor() as i32);
+    column.y = 0;
+    world.chunk(ChunkPos::from(BlockPos(column))).is_some()
+}
+
+// The size of the cube mob in blocks, and how it moves in blocks per second
+// (squared).
+const MOB_SIZE: f32 = 0.8;
+const MOB_SPEED: f32 = 1.5;
+const MOB_GRAVITY: f32 = 32.0;
+const MOB_JUMP_SPEED: f32 = 8.5;
+
+// How long mobs walk and rest for, in seconds.
+const MIN_WALK_TIME: f32 = 2.0;
+const MAX_WALK_TIME: f32 = 6.0;
+const MIN_REST_TIME: f32 = 1.0;
+const MAX_REST_TIME: f32 = 5.0;
+
+// How far (in blocks) in front of their box mobs look, and how far down they
+// are willing to drop.
+const LOOK_AHEAD: f32 = 0.3;
+const MAX_DROP: i32 = 3;
+
+// How many mobs may be alive at once by default, and how often (in seconds)
+// one is attempted to be spawned.
+const DEFAULT_MAX_MOBS: usize = 24;
+const SPAWN_INTERVAL: f32 = 0.5;
@@ -0,0 +1,207 @@
+mod components;
+mod item;
+mod mob;
+mod storage;
+mod system;
+
+pub use components::*;
+pub use item::*;
+pub use mob::*;
+pub use storage::{ Component, Storage };
+pub use system::*;
+
+use std::any::TypeId;
+use std::cell::{ Ref, RefCell, RefMut };
+use std::collections::HashMap;
+use storage::AnyStorage;
+
+/// A handle to an entity. Handles of despawned entities stay invalid, even
+/// after their slot is reused by a new entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    fn new(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    /// Packs the handle into a number, e.g. to send it elsewhere. The
+    /// number can be turned back into the same handle by `from_bits()`.
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    pub fn from_bits(bits: u64) -> Self {
+        Self::new(bits as u32, (bits >> 32) as u32)
+    }
+}
+
+/// Every entity, and the components attached to them. Every type of
+/// component is stored apart from the others, and can be borrowed on its own,
+/// so that a system can e.g. change the positions of entities while reading
+/// their velocities.
+///
+/// Borrowing the same type of component mutably twice at once panics, like
+/// a `RefCell` does.
+pub struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    // Slots of despawned entities, which new entities are put into first.
+    free: Vec<u32>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        let mut entities = Self {
+            generations: Vec::new(),
+            alive: Vec::new(),
+            free: Vec::new(),
+            storages: HashMap::new(),
+        };
+
+        entities.register::<Position>();
+        entities.register::<Velocity>();
+        entities.register::<Aabb>();
+        entities.register::<Gravity>();
+        entities.register::<Grounded>();
+        entities.register::<Render>();
+        entities.register::<ItemDrop>();
+        entities.register::<Wander>();
+        entities
+    }
+
+    /// Makes room for a type of component. Components are registered when
+    /// they are first inserted, this is only needed to borrow the storage of
+    /// a type before that.
+    pub fn register<C: Component>(&mut self) {
+        self.storages
+            .entry(TypeId::of::<C>())
+            .or_insert_with(|| Box::new(RefCell::new(Storage::<C>::new())));
+    }
+
+    /// Creates an entity without any components.
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity::new(index, self.generations[index as usize])
+            },
+
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity::new(self.generations.len() as u32 - 1, 0)
+            },
+        }
+    }
+
+    /// Removes the entity and all of its components. Returns false if the
+    /// entity had already been despawned.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false
+        }
+
+        for storage in self.storages.values() {
+            storage.clear_slot(entity.index);
+        }
+
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index).copied().unwrap_or(false)
+            && self.generations[index] == entity.generation
+    }
+
+    /// How many entities there are.
+    pub fn len(&self) -> usize {
+        self.alive.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(move |(index, _)| Entity::new(index as u32, self.generations[index]))
+    }
+
+    /// Attaches a component to the entity, replacing (and returning) the one
+    /// of the same type it had. Components are not attached to despawned
+    /// entities.
+    pub fn insert<C: Component>(&mut self, entity: Entity, component: C) -> Option<C> {
+        if !self.is_alive(entity) {
+            return None
+        }
+
+        self.register::<C>();
+        self.storage_mut::<C>().insert(entity, component)
+    }
+
+    pub fn remove<C: Component>(&mut self, entity: Entity) -> Option<C> {
+        match self.storages.contains_key(&TypeId::of::<C>()) {
+            true => self.storage_mut::<C>().remove(entity),
+            false => None,
+        }
+    }
+
+    pub fn has<C: Component>(&self, entity: Entity) -> bool {
+        self.storages.contains_key(&TypeId::of::<C>())
+            && self.storage::<C>().contains(entity)
+    }
+
+    /// Returns a copy of a component of the entity.
+    pub fn get<C: Component + Clone>(&self, entity: Entity) -> Option<C> {
+        match self.storages.contains_key(&TypeId::of::<C>()) {
+            true => self.storage::<C>().get(entity).cloned(),
+            false => None,
+        }
+    }
+
+    /// Borrows every component of a type.
+    ///
+    /// Panics if the type has not been registered, or its components are
+    /// borrowed mutably.
+    pub fn storage<C: Component>(&self) -> Ref<'_, Storage<C>> {
+        self.cell::<C>().borrow()
+    }
+
+    /// Borrows every component of a type mutably.
+    ///
+    /// Panics if the type has not been registered, or its components are
+    /// already borrowed.
+    pub fn storage_mut<C: Component>(&self) -> RefMut<'_, Storage<C>> {
+        self.cell::<C>().borrow_mut()
+    }
+
+    fn cell<C: Component>(&self) -> &RefCell<Storage<C>> {
+        self.storages
+            .get(&TypeId::of::<C>())
+            .and_then(|s| s.as_any().downcast_ref::<RefCell<Storage<C>>>())
+            .expect("component type has not been registered")
+    }
+}
+
+impl Default for Entities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,128 @@
+use std::any::Any;
+use std::cell::RefCell;
+use super::Entity;
+
+/// A type that can be attached to entities.
+pub trait Component: 'static {}
+
+/// The components of a single type, indexed by the entities they belong to.
+pub struct Storage<C: Component> {
+    // Every slot remembers the generation of the entity it belongs to, so that
+    // components of despawned entities are never handed to their successors.
+    slots: Vec<Option<(u32, C)>>,
+    len: usize,
+}
+
+impl<C: Component> Storage<C> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&C> {
+        match self.slots.get(entity.index as usize) {
+            Some(Some((generation, c))) if *generation == entity.generation => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut C> {
+        match self.slots.get_mut(entity.index as usize) {
+            Some(Some((generation, c))) if *generation == entity.generation => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Attaches the component to the entity, returning the one it replaces.
+    pub fn insert(&mut self, entity: Entity, component: C) -> Option<C> {
+        let index = entity.index as usize;
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+
+        let old = self.slots[index].replace((entity.generation, component));
+        match old {
+            Some((generation, c)) if generation == entity.generation => Some(c),
+            Some(_) => None,
+            None => {
+                self.len += 1;
+                None
+            },
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<C> {
+        if !self.contains(entity) {
+            return None
+        }
+
+        self.len -= 1;
+        self.slots[entity.index as usize].take().map(|(_, c)| c)
+    }
+
+    /// Iterates over the entities that have this component.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &C)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                slot.as_ref().map(|(generation, c)| (Entity::new(index as u32, *generation), c))
+            })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut C)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                slot.as_mut().map(|(generation, c)| (Entity::new(index as u32, *generation), c))
+            })
+    }
+
+    // Drops the component in the slot, whichever generation it belongs to.
+    fn clear_slot(&mut self, index: u32) {
+        if let Some(slot) = self.slots.get_mut(index as usize) {
+            if slot.take().is_some() {
+                self.len -= 1;
+            }
+        }
+    }
+}
+
+impl<C: Component> Default for Storage<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Lets storages of different component types be kept together, and cleaned up
+// when an entity is despawned.
+pub(super) trait AnyStorage {
+    fn as_any(&self) -> &dyn Any;
+
+    fn clear_slot(&self, index: u32);
+}
+
+impl<C: Component> AnyStorage for RefCell<Storage<C>> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn clear_slot(&self, index: u32) {
+        self.borrow_mut().clear_slot(index);
+    }
+}
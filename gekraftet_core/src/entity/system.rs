@@ -0,0 +1,176 @@
+use std::time::{ Duration, Instant };
+use cgmath::Point3;
+use crate::world::{ BlockPos, ChunkPos, World };
+use super::*;
+
+/// Something that runs over the entities every tick.
+pub trait System {
+    /// Advances the entities by `delta` seconds.
+    fn run(&mut self, entities: &mut Entities, world: &World, delta: f32);
+
+    /// What the system is called, e.g. when it is timed. This is the name of
+    /// its type, unless it says otherwise.
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
+/// The systems run every tick, in the order they were added in.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<Box<dyn System>>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self {
+            systems: Vec::new(),
+        }
+    }
+
+    pub fn system<S: System + 'static>(mut self, system: S) -> Self {
+        self.systems.push(Box::new(system));
+        self
+    }
+
+    pub fn run(&mut self, entities: &mut Entities, world: &World, delta: f32) {
+        for system in self.systems.iter_mut() {
+            system.run(entities, world, delta);
+        }
+    }
+
+    /// Runs the systems like `run()`, and returns how long each of them took.
+    pub fn run_timed(&mut self, entities: &mut Entities, world: &World, delta: f32) -> Vec<(&'static str, Duration)> {
+        self.systems
+            .iter_mut()
+            .map(|system| {
+                let start = Instant::now();
+                system.run(entities, world, delta);
+                (system.name(), start.elapsed())
+            })
+            .collect()
+    }
+}
+
+/// Moves entities by their velocity, and makes those with `Gravity` fall.
+/// Entities with an `Aabb` stop at solid blocks. Entities in chunks that are
+/// not loaded stay where they are, so that they do not fall out of the world.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MovementSystem;
+
+impl System for MovementSystem {
+    fn run(&mut self, entities: &mut Entities, world: &World, delta: f32) {
+        let mut positions = entities.storage_mut::<Position>();
+        let mut velocities = entities.storage_mut::<Velocity>();
+        let gravities = entities.storage::<Gravity>();
+        let boxes = entities.storage::<Aabb>();
+        let mut grounded = entities.storage_mut::<Grounded>();
+
+        for (entity, velocity) in velocities.iter_mut() {
+            let position = match positions.get_mut(entity) {
+                Some(position) => position,
+                None => continue,
+            };
+
+            let mut column = position.0.map(|v| v.floor() as i32);
+            column.y = 0;
+            if world.chunk(ChunkPos::from(BlockPos(column))).is_none() {
+                continue
+            }
+
+            if let Some(gravity) = gravities.get(entity) {
+                velocity.0.y -= gravity.0 * delta;
+                velocity.0.y = velocity.0.y.max(-TERMINAL_SPEED);
+            }
+
+            let aabb = match boxes.get(entity) {
+                Some(aabb) => aabb,
+                None => {
+                    position.0 += velocity.0 * delta;
+                    continue
+                },
+            };
+
+            for axis in 0..3 {
+                let amount = velocity.0[axis] * delta;
+
+                if move_box(world, aabb, &mut position.0, axis, amount) != amount {
+                    velocity.0[axis] = 0.0;
+                }
+            }
+
+            if let Some(grounded) = grounded.get_mut(entity) {
+                grounded.0 = velocity.0.y <= 0.0 && touches_ground(world, aabb, position.0);
+            }
+        }
+    }
+}
+
+// Moves the box along an axis until it touches a solid block, and returns how
+// far it actually moved. Every layer of blocks the moving face passes through
+// is checked, so fast entities cannot tunnel through thin walls.
+fn move_box(world: &World, aabb: &Aabb, position: &mut Point3<f32>, axis: usize, amount: f32) -> f32 {
+    if amount == 0.0 {
+        return 0.0
+    }
+
+    let (min, max) = aabb.bounds(*position);
+    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+    let range = |a: usize| (min[a] + EPSILON).floor() as i32..=(max[a] - EPSILON).floor() as i32;
+
+    let layer_is_solid = |layer: i32| {
+        range(u).any(|a| range(v).any(|b| {
+            let mut at = Point3::new(0, 0, 0);
+            at[axis] = layer;
+            at[u] = a;
+            at[v] = b;
+            is_solid(world, at)
+        }))
+    };
+
+    let moved = if amount > 0.0 {
+        let first = (max[axis] - EPSILON).floor() as i32 + 1;
+        let last = (max[axis] + amount - EPSILON).floor() as i32;
+
+        (first..=last)
+            .find(|&layer| layer_is_solid(layer))
+            .map(|layer| (layer as f32 - max[axis] - SKIN).max(0.0))
+            .unwrap_or(amount)
+    } else {
+        let first = (min[axis] + EPSILON).floor() as i32 - 1;
+        let last = (min[axis] + amount + EPSILON).floor() as i32;
+
+        (last..=first)
+            .rev()
+            .find(|&layer| layer_is_solid(layer))
+            .map(|layer| (layer as f32 + 1.0 - min[axis] + SKIN).min(0.0))
+            .unwrap_or(amount)
+    };
+
+    position[axis] += moved;
+    moved
+}
+
+// Whether a solid block is right below the box.
+fn touches_ground(world: &World, aabb: &Aabb, position: Point3<f32>) -> bool {
+    let (min, max) = aabb.bounds(position);
+    let y = (min.y - SKIN - EPSILON).floor() as i32;
+    let range = |a: usize| (min[a] + EPSILON).floor() as i32..=(max[a] - EPSILON).floor() as i32;
+
+    range(0).any(|x| range(2).any(|z| is_solid(world, Point3::new(x, y, z))))
+}
+
+fn is_solid(world: &World, at: Point3<i32>) -> bool {
+    world.get_block(BlockPos(at))
+        .map(|block| block.is_solid())
+        .unwrap_or(false)
+}
+
+// How fast entities can fall, in blocks per second.
+const TERMINAL_SPEED: f32 = 78.0;
+
+// How close (in blocks) a box may get to a block before it counts as touching
+// it, and the gap left between them.
+const EPSILON: f32 = 1e-4;
+const SKIN: f32 = 1e-4;
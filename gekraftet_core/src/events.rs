@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use crate::world::{ Block, BlockPos, ChunkPos, World };
+
+/// What the player did to a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Interaction {
+    Break,
+    Place,
+    /// The player picked the block to place it elsewhere, which leaves it
+    /// where it is.
+    Pick,
+}
+
+/// Something that happened in the world, which other parts of the game may
+/// want to react to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorldEvent {
+    /// A block was replaced, by the player or by the world itself (e.g. a
+    /// flowing fluid).
+    BlockChanged {
+        at: BlockPos,
+        old: Block,
+        new: Block,
+    },
+    ChunkLoaded(ChunkPos),
+    /// The player broke, placed or picked a block. The block changes on its
+    /// own too, see `BlockChanged`.
+    PlayerInteracted {
+        at: BlockPos,
+        interaction: Interaction,
+        old: Block,
+        new: Block,
+    },
+}
+
+impl WorldEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            WorldEvent::BlockChanged { .. } => EventKind::BlockChanged,
+            WorldEvent::ChunkLoaded(_) => EventKind::ChunkLoaded,
+            WorldEvent::PlayerInteracted { .. } => EventKind::PlayerInteracted,
+        }
+    }
+}
+
+/// The kinds of events, which listeners subscribe to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    BlockChanged,
+    ChunkLoaded,
+    PlayerInteracted,
+}
+
+/// Something that reacts to events. Closures taking the event and the world
+/// are listeners too.
+pub trait Listener {
+    /// Reacts to the event, after it has happened in the world.
+    fn handle(&mut self, event: &WorldEvent, world: &World);
+}
+
+impl<F> Listener for F
+where
+    F: FnMut(&WorldEvent, &World)
+{
+    fn handle(&mut self, event: &WorldEvent, world: &World) {
+        self(event, world)
+    }
+}
+
+/// A handle to a listener, to unsubscribe it with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ListenerId(u32);
+
+/// Hands events to the listeners subscribed to their kind. Events are queued
+/// when they are published, and only handed out by `dispatch()`, so that the
+/// listeners can look at the world without it being borrowed elsewhere.
+#[derive(Default)]
+pub struct EventBus {
+    pending: VecDeque<WorldEvent>,
+    listeners: Vec<(ListenerId, Vec<EventKind>, Box<dyn Listener>)>,
+    next_id: u32,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            pending: VecDeque::new(),
+            listeners: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Makes the listener handle the given kinds of events from now on.
+    /// Listeners handle events in the order they were subscribed in.
+    pub fn subscribe<L>(&mut self, kinds: &[EventKind], listener: L) -> ListenerId
+    where
+        L: Listener + 'static
+    {
+        let id = ListenerId(self.next_id);
+        self.next_id += 1;
+        self.listeners.push((id, kinds.to_vec(), Box::new(listener)));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: ListenerId) {
+        self.listeners.retain(|(listener, _, _)| *listener != id);
+    }
+
+    /// Queues the event, to be handed out by the next `dispatch()`.
+    pub fn publish(&mut self, event: WorldEvent) {
+        self.pending.push_back(event);
+    }
+
+    /// Hands the queued events to their listeners, followed by the events
+    /// that happened in the world since the last time, see
+    /// `World::take_events()`.
+    pub fn dispatch(&mut self, world: &mut World) {
+        self.pending.extend(world.take_events());
+
+        while let Some(event) = self.pending.pop_front() {
+            let kind = event.kind();
+
+            for (_, kinds, listener) in self.listeners.iter_mut() {
+                if kinds.contains(&kind) {
+                    listener.handle(&event, world);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::convert::TryFrom;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The ways reading a serialized inventory can fail.
+#[derive(Debug)]
+pub enum Error {
+    UnexpectedEof,
+    /// A stack holds more items than a stack can.
+    OversizedStack(u32),
+}
+
+/// A number of items of the same kind. Items are blocks for now, so the kind
+/// of an item is the id of its block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    pub id: u16,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub fn new(id: u16, count: u32) -> Self {
+        Self { id, count }
+    }
+
+    /// Returns true if the stack can take more items of its kind.
+    pub fn is_full(&self) -> bool {
+        self.count >= MAX_STACK_SIZE
+    }
+
+    /// Moves as many items as fit from `other` into this stack. Both stacks
+    /// have to be of the same kind, otherwise nothing is moved.
+    pub fn merge(&mut self, other: &mut ItemStack) {
+        if self.id != other.id {
+            return
+        }
+
+        let moved = other.count.min(MAX_STACK_SIZE.saturating_sub(self.count));
+        self.count += moved;
+        other.count -= moved;
+    }
+}
+
+/// The items carried by the player, kept in a fixed number of slots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: vec![None; size],
+        }
+    }
+
+    /// The number of slots.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+
+    pub fn slot(&self, index: usize) -> Option<&ItemStack> {
+        self.slots.get(index).and_then(Option::as_ref)
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    /// The number of items of a kind, across every slot.
+    pub fn count(&self, id: u16) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|s| s.id == id)
+            .map(|s| s.count)
+            .sum()
+    }
+
+    /// Empties the slot, and returns what was in it.
+    pub fn take(&mut self, index: usize) -> Option<ItemStack> {
+        self.slots.get_mut(index)?.take()
+    }
+
+    /// Puts the stack into the slot. Stacks of the same kind are merged, and
+    /// what does not fit is returned. A stack of another kind is swapped out
+    /// and returned instead.
+    ///
+    /// Panics if the slot does not exist.
+    pub fn put(&mut self, index: usize, mut stack: ItemStack) -> Option<ItemStack> {
+        let slot = &mut self.slots[index];
+
+        match slot {
+            Some(old) if old.id == stack.id => {
+                old.merge(&mut stack);
+                match stack.count {
+                    0 => None,
+                    _ => Some(stack),
+                }
+            },
+            _ => slot.replace(stack),
+        }
+    }
+
+    /// Takes half of the stack in the slot out, rounded up. The other half
+    /// stays behind.
+    pub fn split(&mut self, index: usize) -> Option<ItemStack> {
+        let slot = self.slots.get_mut(index)?;
+        let stack = slot.as_mut()?;
+
+        let taken = ItemStack::new(stack.id, (stack.count + 1) / 2);
+        stack.count -= taken.count;
+
+        if stack.count == 0 {
+            *slot = None;
+        }
+
+        Some(taken)
+    }
+
+    /// Moves the stack in one slot onto another. Stacks of the same kind are
+    /// merged (leaving what does not fit behind), other stacks swap places.
+    ///
+    /// Panics if either slot does not exist.
+    pub fn move_stack(&mut self, from: usize, to: usize) {
+        if from == to {
+            return
+        }
+
+        if let Some(stack) = self.slots[from].take() {
+            self.slots[from] = self.put(to, stack);
+        }
+    }
+
+    /// Puts the stack into the inventory, topping up stacks of the same kind
+    /// before taking empty slots. Returns what did not fit, if anything.
+    pub fn insert(&mut self, mut stack: ItemStack) -> Option<ItemStack> {
+        for slot in self.slots.iter_mut().flatten() {
+            slot.merge(&mut stack);
+        }
+
+        for slot in self.slots.iter_mut().filter(|s| s.is_none()) {
+            if stack.count == 0 {
+                break
+            }
+
+            let mut new = ItemStack::new(stack.id, 0);
+            new.merge(&mut stack);
+            *slot = Some(new);
+        }
+
+        match stack.count {
+            0 => None,
+            _ => Some(stack),
+        }
+    }
+
+    /// Takes up to `count` items of the kind out of the inventory, emptying
+    /// the last slots first. Returns how many items were taken.
+    pub fn remove(&mut self, id: u16, count: u32) -> u32 {
+        let mut removed = 0;
+
+        for slot in self.slots.iter_mut().rev() {
+            if let Some(stack) = slot.as_mut().filter(|s| s.id == id) {
+                let taken = stack.count.min(count - removed);
+                stack.count -= taken;
+                removed += taken;
+
+                if stack.count == 0 {
+                    *slot = None;
+                }
+            }
+        }
+
+        removed
+    }
+
+    /// Encodes the inventory. The number of slots comes first, then six bytes
+    /// for every slot: the id of the item, and the number of items, which is
+    /// zero for empty slots.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.slots.len() * 6);
+        bytes.extend_from_slice(&(self.slots.len() as u32).to_be_bytes());
+
+        for slot in self.slots.iter() {
+            let stack = slot.unwrap_or(ItemStack::new(0, 0));
+            bytes.extend_from_slice(&stack.id.to_be_bytes());
+            bytes.extend_from_slice(&stack.count.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    /// Decodes an inventory encoded by `to_bytes()`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let len = bytes.get(0..4).ok_or(Error::UnexpectedEof)?;
+        let len = u32::from_be_bytes(<[u8; 4]>::try_from(len).unwrap()) as usize;
+
+        let slots = bytes.get(4..4 + len * 6).ok_or(Error::UnexpectedEof)?;
+        let slots = slots
+            .chunks(6)
+            .map(|slot| {
+                let id = u16::from_be_bytes([slot[0], slot[1]]);
+                let count = u32::from_be_bytes(<[u8; 4]>::try_from(&slot[2..6]).unwrap());
+
+                match count {
+                    0 => Ok(None),
+                    c if c > MAX_STACK_SIZE => Err(Error::OversizedStack(c)),
+                    c => Ok(Some(ItemStack::new(id, c))),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { slots })
+    }
+}
+
+/// The most items a single stack can hold.
+pub const MAX_STACK_SIZE: u32 = 64;
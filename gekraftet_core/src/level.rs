@@ -0,0 +1,156 @@
+use std::convert::TryFrom;
+use std::string::FromUtf8Error;
+use crate::migration::{ MigrationError, Migrations, Payload };
+use crate::player::{ self, PlayerState };
+use crate::world::{ BlockPos, WorldBorder, WorldGenerator, WorldPreset };
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The ways reading a serialized level can fail.
+#[derive(Debug)]
+pub enum Error {
+    UnexpectedEof,
+    /// The level was written by a version of the game this one cannot read.
+    UnknownVersion(u8),
+    UnknownPreset(u8),
+    InvalidName(FromUtf8Error),
+    Player(player::Error),
+    /// The level was written by an earlier version of the game, and could
+    /// not be migrated.
+    Migration(MigrationError),
+}
+
+impl From<player::Error> for Error {
+    fn from(e: player::Error) -> Self {
+        Error::Player(e)
+    }
+}
+
+impl From<MigrationError> for Error {
+    fn from(e: MigrationError) -> Self {
+        Error::Migration(e)
+    }
+}
+
+/// What is kept about a level besides its blocks.
+#[derive(Clone, Debug)]
+pub struct Level {
+    /// The name shown for the level, which does not have to be unique.
+    pub name: String,
+    /// What the terrain of the level is generated with.
+    pub generator: WorldGenerator,
+    /// Where players spawn and respawn, with their feet at the bottom of the
+    /// block. It is picked once the ground around the origin is generated.
+    pub spawn: Option<BlockPos>,
+    /// The player, unless it has never been in the level.
+    pub player: Option<PlayerState>,
+}
+
+impl Level {
+    /// Creates a level that nothing has happened in yet.
+    pub fn new(name: String, generator: WorldGenerator) -> Self {
+        Self {
+            name,
+            generator,
+            spawn: None,
+            player: None,
+        }
+    }
+
+    /// Encodes the level into bytes, which can be decoded by `from_bytes()`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(LEVEL_HEADER_SIZE);
+        bytes.push(LEVEL_VERSION);
+
+        let flags = self.spawn.is_some() as u8 | (self.player.is_some() as u8) << 1;
+        bytes.push(flags);
+
+        let spawn = self.spawn.unwrap_or(BlockPos::new(0, 0, 0));
+        for axis in 0..3 {
+            bytes.extend_from_slice(&spawn.0[axis].to_be_bytes());
+        }
+
+        bytes.extend_from_slice(&self.generator.seed().to_be_bytes());
+        bytes.push(self.generator.preset().id());
+        bytes.extend_from_slice(&self.generator.border().radius().to_be_bytes());
+
+        let name = self.name.as_bytes();
+        let name = &name[..name.len().min(u16::max_value() as usize)];
+        bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(name);
+
+        if let Some(player) = &self.player {
+            bytes.extend_from_slice(&player.to_bytes());
+        }
+
+        bytes
+    }
+
+    /// Decodes a level encoded by `to_bytes()`, of this version of the game
+    /// or of an earlier one, which is migrated first.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match Self::version(bytes)? {
+            LEVEL_VERSION => Self::decode(bytes),
+            version => match Migrations::builtin().migrate(Payload::Level, bytes, version, LEVEL_VERSION) {
+                Ok(migrated) => Self::decode(&migrated),
+                Err(MigrationError::TooNew { .. }) => Err(Error::UnknownVersion(version)),
+                Err(e) => Err(e.into()),
+            },
+        }
+    }
+
+    /// The version of the encoded level.
+    pub fn version(bytes: &[u8]) -> Result<u8> {
+        bytes.first().copied().ok_or(Error::UnexpectedEof)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let header = bytes.get(0..LEVEL_HEADER_SIZE).ok_or(Error::UnexpectedEof)?;
+
+        if header[0] != LEVEL_VERSION {
+            return Err(Error::UnknownVersion(header[0]))
+        }
+
+        let int = |i: usize| {
+            let at = 2 + i * 4;
+            i32::from_be_bytes(<[u8; 4]>::try_from(&header[at..at + 4]).unwrap())
+        };
+
+        let spawn = match header[1] & 1 != 0 {
+            true => Some(BlockPos::new(int(0), int(1), int(2))),
+            false => None,
+        };
+
+        let seed = u64::from_be_bytes(<[u8; 8]>::try_from(&header[14..22]).unwrap());
+        let preset = WorldPreset::from_id(header[22]).ok_or(Error::UnknownPreset(header[22]))?;
+
+        let border = i32::from_be_bytes(<[u8; 4]>::try_from(&header[23..27]).unwrap());
+        let name_len = u16::from_be_bytes([header[27], header[28]]) as usize;
+        let name = bytes.get(LEVEL_HEADER_SIZE..LEVEL_HEADER_SIZE + name_len).ok_or(Error::UnexpectedEof)?;
+        let name = String::from_utf8(name.to_vec()).map_err(Error::InvalidName)?;
+
+        let player = match header[1] & 2 != 0 {
+            true => Some(PlayerState::from_bytes(&bytes[LEVEL_HEADER_SIZE + name_len..])?),
+            false => None,
+        };
+
+        let mut generator = WorldGenerator::new(seed, preset);
+        generator.set_border(WorldBorder::new(border));
+
+        Ok(Self {
+            name,
+            generator,
+            spawn,
+            player,
+        })
+    }
+}
+
+/// The version of the encoding of levels, which is bumped whenever it
+/// changes, together with a migration from the one before.
+pub const LEVEL_VERSION: u8 = 1;
+
+// How many bytes come before the name: the version, the flags saying what is
+// present, the spawn point, the seed, the preset, the radius of the border and
+// the length of the name. The player follows the name.
+const LEVEL_HEADER_SIZE: usize = 1 + 1 + 3 * 4 + 8 + 1 + 4 + 2;
@@ -1,3 +1,15 @@
+pub mod backups;
+pub mod entity;
+pub mod events;
+pub mod inventory;
+pub mod level;
+pub mod migration;
+pub mod movement;
 pub mod nbt;
+pub mod net;
+pub mod player;
+pub mod region;
+pub mod saves;
+pub mod stats;
 pub mod utils;
 pub mod world;
@@ -0,0 +1,129 @@
+use std::fmt;
+use crate::region;
+
+/// A part of a save that is encoded with a version of its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Payload {
+    /// The level of a world, see `Level::to_bytes()`. Its first byte is its
+    /// version.
+    Level,
+    /// The blocks of a chunk, before they are compressed into a region file.
+    /// Their version is that of the region file.
+    Chunk,
+}
+
+/// Turns a payload of one version into one of a later version, so that
+/// worlds saved by earlier versions of the game can still be opened.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub payload: Payload,
+    pub from: u8,
+    pub to: u8,
+    /// What changed, for telling what migrating a world would do.
+    pub description: &'static str,
+    /// Takes the payload as version `from` stores it, and returns it as
+    /// version `to` does.
+    pub apply: fn(&[u8]) -> Result<Vec<u8>, MigrationError>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    /// There is no migration from the version towards the current one.
+    Missing { payload: Payload, from: u8 },
+    /// The payload was saved by a later version of the game.
+    TooNew { payload: Payload, version: u8 },
+    /// The payload is not what its version says it is.
+    Invalid(String),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MigrationError::Missing { payload, from } =>
+                write!(f, "unable to migrate a {:?} payload from version {}", payload, from),
+            MigrationError::TooNew { payload, version } =>
+                write!(f, "a {:?} payload of version {} is newer than this game", payload, version),
+            MigrationError::Invalid(reason) => write!(f, "invalid payload: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// Every migration the game knows, by payload and version.
+#[derive(Clone)]
+pub struct Migrations {
+    migrations: Vec<Migration>,
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self {
+            migrations: Vec::new(),
+        }
+    }
+
+    /// The migrations of every payload up to the versions this game saves.
+    pub fn builtin() -> Self {
+        Self::new()
+            .with(Migration {
+                payload: Payload::Chunk,
+                from: 1,
+                to: 2,
+                description: "blocks are stored as indices into a palette",
+                apply: region::migrate_raw_chunk,
+            })
+    }
+
+    /// Adds a migration. Migrations of the same payload from the same
+    /// version replace each other.
+    pub fn with(mut self, migration: Migration) -> Self {
+        self.migrations.retain(|m| m.payload != migration.payload || m.from != migration.from);
+        self.migrations.push(migration);
+        self
+    }
+
+    /// The migrations that turn a payload of the version `from` into one of
+    /// the version `to`, in the order they are applied. There are none if
+    /// the versions are the same.
+    pub fn steps(&self, payload: Payload, from: u8, to: u8) -> Result<Vec<&Migration>, MigrationError> {
+        if from > to {
+            return Err(MigrationError::TooNew { payload, version: from })
+        }
+
+        let mut steps = Vec::new();
+        let mut version = from;
+        while version < to {
+            // The migration that goes furthest without going past `to` is
+            // taken, skipping the versions in between.
+            let step = self.migrations
+                .iter()
+                .filter(|m| m.payload == payload && m.from == version && m.to > version && m.to <= to)
+                .max_by_key(|m| m.to)
+                .ok_or(MigrationError::Missing { payload, from: version })?;
+
+            version = step.to;
+            steps.push(step);
+        }
+
+        Ok(steps)
+    }
+
+    /// Turns a payload of the version `from` into one of the version `to`.
+    pub fn migrate(&self, payload: Payload, bytes: &[u8], from: u8, to: u8) -> Result<Vec<u8>, MigrationError> {
+        let mut bytes = bytes.to_vec();
+        for step in self.steps(payload, from, to)? {
+            bytes = (step.apply)(&bytes)?;
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl fmt::Debug for Migrations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.migrations.iter().map(|m| (m.payload, m.from, m.to)))
+            .finish()
+    }
+}
@@ -0,0 +1,547 @@
+use cgmath::{ InnerSpace, Point3, Vector2, Vector3 };
+use crate::player::GameMode;
+use crate::world::{ Block, BlockPos, ChunkPos, World };
+
+/// What the player wants to do during a frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MovementInput {
+    /// The direction to walk in, on the horizontal plane. Its length (up to
+    /// 1.0) is how fast to walk, relative to `speed`.
+    pub direction: Vector2<f32>,
+    /// How fast to walk (or fly), in blocks per second.
+    pub speed: f32,
+    /// Jumping while in water swims up instead.
+    pub jump: bool,
+    /// Sprinting only works while walking forward, and not while crouching.
+    pub sprint: bool,
+    /// Crouching makes the player smaller and slower, and keeps it from
+    /// walking off the edge of the block it is standing on.
+    pub crouch: bool,
+    /// Which way to fly vertically, from -1.0 (down) to 1.0 (up). Ignored
+    /// unless flying.
+    pub vertical: f32,
+}
+
+/// Where the body of a player is and how it moves, which is all that moving
+/// it further depends on. The server sends it to the client, so that the
+/// client can start over from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MovementState {
+    pub position: Point3<f32>,
+    pub velocity: Vector3<f32>,
+    pub on_ground: bool,
+    pub flying: bool,
+    pub crouching: bool,
+}
+
+/// Moves the player through the world: walking, jumping and falling, without
+/// ever entering a solid block. The player can also fly, which turns gravity
+/// off, and optionally lets it pass through blocks. The position of the
+/// player is the center of the bottom of its box, in blocks.
+///
+/// The client and the server move the player alike, so that what the client
+/// shows right away is (almost always) what the server decides later.
+#[derive(Clone, Debug)]
+pub struct PlayerBody {
+    position: Point3<f32>,
+    velocity: Vector3<f32>,
+    on_ground: bool,
+    game_mode: GameMode,
+    flying: bool,
+    // Whether blocks are passed through while flying.
+    noclip: bool,
+    sprinting: bool,
+    crouching: bool,
+    // Whether the box of the player is partly in a fluid.
+    in_fluid: bool,
+    // How far (in blocks) the player has walked since the last footstep, and
+    // the block the last footstep was on, until it is taken.
+    stride: f32,
+    footstep: Option<Block>,
+}
+
+impl PlayerBody {
+    pub fn new(position: Point3<f32>) -> Self {
+        Self {
+            position,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            on_ground: false,
+            game_mode: GameMode::default(),
+            flying: false,
+            noclip: false,
+            sprinting: false,
+            crouching: false,
+            in_fluid: false,
+            stride: 0.0,
+            footstep: None,
+        }
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        self.position
+    }
+
+    /// Where the eyes of the player are, which is where the camera goes.
+    pub fn eye(&self) -> Point3<f32> {
+        self.position + Vector3::unit_y() * self.eye_height()
+    }
+
+    /// Moves the feet of the player to the position, and stops it.
+    pub fn teleport(&mut self, position: Point3<f32>) {
+        self.position = position;
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    /// Moves the player so that its eyes are at the position, and stops it.
+    pub fn set_eye(&mut self, eye: Point3<f32>) {
+        self.position = eye - Vector3::unit_y() * self.eye_height();
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    pub fn state(&self) -> MovementState {
+        MovementState {
+            position: self.position,
+            velocity: self.velocity,
+            on_ground: self.on_ground,
+            flying: self.flying,
+            crouching: self.crouching,
+        }
+    }
+
+    /// Puts the body back into the state. Footsteps are not affected.
+    pub fn set_state(&mut self, state: MovementState) {
+        self.position = state.position;
+        self.velocity = state.velocity;
+        self.on_ground = state.on_ground;
+        self.flying = state.flying && self.game_mode.can_fly();
+        self.crouching = state.crouching;
+    }
+
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    pub fn sprinting(&self) -> bool {
+        self.sprinting
+    }
+
+    pub fn crouching(&self) -> bool {
+        self.crouching
+    }
+
+    /// Whether the player is (at least partly) in water.
+    pub fn in_fluid(&self) -> bool {
+        self.in_fluid
+    }
+
+    pub fn flying(&self) -> bool {
+        self.flying
+    }
+
+    /// Starts or stops flying. Players cannot fly unless their game mode
+    /// lets them.
+    pub fn set_flying(&mut self, flying: bool) {
+        self.flying = flying && self.game_mode.can_fly();
+        self.velocity.y = 0.0;
+    }
+
+    pub fn game_mode(&self) -> GameMode {
+        self.game_mode
+    }
+
+    /// Changes the game mode, landing the player if it may no longer fly.
+    pub fn set_game_mode(&mut self, game_mode: GameMode) {
+        self.game_mode = game_mode;
+
+        if !game_mode.can_fly() {
+            self.set_flying(false);
+        }
+    }
+
+    pub fn noclip(&self) -> bool {
+        self.noclip
+    }
+
+    /// Lets the player fly through blocks. This has no effect unless it is
+    /// flying.
+    pub fn set_noclip(&mut self, noclip: bool) {
+        self.noclip = noclip;
+    }
+
+    /// Moves the player by a frame. `forward` is the direction the player is
+    /// facing, only its horizontal part is used.
+    pub fn update(&mut self, input: &MovementInput, forward: Vector3<f32>, delta_time: f32, world: &World) {
+        let forward = Vector3::new(forward.x, 0.0, forward.z);
+        let forward = match forward.magnitude2() > 0.0 {
+            true => forward.normalize(),
+            false => Vector3::unit_x(),
+        };
+        let right = forward.cross(Vector3::unit_y());
+
+        // Walking changes direction at once, only falling is gradual.
+        let direction = match input.direction.magnitude2() > 1.0 {
+            true => input.direction.normalize(),
+            false => input.direction,
+        };
+
+        // The player only stands up again if there is room above it.
+        let crouch = input.crouch && !self.flying;
+        if crouch != self.crouching && (crouch || self.can_stand(world)) {
+            self.crouching = crouch;
+        }
+        self.in_fluid = self.touches_fluid(world);
+        self.sprinting = input.sprint && direction.y > 0.0 && !self.crouching;
+
+        let mut speed = match (self.sprinting, self.crouching) {
+            (true, _) => input.speed * SPRINT_FACTOR,
+            (_, true) => input.speed * CROUCH_FACTOR,
+            _ => input.speed,
+        };
+        if self.in_fluid && !self.flying {
+            speed *= SWIM_FACTOR;
+        }
+        let walk = (forward * direction.y + right * direction.x) * speed;
+        self.velocity.x = walk.x;
+        self.velocity.z = walk.z;
+
+        if self.flying {
+            self.fly(input, delta_time, world);
+            return
+        }
+
+        if self.in_fluid {
+            self.swim(input, delta_time, world);
+            return
+        }
+
+        if input.jump && self.on_ground {
+            self.velocity.y = JUMP_SPEED;
+        }
+
+        // Until the ground below has been generated, the player waits in the
+        // air instead of falling through it.
+        let mut column = self.block();
+        column.y = 0;
+        let ground_loaded = world.chunk(ChunkPos::from(column)).is_some();
+
+        if ground_loaded {
+            self.velocity.y -= GRAVITY * delta_time;
+            self.velocity.y = self.velocity.y.max(-TERMINAL_SPEED);
+        } else {
+            self.velocity.y = 0.0;
+        }
+
+        let start = self.position;
+        self.move_horizontally(self.velocity * delta_time, world);
+
+        let fall = self.velocity.y * delta_time;
+        let moved = self.move_along(1, fall, world);
+        let blocked = moved != fall;
+
+        let landed = blocked && fall < 0.0 && !self.on_ground;
+        self.on_ground = blocked && fall < 0.0;
+        if blocked {
+            self.velocity.y = 0.0;
+        }
+
+        if self.on_ground && !self.crouching {
+            let walked = Vector2::new(self.position.x - start.x, self.position.z - start.z);
+            self.stride += walked.magnitude();
+        }
+
+        if landed || self.stride >= STEP_LENGTH {
+            self.stride = 0.0;
+            self.footstep = self.ground(world);
+        }
+    }
+
+    /// Takes the block the player last stepped on, if it took a step (or
+    /// landed) since this was last called.
+    pub fn take_footstep(&mut self) -> Option<Block> {
+        self.footstep.take()
+    }
+
+    // In a fluid, the player sinks slowly, since buoyancy takes most of the
+    // gravity away, and drag slows it down. Holding jump swims up.
+    fn swim(&mut self, input: &MovementInput, delta_time: f32, world: &World) {
+        let gravity = match input.jump {
+            true => -SWIM_ACCELERATION,
+            false => GRAVITY * (1.0 - BUOYANCY),
+        };
+        self.velocity.y -= gravity * delta_time;
+        self.velocity.y *= (1.0 - FLUID_DRAG * delta_time).max(0.0);
+        self.velocity.y = self.velocity.y
+            .max(-SINK_SPEED)
+            .min(SWIM_SPEED);
+
+        // Swimming against the shore steps up onto it, see move_horizontally.
+        self.move_horizontally(self.velocity * delta_time, world);
+
+        let rise = self.velocity.y * delta_time;
+        let moved = self.move_along(1, rise, world);
+        let blocked = moved != rise;
+
+        self.on_ground = blocked && rise < 0.0;
+        if blocked {
+            self.velocity.y = 0.0;
+        }
+    }
+
+    // Flying moves up and down as fast as horizontally, and ignores gravity.
+    // Flying into the ground lands the player.
+    fn fly(&mut self, input: &MovementInput, delta_time: f32, world: &World) {
+        self.velocity.y = input.vertical.max(-1.0).min(1.0) * input.speed;
+        self.on_ground = false;
+
+        if self.noclip {
+            self.position += self.within_border(self.velocity * delta_time, world);
+            return
+        }
+
+        self.move_horizontally(self.velocity * delta_time, world);
+
+        let rise = self.velocity.y * delta_time;
+        if self.move_along(1, rise, world) != rise && rise < 0.0 {
+            self.flying = false;
+            self.on_ground = true;
+            self.velocity.y = 0.0;
+        }
+    }
+
+    // Moves along X and Z. If a wall is in the way while standing on the
+    // ground, this tries to step up onto it.
+    fn move_horizontally(&mut self, movement: Vector3<f32>, world: &World) {
+        let movement = self.within_border(movement, world);
+        let start = self.position;
+        let moved_x = self.walk_along(0, movement.x, world);
+        let moved_z = self.walk_along(2, movement.z, world);
+        let blocked = moved_x != movement.x || moved_z != movement.z;
+
+        if !blocked || !(self.on_ground || self.in_fluid) {
+            return
+        }
+
+        let walked = self.position;
+        self.position = start;
+
+        let raised = self.move_along(1, STEP_HEIGHT, world);
+        self.move_along(0, movement.x, world);
+        self.move_along(2, movement.z, world);
+        self.move_along(1, -raised, world);
+
+        // Stepping up is only worth it if it got the player further, and did
+        // not take it over an edge while sneaking.
+        let distance = |p: Point3<f32>| Vector2::new(p.x - start.x, p.z - start.z).magnitude2();
+        let fell_off = self.sneaking() && !self.is_supported(world);
+        if distance(self.position) <= distance(walked) || fell_off {
+            self.position = walked;
+        }
+    }
+
+    // Shortens a movement so that it stops at the border of the world, like
+    // it would at a wall. Only the horizontal part is changed.
+    fn within_border(&self, movement: Vector3<f32>, world: &World) -> Vector3<f32> {
+        let target = world.border().clamp(self.position + movement, PLAYER_WIDTH * 0.5);
+        Vector3::new(target.x - self.position.x, movement.y, target.z - self.position.z)
+    }
+
+    // Moves along a horizontal axis, like `move_along`. While sneaking, the
+    // player only goes as far as it can without losing the ground below it.
+    fn walk_along(&mut self, axis: usize, amount: f32, world: &World) -> f32 {
+        if !self.sneaking() {
+            return self.move_along(axis, amount, world)
+        }
+
+        let mut amount = amount;
+        for _ in 0..EDGE_SEARCH_STEPS {
+            let start = self.position[axis];
+            let moved = self.move_along(axis, amount, world);
+
+            if self.is_supported(world) {
+                return moved
+            }
+
+            self.position[axis] = start;
+            amount /= 2.0;
+        }
+
+        0.0
+    }
+
+    // Sneaking is crouching while standing on the ground.
+    fn sneaking(&self) -> bool {
+        self.crouching && self.on_ground && !self.flying
+    }
+
+    // Whether there is a solid block right below the feet of the player.
+    fn is_supported(&self, world: &World) -> bool {
+        let (mut min, mut max) = self.bounds();
+        max.y = min.y;
+        min.y -= STEP_HEIGHT;
+        any_block(min, max, world, Block::is_solid)
+    }
+
+    fn touches_fluid(&self, world: &World) -> bool {
+        let (min, max) = self.bounds();
+        any_block(min, max, world, Block::is_fluid)
+    }
+
+    // Whether the player would fit if it stood up.
+    fn can_stand(&self, world: &World) -> bool {
+        let (min, mut max) = self.bounds();
+        max.y = min.y + PLAYER_HEIGHT;
+        self.flying && self.noclip || !any_block(min, max, world, Block::is_solid)
+    }
+
+    // Moves along one axis until the box of the player touches a solid block,
+    // and returns how far it actually moved.
+    fn move_along(&mut self, axis: usize, amount: f32, world: &World) -> f32 {
+        if amount == 0.0 {
+            return 0.0
+        }
+
+        let (min, max) = self.bounds();
+
+        // The blocks the box overlaps on the other two axes. The edges are
+        // pulled in slightly, so that merely touching a block does not count.
+        let range = |a: usize| {
+            (min[a] + EPSILON).floor() as i32..=(max[a] - EPSILON).floor() as i32
+        };
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        let layer_is_solid = |layer: i32| {
+            range(u).any(|a| range(v).any(|b| {
+                let mut at = Point3::new(0, 0, 0);
+                at[axis] = layer;
+                at[u] = a;
+                at[v] = b;
+
+                world.get_block(BlockPos(at))
+                    .map(|block| block.is_solid())
+                    .unwrap_or(false)
+            }))
+        };
+
+        // Every layer of blocks the moving face passes through is checked,
+        // so that fast movement cannot tunnel through thin walls.
+        let moved = if amount > 0.0 {
+            let edge = max[axis];
+            let first = (edge - EPSILON).floor() as i32 + 1;
+            let last = (edge + amount - EPSILON).floor() as i32;
+
+            (first..=last)
+                .find(|&layer| layer_is_solid(layer))
+                .map(|layer| (layer as f32 - edge - SKIN).max(0.0))
+                .unwrap_or(amount)
+        } else {
+            let edge = min[axis];
+            let first = (edge + EPSILON).floor() as i32 - 1;
+            let last = (edge + amount + EPSILON).floor() as i32;
+
+            (last..=first)
+                .rev()
+                .find(|&layer| layer_is_solid(layer))
+                .map(|layer| (layer as f32 + 1.0 - edge + SKIN).min(0.0))
+                .unwrap_or(amount)
+        };
+
+        self.position[axis] += moved;
+        moved
+    }
+
+    // The corners of the box of the player.
+    fn bounds(&self) -> (Point3<f32>, Point3<f32>) {
+        let half = Vector3::new(PLAYER_WIDTH / 2.0, 0.0, PLAYER_WIDTH / 2.0);
+        let height = match self.crouching {
+            true => CROUCH_HEIGHT,
+            false => PLAYER_HEIGHT,
+        };
+        let height = Vector3::unit_y() * height;
+        (self.position - half, self.position + half + height)
+    }
+
+    fn eye_height(&self) -> f32 {
+        match self.crouching {
+            true => CROUCH_EYE_HEIGHT,
+            false => EYE_HEIGHT,
+        }
+    }
+
+    // The block the feet of the player are in.
+    fn block(&self) -> BlockPos {
+        BlockPos(self.position.map(|v| v.floor() as i32))
+    }
+
+    // The block the player is standing on, right below its feet.
+    fn ground(&self, world: &World) -> Option<Block> {
+        let below = self.position - Vector3::new(0.0, EPSILON, 0.0);
+        let below = BlockPos(below.map(|v| v.floor() as i32));
+        world.get_block(below).cloned()
+    }
+}
+
+// Whether any block the predicate holds for overlaps the box between the two
+// corners. Blocks that merely touch the box do not count.
+fn any_block<F>(min: Point3<f32>, max: Point3<f32>, world: &World, predicate: F) -> bool
+    where F: Fn(&Block) -> bool
+{
+    let range = |a: usize| {
+        (min[a] + EPSILON).floor() as i32..=(max[a] - EPSILON).floor() as i32
+    };
+
+    range(0).any(|x| range(1).any(|y| range(2).any(|z| {
+        world.get_block(BlockPos::new(x, y, z))
+            .map(|block| predicate(block))
+            .unwrap_or(false)
+    })))
+}
+
+/// How fast the player walks, and how fast it may fly at most, in blocks per
+/// second, before sprinting. The server lets no one go faster.
+pub const WALKING_SPEED: f32 = 4.3;
+pub const MAX_FLYING_SPEED: f32 = 40.0;
+
+// The size of the box of the player, and how high its eyes are, in blocks.
+const PLAYER_WIDTH: f32 = 0.6;
+const PLAYER_HEIGHT: f32 = 1.8;
+const EYE_HEIGHT: f32 = 1.6;
+const CROUCH_HEIGHT: f32 = 1.5;
+const CROUCH_EYE_HEIGHT: f32 = 1.27;
+
+// How much faster sprinting is, and how much slower crouching is, than walking.
+const SPRINT_FACTOR: f32 = 2.0;
+const CROUCH_FACTOR: f32 = 0.3;
+
+// How fast the player falls and jumps, in blocks per second (squared).
+const GRAVITY: f32 = 32.0;
+const TERMINAL_SPEED: f32 = 78.0;
+const JUMP_SPEED: f32 = 9.0;
+
+// How the player moves in fluids: how much of gravity buoyancy cancels, how
+// quickly drag slows it down (per second), how much slower it moves
+// horizontally, and how fast it swims up and sinks, in blocks per second
+// (squared).
+const BUOYANCY: f32 = 0.8;
+const FLUID_DRAG: f32 = 2.0;
+const SWIM_FACTOR: f32 = 0.5;
+const SWIM_ACCELERATION: f32 = 12.0;
+const SWIM_SPEED: f32 = 4.0;
+const SINK_SPEED: f32 = 2.5;
+
+// How high a ledge the player walks onto without jumping, and how far it
+// walks between footsteps, in blocks.
+const STEP_HEIGHT: f32 = 0.55;
+const STEP_LENGTH: f32 = 1.6;
+
+// How many times the movement is halved while looking for how close to an
+// edge a sneaking player can get.
+const EDGE_SEARCH_STEPS: usize = 6;
+
+// How close the box may get to a block before it counts as touching it, and
+// the gap left between them, in blocks.
+const EPSILON: f32 = 1e-4;
+const SKIN: f32 = 1e-5;
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fmt;
 use std::string::FromUtf8Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -7,20 +8,50 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
     DepthOver512,
+    /// A list has elements of another kind than it says, or elements of the
+    /// end tag.
     InvalidList,
     InvalidTag(u8),
     InvalidUtf8(FromUtf8Error),
+    /// The outermost tag is not a compound.
+    RootNotCompound(u8),
+    /// A string is longer than the 65535 bytes its length can tell.
+    StringTooLong(usize),
     TagsOfSameName,
-    TagUnnamed,
     UnexpectedEof,
 }
 
-#[derive(Debug)]
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::DepthOver512 => write!(f, "tags are nested more than {} deep", MAX_DEPTH),
+            Error::InvalidList => write!(f, "invalid list"),
+            Error::InvalidTag(tag) => write!(f, "invalid tag {}", tag),
+            Error::InvalidUtf8(e) => write!(f, "invalid string: {}", e),
+            Error::RootNotCompound(tag) => write!(f, "the root is tag {} instead of a compound", tag),
+            Error::StringTooLong(len) => write!(f, "a string of {} bytes is too long", len),
+            Error::TagsOfSameName => write!(f, "two tags of a compound have the same name"),
+            Error::UnexpectedEof => write!(f, "unexpected end of data"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A tree of named tags in the binary format (NBT) that Minecraft keeps its
+/// worlds in, as read from or written to a file. Compressing the file, which
+/// is usual, is left to the caller.
+///
+/// Strings are taken to be UTF-8, which they are unless they contain a null
+/// character or characters outside the Basic Multilingual Plane.
+#[derive(Debug, Clone, PartialEq)]
 pub struct NamedBinaryTag {
+    // The name of the root compound, which is usually empty.
+    name: String,
     root: HashMap<String, ValueData>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ValueData {
     Byte(i8),
     Short(i16),
@@ -30,24 +61,70 @@ pub enum ValueData {
     Double(f64),
     ByteArray(Vec<i8>),
     Str(String),
+    /// The tag of the elements, and the elements. An empty list may have the
+    /// end tag (0).
     List(u8, Vec<ValueData>),
     Compound(HashMap<String, ValueData>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
 }
 
 impl NamedBinaryTag {
     pub fn new() -> Self {
-        Self { 
-            root: HashMap::new() 
+        Self {
+            name: String::new(),
+            root: HashMap::new()
         }
     }
 
     pub fn from_binary(value: &[u8]) -> Result<Self> {
-        let mut root = Self::new();
-        root.root = Self::parse_compound(value, 0)?.1
-            .into_compound()
-            .unwrap();
+        let tag = *value.get(0).ok_or(Error::UnexpectedEof)?;
+        if tag != TAG_COMPOUND {
+            Err(Error::RootNotCompound(tag))?
+        }
+
+        let (len_name, name) = Self::parse_string(&value[1..])?;
+        let (_, root) = Self::parse_compound(&value[1 + len_name..], 0)?;
+
+        Ok(Self {
+            name: name.into_string().unwrap(),
+            root: root.into_compound().unwrap(),
+        })
+    }
+
+    /// Writes the tags in the binary format. The tags of compounds are
+    /// sorted by name, so that the same tags always give the same bytes.
+    pub fn to_binary(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![TAG_COMPOUND];
+        Self::write_string(&self.name, &mut bytes)?;
+        Self::write_compound(&self.root, &mut bytes, 0)?;
+        Ok(bytes)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn root(&self) -> &HashMap<String, ValueData> {
+        &self.root
+    }
 
-        Ok(root)
+    pub fn root_mut(&mut self) -> &mut HashMap<String, ValueData> {
+        &mut self.root
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ValueData> {
+        self.root.get(name)
+    }
+
+    /// Puts the tag into the root compound, replacing the tag of the same
+    /// name if there is one.
+    pub fn insert(&mut self, name: String, value: ValueData) -> Option<ValueData> {
+        self.root.insert(name, value)
     }
 
     fn parse_byte(bytes: &[u8]) -> Result<(usize, ValueData)> {
@@ -64,134 +141,230 @@ impl NamedBinaryTag {
     fn parse_int(bytes: &[u8]) -> Result<(usize, ValueData)> {
         let result = bytes.get(0..4).ok_or(Error::UnexpectedEof)?;
         let int = i32::from_be_bytes(<[u8; 4]>::try_from(result).unwrap());
-        Ok((2, ValueData::Int(int)))
+        Ok((4, ValueData::Int(int)))
     }
 
     fn parse_long(bytes: &[u8]) -> Result<(usize, ValueData)> {
         let result = bytes.get(0..8).ok_or(Error::UnexpectedEof)?;
         let long = i64::from_be_bytes(<[u8; 8]>::try_from(result).unwrap());
-        Ok((2, ValueData::Long(long)))
+        Ok((8, ValueData::Long(long)))
     }
 
     fn parse_float(bytes: &[u8]) -> Result<(usize, ValueData)> {
         let result = bytes.get(0..4).ok_or(Error::UnexpectedEof)?;
         let float = f32::from_be_bytes(<[u8; 4]>::try_from(result).unwrap());
-        Ok((2, ValueData::Float(float)))
+        Ok((4, ValueData::Float(float)))
     }
-    
+
     fn parse_double(bytes: &[u8]) -> Result<(usize, ValueData)> {
         let result = bytes.get(0..8).ok_or(Error::UnexpectedEof)?;
         let double = f64::from_be_bytes(<[u8; 8]>::try_from(result).unwrap());
-        Ok((2, ValueData::Double(double)))
+        Ok((8, ValueData::Double(double)))
     }
 
     fn parse_string(bytes: &[u8]) -> Result<(usize, ValueData)> {
         let len = bytes.get(0..2).ok_or(Error::UnexpectedEof)?;
         let len = u16::from_be_bytes([len[0], len[1]]) as usize;
-        
-        let str_bytes = bytes.get(3..3 + len).ok_or(Error::UnexpectedEof)?;
+
+        let str_bytes = bytes.get(2..2 + len).ok_or(Error::UnexpectedEof)?;
         let result = String::from_utf8(Vec::from(str_bytes))
             .map(|s| ValueData::Str(s))
             .map_err(|err| Error::InvalidUtf8(err))?;
-        
-        Ok((len, result))
+
+        Ok((2 + len, result))
     }
 
-    fn parse_byte_array(bytes: &[u8]) -> Result<(usize, ValueData)> {
+    // Parses the length of an array, which is followed by that many elements
+    // of `size` bytes, and returns the length and the elements.
+    fn parse_array(bytes: &[u8], size: usize) -> Result<(usize, &[u8])> {
         let len = bytes.get(0..4).ok_or(Error::UnexpectedEof)?;
-        let len = u32::from_be_bytes(<[u8; 4]>::try_from(len).unwrap()) as usize;
-        
-        let bytes = bytes.get(3..3 + len).ok_or(Error::UnexpectedEof)?;
-        let result = unsafe {
-            let bytes = bytes.as_ptr() as *const i8;
-            let i8_bytes = std::slice::from_raw_parts(bytes, len);
-            ValueData::ByteArray(Vec::from(i8_bytes))
-        };
+        let len = i32::from_be_bytes(<[u8; 4]>::try_from(len).unwrap()).max(0) as usize;
+
+        let elements = bytes.get(4..4 + len * size).ok_or(Error::UnexpectedEof)?;
+        Ok((len, elements))
+    }
+
+    fn parse_byte_array(bytes: &[u8]) -> Result<(usize, ValueData)> {
+        let (len, elements) = Self::parse_array(bytes, 1)?;
+        let result = elements.iter().map(|&b| b as i8).collect();
+
+        Ok((4 + len, ValueData::ByteArray(result)))
+    }
+
+    fn parse_int_array(bytes: &[u8]) -> Result<(usize, ValueData)> {
+        let (len, elements) = Self::parse_array(bytes, 4)?;
+        let result = elements
+            .chunks(4)
+            .map(|int| i32::from_be_bytes(<[u8; 4]>::try_from(int).unwrap()))
+            .collect();
+
+        Ok((4 + len * 4, ValueData::IntArray(result)))
+    }
+
+    fn parse_long_array(bytes: &[u8]) -> Result<(usize, ValueData)> {
+        let (len, elements) = Self::parse_array(bytes, 8)?;
+        let result = elements
+            .chunks(8)
+            .map(|long| i64::from_be_bytes(<[u8; 8]>::try_from(long).unwrap()))
+            .collect();
 
-        
-        Ok((len, result))
+        Ok((4 + len * 8, ValueData::LongArray(result)))
     }
 
     fn parse_list(bytes: &[u8], depth: u16) -> Result<(usize, ValueData)> {
+        if depth > MAX_DEPTH {
+            Err(Error::DepthOver512)?
+        }
+
         let tag_id = *bytes.get(0).ok_or(Error::UnexpectedEof)?;
         let len = bytes.get(1..5).ok_or(Error::UnexpectedEof)?;
-        let len = u32::from_be_bytes(<[u8; 4]>::try_from(len).unwrap()) as usize;
-        
-        let mut offset = 0;
-        let mut values = Vec::with_capacity(len);
-        for i in 0..len {
-            let (len, data) = match tag_id {
-                0 => Err(Error::InvalidList)?,
-                
-                1 => Self::parse_byte(&bytes[5 + i..])?,
-                2 => Self::parse_short(&bytes[5 + i * 2..])?,
-                3 => Self::parse_int(&bytes[5 + i * 4..])?,
-                4 => Self::parse_long(&bytes[5 + i * 8..])?,
-                5 => Self::parse_float(&bytes[5 + i * 4..])?,
-                6 => Self::parse_double(&bytes[5 + i * 8..])?,
-                7 => Self::parse_byte_array(&bytes[5 + offset..])?,
-                8 => Self::parse_string(&bytes[5 + offset..])?,
-                9 => Self::parse_list(&bytes[5 + offset..], depth + 1)?,
-                10 => Self::parse_compound(&bytes[5 + offset..], depth + 1)?,
-
-                x => Err(Error::InvalidTag(x))?,
-            };
+        let len = i32::from_be_bytes(<[u8; 4]>::try_from(len).unwrap()).max(0) as usize;
+
+        if tag_id == TAG_END && len > 0 {
+            Err(Error::InvalidList)?
+        }
 
+        let mut offset = 5;
+        // Every element takes at least a byte, so a list cannot have more
+        // elements than there are bytes left.
+        let mut values = Vec::with_capacity(len.min(bytes.len()));
+        for _ in 0..len {
+            let (len, data) = Self::parse_payload(tag_id, &bytes[offset..], depth + 1)?;
             offset += len;
             values.push(data);
         };
 
-        Ok((offset + 5, ValueData::List(tag_id, values)))
+        Ok((offset, ValueData::List(tag_id, values)))
     }
 
     fn parse_compound(bytes: &[u8], depth: u16) -> Result<(usize, ValueData)> {
         let mut offset = 0;
         let mut result = HashMap::new();
 
-        if depth > 512 {
+        if depth > MAX_DEPTH {
             Err(Error::DepthOver512)?
         }
 
-        while let Some(current_byte) = bytes.get(offset) {
+        loop {
+            let current_byte = *bytes.get(offset).ok_or(Error::UnexpectedEof)?;
             offset += 1;
 
-            let (len, key, value) = match current_byte {
-                0 => break,
-                
-                x => {
-                    let (len_name, name) = Self::parse_string(&bytes[offset..])?;
-                    let name = name.into_string().unwrap();
-                    offset += len_name;
-
-                    let (len, data) = match *x {
-                        1 => Self::parse_byte(&bytes[offset..])?,
-                        2 => Self::parse_short(&bytes[offset..])?,
-                        3 => Self::parse_int(&bytes[offset..])?,
-                        4 => Self::parse_long(&bytes[offset..])?,
-                        5 => Self::parse_float(&bytes[offset..])?,
-                        6 => Self::parse_double(&bytes[offset..])?,
-                        7 => Self::parse_byte_array(&bytes[offset..])?,
-                        8 => Self::parse_string(&bytes[offset..])?,
-                        9 => Self::parse_list(&bytes[offset..], depth + 1)?,
-                        10 => Self::parse_compound(&bytes[offset..], depth + 1)?,
-
-                        invalid => Err(Error::InvalidTag(invalid))?,
-                    };
-
-                    (len, name, data)
-                },
-            };
-            
+            if current_byte == TAG_END {
+                break
+            }
+
+            let (len_name, name) = Self::parse_string(&bytes[offset..])?;
+            let name = name.into_string().unwrap();
+            offset += len_name;
+
+            let (len, data) = Self::parse_payload(current_byte, &bytes[offset..], depth + 1)?;
             offset += len;
-            
-            match result.insert(key, value) {
-                Some(x) => Err(Error::TagsOfSameName)?,
+
+            match result.insert(name, data) {
+                Some(_) => Err(Error::TagsOfSameName)?,
                 None => {},
             };
         };
 
         Ok((offset, ValueData::Compound(result)))
     }
+
+    // Parses what comes after the tag (and name) of a value.
+    fn parse_payload(tag: u8, bytes: &[u8], depth: u16) -> Result<(usize, ValueData)> {
+        match tag {
+            TAG_BYTE => Self::parse_byte(bytes),
+            TAG_SHORT => Self::parse_short(bytes),
+            TAG_INT => Self::parse_int(bytes),
+            TAG_LONG => Self::parse_long(bytes),
+            TAG_FLOAT => Self::parse_float(bytes),
+            TAG_DOUBLE => Self::parse_double(bytes),
+            TAG_BYTE_ARRAY => Self::parse_byte_array(bytes),
+            TAG_STRING => Self::parse_string(bytes),
+            TAG_LIST => Self::parse_list(bytes, depth),
+            TAG_COMPOUND => Self::parse_compound(bytes, depth),
+            TAG_INT_ARRAY => Self::parse_int_array(bytes),
+            TAG_LONG_ARRAY => Self::parse_long_array(bytes),
+
+            invalid => Err(Error::InvalidTag(invalid)),
+        }
+    }
+
+    fn write_string(value: &str, bytes: &mut Vec<u8>) -> Result<()> {
+        let len = u16::try_from(value.len()).map_err(|_| Error::StringTooLong(value.len()))?;
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn write_compound(values: &HashMap<String, ValueData>, bytes: &mut Vec<u8>, depth: u16) -> Result<()> {
+        if depth > MAX_DEPTH {
+            Err(Error::DepthOver512)?
+        }
+
+        let mut names = values.keys().collect::<Vec<_>>();
+        names.sort();
+
+        for name in names {
+            let value = &values[name];
+            bytes.push(value.tag());
+            Self::write_string(name, bytes)?;
+            Self::write_payload(value, bytes, depth + 1)?;
+        }
+
+        bytes.push(TAG_END);
+        Ok(())
+    }
+
+    fn write_payload(value: &ValueData, bytes: &mut Vec<u8>, depth: u16) -> Result<()> {
+        match value {
+            ValueData::Byte(x) => bytes.push(*x as u8),
+            ValueData::Short(x) => bytes.extend_from_slice(&x.to_be_bytes()),
+            ValueData::Int(x) => bytes.extend_from_slice(&x.to_be_bytes()),
+            ValueData::Long(x) => bytes.extend_from_slice(&x.to_be_bytes()),
+            ValueData::Float(x) => bytes.extend_from_slice(&x.to_be_bytes()),
+            ValueData::Double(x) => bytes.extend_from_slice(&x.to_be_bytes()),
+
+            ValueData::ByteArray(array) => {
+                bytes.extend_from_slice(&(array.len() as i32).to_be_bytes());
+                bytes.extend(array.iter().map(|&b| b as u8));
+            },
+
+            ValueData::Str(string) => Self::write_string(string, bytes)?,
+
+            ValueData::List(tag, values) => {
+                if depth > MAX_DEPTH {
+                    Err(Error::DepthOver512)?
+                }
+                if (*tag == TAG_END && !values.is_empty()) || values.iter().any(|v| v.tag() != *tag) {
+                    Err(Error::InvalidList)?
+                }
+
+                bytes.push(*tag);
+                bytes.extend_from_slice(&(values.len() as i32).to_be_bytes());
+                for value in values {
+                    Self::write_payload(value, bytes, depth + 1)?;
+                }
+            },
+
+            ValueData::Compound(values) => Self::write_compound(values, bytes, depth)?,
+
+            ValueData::IntArray(array) => {
+                bytes.extend_from_slice(&(array.len() as i32).to_be_bytes());
+                for x in array {
+                    bytes.extend_from_slice(&x.to_be_bytes());
+                }
+            },
+
+            ValueData::LongArray(array) => {
+                bytes.extend_from_slice(&(array.len() as i32).to_be_bytes());
+                for x in array {
+                    bytes.extend_from_slice(&x.to_be_bytes());
+                }
+            },
+        }
+
+        Ok(())
+    }
 }
 
 macro_rules! impl_getter {
@@ -233,6 +406,8 @@ impl ValueData {
     impl_getter!(byte_array, Self::ByteArray, [i8]);
     impl_getter!(string, Self::Str, str);
     impl_getter!(compound, Self::Compound, HashMap<String, Self>);
+    impl_getter!(int_array, Self::IntArray, [i32]);
+    impl_getter!(long_array, Self::LongArray, [i64]);
 
     impl_getter!(mut byte_mut, Self::Byte, i8);
     impl_getter!(mut short_mut, Self::Short, i16);
@@ -243,7 +418,9 @@ impl ValueData {
     impl_getter!(mut byte_array_mut, Self::ByteArray, [i8]);
     impl_getter!(mut string_mut, Self::Str, str);
     impl_getter!(mut compound_mut, Self::Compound, HashMap<String, Self>);
-    
+    impl_getter!(mut int_array_mut, Self::IntArray, [i32]);
+    impl_getter!(mut long_array_mut, Self::LongArray, [i64]);
+
     impl_getter!(into into_byte, Self::Byte, i8);
     impl_getter!(into into_short, Self::Short, i16);
     impl_getter!(into into_int, Self::Int, i32);
@@ -253,6 +430,8 @@ impl ValueData {
     impl_getter!(into into_byte_array, Self::ByteArray, Vec<i8>);
     impl_getter!(into into_string, Self::Str, String);
     impl_getter!(into into_compound, Self::Compound, HashMap<String, Self>);
+    impl_getter!(into into_int_array, Self::IntArray, Vec<i32>);
+    impl_getter!(into into_long_array, Self::LongArray, Vec<i64>);
 
     pub fn list(&self) -> Option<(u8, &Vec<Self>)> {
         match self {
@@ -274,4 +453,169 @@ impl ValueData {
             _ => None,
         }
     }
+
+    /// The tag the value is written with, e.g. to make a list of values of
+    /// this kind.
+    pub fn tag(&self) -> u8 {
+        match self {
+            Self::Byte(_) => TAG_BYTE,
+            Self::Short(_) => TAG_SHORT,
+            Self::Int(_) => TAG_INT,
+            Self::Long(_) => TAG_LONG,
+            Self::Float(_) => TAG_FLOAT,
+            Self::Double(_) => TAG_DOUBLE,
+            Self::ByteArray(_) => TAG_BYTE_ARRAY,
+            Self::Str(_) => TAG_STRING,
+            Self::List(..) => TAG_LIST,
+            Self::Compound(_) => TAG_COMPOUND,
+            Self::IntArray(_) => TAG_INT_ARRAY,
+            Self::LongArray(_) => TAG_LONG_ARRAY,
+        }
+    }
+}
+
+// The tag every kind of value is written with.
+pub const TAG_END: u8 = 0;
+pub const TAG_BYTE: u8 = 1;
+pub const TAG_SHORT: u8 = 2;
+pub const TAG_INT: u8 = 3;
+pub const TAG_LONG: u8 = 4;
+pub const TAG_FLOAT: u8 = 5;
+pub const TAG_DOUBLE: u8 = 6;
+pub const TAG_BYTE_ARRAY: u8 = 7;
+pub const TAG_STRING: u8 = 8;
+pub const TAG_LIST: u8 = 9;
+pub const TAG_COMPOUND: u8 = 10;
+pub const TAG_INT_ARRAY: u8 = 11;
+pub const TAG_LONG_ARRAY: u8 = 12;
+
+// How deep lists and compounds may be nested, so that a malicious file cannot
+// overflow the stack.
+const MAX_DEPTH: u16 = 512;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The "hello world" example of the format: a root compound named "hello
+    // world" with the string "name" set to "Bananrama".
+    const HELLO_WORLD: &[u8] = b"\x0a\x00\x0bhello world\x08\x00\x04name\x00\x09Bananrama\x00";
+
+    fn every_tag() -> NamedBinaryTag {
+        let mut nested = HashMap::new();
+        nested.insert("inner".to_owned(), ValueData::Short(-2));
+
+        let mut nbt = NamedBinaryTag::new();
+        nbt.set_name("level".to_owned());
+        nbt.insert("byte".to_owned(), ValueData::Byte(-1));
+        nbt.insert("short".to_owned(), ValueData::Short(12345));
+        nbt.insert("int".to_owned(), ValueData::Int(-123456789));
+        nbt.insert("long".to_owned(), ValueData::Long(i64::MIN));
+        nbt.insert("float".to_owned(), ValueData::Float(0.5));
+        nbt.insert("double".to_owned(), ValueData::Double(-1.25e100));
+        nbt.insert("bytes".to_owned(), ValueData::ByteArray(vec![0, -128, 127]));
+        nbt.insert("string".to_owned(), ValueData::Str("grüße".to_owned()));
+        nbt.insert("list".to_owned(), ValueData::List(TAG_STRING, vec![
+            ValueData::Str("a".to_owned()),
+            ValueData::Str("bc".to_owned()),
+        ]));
+        nbt.insert("empty".to_owned(), ValueData::List(TAG_END, Vec::new()));
+        nbt.insert("compound".to_owned(), ValueData::Compound(nested.clone()));
+        nbt.insert("compounds".to_owned(), ValueData::List(TAG_COMPOUND, vec![
+            ValueData::Compound(nested.clone()),
+            ValueData::Compound(HashMap::new()),
+        ]));
+        nbt.insert("ints".to_owned(), ValueData::IntArray(vec![1, -2, i32::MAX]));
+        nbt.insert("longs".to_owned(), ValueData::LongArray(vec![i64::MAX, 0]));
+        nbt
+    }
+
+    // Nests `depth` lists in each other.
+    fn nested_lists(depth: usize) -> Vec<u8> {
+        let mut bytes = b"\x0a\x00\x00\x09\x00\x01a".to_vec();
+        for _ in 1..depth {
+            bytes.extend_from_slice(b"\x09\x00\x00\x00\x01");
+        }
+        // The innermost list is empty, and is followed by the end of the
+        // root.
+        bytes.extend_from_slice(b"\x00\x00\x00\x00\x00\x00");
+        bytes
+    }
+
+    #[test]
+    fn reads_hello_world() {
+        let nbt = NamedBinaryTag::from_binary(HELLO_WORLD).unwrap();
+        assert_eq!(nbt.name(), "hello world");
+        assert_eq!(nbt.root().len(), 1);
+        assert_eq!(nbt.get("name").and_then(ValueData::string), Some("Bananrama"));
+    }
+
+    #[test]
+    fn writes_hello_world() {
+        let mut nbt = NamedBinaryTag::new();
+        nbt.set_name("hello world".to_owned());
+        nbt.insert("name".to_owned(), ValueData::Str("Bananrama".to_owned()));
+        assert_eq!(nbt.to_binary().unwrap(), HELLO_WORLD);
+    }
+
+    #[test]
+    fn round_trips_every_tag() {
+        let nbt = every_tag();
+        let bytes = nbt.to_binary().unwrap();
+        assert_eq!(NamedBinaryTag::from_binary(&bytes).unwrap(), nbt);
+    }
+
+    #[test]
+    fn writes_the_same_bytes_every_time() {
+        assert_eq!(every_tag().to_binary().unwrap(), every_tag().to_binary().unwrap());
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let bytes = every_tag().to_binary().unwrap();
+        for len in 0..bytes.len() {
+            match NamedBinaryTag::from_binary(&bytes[..len]) {
+                Err(Error::UnexpectedEof) => {},
+                result => panic!("{} bytes gave {:?}", len, result),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let not_compound = b"\x08\x00\x00\x00\x00";
+        assert!(matches!(NamedBinaryTag::from_binary(not_compound), Err(Error::RootNotCompound(8))));
+
+        let invalid_tag = b"\x0a\x00\x00\x0d\x00\x01a\x00";
+        assert!(matches!(NamedBinaryTag::from_binary(invalid_tag), Err(Error::InvalidTag(13))));
+
+        let same_name = b"\x0a\x00\x00\x01\x00\x01a\x05\x01\x00\x01a\x06\x00";
+        assert!(matches!(NamedBinaryTag::from_binary(same_name), Err(Error::TagsOfSameName)));
+
+        let list_of_ends = b"\x0a\x00\x00\x09\x00\x01a\x00\x00\x00\x00\x01\x00";
+        assert!(matches!(NamedBinaryTag::from_binary(list_of_ends), Err(Error::InvalidList)));
+
+        let invalid_utf8 = b"\x0a\x00\x00\x08\x00\x01a\x00\x01\xff\x00";
+        assert!(matches!(NamedBinaryTag::from_binary(invalid_utf8), Err(Error::InvalidUtf8(_))));
+    }
+
+    #[test]
+    fn limits_nesting() {
+        assert!(NamedBinaryTag::from_binary(&nested_lists(MAX_DEPTH as usize)).is_ok());
+        assert!(matches!(
+            NamedBinaryTag::from_binary(&nested_lists(MAX_DEPTH as usize + 1)),
+            Err(Error::DepthOver512)
+        ));
+    }
+
+    #[test]
+    fn refuses_to_write_invalid_tags() {
+        let mut mixed = NamedBinaryTag::new();
+        mixed.insert("list".to_owned(), ValueData::List(TAG_INT, vec![ValueData::Int(1), ValueData::Byte(1)]));
+        assert!(matches!(mixed.to_binary(), Err(Error::InvalidList)));
+
+        let mut long_name = NamedBinaryTag::new();
+        long_name.insert("a".repeat(70000), ValueData::Byte(0));
+        assert!(matches!(long_name.to_binary(), Err(Error::StringTooLong(70000))));
+    }
 }
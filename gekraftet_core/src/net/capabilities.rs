@@ -0,0 +1,77 @@
+use std::fmt;
+
+/// Optional features of the protocol, which a connection only uses if both
+/// sides support them. The client tells the server what it supports when it
+/// logs in, and the server answers with what the connection will use.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// Large frames may be compressed.
+    pub const COMPRESSION: Self = Self(1 << 0);
+    /// Chunks that changed are sent as the blocks that changed, rather than
+    /// whole.
+    pub const CHUNK_DELTAS: Self = Self(1 << 1);
+    /// Where entities moved to is sent over UDP, see `DatagramSocket`.
+    /// Servers choose whether they offer it.
+    pub const DATAGRAMS: Self = Self(1 << 2);
+    /// The client may ask for the statistics of its player.
+    pub const STATS: Self = Self(1 << 3);
+
+    /// Every capability this version of the game supports.
+    pub const SUPPORTED: Self = Self(Self::COMPRESSION.0 | Self::CHUNK_DELTAS.0 | Self::DATAGRAMS.0 | Self::STATS.0);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Takes the capabilities as sent over the network. Bits this version of
+    /// the game does not know of are kept, but never used.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The capabilities both sides have, i.e. those a connection can use.
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = NAMES
+            .iter()
+            .filter(|&&(capability, _)| self.contains(capability))
+            .map(|&(_, name)| name);
+
+        let mut list = f.debug_set();
+        list.entries(names);
+
+        let unknown = self.0 & !Self::SUPPORTED.0;
+        if unknown != 0 {
+            list.entry(&format_args!("{:#x}", unknown));
+        }
+
+        list.finish()
+    }
+}
+
+// The names of the capabilities, as they are printed.
+const NAMES: &[(Capabilities, &str)] = &[
+    (Capabilities::COMPRESSION, "compression"),
+    (Capabilities::CHUNK_DELTAS, "chunk deltas"),
+    (Capabilities::DATAGRAMS, "datagrams"),
+    (Capabilities::STATS, "stats"),
+];
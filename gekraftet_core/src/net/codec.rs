@@ -0,0 +1,159 @@
+use std::convert::TryFrom;
+use cgmath::{ Point3, Vector3 };
+use crate::world::{ Block, BlockPos, ChunkPos };
+use super::{ Error, Result };
+
+// Reads the big-endian values messages are made of, in order.
+pub(super) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < count {
+            return Err(Error::UnexpectedEof)
+        }
+
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(<[u8; 2]>::try_from(self.bytes(2)?).unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(<[u8; 4]>::try_from(self.bytes(4)?).unwrap()))
+    }
+
+    pub fn i32(&mut self) -> Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(<[u8; 8]>::try_from(self.bytes(8)?).unwrap()))
+    }
+
+    pub fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    pub fn point(&mut self) -> Result<Point3<f32>> {
+        Ok(Point3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    pub fn vector(&mut self) -> Result<Vector3<f32>> {
+        Ok(Vector3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let length = self.u16()? as usize;
+        String::from_utf8(self.bytes(length)?.to_vec())
+            .map_err(|_| Error::InvalidMessage("string is not utf-8"))
+    }
+
+    pub fn block_pos(&mut self) -> Result<BlockPos> {
+        Ok(BlockPos::new(self.i32()?, self.i32()?, self.i32()?))
+    }
+
+    pub fn chunk_pos(&mut self) -> Result<ChunkPos> {
+        Ok(ChunkPos::new(self.i32()?, self.i32()?, self.i32()?))
+    }
+
+    pub fn block(&mut self) -> Result<Block> {
+        let id = self.u16()?;
+        let metadata = self.u16()?;
+        Ok(Block { id, metadata })
+    }
+}
+
+// Writes the values read by `Reader`.
+pub(super) trait Writer {
+    fn u8(&mut self, value: u8);
+    fn u16(&mut self, value: u16);
+    fn u32(&mut self, value: u32);
+    fn i32(&mut self, value: i32);
+    fn u64(&mut self, value: u64);
+    fn f32(&mut self, value: f32);
+    fn point(&mut self, value: Point3<f32>);
+    fn vector(&mut self, value: Vector3<f32>);
+    fn string(&mut self, value: &str);
+    fn block_pos(&mut self, value: BlockPos);
+    fn chunk_pos(&mut self, value: ChunkPos);
+    fn block(&mut self, value: &Block);
+}
+
+impl Writer for Vec<u8> {
+    fn u8(&mut self, value: u8) {
+        self.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.extend_from_slice(&value.to_be_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.u32(value.to_bits());
+    }
+
+    fn point(&mut self, value: Point3<f32>) {
+        self.f32(value.x);
+        self.f32(value.y);
+        self.f32(value.z);
+    }
+
+    fn vector(&mut self, value: Vector3<f32>) {
+        self.f32(value.x);
+        self.f32(value.y);
+        self.f32(value.z);
+    }
+
+    // Strings longer than a u16 can count are cut off.
+    fn string(&mut self, value: &str) {
+        let mut length = value.len().min(u16::MAX as usize);
+        while !value.is_char_boundary(length) {
+            length -= 1;
+        }
+
+        self.u16(length as u16);
+        self.extend_from_slice(value[..length].as_bytes());
+    }
+
+    fn block_pos(&mut self, value: BlockPos) {
+        self.i32(value.x);
+        self.i32(value.y);
+        self.i32(value.z);
+    }
+
+    fn chunk_pos(&mut self, value: ChunkPos) {
+        self.i32(value.x);
+        self.i32(value.y);
+        self.i32(value.z);
+    }
+
+    fn block(&mut self, value: &Block) {
+        self.u16(value.id);
+        self.u16(value.metadata);
+    }
+}
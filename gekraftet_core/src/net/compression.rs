@@ -0,0 +1,112 @@
+use super::{ Error, Result };
+
+// Compresses the bytes by replacing sequences that appeared shortly before
+// with references to them. The output is a list of tokens, each starting
+// with a byte: below 128, it is followed by that many plus one bytes, which
+// are copied as they are; otherwise, the low seven bits plus `MIN_MATCH` are
+// the length of a sequence to repeat, followed by how far back (as a u16) it
+// starts.
+pub(super) fn compress(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() / 2);
+    // Where the last sequence of `MIN_MATCH` bytes with the same hash
+    // started.
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let mut literals = 0;
+    let mut i = 0;
+
+    while i + MIN_MATCH <= bytes.len() {
+        let slot = hash(&bytes[i..i + MIN_MATCH]);
+        let candidate = table[slot];
+        table[slot] = i;
+
+        let length = match candidate {
+            c if c != usize::MAX && i - c <= MAX_OFFSET => bytes[c..]
+                .iter()
+                .zip(bytes[i..].iter())
+                .take(MAX_MATCH)
+                .take_while(|(a, b)| a == b)
+                .count(),
+            _ => 0,
+        };
+
+        if length < MIN_MATCH {
+            i += 1;
+            literals += 1;
+            continue
+        }
+
+        push_literals(&mut out, &bytes[i - literals..i]);
+        literals = 0;
+
+        out.push(0x80 | (length - MIN_MATCH) as u8);
+        out.extend_from_slice(&((i - candidate) as u16).to_be_bytes());
+        i += length;
+    }
+
+    push_literals(&mut out, &bytes[i - literals..]);
+    out
+}
+
+// Undoes `compress`, refusing to produce more than `max_length` bytes.
+pub(super) fn decompress(bytes: &[u8], max_length: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(max_length.min(bytes.len() * 4));
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let token = bytes[i] as usize;
+        i += 1;
+
+        match token < 0x80 {
+            true => {
+                let literals = bytes.get(i..i + token + 1).ok_or(Error::UnexpectedEof)?;
+                out.extend_from_slice(literals);
+                i += token + 1;
+            },
+            false => {
+                let offset = bytes.get(i..i + 2).ok_or(Error::UnexpectedEof)?;
+                let offset = u16::from_be_bytes([offset[0], offset[1]]) as usize;
+                i += 2;
+
+                if offset == 0 || offset > out.len() {
+                    return Err(Error::InvalidMessage("compressed data refers to nothing"))
+                }
+
+                // The sequence may overlap what it is repeated into, so it
+                // is copied a byte at a time.
+                let start = out.len() - offset;
+                for j in 0..(token & 0x7f) + MIN_MATCH {
+                    out.push(out[start + j]);
+                }
+            },
+        }
+
+        if out.len() > max_length {
+            return Err(Error::InvalidMessage("compressed data too large"))
+        }
+    }
+
+    Ok(out)
+}
+
+fn push_literals(out: &mut Vec<u8>, literals: &[u8]) {
+    for run in literals.chunks(MAX_LITERALS) {
+        out.push((run.len() - 1) as u8);
+        out.extend_from_slice(run);
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+// How short and how long the repeated sequences may be, how far back they
+// may start, and how many bytes a run of literals may have.
+const MIN_MATCH: usize = 4;
+const MAX_MATCH: usize = MIN_MATCH + 0x7f;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const MAX_LITERALS: usize = 0x80;
+
+// How many sequences are remembered while looking for repetitions, as a
+// power of two.
+const HASH_BITS: u32 = 14;
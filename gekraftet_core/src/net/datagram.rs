@@ -0,0 +1,60 @@
+use std::io;
+use std::net::{ SocketAddr, ToSocketAddrs, UdpSocket };
+use super::{ Message, Result };
+
+/// Messages sent over UDP, a message per datagram. Datagrams may be lost,
+/// arrive twice or out of order, so they only carry what is sent again soon
+/// anyway, e.g. where entities are. The socket never blocks.
+pub struct DatagramSocket {
+    socket: UdpSocket,
+}
+
+impl DatagramSocket {
+    pub fn bind<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    /// Sends the message to the address. Messages that do not fit into a
+    /// datagram, or that the socket has no room for, are dropped.
+    pub fn send_to<M: Message>(&self, message: &M, address: SocketAddr) -> Result<()> {
+        let mut datagram = Vec::new();
+        message.encode(&mut datagram);
+
+        if datagram.len() > MAX_DATAGRAM_SIZE {
+            return Ok(())
+        }
+
+        match self.socket.send_to(&datagram, address) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the next message that has arrived, and who sent it, or `None`
+    /// if there is none yet. Datagrams that are not a message are skipped,
+    /// as anyone may send them.
+    pub fn receive_from<M: Message>(&self) -> Result<Option<(M, SocketAddr)>> {
+        let mut buffer = [0; MAX_DATAGRAM_SIZE];
+
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((length, address)) => match M::decode(&buffer[..length]) {
+                    Ok(message) => return Ok(Some((message, address))),
+                    Err(_) => continue,
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                // Some systems report that an earlier datagram could not be
+                // delivered this way, which is of no concern.
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+// How large a datagram may be. Anything larger may be split up on the way,
+// making it likelier to get lost.
+const MAX_DATAGRAM_SIZE: usize = 1200;
@@ -0,0 +1,608 @@
+use std::collections::BTreeMap;
+use cgmath::{ Point3, Vector2, Vector3, Vector4 };
+use crate::entity::{ Entity, Render, RenderShape };
+use crate::movement::{ MovementInput, MovementState };
+use crate::stats::PlayerStats;
+use crate::world::*;
+use super::codec::{ Reader, Writer };
+use super::{ Capabilities, Error, Result };
+
+/// Something that can be sent over a `Connection`. Every message starts with
+/// a byte telling what kind of message it is.
+pub trait Message: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    fn decode(bytes: &[u8]) -> Result<Self>;
+}
+
+/// What the client tells the server.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    /// How the player moved during a frame of the client: the input, which
+    /// way it faced, and how long the frame was. The server moves the player
+    /// the same way, and acknowledges the sequence number once it has.
+    PlayerInput {
+        sequence: u32,
+        delta: f32,
+        input: MovementInput,
+        forward: Vector3<f32>,
+        flying: bool,
+        noclip: bool,
+    },
+    /// Asks to break the block. Nothing changes until the server says so.
+    BreakBlock {
+        at: BlockPos,
+    },
+    /// Asks to put the block where there is nothing solid yet.
+    PlaceBlock {
+        at: BlockPos,
+        block: Block,
+    },
+    /// The first message of a client, with the version of the protocol it
+    /// speaks and the capabilities it supports. Without a token, the player
+    /// asks to play in offline mode, which only some servers allow.
+    Login {
+        version: u32,
+        capabilities: Capabilities,
+        name: String,
+        token: Option<String>,
+    },
+    /// The client has the chunk, as it was at the revision. Chunks it just
+    /// generated are at revision 0. The server sends what changed since, and
+    /// every change to the chunk after that.
+    ChunkLoaded {
+        at: ChunkPos,
+        revision: u64,
+    },
+    /// The client no longer has the chunk, and need not hear about it.
+    ChunkUnloaded {
+        at: ChunkPos,
+    },
+    /// Something the player typed into the chat. Messages starting with a
+    /// slash are commands, which the server runs instead of passing on.
+    Chat {
+        text: String,
+    },
+    /// Sent over UDP to the server, with the token it gave, so that it knows
+    /// where to send datagrams to. Sent again until the server answers.
+    DatagramHello {
+        token: u64,
+    },
+    /// Asks for the statistics of the player, which the server answers with
+    /// `ServerMessage::Stats`. Only sent once the `STATS` capability was
+    /// agreed on.
+    RequestStats,
+}
+
+impl Message for ClientMessage {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ClientMessage::PlayerInput { sequence, delta, input, forward, flying, noclip } => {
+                out.u8(0);
+                out.u32(*sequence);
+                out.f32(*delta);
+                out.f32(input.direction.x);
+                out.f32(input.direction.y);
+                out.f32(input.speed);
+                out.f32(input.vertical);
+                out.vector(*forward);
+                out.u8(flags(&[input.jump, input.sprint, input.crouch, *flying, *noclip]));
+            },
+            ClientMessage::BreakBlock { at } => {
+                out.u8(1);
+                out.block_pos(*at);
+            },
+            ClientMessage::PlaceBlock { at, block } => {
+                out.u8(2);
+                out.block_pos(*at);
+                out.block(block);
+            },
+            ClientMessage::Login { version, capabilities, name, token } => {
+                out.u8(3);
+                out.u32(*version);
+                out.u32(capabilities.bits());
+                out.string(name);
+
+                match token {
+                    Some(token) => {
+                        out.u8(1);
+                        out.string(token);
+                    },
+                    None => out.u8(0),
+                }
+            },
+            ClientMessage::ChunkLoaded { at, revision } => {
+                out.u8(4);
+                out.chunk_pos(*at);
+                out.u64(*revision);
+            },
+            ClientMessage::ChunkUnloaded { at } => {
+                out.u8(5);
+                out.chunk_pos(*at);
+            },
+            ClientMessage::Chat { text } => {
+                out.u8(6);
+                out.string(text);
+            },
+            ClientMessage::DatagramHello { token } => {
+                out.u8(7);
+                out.u64(*token);
+            },
+            ClientMessage::RequestStats => out.u8(8),
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+
+        match reader.u8()? {
+            0 => {
+                let sequence = reader.u32()?;
+                let delta = reader.f32()?;
+                let direction = Vector2::new(reader.f32()?, reader.f32()?);
+                let speed = reader.f32()?;
+                let vertical = reader.f32()?;
+                let forward = reader.vector()?;
+                let flags = reader.u8()?;
+
+                Ok(ClientMessage::PlayerInput {
+                    sequence,
+                    delta,
+                    input: MovementInput {
+                        direction,
+                        speed,
+                        jump: flags & 1 != 0,
+                        sprint: flags & 2 != 0,
+                        crouch: flags & 4 != 0,
+                        vertical,
+                    },
+                    forward,
+                    flying: flags & 8 != 0,
+                    noclip: flags & 16 != 0,
+                })
+            },
+            1 => Ok(ClientMessage::BreakBlock {
+                at: reader.block_pos()?,
+            }),
+            2 => Ok(ClientMessage::PlaceBlock {
+                at: reader.block_pos()?,
+                block: reader.block()?,
+            }),
+            3 => {
+                let version = reader.u32()?;
+                let capabilities = Capabilities::from_bits(reader.u32()?);
+                let name = reader.string()?;
+                let token = match reader.u8()? {
+                    0 => None,
+                    1 => Some(reader.string()?),
+                    _ => return Err(Error::InvalidMessage("invalid token flag")),
+                };
+
+                Ok(ClientMessage::Login { version, capabilities, name, token })
+            },
+            4 => Ok(ClientMessage::ChunkLoaded {
+                at: reader.chunk_pos()?,
+                revision: reader.u64()?,
+            }),
+            5 => Ok(ClientMessage::ChunkUnloaded {
+                at: reader.chunk_pos()?,
+            }),
+            6 => Ok(ClientMessage::Chat {
+                text: reader.string()?,
+            }),
+            7 => Ok(ClientMessage::DatagramHello {
+                token: reader.u64()?,
+            }),
+            8 => Ok(ClientMessage::RequestStats),
+            id => Err(Error::UnknownMessage(id)),
+        }
+    }
+}
+
+/// What the server tells the client. Entities are only told about while they
+/// are near the player.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    /// Sent once the client has logged in: the entity that is the player,
+    /// what the terrain of the world is generated with, and the capabilities
+    /// the connection uses.
+    Welcome {
+        player: Entity,
+        generator: WorldGenerator,
+        capabilities: Capabilities,
+    },
+    /// An entity came near the player, at the given tick of the server.
+    EntitySpawned {
+        entity: Entity,
+        tick: u64,
+        position: Point3<f32>,
+        size: Vector3<f32>,
+        render: Render,
+    },
+    EntityMoved {
+        entity: Entity,
+        tick: u64,
+        position: Point3<f32>,
+    },
+    /// The entity is gone, or too far away from the player.
+    EntityDespawned {
+        entity: Entity,
+    },
+    /// What a block really is, after the player asked for it to be changed
+    /// but was refused.
+    BlockChanged {
+        at: BlockPos,
+        block: Block,
+    },
+    /// The blocks of a chunk of the client that changed between the two
+    /// revisions, each with what it is at the newer one.
+    ChunkDelta {
+        at: ChunkPos,
+        base: u64,
+        revision: u64,
+        changes: Vec<(BlockPos, Block)>,
+    },
+    /// Every block of a chunk of the client, in the order `Chunk::blocks()`
+    /// returns them in. Sent instead of a delta when too much has changed.
+    ChunkData {
+        at: ChunkPos,
+        revision: u64,
+        blocks: Vec<Block>,
+    },
+    /// Where the server moved the player to, having moved it by every input
+    /// up to the sequence number.
+    PlayerAcknowledged {
+        sequence: u32,
+        state: MovementState,
+    },
+    /// The login was refused, or the player was kicked later on, and the
+    /// connection is about to be closed.
+    Rejected {
+        reason: String,
+    },
+    /// How the server is doing, sent to operators once a second: how many
+    /// ticks it runs per second, how many milliseconds they take, and how
+    /// many of those every part of a tick takes.
+    Metrics {
+        tps: f32,
+        mspt: f32,
+        parts: Vec<(String, f32)>,
+    },
+    /// A line of the chat: what a player said, or, without a sender, what
+    /// the server says, e.g. the output of a command.
+    Chat {
+        sender: Option<String>,
+        text: String,
+    },
+    /// Sent once the `DATAGRAMS` capability was agreed on: the token the
+    /// client says hello over UDP with. The server sends it back over UDP
+    /// once the hello arrived.
+    DatagramSession {
+        token: u64,
+    },
+    /// The statistics of the player in the world, as it asked for them.
+    Stats {
+        stats: PlayerStats,
+    },
+}
+
+impl Message for ServerMessage {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ServerMessage::Welcome { player, generator, capabilities } => {
+                out.u8(0);
+                out.u64(player.to_bits());
+                out.u64(generator.seed());
+                out.u8(generator.preset().id());
+                out.i32(generator.border().radius());
+                out.u32(capabilities.bits());
+            },
+            ServerMessage::EntitySpawned { entity, tick, position, size, render } => {
+                out.u8(1);
+                out.u64(entity.to_bits());
+                out.u64(*tick);
+                out.point(*position);
+                out.vector(*size);
+
+                match render.shape {
+                    RenderShape::Cube => out.u8(0),
+                    RenderShape::Block(id) => {
+                        out.u8(1);
+                        out.u16(id);
+                    },
+                }
+
+                for channel in 0..4 {
+                    out.f32(render.color[channel]);
+                }
+            },
+            ServerMessage::EntityMoved { entity, tick, position } => {
+                out.u8(2);
+                out.u64(entity.to_bits());
+                out.u64(*tick);
+                out.point(*position);
+            },
+            ServerMessage::EntityDespawned { entity } => {
+                out.u8(3);
+                out.u64(entity.to_bits());
+            },
+            ServerMessage::BlockChanged { at, block } => {
+                out.u8(4);
+                out.block_pos(*at);
+                out.block(block);
+            },
+            ServerMessage::Rejected { reason } => {
+                out.u8(5);
+                out.string(reason);
+            },
+            ServerMessage::ChunkDelta { at, base, revision, changes } => {
+                out.u8(6);
+                out.chunk_pos(*at);
+                out.u64(*base);
+                out.u64(*revision);
+                out.u32(changes.len() as u32);
+
+                for (position, block) in changes {
+                    out.u16(local_index(*at, *position));
+                    out.block(block);
+                }
+            },
+            ServerMessage::PlayerAcknowledged { sequence, state } => {
+                out.u8(8);
+                out.u32(*sequence);
+                out.point(state.position);
+                out.vector(state.velocity);
+                out.u8(flags(&[state.on_ground, state.flying, state.crouching]));
+            },
+            ServerMessage::ChunkData { at, revision, blocks } => {
+                out.u8(7);
+                out.chunk_pos(*at);
+                out.u64(*revision);
+                encode_runs(out, blocks);
+            },
+            ServerMessage::Metrics { tps, mspt, parts } => {
+                out.u8(9);
+                out.f32(*tps);
+                out.f32(*mspt);
+                out.u16(parts.len() as u16);
+
+                for (name, millis) in parts {
+                    out.string(name);
+                    out.f32(*millis);
+                }
+            },
+            ServerMessage::Chat { sender, text } => {
+                out.u8(10);
+
+                match sender {
+                    Some(sender) => {
+                        out.u8(1);
+                        out.string(sender);
+                    },
+                    None => out.u8(0),
+                }
+
+                out.string(text);
+            },
+            ServerMessage::DatagramSession { token } => {
+                out.u8(11);
+                out.u64(*token);
+            },
+            ServerMessage::Stats { stats } => {
+                out.u8(12);
+                out.u64(stats.distance.to_bits());
+                out.u64(stats.play_time.to_bits());
+                encode_counts(out, &stats.blocks_broken);
+                encode_counts(out, &stats.blocks_placed);
+            },
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+
+        match reader.u8()? {
+            0 => {
+                let player = Entity::from_bits(reader.u64()?);
+                let seed = reader.u64()?;
+                let preset = WorldPreset::from_id(reader.u8()?)
+                    .ok_or(Error::InvalidMessage("unknown world preset"))?;
+
+                let mut generator = WorldGenerator::new(seed, preset);
+                generator.set_border(WorldBorder::new(reader.i32()?));
+                let capabilities = Capabilities::from_bits(reader.u32()?);
+                Ok(ServerMessage::Welcome { player, generator, capabilities })
+            },
+            1 => {
+                let entity = Entity::from_bits(reader.u64()?);
+                let tick = reader.u64()?;
+                let position = reader.point()?;
+                let size = reader.vector()?;
+
+                let shape = match reader.u8()? {
+                    0 => RenderShape::Cube,
+                    1 => RenderShape::Block(reader.u16()?),
+                    _ => return Err(Error::InvalidMessage("unknown entity shape")),
+                };
+
+                let color = Vector4::new(reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?);
+                Ok(ServerMessage::EntitySpawned {
+                    entity,
+                    tick,
+                    position,
+                    size,
+                    render: Render { shape, color },
+                })
+            },
+            2 => Ok(ServerMessage::EntityMoved {
+                entity: Entity::from_bits(reader.u64()?),
+                tick: reader.u64()?,
+                position: reader.point()?,
+            }),
+            3 => Ok(ServerMessage::EntityDespawned {
+                entity: Entity::from_bits(reader.u64()?),
+            }),
+            4 => Ok(ServerMessage::BlockChanged {
+                at: reader.block_pos()?,
+                block: reader.block()?,
+            }),
+            5 => Ok(ServerMessage::Rejected {
+                reason: reader.string()?,
+            }),
+            6 => {
+                let at = reader.chunk_pos()?;
+                let base = reader.u64()?;
+                let revision = reader.u64()?;
+
+                let count = reader.u32()? as usize;
+                if count > CHUNK_VOLUME {
+                    return Err(Error::InvalidMessage("too many changes in a chunk"))
+                }
+
+                let mut changes = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let position = block_position(at, reader.u16()?);
+                    changes.push((position, reader.block()?));
+                }
+
+                Ok(ServerMessage::ChunkDelta { at, base, revision, changes })
+            },
+            7 => Ok(ServerMessage::ChunkData {
+                at: reader.chunk_pos()?,
+                revision: reader.u64()?,
+                blocks: decode_runs(&mut reader)?,
+            }),
+            8 => {
+                let sequence = reader.u32()?;
+                let position = reader.point()?;
+                let velocity = reader.vector()?;
+                let flags = reader.u8()?;
+
+                Ok(ServerMessage::PlayerAcknowledged {
+                    sequence,
+                    state: MovementState {
+                        position,
+                        velocity,
+                        on_ground: flags & 1 != 0,
+                        flying: flags & 2 != 0,
+                        crouching: flags & 4 != 0,
+                    },
+                })
+            },
+            9 => {
+                let tps = reader.f32()?;
+                let mspt = reader.f32()?;
+
+                let count = reader.u16()? as usize;
+                let mut parts = Vec::with_capacity(count);
+                for _ in 0..count {
+                    parts.push((reader.string()?, reader.f32()?));
+                }
+
+                Ok(ServerMessage::Metrics { tps, mspt, parts })
+            },
+            10 => {
+                let sender = match reader.u8()? {
+                    0 => None,
+                    1 => Some(reader.string()?),
+                    _ => return Err(Error::InvalidMessage("invalid sender flag")),
+                };
+
+                Ok(ServerMessage::Chat { sender, text: reader.string()? })
+            },
+            11 => Ok(ServerMessage::DatagramSession {
+                token: reader.u64()?,
+            }),
+            12 => Ok(ServerMessage::Stats {
+                stats: PlayerStats {
+                    distance: f64::from_bits(reader.u64()?),
+                    play_time: f64::from_bits(reader.u64()?),
+                    blocks_broken: decode_counts(&mut reader)?,
+                    blocks_placed: decode_counts(&mut reader)?,
+                },
+            }),
+            id => Err(Error::UnknownMessage(id)),
+        }
+    }
+}
+
+// Writes how many there are of every block id.
+fn encode_counts(out: &mut Vec<u8>, counts: &BTreeMap<u16, u64>) {
+    out.u16(counts.len() as u16);
+
+    for (&id, &count) in counts {
+        out.u16(id);
+        out.u64(count);
+    }
+}
+
+fn decode_counts(reader: &mut Reader) -> Result<BTreeMap<u16, u64>> {
+    let length = reader.u16()?;
+    (0..length).map(|_| Ok((reader.u16()?, reader.u64()?))).collect()
+}
+
+// Packs up to 8 flags into a byte, the first one into the lowest bit.
+fn flags(values: &[bool]) -> u8 {
+    values
+        .iter()
+        .enumerate()
+        .fold(0, |flags, (i, &value)| flags | (value as u8) << i)
+}
+
+// Where a block is within its chunk, packed into 16 bits: x, then z, then y.
+fn local_index(chunk: ChunkPos, at: BlockPos) -> u16 {
+    let x = (at.x - chunk.x * CHUNK_LENGTH_X as i32) as u16;
+    let y = (at.y - chunk.y * CHUNK_LENGTH_Y as i32) as u16;
+    let z = (at.z - chunk.z * CHUNK_LENGTH_Z as i32) as u16;
+    (x & 0xF) << 12 | (z & 0xF) << 8 | (y & 0xFF)
+}
+
+fn block_position(chunk: ChunkPos, index: u16) -> BlockPos {
+    let x = (index >> 12 & 0xF) as i32;
+    let z = (index >> 8 & 0xF) as i32;
+    let y = (index & 0xFF) as i32;
+
+    BlockPos::new(
+        chunk.x * CHUNK_LENGTH_X as i32 + x,
+        chunk.y * CHUNK_LENGTH_Y as i32 + y,
+        chunk.z * CHUNK_LENGTH_Z as i32 + z,
+    )
+}
+
+// Chunks are mostly long runs of the same block, so each run is written as
+// the block and how many times it repeats.
+fn encode_runs(out: &mut Vec<u8>, blocks: &[Block]) {
+    let mut runs = Vec::<(&Block, u16)>::new();
+    for block in blocks {
+        match runs.last_mut() {
+            Some((last, length)) if *last == block && *length < u16::MAX => *length += 1,
+            _ => runs.push((block, 1)),
+        }
+    }
+
+    out.u32(runs.len() as u32);
+    for (block, length) in runs {
+        out.block(block);
+        out.u16(length);
+    }
+}
+
+fn decode_runs(reader: &mut Reader) -> Result<Vec<Block>> {
+    let runs = reader.u32()?;
+    let mut blocks = Vec::new();
+
+    for _ in 0..runs {
+        let block = reader.block()?;
+        let length = reader.u16()? as usize;
+
+        if blocks.len() + length > CHUNK_VOLUME {
+            return Err(Error::InvalidMessage("too many blocks in a chunk"))
+        }
+        blocks.extend(std::iter::repeat(block).take(length));
+    }
+
+    Ok(blocks)
+}
+
+// How many blocks a chunk holds.
+const CHUNK_VOLUME: usize = CHUNK_LENGTH_X * CHUNK_LENGTH_Y * CHUNK_LENGTH_Z;
@@ -0,0 +1,53 @@
+//! The protocol the client and the server talk over: the messages they send
+//! each other, and the transports carrying them.
+
+mod capabilities;
+mod codec;
+mod compression;
+mod datagram;
+mod message;
+mod transport;
+
+pub use capabilities::Capabilities;
+pub use datagram::DatagramSocket;
+pub use message::*;
+pub use transport::*;
+
+use std::io;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The ways sending or receiving a message can fail.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The other side went away.
+    Disconnected,
+    /// A message ended before all of it was read.
+    UnexpectedEof,
+    UnknownMessage(u8),
+    /// A message holds something that cannot be decoded, e.g. an unknown
+    /// world preset.
+    InvalidMessage(&'static str),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe => Error::Disconnected,
+            _ => Error::Io(e),
+        }
+    }
+}
+
+/// The version of the protocol. Servers talk to clients of any version from
+/// `MIN_PROTOCOL_VERSION` up to their own, which only differ in the
+/// capabilities they support.
+pub const PROTOCOL_VERSION: u32 = 6;
+pub const MIN_PROTOCOL_VERSION: u32 = 5;
+
+/// How many characters a chat message may have. Longer ones are cut off.
+pub const MAX_CHAT_LENGTH: usize = 256;
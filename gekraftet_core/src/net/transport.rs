@@ -0,0 +1,222 @@
+use std::io::{ self, Read, Write };
+use std::net::{ TcpStream, ToSocketAddrs };
+use std::sync::mpsc::{ self, Receiver, Sender, TryRecvError };
+use super::compression::{ compress, decompress };
+use super::{ Error, Message, Result };
+
+/// Carries frames of bytes to the other side of a connection, and back. The
+/// frames arrive whole and in the order they were sent in. Transports never
+/// block: frames that cannot be sent right away are kept until they can.
+pub trait Transport {
+    fn send_frame(&mut self, frame: &[u8]) -> Result<()>;
+
+    /// Returns the next frame that has arrived, or `None` if there is none
+    /// yet.
+    fn receive_frame(&mut self) -> Result<Option<Vec<u8>>>;
+}
+
+/// Frames sent over TCP, each one after its length.
+pub struct TcpTransport {
+    stream: TcpStream,
+    // What has been received but not taken as a frame yet, and what has been
+    // sent but not written into the stream yet.
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl TcpTransport {
+    pub fn new(stream: TcpStream) -> Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    /// Connects to the address. This blocks until the connection is made.
+    pub fn connect<A: ToSocketAddrs>(address: A) -> Result<Self> {
+        Self::new(TcpStream::connect(address)?)
+    }
+
+    // Writes as much of the outgoing bytes as the stream takes.
+    fn flush(&mut self) -> Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(Error::Disconnected),
+                Ok(written) => { self.outgoing.drain(..written); },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send_frame(&mut self, frame: &[u8]) -> Result<()> {
+        self.outgoing.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        self.outgoing.extend_from_slice(frame);
+        self.flush()
+    }
+
+    fn receive_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        self.flush()?;
+
+        // The frames that arrived before the stream was closed are still
+        // taken, e.g. the reason a server gives for closing it.
+        let mut buffer = [0; READ_BUFFER_SIZE];
+        let mut closed = false;
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    closed = true;
+                    break
+                },
+                Ok(read) => self.incoming.extend_from_slice(&buffer[..read]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let length = match self.incoming.get(..4) {
+            Some(header) => u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize,
+            None if closed => return Err(Error::Disconnected),
+            None => return Ok(None),
+        };
+
+        if length > MAX_FRAME_SIZE {
+            return Err(Error::InvalidMessage("frame too large"))
+        }
+
+        match (self.incoming.len() >= 4 + length, closed) {
+            (true, _) => Ok(Some(self.incoming.drain(..4 + length).skip(4).collect())),
+            (false, true) => Err(Error::Disconnected),
+            (false, false) => Ok(None),
+        }
+    }
+}
+
+/// Frames handed to the other end of a pair, within the same process, e.g.
+/// to a server running in another thread. Either end is disconnected once
+/// the other is dropped.
+pub struct MemoryTransport {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+}
+
+impl MemoryTransport {
+    /// Returns two transports, each receiving what the other sends.
+    pub fn pair() -> (Self, Self) {
+        let (a_sender, b_receiver) = mpsc::channel();
+        let (b_sender, a_receiver) = mpsc::channel();
+
+        let a = Self { sender: a_sender, receiver: a_receiver };
+        let b = Self { sender: b_sender, receiver: b_receiver };
+        (a, b)
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send_frame(&mut self, frame: &[u8]) -> Result<()> {
+        self.sender.send(frame.to_vec()).map_err(|_| Error::Disconnected)
+    }
+
+    fn receive_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        match self.receiver.try_recv() {
+            Ok(frame) => Ok(Some(frame)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(Error::Disconnected),
+        }
+    }
+}
+
+/// A transport, with messages going over it instead of frames. Every frame
+/// starts with a byte telling whether the message in it is compressed.
+pub struct Connection {
+    transport: Box<dyn Transport>,
+    compress: bool,
+}
+
+impl Connection {
+    pub fn new<T: Transport + 'static>(transport: T) -> Self {
+        Self {
+            transport: Box::new(transport),
+            compress: false,
+        }
+    }
+
+    /// Compresses the large messages sent from now on. Only do so once the
+    /// other side agreed to the `COMPRESSION` capability; compressed messages
+    /// are always received, though.
+    pub fn set_compression(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+
+    pub fn send<M: Message>(&mut self, message: &M) -> Result<()> {
+        let mut encoded = Vec::new();
+        message.encode(&mut encoded);
+
+        let compressed = match self.compress && encoded.len() >= COMPRESSION_THRESHOLD {
+            true => Some(compress(&encoded)).filter(|c| c.len() + 4 < encoded.len()),
+            false => None,
+        };
+
+        let frame = match compressed {
+            Some(compressed) => {
+                let mut frame = Vec::with_capacity(compressed.len() + 5);
+                frame.push(COMPRESSED);
+                frame.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+                frame.extend_from_slice(&compressed);
+                frame
+            },
+            None => {
+                encoded.insert(0, UNCOMPRESSED);
+                encoded
+            },
+        };
+
+        self.transport.send_frame(&frame)
+    }
+
+    /// Returns the next message that has arrived, or `None` if there is none
+    /// yet.
+    pub fn receive<M: Message>(&mut self) -> Result<Option<M>> {
+        let frame = match self.transport.receive_frame()? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+
+        match frame.split_first() {
+            Some((&UNCOMPRESSED, message)) => Ok(Some(M::decode(message)?)),
+            Some((&COMPRESSED, rest)) if rest.len() >= 4 => {
+                let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+                if length > MAX_FRAME_SIZE {
+                    return Err(Error::InvalidMessage("frame too large"))
+                }
+
+                let message = decompress(&rest[4..], length)?;
+                match message.len() == length {
+                    true => Ok(Some(M::decode(&message)?)),
+                    false => Err(Error::InvalidMessage("compressed frame has the wrong length")),
+                }
+            },
+            Some(_) => Err(Error::InvalidMessage("unknown frame kind")),
+            None => Err(Error::UnexpectedEof),
+        }
+    }
+}
+
+// How many bytes are read from a stream at once, and how large a frame may be
+// before the connection is given up on.
+const READ_BUFFER_SIZE: usize = 4096;
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+// What the first byte of a frame is, and how large a message has to be for
+// compressing it to be worth it.
+const UNCOMPRESSED: u8 = 0;
+const COMPRESSED: u8 = 1;
+const COMPRESSION_THRESHOLD: usize = 256;
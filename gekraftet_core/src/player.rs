@@ -0,0 +1,152 @@
+use std::convert::TryFrom;
+use cgmath::Point3;
+use crate::inventory::{ self, Inventory };
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The ways reading a serialized player can fail.
+#[derive(Debug)]
+pub enum Error {
+    UnexpectedEof,
+    /// The player was written by a version of the game this one cannot read.
+    UnknownVersion(u8),
+    UnknownGameMode(u8),
+    Inventory(inventory::Error),
+}
+
+impl From<inventory::Error> for Error {
+    fn from(e: inventory::Error) -> Self {
+        Error::Inventory(e)
+    }
+}
+
+/// The rules the player plays by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// Blocks take time to break, and have to be collected before they can
+    /// be placed. The player cannot fly.
+    Survival,
+    /// Blocks break at once and can be placed without end, and the player
+    /// can fly.
+    Creative,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 2] = [
+        GameMode::Survival,
+        GameMode::Creative,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+        }
+    }
+
+    /// The game mode with the given name, see `name()`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|m| m.name() == name)
+    }
+
+    /// The number the game mode is saved as, see `from_id()`.
+    pub fn id(self) -> u8 {
+        match self {
+            GameMode::Survival => 0,
+            GameMode::Creative => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|m| m.id() == id)
+    }
+
+    pub fn can_fly(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    /// Whether blocks break as soon as they are hit, instead of having to be
+    /// hit for a while.
+    pub fn breaks_instantly(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    /// Whether placing a block takes it out of the inventory, and breaking
+    /// one drops it.
+    pub fn uses_items(self) -> bool {
+        self == GameMode::Survival
+    }
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Creative
+    }
+}
+
+/// Everything about the player that is kept with the level, so that it can
+/// carry on where it left off.
+#[derive(Clone, Debug)]
+pub struct PlayerState {
+    /// Where the feet of the player are, in blocks.
+    pub position: Point3<f32>,
+    /// Which way the player is looking, in radians.
+    pub yaw: f32,
+    pub pitch: f32,
+    pub flying: bool,
+    pub game_mode: GameMode,
+    /// The block the player places.
+    pub selected_block: u16,
+    pub inventory: Inventory,
+}
+
+impl PlayerState {
+    /// Encodes the player into bytes, which can be decoded by `from_bytes()`.
+    /// Everything is big-endian, and the inventory comes last.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PLAYER_HEADER_SIZE);
+        bytes.push(PLAYER_VERSION);
+
+        let floats = [self.position.x, self.position.y, self.position.z, self.yaw, self.pitch];
+        for float in floats.iter() {
+            bytes.extend_from_slice(&float.to_be_bytes());
+        }
+
+        bytes.push(self.flying as u8);
+        bytes.push(self.game_mode.id());
+        bytes.extend_from_slice(&self.selected_block.to_be_bytes());
+        bytes.extend_from_slice(&self.inventory.to_bytes());
+        bytes
+    }
+
+    /// Decodes a player encoded by `to_bytes()`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let header = bytes.get(0..PLAYER_HEADER_SIZE).ok_or(Error::UnexpectedEof)?;
+
+        if header[0] != PLAYER_VERSION {
+            return Err(Error::UnknownVersion(header[0]))
+        }
+
+        let float = |i: usize| {
+            let at = 1 + i * 4;
+            f32::from_be_bytes(<[u8; 4]>::try_from(&header[at..at + 4]).unwrap())
+        };
+
+        Ok(Self {
+            position: Point3::new(float(0), float(1), float(2)),
+            yaw: float(3),
+            pitch: float(4),
+            flying: header[21] != 0,
+            game_mode: GameMode::from_id(header[22]).ok_or(Error::UnknownGameMode(header[22]))?,
+            selected_block: u16::from_be_bytes([header[23], header[24]]),
+            inventory: Inventory::from_bytes(&bytes[PLAYER_HEADER_SIZE..])?,
+        })
+    }
+}
+
+// Bumped whenever the encoding of the player changes.
+const PLAYER_VERSION: u8 = 1;
+
+// How many bytes come before the inventory: the version, five floats, the
+// flying flag, the game mode and the selected block.
+const PLAYER_HEADER_SIZE: usize = 1 + 5 * 4 + 1 + 1 + 2;
@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::{ self, File };
+use std::io::{ self, Read, Seek, SeekFrom, Write };
+use std::path::PathBuf;
+use crate::migration::{ MigrationError, Migrations, Payload };
+use crate::saves::{ is_temporary, write_atomically };
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use crate::world::{ Block, Chunk, ChunkPos, CHUNK_LENGTH_X, CHUNK_LENGTH_Y, CHUNK_LENGTH_Z };
+
+/// Chunks kept on disk, so that they do not have to be generated again. The
+/// chunks are grouped into regions of 32 by 32 chunks, with a file each, so
+/// that there are not too many files.
+///
+/// A region file starts with a table of where every chunk is in the file,
+/// and how long it is, followed by the chunks. Every chunk is compressed on
+/// its own, so that it can be read without reading the others.
+///
+/// Chunks saved by earlier versions of the game are migrated as they are
+/// read, see `Migrations`, or all at once by `migrate`.
+///
+/// Region files are replaced atomically, so that they are never left half
+/// written. While regions are being saved, they are listed in a journal, so
+/// that a save that was cut short can be told apart, see `recover`.
+#[derive(Clone, Debug)]
+pub struct RegionStore {
+    root: PathBuf,
+}
+
+impl RegionStore {
+    /// Keeps the region files in the folder, which is created once the first
+    /// chunk is saved.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+        }
+    }
+
+    /// Reads the chunk, or returns `None` if it was never saved.
+    pub fn load(&self, at: ChunkPos) -> io::Result<Option<Chunk>> {
+        let mut file = match File::open(self.region_path(region_of(at))) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let (version, table) = read_table(&mut file)?;
+        let (offset, length) = table[chunk_index(at)];
+        if length == 0 {
+            return Ok(None)
+        }
+
+        let mut compressed = vec![0; length as usize];
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.read_exact(&mut compressed)?;
+
+        decode_chunk(at, &compressed, version).map(Some)
+    }
+
+    /// Writes the chunks, replacing those that were saved before. Every
+    /// region is written once, however many of its chunks are saved.
+    pub fn save<'a, I: IntoIterator<Item = &'a Chunk>>(&self, chunks: I) -> io::Result<()> {
+        let mut regions = HashMap::<(i32, i32), Vec<&Chunk>>::new();
+        for chunk in chunks {
+            regions.entry(region_of(chunk.position())).or_default().push(chunk);
+        }
+
+        let keys = regions.keys().copied().collect::<Vec<_>>();
+        self.rewrite(&keys, |region, entries| {
+            for chunk in regions[&region].iter() {
+                entries[chunk_index(chunk.position())] = Some(encode_chunk(chunk)?);
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Rewrites every region file saved by an earlier version of the game the
+    /// way this one saves it. With `dry_run`, nothing is written. Returns the
+    /// name of every region file that is out of date, with its version.
+    pub fn migrate(&self, dry_run: bool) -> io::Result<Vec<(String, u8)>> {
+        let files = match fs::read_dir(&self.root) {
+            Ok(files) => files,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut outdated = Vec::new();
+        for file in files {
+            let file = file?;
+            let name = file.file_name().to_string_lossy().into_owned();
+
+            let region = match parse_region_name(&name) {
+                Some(region) => region,
+                None => continue,
+            };
+
+            let (version, _) = read_table(&mut File::open(file.path())?)?;
+            if version != REGION_VERSION {
+                Migrations::builtin().steps(Payload::Chunk, version, REGION_VERSION).map_err(invalid_data)?;
+                outdated.push((region, name, version));
+            }
+        }
+
+        outdated.sort();
+
+        if !dry_run {
+            // Reading a region migrates its chunks, so it only has to be
+            // written again.
+            let regions = outdated.iter().map(|&(region, _, _)| region).collect::<Vec<_>>();
+            self.rewrite(&regions, |_, _| Ok(()))?;
+        }
+
+        Ok(outdated.into_iter().map(|(_, name, version)| (name, version)).collect())
+    }
+
+    /// Cleans up after a save that was cut short, e.g. by a crash, deleting
+    /// the files it left over. Every region file is whole, but those the
+    /// save was writing may still hold the chunks from before it; they are
+    /// returned as the coordinates of the regions.
+    pub fn recover(&self) -> io::Result<Vec<(i32, i32)>> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            if is_temporary(&path) {
+                fs::remove_file(path)?;
+            }
+        }
+
+        let journal = match fs::read_to_string(self.root.join(JOURNAL_FILE)) {
+            Ok(journal) => journal,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let regions = journal
+            .lines()
+            .filter_map(|line| {
+                let mut coordinates = line.split_whitespace().map(str::parse::<i32>);
+                match (coordinates.next(), coordinates.next()) {
+                    (Some(Ok(x)), Some(Ok(z))) => Some((x, z)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        fs::remove_file(self.root.join(JOURNAL_FILE))?;
+        Ok(regions)
+    }
+
+    // Reads the regions, lets `change` change their chunks, and writes them
+    // again. The regions are listed in the journal while they are written.
+    fn rewrite<F>(&self, regions: &[(i32, i32)], mut change: F) -> io::Result<()>
+    where
+        F: FnMut((i32, i32), &mut Vec<Option<Vec<u8>>>) -> io::Result<()>,
+    {
+        if regions.is_empty() {
+            return Ok(())
+        }
+
+        fs::create_dir_all(&self.root)?;
+        self.recover()?;
+
+        let journal = regions
+            .iter()
+            .map(|(x, z)| format!("{} {}\n", x, z))
+            .collect::<String>();
+        write_atomically(self.root.join(JOURNAL_FILE), journal.as_bytes())?;
+
+        for &region in regions {
+            let mut entries = self.read_region(region)?;
+            change(region, &mut entries)?;
+            self.write_region(region, &entries)?;
+        }
+
+        fs::remove_file(self.root.join(JOURNAL_FILE))
+    }
+
+    // Every chunk of the region that was saved, still compressed, by index.
+    // Chunks saved by an earlier version are migrated.
+    fn read_region(&self, region: (i32, i32)) -> io::Result<Vec<Option<Vec<u8>>>> {
+        let mut entries = vec![None; REGION_AREA];
+
+        let mut file = match File::open(self.region_path(region)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(entries),
+            Err(e) => return Err(e),
+        };
+
+        let (version, table) = read_table(&mut file)?;
+        for (entry, &(offset, length)) in entries.iter_mut().zip(table.iter()) {
+            if length == 0 {
+                continue
+            }
+
+            let mut compressed = vec![0; length as usize];
+            file.seek(SeekFrom::Start(offset as u64))?;
+            file.read_exact(&mut compressed)?;
+
+            if version != REGION_VERSION {
+                let bytes = Migrations::builtin()
+                    .migrate(Payload::Chunk, &decompress(&compressed)?, version, REGION_VERSION)
+                    .map_err(invalid_data)?;
+                compressed = compress(&bytes)?;
+            }
+
+            *entry = Some(compressed);
+        }
+
+        Ok(entries)
+    }
+
+    fn write_region(&self, region: (i32, i32), entries: &[Option<Vec<u8>>]) -> io::Result<()> {
+        let mut table = Vec::with_capacity(TABLE_SIZE);
+        let mut chunks = Vec::new();
+        table.push(REGION_VERSION);
+
+        for entry in entries {
+            let (offset, length) = match entry {
+                Some(compressed) => ((TABLE_SIZE + chunks.len()) as u32, compressed.len() as u32),
+                None => (0, 0),
+            };
+
+            table.extend_from_slice(&offset.to_be_bytes());
+            table.extend_from_slice(&length.to_be_bytes());
+            chunks.extend_from_slice(entry.as_deref().unwrap_or(&[]));
+        }
+
+        let mut bytes = table;
+        bytes.append(&mut chunks);
+        write_atomically(self.region_path(region), &bytes)
+    }
+
+    fn region_path(&self, (x, z): (i32, i32)) -> PathBuf {
+        self.root.join(format!("r.{}.{}.dat", x, z))
+    }
+}
+
+// The region the chunk is in.
+fn region_of(at: ChunkPos) -> (i32, i32) {
+    (at.x.div_euclid(REGION_LENGTH as i32), at.z.div_euclid(REGION_LENGTH as i32))
+}
+
+// The region a region file is of, from its name.
+fn parse_region_name(name: &str) -> Option<(i32, i32)> {
+    let mut parts = name.strip_prefix("r.")?.strip_suffix(".dat")?.split('.');
+
+    match (parts.next()?.parse(), parts.next()?.parse(), parts.next()) {
+        (Ok(x), Ok(z), None) => Some((x, z)),
+        _ => None,
+    }
+}
+
+// Where the chunk is in the table of its region.
+fn chunk_index(at: ChunkPos) -> usize {
+    let x = at.x.rem_euclid(REGION_LENGTH as i32) as usize;
+    let z = at.z.rem_euclid(REGION_LENGTH as i32) as usize;
+    z * REGION_LENGTH + x
+}
+
+// Reads the version of a region file, and the offset and length of every
+// chunk in it. Versions later than this one are refused.
+fn read_table(file: &mut File) -> io::Result<(u8, Vec<(u32, u32)>)> {
+    let mut table = vec![0; TABLE_SIZE];
+    file.read_exact(&mut table)?;
+
+    if table[0] == 0 || table[0] > REGION_VERSION {
+        return Err(invalid_data(format!("unknown region version {}", table[0])))
+    }
+
+    let int = |at: usize| u32::from_be_bytes(<[u8; 4]>::try_from(&table[at..at + 4]).unwrap());
+    Ok((table[0], (0..REGION_AREA).map(|i| (int(1 + i * 8), int(5 + i * 8))).collect()))
+}
+
+fn encode_chunk(chunk: &Chunk) -> io::Result<Vec<u8>> {
+    compress(&encode_blocks(chunk.blocks()))
+}
+
+fn decode_chunk(at: ChunkPos, compressed: &[u8], version: u8) -> io::Result<Chunk> {
+    let mut bytes = decompress(compressed)?;
+    if version != REGION_VERSION {
+        bytes = Migrations::builtin()
+            .migrate(Payload::Chunk, &bytes, version, REGION_VERSION)
+            .map_err(invalid_data)?;
+    }
+
+    let mut chunk = Chunk::empty(at);
+    chunk.replace_blocks(&decode_blocks(&bytes)?);
+    Ok(chunk)
+}
+
+// Encodes the blocks, in the order `Chunk::blocks()` returns them in, as a
+// palette of the different blocks (their ids and metadata), followed by the
+// index into it of every block. The indices take a byte each unless there
+// are more than 256 different blocks.
+fn encode_blocks<'a, I: IntoIterator<Item = &'a Block>>(blocks: I) -> Vec<u8> {
+    let mut palette = Vec::new();
+    let mut palette_index = HashMap::new();
+    let mut indices = Vec::with_capacity(CHUNK_VOLUME);
+
+    for block in blocks {
+        let index = *palette_index.entry((block.id, block.metadata)).or_insert_with(|| {
+            palette.push((block.id, block.metadata));
+            (palette.len() - 1) as u16
+        });
+        indices.push(index);
+    }
+
+    let mut bytes = Vec::with_capacity(4 + palette.len() * 4 + indices.len() * 2);
+    bytes.extend_from_slice(&(palette.len() as u32).to_be_bytes());
+    for (id, metadata) in palette.iter() {
+        bytes.extend_from_slice(&id.to_be_bytes());
+        bytes.extend_from_slice(&metadata.to_be_bytes());
+    }
+
+    match palette.len() <= 256 {
+        true => bytes.extend(indices.iter().map(|&index| index as u8)),
+        false => indices.iter().for_each(|index| bytes.extend_from_slice(&index.to_be_bytes())),
+    }
+
+    bytes
+}
+
+fn decode_blocks(bytes: &[u8]) -> io::Result<Vec<Block>> {
+    let truncated = || invalid_data("a chunk is cut short");
+
+    let palette_length = u32::from_be_bytes(<[u8; 4]>::try_from(bytes.get(0..4).ok_or_else(truncated)?).unwrap()) as usize;
+    let palette = bytes
+        .get(4..4 + palette_length.min(CHUNK_VOLUME) * 4)
+        .ok_or_else(truncated)?
+        .chunks(4)
+        .map(|b| Block {
+            id: u16::from_be_bytes([b[0], b[1]]),
+            metadata: u16::from_be_bytes([b[2], b[3]]),
+        })
+        .collect::<Vec<_>>();
+
+    let indices = &bytes[4 + palette.len() * 4..];
+    let indices = match palette_length <= 256 {
+        true => indices.iter().map(|&index| index as usize).collect::<Vec<_>>(),
+        false => indices.chunks(2).map(|i| u16::from_be_bytes([i[0], i[1]]) as usize).collect(),
+    };
+
+    if indices.len() != CHUNK_VOLUME {
+        return Err(invalid_data("a chunk has the wrong number of blocks"))
+    }
+
+    indices
+        .into_iter()
+        .map(|index| palette.get(index).cloned().ok_or_else(|| invalid_data("a block is not in the palette")))
+        .collect()
+}
+
+/// Turns the blocks of a chunk of version 1, which were stored as the id and
+/// metadata of every block, into those of version 2, see `Migrations`.
+pub(crate) fn migrate_raw_chunk(bytes: &[u8]) -> Result<Vec<u8>, MigrationError> {
+    if bytes.len() != CHUNK_VOLUME * 4 {
+        return Err(MigrationError::Invalid("a chunk has the wrong number of blocks".to_owned()))
+    }
+
+    let blocks = bytes
+        .chunks(4)
+        .map(|b| Block {
+            id: u16::from_be_bytes([b[0], b[1]]),
+            metadata: u16::from_be_bytes([b[2], b[3]]),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(encode_blocks(blocks.iter()))
+}
+
+fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+fn decompress(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    DeflateDecoder::new(compressed).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+// How many chunks a region is long, on both axes.
+const REGION_LENGTH: usize = 32;
+const REGION_AREA: usize = REGION_LENGTH * REGION_LENGTH;
+
+/// The version of region files, which is that of the chunks in them. It is
+/// bumped whenever the encoding of chunks changes, together with a migration
+/// from the one before.
+pub const REGION_VERSION: u8 = 2;
+
+// The version, then an offset and a length for every chunk.
+const TABLE_SIZE: usize = 1 + REGION_AREA * 8;
+
+// Lists the regions being saved, while they are.
+const JOURNAL_FILE: &'static str = "dirty";
+
+const CHUNK_VOLUME: usize = CHUNK_LENGTH_X * CHUNK_LENGTH_Y * CHUNK_LENGTH_Z;
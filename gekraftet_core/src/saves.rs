@@ -0,0 +1,274 @@
+use std::{ fs, io };
+use std::fs::File;
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+use crate::level::{ self, Level, LEVEL_VERSION };
+use crate::migration::{ Migrations, Payload };
+use crate::region::{ RegionStore, REGION_VERSION };
+use crate::stats::{ self, WorldStats };
+use crate::world::WorldGenerator;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The ways loading or saving a world can fail.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Level(level::Error),
+    Stats(stats::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<level::Error> for Error {
+    fn from(e: level::Error) -> Self {
+        Error::Level(e)
+    }
+}
+
+impl From<stats::Error> for Error {
+    fn from(e: stats::Error) -> Self {
+        Error::Stats(e)
+    }
+}
+
+/// The worlds saved in a data directory. Every world has a folder of its own,
+/// which holds its level.
+#[derive(Clone, Debug)]
+pub struct WorldManager {
+    root: PathBuf,
+}
+
+impl WorldManager {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the folders of the saved worlds, sorted by name. Folders
+    /// without a level are not worlds, and a data directory that does not
+    /// exist yet has none.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut folders = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+
+            if !entry.path().join(LEVEL_FILE).is_file() {
+                continue
+            }
+
+            if let Some(folder) = entry.file_name().to_str() {
+                folders.push(folder.to_owned());
+            }
+        }
+
+        folders.sort();
+        Ok(folders)
+    }
+
+    pub fn load(&self, folder: &str) -> Result<Level> {
+        let bytes = fs::read(self.level_path(folder))?;
+        Ok(Level::from_bytes(&bytes)?)
+    }
+
+    pub fn save(&self, folder: &str, level: &Level) -> Result<()> {
+        fs::create_dir_all(self.root.join(folder))?;
+        write_atomically(self.level_path(folder), &level.to_bytes())?;
+        Ok(())
+    }
+
+    /// Creates and saves a new world. Its folder is named after it, with a
+    /// number added if another world already has that folder. Returns the
+    /// folder together with the level.
+    pub fn create(&self, name: &str, generator: WorldGenerator) -> Result<(String, Level)> {
+        let folder = self.free_folder(name);
+        let level = Level::new(name.to_owned(), generator);
+        self.save(&folder, &level)?;
+        Ok((folder, level))
+    }
+
+    /// Copies the world saved in `path` (e.g. a folder dropped onto the
+    /// window) into a folder of its own, named like the folders of new
+    /// worlds. Worlds that are already in the data directory are left where
+    /// they are. Returns the folder together with the level.
+    pub fn import(&self, path: &Path) -> Result<(String, Level)> {
+        let level = Level::from_bytes(&fs::read(path.join(LEVEL_FILE))?)?;
+
+        let parent = path.parent().and_then(|parent| parent.canonicalize().ok());
+        let existing = path.file_name().and_then(|name| name.to_str());
+        if let (Some(parent), Some(folder)) = (parent, existing) {
+            if self.root.canonicalize().ok() == Some(parent) {
+                return Ok((folder.to_owned(), level))
+            }
+        }
+
+        let folder = self.free_folder(&level.name);
+        copy_folder(path, &self.root.join(&folder))?;
+        Ok((folder, level))
+    }
+
+    /// Rewrites the level and the regions of the world, if they were saved
+    /// by an earlier version of the game, the way this one saves them. They
+    /// are migrated whenever they are read anyway, but only in memory. With
+    /// `dry_run`, nothing is written. Returns what was (or would be)
+    /// migrated, a line each.
+    pub fn migrate(&self, folder: &str, dry_run: bool) -> Result<Vec<String>> {
+        let migrations = Migrations::builtin();
+        let mut changes = Vec::new();
+
+        let version = Level::version(&fs::read(self.level_path(folder))?)?;
+        if version != LEVEL_VERSION {
+            for step in migrations.steps(Payload::Level, version, LEVEL_VERSION).map_err(level::Error::from)? {
+                changes.push(format!("level: version {} to {}, {}", step.from, step.to, step.description));
+            }
+
+            if !dry_run {
+                self.save(folder, &self.load(folder)?)?;
+            }
+        }
+
+        for (file, version) in self.regions(folder).migrate(dry_run)? {
+            let steps = migrations.steps(Payload::Chunk, version, REGION_VERSION).map_err(level::Error::from)?;
+            let steps = steps.iter().map(|step| step.description).collect::<Vec<_>>();
+            changes.push(format!("{}: version {} to {}, {}", file, version, REGION_VERSION, steps.join(", ")));
+        }
+
+        Ok(changes)
+    }
+
+    /// Loads the statistics of the players of the world. A world nobody has
+    /// played in yet has none.
+    pub fn load_stats(&self, folder: &str) -> Result<WorldStats> {
+        match fs::read(self.root.join(folder).join(STATS_FILE)) {
+            Ok(bytes) => Ok(WorldStats::from_bytes(&bytes)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(WorldStats::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save_stats(&self, folder: &str, stats: &WorldStats) -> Result<()> {
+        fs::create_dir_all(self.root.join(folder))?;
+        write_atomically(self.root.join(folder).join(STATS_FILE), &stats.to_bytes())?;
+        Ok(())
+    }
+
+    /// The chunks saved in the folder of the world, e.g. by pre-generating
+    /// them.
+    pub fn regions(&self, folder: &str) -> RegionStore {
+        RegionStore::new(self.root.join(folder).join(REGIONS_FOLDER))
+    }
+
+    fn level_path(&self, folder: &str) -> PathBuf {
+        self.root.join(folder).join(LEVEL_FILE)
+    }
+
+    // The folder a world of the name goes into, with a number added if
+    // another world already has that folder.
+    fn free_folder(&self, name: &str) -> String {
+        let base = folder_name(name);
+
+        (1..)
+            .map(|i| match i {
+                1 => base.clone(),
+                i => format!("{} ({})", base, i),
+            })
+            .find(|folder| !self.root.join(folder).exists())
+            .unwrap()
+    }
+}
+
+/// Replaces the file with the bytes, such that it holds either all of them or
+/// whatever it held before, however the game stops. They are written to a
+/// file next to it first, which is flushed to the disk and then renamed over
+/// it.
+pub fn write_atomically<P: AsRef<Path>>(path: P, bytes: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(TEMPORARY_SUFFIX);
+    let temporary = PathBuf::from(temporary);
+
+    let mut file = File::create(&temporary)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temporary, path)?;
+
+    // The rename itself is only on the disk once the folder is. Folders
+    // cannot be opened as files everywhere, e.g. on Windows, where renaming
+    // does not need this.
+    if let Some(folder) = path.parent() {
+        if let Ok(folder) = File::open(folder) {
+            let _ = folder.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the file is left over from writing another one atomically, see
+/// `write_atomically`.
+pub fn is_temporary(path: &Path) -> bool {
+    path.to_str().map_or(false, |path| path.ends_with(TEMPORARY_SUFFIX))
+}
+
+// Copies the folder and everything in it, creating `to` and the folders
+// leading to it.
+fn copy_folder(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+
+        match entry.file_type()?.is_dir() {
+            true => copy_folder(&entry.path(), &target)?,
+            false => { fs::copy(entry.path(), target)?; },
+        }
+    }
+
+    Ok(())
+}
+
+// Turns the name of a world into something every file system accepts as the
+// name of a folder.
+fn folder_name(name: &str) -> String {
+    let folder = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            c if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    match folder.is_empty() {
+        true => "World".to_owned(),
+        false => folder,
+    }
+}
+
+// The files in the folder of a world that hold its level and the statistics
+// of its players, and the folder that holds its regions. The statistics are
+// not part of the level, which clients save as well.
+const LEVEL_FILE: &'static str = "level.dat";
+const STATS_FILE: &'static str = "stats.dat";
+const REGIONS_FOLDER: &'static str = "regions";
+
+// Added to the name of a file for the one its bytes are written to first.
+const TEMPORARY_SUFFIX: &'static str = ".tmp";
@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::string::FromUtf8Error;
+use crate::world::BlockRegistry;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The ways reading serialized statistics can fail.
+#[derive(Debug)]
+pub enum Error {
+    UnexpectedEof,
+    /// The statistics were written by a version of the game this one cannot
+    /// read.
+    UnknownVersion(u8),
+    InvalidName(FromUtf8Error),
+}
+
+/// What a player has done in a world.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerStats {
+    /// How many blocks of every id the player broke.
+    pub blocks_broken: BTreeMap<u16, u64>,
+    /// How many blocks of every id the player placed.
+    pub blocks_placed: BTreeMap<u16, u64>,
+    /// How far the player moved, in blocks.
+    pub distance: f64,
+    /// How long the player played, in seconds.
+    pub play_time: f64,
+}
+
+impl PlayerStats {
+    pub fn broke(&mut self, id: u16) {
+        *self.blocks_broken.entry(id).or_insert(0) += 1;
+    }
+
+    pub fn placed(&mut self, id: u16) {
+        *self.blocks_placed.entry(id).or_insert(0) += 1;
+    }
+
+    pub fn total_broken(&self) -> u64 {
+        self.blocks_broken.values().sum()
+    }
+
+    pub fn total_placed(&self) -> u64 {
+        self.blocks_placed.values().sum()
+    }
+
+    /// Describes the statistics, a line each: the totals first, then how
+    /// many blocks of every kind were broken and placed, most first.
+    pub fn lines(&self) -> Vec<String> {
+        let seconds = self.play_time as u64;
+        let mut lines = vec![
+            format!("Time played: {}h {:02}m {:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60),
+            format!("Distance travelled: {:.0} blocks", self.distance),
+            format!("Blocks broken: {}", self.total_broken()),
+            format!("Blocks placed: {}", self.total_placed()),
+        ];
+
+        let mut blocks = self.blocks_broken
+            .keys()
+            .chain(self.blocks_placed.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        blocks.sort();
+        blocks.dedup();
+
+        let count = |counts: &BTreeMap<u16, u64>, id| counts.get(&id).copied().unwrap_or(0);
+        blocks.sort_by_key(|&id| std::cmp::Reverse(count(&self.blocks_broken, id) + count(&self.blocks_placed, id)));
+
+        for id in blocks {
+            lines.push(format!(
+                "{}: {} broken, {} placed",
+                BlockRegistry::global().get(id).name,
+                count(&self.blocks_broken, id),
+                count(&self.blocks_placed, id),
+            ));
+        }
+
+        lines
+    }
+
+    // Everything is big-endian, the counts of blocks come last.
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.distance.to_be_bytes());
+        bytes.extend_from_slice(&self.play_time.to_be_bytes());
+
+        for counts in [&self.blocks_broken, &self.blocks_placed].iter() {
+            bytes.extend_from_slice(&(counts.len() as u16).to_be_bytes());
+
+            for (id, count) in counts.iter() {
+                bytes.extend_from_slice(&id.to_be_bytes());
+                bytes.extend_from_slice(&count.to_be_bytes());
+            }
+        }
+    }
+
+    fn decode(bytes: &mut &[u8]) -> Result<Self> {
+        let distance = f64::from_bits(take_u64(bytes)?);
+        let play_time = f64::from_bits(take_u64(bytes)?);
+
+        let mut counts = || -> Result<BTreeMap<u16, u64>> {
+            let length = take_u16(bytes)?;
+            (0..length).map(|_| Ok((take_u16(bytes)?, take_u64(bytes)?))).collect()
+        };
+        let blocks_broken = counts()?;
+        let blocks_placed = counts()?;
+
+        Ok(Self {
+            blocks_broken,
+            blocks_placed,
+            distance,
+            play_time,
+        })
+    }
+}
+
+/// The statistics of every player of a world, by name. They are kept with
+/// the world, see `WorldManager::load_stats()`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorldStats {
+    players: BTreeMap<String, PlayerStats>,
+}
+
+impl WorldStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PlayerStats> {
+        self.players.get(name)
+    }
+
+    /// The statistics of the player, which start out empty.
+    pub fn get_mut(&mut self, name: &str) -> &mut PlayerStats {
+        self.players.entry(name.to_owned()).or_default()
+    }
+
+    /// Every player, sorted by name.
+    pub fn players(&self) -> impl Iterator<Item = (&str, &PlayerStats)> + '_ {
+        self.players.iter().map(|(name, stats)| (name.as_str(), stats))
+    }
+
+    /// Encodes the statistics into bytes, which can be decoded by
+    /// `from_bytes()`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![STATS_VERSION];
+        bytes.extend_from_slice(&(self.players.len() as u32).to_be_bytes());
+
+        for (name, stats) in self.players.iter() {
+            let name = name.as_bytes();
+            let name = &name[..name.len().min(u16::max_value() as usize)];
+            bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(name);
+            stats.encode(&mut bytes);
+        }
+
+        bytes
+    }
+
+    /// Decodes statistics encoded by `to_bytes()`.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        let version = take(&mut bytes, 1)?[0];
+        if version != STATS_VERSION {
+            return Err(Error::UnknownVersion(version))
+        }
+
+        let count = u32::from_be_bytes(<[u8; 4]>::try_from(take(&mut bytes, 4)?).unwrap());
+        let mut players = BTreeMap::new();
+
+        for _ in 0..count {
+            let length = take_u16(&mut bytes)? as usize;
+            let name = String::from_utf8(take(&mut bytes, length)?.to_vec()).map_err(Error::InvalidName)?;
+            players.insert(name, PlayerStats::decode(&mut bytes)?);
+        }
+
+        Ok(Self {
+            players,
+        })
+    }
+}
+
+// Takes the first bytes off the slice.
+fn take<'a>(bytes: &mut &'a [u8], count: usize) -> Result<&'a [u8]> {
+    if bytes.len() < count {
+        return Err(Error::UnexpectedEof)
+    }
+
+    let (taken, rest) = bytes.split_at(count);
+    *bytes = rest;
+    Ok(taken)
+}
+
+fn take_u16(bytes: &mut &[u8]) -> Result<u16> {
+    Ok(u16::from_be_bytes(<[u8; 2]>::try_from(take(bytes, 2)?).unwrap()))
+}
+
+fn take_u64(bytes: &mut &[u8]) -> Result<u64> {
+    Ok(u64::from_be_bytes(<[u8; 8]>::try_from(take(bytes, 8)?).unwrap()))
+}
+
+// Bumped whenever the encoding of the statistics changes.
+const STATS_VERSION: u8 = 1;
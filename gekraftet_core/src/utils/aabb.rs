@@ -0,0 +1,142 @@
+use cgmath::{ EuclideanSpace, Point3, Vector3 };
+
+/// A box whose faces are aligned with the axes, between two corners.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+/// Where a moving box first hits another, see `Aabb::sweep()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sweep {
+    /// How far along the movement the boxes touch, from 0.0 (where it starts)
+    /// to 1.0 (where it ends).
+    pub time: f32,
+    /// The face of the other box that was hit, pointing out of it. It is zero
+    /// if the boxes overlapped from the start.
+    pub normal: Vector3<f32>,
+}
+
+impl Aabb {
+    /// Creates the box between two opposite corners, in any order.
+    pub fn new(a: Point3<f32>, b: Point3<f32>) -> Self {
+        Self {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    /// Creates the box reaching `half_size` from the center in every
+    /// direction.
+    pub fn from_center(center: Point3<f32>, half_size: Vector3<f32>) -> Self {
+        Self::new(center - half_size, center + half_size)
+    }
+
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    /// The box moved by the offset.
+    pub fn translate(&self, offset: Vector3<f32>) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    /// The box grown by the amount on every side.
+    pub fn grow(&self, amount: Vector3<f32>) -> Self {
+        Self::new(self.min - amount, self.max + amount)
+    }
+
+    /// The smallest box holding both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(
+            Point3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            Point3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        )
+    }
+
+    /// Whether the boxes overlap. Boxes that only touch do not.
+    pub fn intersects(&self, other: &Self) -> bool {
+        (0..3).all(|axis| self.min[axis] < other.max[axis] && other.min[axis] < self.max[axis])
+    }
+
+    /// Whether the point is inside the box or on its surface.
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        (0..3).all(|axis| self.min[axis] <= point[axis] && point[axis] <= self.max[axis])
+    }
+
+    /// Whether the other box is entirely inside this one.
+    pub fn contains(&self, other: &Self) -> bool {
+        self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    /// Where the ray from `origin` enters the box, in multiples of
+    /// `direction`, or 0.0 if it starts inside. Returns `None` if it misses
+    /// the box, or the box is behind it.
+    pub fn ray_intersection(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<f32> {
+        self.entry(origin, direction).map(|(time, _)| time.max(0.0))
+    }
+
+    /// Moves this box by `velocity`, and returns where it first hits the
+    /// other box on the way, if it does. Boxes that only slide along each
+    /// other do not hit.
+    pub fn sweep(&self, velocity: Vector3<f32>, other: &Self) -> Option<Sweep> {
+        if self.intersects(other) {
+            return Some(Sweep { time: 0.0, normal: Vector3::new(0.0, 0.0, 0.0) })
+        }
+
+        // Moving the box against the other is the same as moving its center
+        // against the other grown by the box.
+        let half_size = self.size() / 2.0;
+        let (time, axis) = other.grow(half_size).entry(self.center(), velocity)?;
+
+        if time < 0.0 || time > 1.0 {
+            return None
+        }
+
+        let mut normal = Vector3::new(0.0, 0.0, 0.0);
+        normal[axis] = -velocity[axis].signum();
+        Some(Sweep { time, normal })
+    }
+
+    // Where the ray enters the box, and through the face of which axis, with
+    // the slab method: the ray is inside the box while it is between the two
+    // faces of every axis at once. The entry may be behind the origin, if it
+    // starts inside.
+    fn entry(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<(f32, usize)> {
+        let mut entry = (f32::NEG_INFINITY, 0);
+        let mut exit = f32::INFINITY;
+
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                // Parallel to the faces, so the ray is either always between
+                // them or never.
+                match self.min[axis] < origin[axis] && origin[axis] < self.max[axis] {
+                    true => continue,
+                    false => return None,
+                }
+            }
+
+            let a = (self.min[axis] - origin[axis]) / direction[axis];
+            let b = (self.max[axis] - origin[axis]) / direction[axis];
+            let (near, far) = (a.min(b), a.max(b));
+
+            if near > entry.0 {
+                entry = (near, axis);
+            }
+            exit = exit.min(far);
+        }
+
+        match entry.0 <= exit && exit > 0.0 {
+            true => Some(entry),
+            false => None,
+        }
+    }
+}
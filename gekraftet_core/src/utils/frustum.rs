@@ -0,0 +1,58 @@
+use cgmath::{ Matrix4, Point3, Vector4 };
+use super::{ Aabb, Plane };
+
+/// The volume seen by a camera, bounded by six planes facing inwards. It is
+/// used to skip what cannot be seen, be it for drawing or for sending.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far, in this order.
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes out of a combined projection and view matrix.
+    pub fn from_matrix(m: &Matrix4<f32>) -> Self {
+        // The rows of the matrix. cgmath stores the columns.
+        let row = |i: usize| Vector4::new(m.x[i], m.y[i], m.z[i], m.w[i]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        Self {
+            planes: [
+                Plane::from_coefficients(w + x),
+                Plane::from_coefficients(w - x),
+                Plane::from_coefficients(w + y),
+                Plane::from_coefficients(w - y),
+                Plane::from_coefficients(w + z),
+                Plane::from_coefficients(w - z),
+            ]
+        }
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.planes.iter().all(|p| p.distance_to(point) >= 0.0)
+    }
+
+    /// Checks whether the sphere is at least partially inside the frustum.
+    /// Like `intersects_aabb()`, this errs on the side of caution.
+    pub fn intersects_sphere(&self, center: Point3<f32>, radius: f32) -> bool {
+        self.planes.iter().all(|p| p.distance_to(center) >= -radius)
+    }
+
+    /// Checks whether the box is at least partially inside the frustum. This
+    /// errs on the side of caution: boxes near the corners of the frustum
+    /// might be reported as intersecting even if they are not.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let Aabb { min, max } = *aabb;
+
+        self.planes.iter().all(|p| {
+            // The corner of the box furthest along the normal of the plane.
+            let corner = Point3::new(
+                if p.normal.x >= 0.0 { max.x } else { min.x },
+                if p.normal.y >= 0.0 { max.y } else { min.y },
+                if p.normal.z >= 0.0 { max.z } else { min.z },
+            );
+
+            p.distance_to(corner) >= 0.0
+        })
+    }
+}
@@ -1,7 +1,19 @@
+mod aabb;
+mod frustum;
 mod math;
+mod morton;
 mod random;
+mod ray;
+mod spatial_hash;
+mod timestep;
 mod unsafety;
 
+pub use aabb::*;
+pub use frustum::*;
 pub use math::*;
+pub use morton::*;
 pub use random::*;
+pub use ray::*;
+pub use spatial_hash::SpatialHash;
+pub use timestep::*;
 pub use unsafety::*;
\ No newline at end of file
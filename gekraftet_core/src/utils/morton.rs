@@ -0,0 +1,64 @@
+// A Morton code interleaves the bits of the coordinates of a point, so that
+// points near each other mostly get codes near each other. The bits of x go
+// into the lowest bit, those of y into the next one, and so on.
+
+/// Interleaves the bits of the coordinates.
+pub fn morton_encode2(x: u32, y: u32) -> u64 {
+    spread2(x) | spread2(y) << 1
+}
+
+/// Takes the coordinates back out of a code made by `morton_encode2()`.
+pub fn morton_decode2(code: u64) -> (u32, u32) {
+    (compact2(code), compact2(code >> 1))
+}
+
+/// Interleaves the bits of the coordinates. Only the lowest 21 bits of each
+/// fit into the code.
+pub fn morton_encode3(x: u32, y: u32, z: u32) -> u64 {
+    spread3(x) | spread3(y) << 1 | spread3(z) << 2
+}
+
+/// Takes the coordinates back out of a code made by `morton_encode3()`.
+pub fn morton_decode3(code: u64) -> (u32, u32, u32) {
+    (compact3(code), compact3(code >> 1), compact3(code >> 2))
+}
+
+// Puts a zero bit in front of every bit, by moving ever smaller halves apart.
+fn spread2(v: u32) -> u64 {
+    let mut x = v as u64;
+    x = (x | x << 16) & 0x0000_FFFF_0000_FFFF;
+    x = (x | x << 8) & 0x00FF_00FF_00FF_00FF;
+    x = (x | x << 4) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | x << 2) & 0x3333_3333_3333_3333;
+    (x | x << 1) & 0x5555_5555_5555_5555
+}
+
+// Takes every other bit, undoing `spread2()`.
+fn compact2(code: u64) -> u32 {
+    let mut x = code & 0x5555_5555_5555_5555;
+    x = (x | x >> 1) & 0x3333_3333_3333_3333;
+    x = (x | x >> 2) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | x >> 4) & 0x00FF_00FF_00FF_00FF;
+    x = (x | x >> 8) & 0x0000_FFFF_0000_FFFF;
+    (x | x >> 16) as u32
+}
+
+// Puts two zero bits in front of every bit.
+fn spread3(v: u32) -> u64 {
+    let mut x = v as u64 & 0x1F_FFFF;
+    x = (x | x << 32) & 0x001F_0000_0000_FFFF;
+    x = (x | x << 16) & 0x001F_0000_FF00_00FF;
+    x = (x | x << 8) & 0x100F_00F0_0F00_F00F;
+    x = (x | x << 4) & 0x10C3_0C30_C30C_30C3;
+    (x | x << 2) & 0x1249_2492_4924_9249
+}
+
+// Takes every third bit, undoing `spread3()`.
+fn compact3(code: u64) -> u32 {
+    let mut x = code & 0x1249_2492_4924_9249;
+    x = (x | x >> 2) & 0x10C3_0C30_C30C_30C3;
+    x = (x | x >> 4) & 0x100F_00F0_0F00_F00F;
+    x = (x | x >> 8) & 0x001F_0000_FF00_00FF;
+    x = (x | x >> 16) & 0x001F_0000_0000_FFFF;
+    ((x | x >> 32) & 0x1F_FFFF) as u32
+}
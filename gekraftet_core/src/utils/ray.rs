@@ -0,0 +1,87 @@
+use cgmath::{ InnerSpace, Point3, Vector3, Vector4 };
+use super::Aabb;
+
+/// A half-line, starting at a point and going on forever in a direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    /// Always of length 1.0, so that how far along the ray a point is is the
+    /// distance to it.
+    pub direction: Vector3<f32>,
+}
+
+/// A plane, given as the points `p` for which `normal.dot(p) + distance` is
+/// zero. Points on the side the normal points to are in front of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Ray {
+    /// Creates the ray going from the origin in the direction, which is
+    /// normalized. Returns `None` if there is no direction.
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Option<Self> {
+        match direction.magnitude2() > 0.0 && direction.magnitude2().is_finite() {
+            true => Some(Self { origin, direction: direction.normalize() }),
+            false => None,
+        }
+    }
+
+    /// The point at the distance along the ray.
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// How far along the ray it enters the box, or 0.0 if it starts inside.
+    /// Returns `None` if it misses the box.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        aabb.ray_intersection(self.origin, self.direction)
+    }
+
+    /// How far along the ray it crosses the plane, from either side. Returns
+    /// `None` if the ray runs alongside the plane, or away from it.
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let facing = plane.normal.dot(self.direction);
+        if facing == 0.0 {
+            return None
+        }
+
+        let distance = -plane.distance_to(self.origin) / facing;
+        match distance >= 0.0 {
+            true => Some(distance),
+            false => None,
+        }
+    }
+}
+
+impl Plane {
+    /// Creates the plane through the point, facing the normal, which is
+    /// normalized.
+    pub fn new(normal: Vector3<f32>, point: Point3<f32>) -> Self {
+        let normal = normal.normalize();
+
+        Self {
+            normal,
+            distance: -(normal.x * point.x + normal.y * point.y + normal.z * point.z),
+        }
+    }
+
+    /// Creates the plane from the coefficients `a`, `b`, `c` and `d` of
+    /// `ax + by + cz + d = 0`.
+    pub fn from_coefficients(v: Vector4<f32>) -> Self {
+        let normal = Vector3::new(v.x, v.y, v.z);
+        let length = normal.magnitude();
+
+        Self {
+            normal: normal / length,
+            distance: v.w / length,
+        }
+    }
+
+    /// Returns the signed distance from the plane to the point.
+    pub fn distance_to(&self, point: Point3<f32>) -> f32 {
+        self.normal.x * point.x + self.normal.y * point.y + self.normal.z * point.z
+            + self.distance
+    }
+}
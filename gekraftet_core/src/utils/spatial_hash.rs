@@ -0,0 +1,280 @@
+use std::collections::{ HashMap, HashSet };
+use std::hash::Hash;
+use cgmath::{ InnerSpace, Point3, Vector3 };
+use super::Aabb;
+
+/// Finds what is near a place without looking at everything: space is split
+/// into cubic cells of the same size, and every item is kept in the cells
+/// its box overlaps. Only the cells around a place have to be searched, and
+/// only the cells that hold something are kept.
+///
+/// The items are usually entities, whose boxes are where they are in the
+/// world. Boxes too large to be put into every cell they overlap (or with
+/// no end at all) are kept aside, and checked by every query.
+#[derive(Clone, Debug)]
+pub struct SpatialHash<T> {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<T>>,
+    oversized: Vec<T>,
+    bounds: HashMap<T, Aabb>,
+}
+
+impl<T: Copy + Eq + Hash> SpatialHash<T> {
+    /// Creates an empty grid of cells of `cell_size` along every axis. Cells
+    /// about the size of the items, or of the usual query, work best.
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "the cells of a spatial hash must have a size");
+
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            oversized: Vec::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    pub fn contains(&self, item: T) -> bool {
+        self.bounds.contains_key(&item)
+    }
+
+    /// The box the item was put in with.
+    pub fn get(&self, item: T) -> Option<Aabb> {
+        self.bounds.get(&item).copied()
+    }
+
+    /// Puts the item into the grid, with the box it takes up. An item that
+    /// is already in the grid is moved instead.
+    pub fn insert(&mut self, item: T, bounds: Aabb) {
+        if self.move_to(item, bounds) {
+            return
+        }
+
+        self.bounds.insert(item, bounds);
+        self.link(item, &bounds);
+    }
+
+    /// Moves the item to a new box. Returns false (and does nothing) if it
+    /// is not in the grid.
+    pub fn move_to(&mut self, item: T, bounds: Aabb) -> bool {
+        let old = match self.bounds.get_mut(&item) {
+            Some(old) => std::mem::replace(old, bounds),
+            None => return false,
+        };
+
+        // Most moves stay within the same cells.
+        if self.cell_range(&old) != self.cell_range(&bounds) {
+            self.unlink(item, &old);
+            self.link(item, &bounds);
+        }
+
+        true
+    }
+
+    /// Takes the item out of the grid, and returns the box it had.
+    pub fn remove(&mut self, item: T) -> Option<Aabb> {
+        let bounds = self.bounds.remove(&item)?;
+        self.unlink(item, &bounds);
+        Some(bounds)
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.oversized.clear();
+        self.bounds.clear();
+    }
+
+    /// Every item whose box is within `radius` of the center.
+    pub fn query_radius(&self, center: Point3<f32>, radius: f32) -> Vec<T> {
+        let reach = Aabb::from_center(center, Vector3::new(radius, radius, radius));
+
+        self.query(&reach, |bounds| {
+            // The point of the box closest to the center.
+            let closest = Point3::new(
+                center.x.max(bounds.min.x).min(bounds.max.x),
+                center.y.max(bounds.min.y).min(bounds.max.y),
+                center.z.max(bounds.min.z).min(bounds.max.z),
+            );
+            (closest - center).magnitude2() <= radius * radius
+        })
+    }
+
+    /// Every item whose box overlaps or touches the box.
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<T> {
+        self.query(aabb, |bounds| {
+            (0..3).all(|axis| bounds.min[axis] <= aabb.max[axis] && aabb.min[axis] <= bounds.max[axis])
+        })
+    }
+
+    // Every item in the cells the area overlaps that passes the test, once
+    // each. An area too large to walk through cell by cell looks at the
+    // cells that hold something instead.
+    fn query(&self, area: &Aabb, mut test: impl FnMut(&Aabb) -> bool) -> Vec<T> {
+        let mut seen = HashSet::new();
+        let mut found = Vec::new();
+        let mut search = |items: &[T]| {
+            for &item in items {
+                if seen.insert(item) && test(&self.bounds[&item]) {
+                    found.push(item);
+                }
+            }
+        };
+
+        let range = self.cell_range(area);
+        match Self::cell_count(range) > MAX_LINKED_CELLS {
+            true => {
+                let ((x0, y0, z0), (x1, y1, z1)) = range;
+                let overlapped = self.cells.iter().filter(|((x, y, z), _)| {
+                    (x0..=x1).contains(x) && (y0..=y1).contains(y) && (z0..=z1).contains(z)
+                });
+
+                for (_, items) in overlapped {
+                    search(items);
+                }
+            },
+
+            false => for cell in self.cells_of(area) {
+                if let Some(items) = self.cells.get(&cell) {
+                    search(items);
+                }
+            },
+        }
+
+        search(&self.oversized);
+        found
+    }
+
+    // Puts the item into the cells the box overlaps, or aside if there are
+    // too many of them.
+    fn link(&mut self, item: T, bounds: &Aabb) {
+        if Self::cell_count(self.cell_range(bounds)) > MAX_LINKED_CELLS {
+            self.oversized.push(item);
+            return
+        }
+
+        for cell in self.cells_of(bounds) {
+            self.cells.entry(cell).or_default().push(item);
+        }
+    }
+
+    // Takes the item out of the cells the box overlaps, and forgets the cells
+    // that are left empty.
+    fn unlink(&mut self, item: T, bounds: &Aabb) {
+        if Self::cell_count(self.cell_range(bounds)) > MAX_LINKED_CELLS {
+            self.oversized.retain(|&other| other != item);
+            return
+        }
+
+        for cell in self.cells_of(bounds) {
+            if let Some(items) = self.cells.get_mut(&cell) {
+                items.retain(|&other| other != item);
+
+                if items.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    // The first and the last cell the box overlaps. Boxes reaching past the
+    // cells an i32 can count end at the last of them.
+    fn cell_range(&self, bounds: &Aabb) -> (Cell, Cell) {
+        let cell = |point: Point3<f32>| {
+            let point = point.map(|v| (v / self.cell_size).floor() as i32);
+            (point.x, point.y, point.z)
+        };
+
+        (cell(bounds.min), cell(bounds.max))
+    }
+
+    // How many cells are between the first and the last, both included.
+    fn cell_count(((x0, y0, z0), (x1, y1, z1)): (Cell, Cell)) -> u128 {
+        let length = |from: i32, to: i32| (i64::from(to) - i64::from(from) + 1).max(0) as u128;
+        length(x0, x1) * length(y0, y1) * length(z0, z1)
+    }
+
+    fn cells_of(&self, bounds: &Aabb) -> impl Iterator<Item = Cell> {
+        let ((x0, y0, z0), (x1, y1, z1)) = self.cell_range(bounds);
+
+        (x0..=x1).flat_map(move |x| {
+            (y0..=y1).flat_map(move |y| (z0..=z1).map(move |z| (x, y, z)))
+        })
+    }
+}
+
+// Where a cell is, in cells.
+type Cell = (i32, i32, i32);
+
+// The most cells an item is put into; larger items are kept aside.
+const MAX_LINKED_CELLS: u128 = 4096;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(x: f32, y: f32, z: f32, half_size: f32) -> Aabb {
+        Aabb::from_center(Point3::new(x, y, z), Vector3::new(half_size, half_size, half_size))
+    }
+
+    fn sorted(mut items: Vec<u32>) -> Vec<u32> {
+        items.sort_unstable();
+        items
+    }
+
+    #[test]
+    fn insert_and_query() {
+        let mut hash = SpatialHash::new(4.0);
+        hash.insert(1, cube(0.0, 0.0, 0.0, 0.5));
+        hash.insert(2, cube(3.0, 0.0, 0.0, 0.5));
+        hash.insert(3, cube(-20.0, 5.0, 9.0, 0.5));
+
+        assert_eq!(hash.len(), 3);
+        assert_eq!(sorted(hash.query_radius(Point3::new(1.5, 0.0, 0.0), 1.0)), vec![1, 2]);
+        assert_eq!(hash.query_aabb(&cube(-20.0, 5.0, 9.0, 1.0)), vec![3]);
+        assert!(hash.query_radius(Point3::new(100.0, 0.0, 0.0), 10.0).is_empty());
+    }
+
+    #[test]
+    fn move_and_remove() {
+        let mut hash = SpatialHash::new(4.0);
+        hash.insert(1, cube(0.0, 0.0, 0.0, 0.5));
+        hash.insert(1, cube(50.0, 0.0, 0.0, 0.5));
+
+        assert_eq!(hash.len(), 1);
+        assert!(hash.query_radius(Point3::new(0.0, 0.0, 0.0), 1.0).is_empty());
+        assert_eq!(hash.query_radius(Point3::new(50.0, 0.0, 0.0), 1.0), vec![1]);
+
+        assert_eq!(hash.remove(1), Some(cube(50.0, 0.0, 0.0, 0.5)));
+        assert_eq!(hash.remove(1), None);
+        assert!(hash.is_empty());
+        assert!(hash.cells.is_empty());
+    }
+
+    #[test]
+    fn unbounded_boxes() {
+        let mut hash = SpatialHash::new(1.0);
+        hash.insert(1, cube(0.0, 0.0, 0.0, 0.5));
+        hash.insert(2, cube(0.0, 0.0, 0.0, f32::INFINITY));
+        hash.insert(3, cube(1.0e30, 0.0, 0.0, 1.0e30));
+
+        assert_eq!(sorted(hash.query_radius(Point3::new(0.0, 0.0, 0.0), 1.0)), vec![1, 2, 3]);
+        assert_eq!(sorted(hash.query_radius(Point3::new(0.0, 0.0, 0.0), f32::INFINITY)), vec![1, 2, 3]);
+        assert_eq!(sorted(hash.query_aabb(&cube(0.0, 0.0, 0.0, 1.0e20))), vec![1, 2, 3]);
+
+        hash.insert(2, cube(0.0, 0.0, 0.0, 0.5));
+        assert_eq!(hash.remove(3), Some(cube(1.0e30, 0.0, 0.0, 1.0e30)));
+        assert!(hash.oversized.is_empty());
+        assert_eq!(sorted(hash.query_radius(Point3::new(0.0, 0.0, 0.0), 1.0)), vec![1, 2]);
+    }
+}
@@ -0,0 +1,59 @@
+/// Turns the time passed between frames into a number of steps of a fixed
+/// length, so that whatever is stepped runs alike at any frame rate. The time
+/// left over is carried into the next frame.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step: f32,
+    // Time (in seconds) passed that has not been turned into steps yet.
+    accumulator: f32,
+    max_steps_per_frame: u32,
+}
+
+impl FixedTimestep {
+    /// Creates a timestep of `step` seconds.
+    pub fn new(step: f32) -> Self {
+        Self {
+            step,
+            accumulator: 0.0,
+            max_steps_per_frame: DEFAULT_MAX_STEPS_PER_FRAME,
+        }
+    }
+
+    /// How many steps a single frame may catch up on at most. If the game
+    /// falls further behind than that (e.g. after a hitch), the rest of the
+    /// backlog is dropped and the simulation slows down instead.
+    pub fn max_steps_per_frame(mut self, max: u32) -> Self {
+        self.max_steps_per_frame = max.max(1);
+        self
+    }
+
+    /// How long (in seconds) each step is.
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds `delta` seconds, and returns how many steps have to be run to
+    /// catch up.
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.accumulator += delta.max(0.0);
+
+        let steps = (self.accumulator / self.step).floor() as u32;
+        self.accumulator -= steps as f32 * self.step;
+
+        if steps > self.max_steps_per_frame {
+            self.accumulator = 0.0;
+            return self.max_steps_per_frame
+        }
+
+        steps
+    }
+
+    /// How far (from 0 to 1) the time is into the next step, for blending
+    /// the last two steps when drawing.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).min(1.0)
+    }
+}
+
+// How many steps a frame catches up on by default.
+const DEFAULT_MAX_STEPS_PER_FRAME: u32 = 5;
@@ -1,32 +1,33 @@
 // --- Unsafety utilities start here ---
 
-use std::mem::MaybeUninit;
+use std::iter::FromIterator;
+use std::mem::{ ManuallyDrop, MaybeUninit };
 use std::fmt::{ Debug, Formatter, Result as FmtResult };
 
-pub type Iter<'a, T> = std::iter::Take<std::slice::Iter<'a, T>>;
-pub type IterMut<'a, T> = std::iter::Take<std::slice::IterMut<'a, T>>;
+pub type Iter<'a, T> = std::slice::Iter<'a, T>;
+pub type IterMut<'a, T> = std::slice::IterMut<'a, T>;
 
+/// An array that is filled one element after another, e.g. to build an array
+/// of elements that are not `Copy` without initializing it twice. Only the
+/// elements pushed so far are dropped with it.
 pub struct PartialArray<T, const N: usize> {
-    inner: [T; N],
+    inner: [MaybeUninit<T>; N],
     len: usize
 }
 
+/// Like `PartialArray`, but on the heap, for arrays that would blow the stack
+/// up.
 pub struct PartialHeapArray<T, const N: usize> {
-    inner: Box<[T; N]>,
+    inner: Box<[MaybeUninit<T>; N]>,
     len: usize
 }
 
 impl<T, const N: usize> PartialArray<T, N> {
     pub fn new() -> Self {
+        // An array of uninitialized elements needs no initialization itself.
+        // This is how MaybeUninit documents building one.
         let arr = unsafe {
-            // Why not MaybeUninit<T> too? You may ask. That's because rustc is stupid and
-            // programmers CANNOT, in any conceivable way, transmute a [MaybeUninit<T>; N]
-            // into [T; N] because rustc is stupid (yes, I said that TWICE.)
-            //
-            // This is EXTREMELY frustrating. Hours wasted just to think of a workaround.
-            // In the end I decided to just ditch MaybeUninit for the most part and handle
-            // things myself.
-            MaybeUninit::<[T; N]>::uninit().assume_init()
+            MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init()
         };
 
         Self {
@@ -35,52 +36,137 @@ impl<T, const N: usize> PartialArray<T, N> {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Appends the element, or returns it if the array is full.
     pub fn push(&mut self, elem: T) -> Result<(), T> {
         if self.len >= N {
             return Err(elem)
         };
 
-        unsafe {
-            let ptr = self.inner.as_mut_ptr().offset(self.len as isize);
-            std::ptr::write(ptr, elem) 
-        };
+        self.inner[self.len] = MaybeUninit::new(elem);
         self.len += 1;
         Ok(())
     }
 
-    pub fn get_ref(&self, index: usize) -> Option<&T> {
-        if index < self.len {
-            Some(&self.inner[index])
-        } else {
-            None
+    /// Appends clones of as many of the elements as fit, and returns those
+    /// that did not.
+    pub fn extend_from_slice<'a>(&mut self, elems: &'a [T]) -> Result<(), &'a [T]>
+        where T: Clone
+    {
+        let fitting = elems.len().min(N - self.len);
+        for elem in &elems[..fitting] {
+            self.inner[self.len] = MaybeUninit::new(elem.clone());
+            self.len += 1;
+        }
+
+        match fitting == elems.len() {
+            true => Ok(()),
+            false => Err(&elems[fitting..]),
         }
     }
 
+    pub fn get_ref(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+
     pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        if index < self.len {
-            Some(&mut self.inner[index])
-        } else {
-            None
+        self.as_mut_slice().get_mut(index)
+    }
+
+    /// The elements pushed so far.
+    pub fn as_slice(&self) -> &[T] {
+        // The first `len` elements are initialized.
+        unsafe {
+            std::slice::from_raw_parts(self.inner.as_ptr() as *const T, self.len)
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe {
+            std::slice::from_raw_parts_mut(self.inner.as_mut_ptr() as *mut T, self.len)
         }
     }
 
     pub fn iter(&self) -> Iter<'_, T> {
-        self.inner.iter().take(self.len)
+        self.as_slice().iter()
     }
 
     pub fn iter_mut(&mut self) -> IterMut<'_, T> {
-        self.inner.iter_mut().take(self.len)
+        self.as_mut_slice().iter_mut()
     }
 
+    /// Returns the array, unless it is not full yet.
     pub fn into_full_array(self) -> Result<[T; N], Self> {
-        if self.len == N {
-            Ok(self.inner)
-        } else {
-            Err(self)
+        if self.len != N {
+            return Err(self)
+        }
+
+        // Every element is initialized, and moved out of `self`, which must
+        // then not drop them.
+        let this = ManuallyDrop::new(self);
+        Ok(unsafe {
+            std::ptr::read(&this.inner as *const [MaybeUninit<T>; N] as *const [T; N])
+        })
+    }
+}
+
+impl<T, const N: usize> Default for PartialArray<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for PartialArray<T, N> {
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(self.as_mut_slice());
         }
     }
 }
 
+/// Collects the elements into an array. Panics if there are more than fit.
+impl<T, const N: usize> FromIterator<T> for PartialArray<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut arr = Self::new();
+        for elem in iter {
+            if arr.push(elem).is_err() {
+                panic!("more than {} elements were collected into a PartialArray", N);
+            }
+        }
+
+        arr
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a PartialArray<T, N> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut PartialArray<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
 impl<T: Debug, const N: usize> Debug for PartialArray<T, N> {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         write!(f, "[")?;
@@ -105,7 +191,7 @@ impl<T: Debug, const N: usize> Debug for PartialArray<T, N> {
 impl<T, const N: usize> PartialHeapArray<T, N> {
     pub fn new() -> Self {
         let arr = unsafe {
-            Box::new(MaybeUninit::uninit().assume_init())
+            Box::new(MaybeUninit::<[MaybeUninit<T>; N]>::uninit().assume_init())
         };
 
         Self {
@@ -114,49 +200,131 @@ impl<T, const N: usize> PartialHeapArray<T, N> {
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Appends the element, or returns it if the array is full.
     pub fn push(&mut self, elem: T) -> Result<(), T> {
         if self.len >= N {
             return Err(elem)
         };
 
-        unsafe {
-            let ptr = self.inner.as_mut_ptr().offset(self.len as isize);
-            std::ptr::write(ptr, elem) 
-        };
+        self.inner[self.len] = MaybeUninit::new(elem);
         self.len += 1;
         Ok(())
     }
 
-    pub fn get_ref(&self, index: usize) -> Option<&T> {
-        if index < self.len {
-            Some(&self.inner[index])
-        } else {
-            None
+    /// Appends clones of as many of the elements as fit, and returns those
+    /// that did not.
+    pub fn extend_from_slice<'a>(&mut self, elems: &'a [T]) -> Result<(), &'a [T]>
+        where T: Clone
+    {
+        let fitting = elems.len().min(N - self.len);
+        for elem in &elems[..fitting] {
+            self.inner[self.len] = MaybeUninit::new(elem.clone());
+            self.len += 1;
+        }
+
+        match fitting == elems.len() {
+            true => Ok(()),
+            false => Err(&elems[fitting..]),
         }
     }
 
+    pub fn get_ref(&self, index: usize) -> Option<&T> {
+        self.as_slice().get(index)
+    }
+
     pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        if index < self.len {
-            Some(&mut self.inner[index])
-        } else {
-            None
+        self.as_mut_slice().get_mut(index)
+    }
+
+    /// The elements pushed so far.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe {
+            std::slice::from_raw_parts(self.inner.as_ptr() as *const T, self.len)
+        }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe {
+            std::slice::from_raw_parts_mut(self.inner.as_mut_ptr() as *mut T, self.len)
         }
     }
 
     pub fn iter(&self) -> Iter<'_, T> {
-        self.inner.iter().take(self.len)
+        self.as_slice().iter()
     }
 
     pub fn iter_mut(&mut self) -> IterMut<'_, T> {
-        self.inner.iter_mut().take(self.len)
+        self.as_mut_slice().iter_mut()
     }
 
+    /// Returns the array, unless it is not full yet.
     pub fn into_full_array(self) -> Result<Box<[T; N]>, Self> {
-        if self.len == N {
-            Ok(self.inner)
-        } else {
-            Err(self)
+        if self.len != N {
+            return Err(self)
+        }
+
+        // The box is taken out of `self`, which must then not drop it.
+        let this = ManuallyDrop::new(self);
+        let inner = unsafe { std::ptr::read(&this.inner) };
+        Ok(unsafe { Box::from_raw(Box::into_raw(inner) as *mut [T; N]) })
+    }
+}
+
+impl<T, const N: usize> Default for PartialHeapArray<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for PartialHeapArray<T, N> {
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(self.as_mut_slice());
+        }
+    }
+}
+
+/// Collects the elements into an array. Panics if there are more than fit.
+impl<T, const N: usize> FromIterator<T> for PartialHeapArray<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut arr = Self::new();
+        for elem in iter {
+            if arr.push(elem).is_err() {
+                panic!("more than {} elements were collected into a PartialHeapArray", N);
+            }
         }
+
+        arr
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a PartialHeapArray<T, N> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a mut PartialHeapArray<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
@@ -180,3 +348,112 @@ impl<T: Debug, const N: usize> Debug for PartialHeapArray<T, N> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use super::*;
+
+    #[test]
+    fn pushes_until_full() {
+        let mut arr = PartialArray::<u32, 3>::new();
+        assert!(arr.is_empty());
+        assert_eq!(arr.push(1), Ok(()));
+        assert_eq!(arr.push(2), Ok(()));
+        assert_eq!(arr.push(3), Ok(()));
+        assert_eq!(arr.push(4), Err(4));
+
+        assert!(arr.is_full());
+        assert_eq!(arr.len(), 3);
+        assert_eq!(arr.get_ref(2), Some(&3));
+        assert_eq!(arr.get_ref(3), None);
+    }
+
+    #[test]
+    fn iterates_over_pushed_elements() {
+        let mut arr = PartialArray::<u32, 4>::new();
+        arr.push(1).unwrap();
+        arr.push(2).unwrap();
+
+        assert_eq!(arr.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+
+        for elem in arr.iter_mut() {
+            *elem *= 10;
+        }
+        assert_eq!(arr.as_slice(), &[10, 20]);
+        assert_eq!((&arr).into_iter().count(), 2);
+    }
+
+    #[test]
+    fn collects_from_iterators() {
+        let arr = (0..4).collect::<PartialArray<u32, 4>>();
+        assert_eq!(arr.into_full_array().unwrap(), [0, 1, 2, 3]);
+
+        let arr = (0..2).collect::<PartialHeapArray<u32, 4>>();
+        assert_eq!(arr.as_slice(), &[0, 1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn refuses_to_collect_too_many() {
+        let _ = (0..5).collect::<PartialArray<u32, 4>>();
+    }
+
+    #[test]
+    fn extends_from_slices() {
+        let mut arr = PartialArray::<u32, 4>::new();
+        assert_eq!(arr.extend_from_slice(&[1, 2]), Ok(()));
+        assert_eq!(arr.extend_from_slice(&[3, 4, 5, 6]), Err(&[5, 6][..]));
+        assert_eq!(arr.as_slice(), &[1, 2, 3, 4]);
+        assert_eq!(arr.extend_from_slice(&[]), Ok(()));
+    }
+
+    #[test]
+    fn only_full_arrays_are_returned() {
+        let mut arr = PartialArray::<u32, 2>::new();
+        arr.push(1).unwrap();
+
+        let mut arr = arr.into_full_array().unwrap_err();
+        arr.push(2).unwrap();
+        assert_eq!(arr.into_full_array().unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn drops_pushed_elements_only() {
+        let counted = Rc::new(());
+
+        let mut arr = PartialArray::<Rc<()>, 8>::new();
+        arr.push(counted.clone()).unwrap();
+        arr.push(counted.clone()).unwrap();
+        assert_eq!(Rc::strong_count(&counted), 3);
+
+        drop(arr);
+        assert_eq!(Rc::strong_count(&counted), 1);
+
+        let mut arr = PartialHeapArray::<Rc<()>, 8>::new();
+        arr.extend_from_slice(&[counted.clone(), counted.clone()]).unwrap();
+        assert_eq!(Rc::strong_count(&counted), 3);
+
+        drop(arr);
+        assert_eq!(Rc::strong_count(&counted), 1);
+    }
+
+    #[test]
+    fn full_arrays_are_moved_out_without_dropping() {
+        let counted = Rc::new(());
+
+        let arr = vec![counted.clone(), counted.clone()].into_iter().collect::<PartialArray<_, 2>>();
+        let full = arr.into_full_array().unwrap();
+        assert_eq!(Rc::strong_count(&counted), 3);
+
+        drop(full);
+        assert_eq!(Rc::strong_count(&counted), 1);
+
+        let arr = vec![counted.clone(), counted.clone()].into_iter().collect::<PartialHeapArray<_, 2>>();
+        let full = arr.into_full_array().unwrap();
+        assert_eq!(Rc::strong_count(&counted), 3);
+
+        drop(full);
+        assert_eq!(Rc::strong_count(&counted), 1);
+    }
+}
@@ -1,3 +1,18 @@
+use serde::Deserialize;
+use super::{ BlockDefinition, BlockRegistry, RenderType };
+
+/// What a block sounds like when it is walked on, broken or placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum SoundMaterial {
+    Stone,
+    Glass,
+    Liquid,
+    /// Makes no sound at all, e.g. air.
+    Silent,
+}
+
+/// A block in the world. What it is like depends on its id, and is looked up
+/// in the global `BlockRegistry`.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Block {
     pub metadata: u16,
@@ -5,10 +20,61 @@ pub struct Block {
 }
 
 impl Block {
+    pub const AIR: u16 = 0;
+    pub const STONE: u16 = 1;
+    pub const WATER: u16 = 2;
+    pub const GLASS: u16 = 3;
+
     pub fn new(id: u16) -> Self {
         Self {
             id,
             metadata: 0,
         }
     }
+
+    /// What the block is like.
+    pub fn definition(&self) -> &'static BlockDefinition {
+        BlockRegistry::global().get(self.id)
+    }
+
+    pub fn is_air(&self) -> bool {
+        self.id == Self::AIR
+    }
+
+    /// Translucent blocks let the blocks behind them show through, so they
+    /// neither hide the faces of their neighbours nor get drawn together with
+    /// opaque blocks.
+    pub fn is_translucent(&self) -> bool {
+        self.definition().render == RenderType::Translucent
+    }
+
+    /// Returns true if the block cannot be walked (or flown) through.
+    pub fn is_solid(&self) -> bool {
+        !self.is_air() && self.definition().solid
+    }
+
+    /// Returns true if the block can be swum in.
+    pub fn is_fluid(&self) -> bool {
+        self.definition().fluid
+    }
+
+    pub fn sound_material(&self) -> SoundMaterial {
+        self.definition().sound
+    }
+
+    /// How long (in seconds) the block has to be hit before it breaks, or
+    /// `None` if it cannot be broken at all.
+    pub fn hardness(&self) -> Option<f32> {
+        self.definition().hardness
+    }
+
+    /// How far (in blocks) the light the block gives off reaches.
+    pub fn light_emission(&self) -> u8 {
+        self.definition().light
+    }
+
+    /// Returns true if the block hides the faces of blocks next to it.
+    pub fn is_opaque(&self) -> bool {
+        self.definition().render == RenderType::Opaque
+    }
 }
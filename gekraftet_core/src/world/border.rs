@@ -0,0 +1,60 @@
+use cgmath::Point3;
+use super::*;
+
+/// A square around the origin where the world ends. Nothing is generated or
+/// placed beyond it, and players cannot walk through it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WorldBorder {
+    radius: i32,
+}
+
+impl WorldBorder {
+    /// Creates a border `radius` blocks away from the origin in every
+    /// direction along the X and Z axes.
+    pub fn new(radius: i32) -> Self {
+        Self {
+            radius: radius.max(1),
+        }
+    }
+
+    pub fn radius(&self) -> i32 {
+        self.radius
+    }
+
+    /// Whether the block is inside the border.
+    pub fn contains(&self, at: BlockPos) -> bool {
+        let inside = |v: i32| v >= -self.radius && v < self.radius;
+        inside(at.x) && inside(at.z)
+    }
+
+    /// Whether any part of the chunk is inside the border.
+    pub fn intersects_chunk(&self, at: ChunkPos) -> bool {
+        let (length_x, length_z) = (CHUNK_LENGTH_X as i32, CHUNK_LENGTH_Z as i32);
+        let overlaps = |start: i32, length: i32| start < self.radius && start + length > -self.radius;
+        overlaps(at.x * length_x, length_x) && overlaps(at.z * length_z, length_z)
+    }
+
+    /// How far (in blocks) the point is from the nearest edge of the border.
+    /// Points beyond the border are a negative distance away.
+    pub fn distance(&self, point: Point3<f32>) -> f32 {
+        let radius = self.radius as f32;
+        (radius - point.x.abs()).min(radius - point.z.abs())
+    }
+
+    /// Moves the point back inside the border, at least `margin` blocks away
+    /// from its edges.
+    pub fn clamp(&self, point: Point3<f32>, margin: f32) -> Point3<f32> {
+        let limit = (self.radius as f32 - margin).max(0.0);
+        Point3::new(point.x.max(-limit).min(limit), point.y, point.z.max(-limit).min(limit))
+    }
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self::new(DEFAULT_BORDER_RADIUS)
+    }
+}
+
+// How far the border is from the origin (in blocks) unless it is changed. It
+// is kept well below where floats stop being precise enough to walk on.
+const DEFAULT_BORDER_RADIUS: i32 = 1_000_000;
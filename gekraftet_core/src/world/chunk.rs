@@ -1,12 +1,14 @@
-use std::ops::Deref;
+use std::ops::{ Deref, DerefMut };
 use cgmath::Vector3;
-use crate::utils::{ lerp, PartialArray, PartialHeapArray };
+use crate::utils::{ lerp, morton_decode3, morton_encode3, PartialArray, PartialHeapArray };
 use super::*;
 
 #[derive(Clone, Debug)]
 pub struct Chunk {
     position: ChunkPos,
     sections: [Section; CHUNK_LENGTH_Y / SECTION_LENGTH_Y], 
+    // The height of every column, see height().
+    heights: [[u16; CHUNK_LENGTH_Z]; CHUNK_LENGTH_X],
 }
 
 #[derive(Clone, Debug)]
@@ -14,6 +16,20 @@ pub struct Section {
     blocks: Box<[[[Block; SECTION_LENGTH_Y]; SECTION_LENGTH_X]; SECTION_LENGTH_Z]>,
 }
 
+/// How the blocks of a section are numbered, from 0 up to `SECTION_VOLUME`,
+/// e.g. to keep something about every block in a flat array.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SectionOrder {
+    /// Column by column, like sections hold their blocks: x first, then z,
+    /// then y.
+    Linear,
+    /// Along a Morton curve, which keeps the neighbours of a block near it
+    /// in every direction, not only along y. This only numbers the blocks
+    /// without gaps as long as sections are as long on every side, and that
+    /// length is a power of two.
+    Morton,
+}
+
 impl Chunk {
     pub fn new<A, G>(at: A, noise: &mut Noise<G>) -> Self 
         where A: Into<ChunkPos>,
@@ -30,9 +46,33 @@ impl Chunk {
             sections.push(Section::new(sect, noise)).unwrap();
         };
 
-        Self {
+        let mut chunk = Self {
             position: at,
-            sections: sections.into_full_array().unwrap()
+            sections: sections.into_full_array().unwrap(),
+            heights: [[0; CHUNK_LENGTH_Z]; CHUNK_LENGTH_X],
+        };
+
+        for x in 0..CHUNK_LENGTH_X {
+            for z in 0..CHUNK_LENGTH_Z {
+                chunk.update_height(x, z);
+            }
+        }
+
+        chunk
+    }
+
+    /// Creates a chunk of nothing but air, e.g. to put blocks read from a
+    /// file into.
+    pub fn empty<A: Into<ChunkPos>>(at: A) -> Self {
+        let mut sections = PartialArray::<Section, 16>::new();
+        for _ in 0..CHUNK_LENGTH_Y / SECTION_LENGTH_Y {
+            sections.push(Section::empty()).unwrap();
+        }
+
+        Self {
+            position: at.into(),
+            sections: sections.into_full_array().unwrap(),
+            heights: [[0; CHUNK_LENGTH_Z]; CHUNK_LENGTH_X],
         }
     }
 
@@ -43,6 +83,114 @@ impl Chunk {
     pub fn sections(&self) -> &[Section] {
         self.sections.as_ref()
     }
+
+    /// Returns the block at the given position, relative to the chunk.
+    pub fn block(&self, x: usize, y: usize, z: usize) -> Option<&Block> {
+        let section = self.sections.get(y / SECTION_LENGTH_Y)?;
+        section.get(x)?.get(z)?.get(y % SECTION_LENGTH_Y)
+    }
+
+    /// Returns the block at the given position, relative to the chunk.
+    ///
+    /// The heightmap is not updated for blocks changed through this, use
+    /// `set_block()` where the height of the column may change.
+    pub fn block_mut(&mut self, x: usize, y: usize, z: usize) -> Option<&mut Block> {
+        let section = self.sections.get_mut(y / SECTION_LENGTH_Y)?;
+        section.get_mut(x)?.get_mut(z)?.get_mut(y % SECTION_LENGTH_Y)
+    }
+
+    /// Replaces the block at the given position (relative to the chunk), and
+    /// returns the previous one.
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: Block) -> Option<Block> {
+        let old = std::mem::replace(self.block_mut(x, y, z)?, block);
+        self.update_height(x, z);
+        Some(old)
+    }
+
+    /// Every block of the chunk, column by column: x first, then z, then y.
+    pub fn blocks(&self) -> impl Iterator<Item = &Block> + '_ {
+        (0..CHUNK_LENGTH_X)
+            .flat_map(move |x| (0..CHUNK_LENGTH_Z).map(move |z| (x, z)))
+            .flat_map(move |(x, z)| (0..CHUNK_LENGTH_Y).filter_map(move |y| self.block(x, y, z)))
+    }
+
+    /// Replaces every block of the chunk, taking them in the order `blocks()`
+    /// returns them in. Returns false, and changes nothing, unless there are
+    /// exactly as many blocks as the chunk holds.
+    pub fn replace_blocks(&mut self, blocks: &[Block]) -> bool {
+        if blocks.len() != CHUNK_LENGTH_X * CHUNK_LENGTH_Y * CHUNK_LENGTH_Z {
+            return false
+        }
+
+        let mut blocks = blocks.iter();
+        for x in 0..CHUNK_LENGTH_X {
+            for z in 0..CHUNK_LENGTH_Z {
+                for y in 0..CHUNK_LENGTH_Y {
+                    if let (Some(block), Some(new)) = (self.block_mut(x, y, z), blocks.next()) {
+                        *block = new.clone();
+                    }
+                }
+
+                self.update_height(x, z);
+            }
+        }
+
+        true
+    }
+
+    /// Replaces every block in the column at (x, z), relative to the chunk,
+    /// with air.
+    pub fn clear_column(&mut self, x: usize, z: usize) {
+        for y in 0..CHUNK_LENGTH_Y {
+            if let Some(block) = self.block_mut(x, y, z) {
+                *block = Block::new(Block::AIR);
+            }
+        }
+
+        self.update_height(x, z);
+    }
+
+    /// Returns the height of the column at the given position, relative to
+    /// the chunk: the y just above its highest block that is not air, or zero
+    /// if the column is empty. Everything at or above it sees the sky.
+    pub fn height(&self, x: usize, z: usize) -> Option<u16> {
+        self.heights.get(x)?.get(z).copied()
+    }
+
+    fn update_height(&mut self, x: usize, z: usize) {
+        let height = (0..CHUNK_LENGTH_Y)
+            .rev()
+            .find(|&y| self.block(x, y, z).map_or(false, |b| !b.is_air()))
+            .map_or(0, |y| y + 1);
+
+        self.heights[x][z] = height as u16;
+    }
+}
+
+impl SectionOrder {
+    /// The number of the block at the position, relative to the section.
+    pub fn index(self, x: usize, y: usize, z: usize) -> usize {
+        match self {
+            SectionOrder::Linear => (x * SECTION_LENGTH_Z + z) * SECTION_LENGTH_Y + y,
+            SectionOrder::Morton => morton_encode3(x as u32, y as u32, z as u32) as usize,
+        }
+    }
+
+    /// The position of the block with the number, relative to the section,
+    /// as (x, y, z).
+    pub fn position(self, index: usize) -> (usize, usize, usize) {
+        match self {
+            SectionOrder::Linear => (
+                index / (SECTION_LENGTH_Y * SECTION_LENGTH_Z),
+                index % SECTION_LENGTH_Y,
+                index / SECTION_LENGTH_Y % SECTION_LENGTH_Z,
+            ),
+            SectionOrder::Morton => {
+                let (x, y, z) = morton_decode3(index as u64);
+                (x as usize, y as usize, z as usize)
+            },
+        }
+    }
 }
 
 impl Deref for Section {
@@ -53,7 +201,26 @@ impl Deref for Section {
     }
 }
 
+impl DerefMut for Section {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.blocks
+    }
+}
+
 impl Section {
+    /// Creates a section of nothing but air.
+    pub fn empty() -> Self {
+        // Arrays of blocks can only be repeated as constants, as blocks are
+        // not Copy.
+        const AIR: Block = Block { metadata: 0, id: Block::AIR };
+        const COLUMN: [Block; SECTION_LENGTH_Y] = [AIR; SECTION_LENGTH_Y];
+        const LAYER: [[Block; SECTION_LENGTH_Y]; SECTION_LENGTH_X] = [COLUMN; SECTION_LENGTH_X];
+
+        Self {
+            blocks: Box::new([LAYER; SECTION_LENGTH_Z]),
+        }
+    }
+
     pub fn new<G>(at: SectionPos, noise: &mut Noise<G>) -> Self 
         where G: NoiseGen
     {
@@ -123,9 +290,11 @@ impl Section {
                     };
 
                     let id = if noise + 64.0 - actual_pos.y as f64 > 0.0 {
-                        1
+                        Block::STONE
+                    } else if actual_pos.y < SEA_LEVEL {
+                        Block::WATER
                     } else {
-                        0
+                        Block::AIR
                     };
 
                     bloy.push(Block::new(id)).unwrap();
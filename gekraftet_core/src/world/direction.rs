@@ -0,0 +1,114 @@
+use cgmath::Vector3;
+use super::BlockPos;
+
+/// One of the three axes of the world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    /// The index of the axis into vectors and points.
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// One of the six directions along the axes, which is also the side of a
+/// block facing that way. Up and down are +Y and -Y, north and south are -Z
+/// and +Z, and east and west are +X and -X.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    /// The directions on the horizontal plane.
+    pub const HORIZONTAL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    /// The direction along the axis, towards + if `positive` is true.
+    pub const fn from_axis(axis: Axis, positive: bool) -> Self {
+        match (axis, positive) {
+            (Axis::X, true) => Direction::East,
+            (Axis::X, false) => Direction::West,
+            (Axis::Y, true) => Direction::Up,
+            (Axis::Y, false) => Direction::Down,
+            (Axis::Z, true) => Direction::South,
+            (Axis::Z, false) => Direction::North,
+        }
+    }
+
+    pub const fn axis(self) -> Axis {
+        match self {
+            Direction::East | Direction::West => Axis::X,
+            Direction::Up | Direction::Down => Axis::Y,
+            Direction::North | Direction::South => Axis::Z,
+        }
+    }
+
+    /// Whether the direction points towards + along its axis.
+    pub const fn is_positive(self) -> bool {
+        matches!(self, Direction::Up | Direction::South | Direction::East)
+    }
+
+    pub const fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// A step of one block in the direction.
+    pub fn offset(self) -> Vector3<i32> {
+        let mut offset = Vector3::new(0, 0, 0);
+        offset[self.axis().index()] = match self.is_positive() {
+            true => 1,
+            false => -1,
+        };
+        offset
+    }
+
+    /// Returns the position of the block next to the block at `at` in this
+    /// direction, which is where a block placed against that side goes.
+    pub fn neighbour(self, at: BlockPos) -> BlockPos {
+        BlockPos(at.0 + self.offset())
+    }
+
+    /// The direction as a single bit, for keeping sets of directions in the
+    /// lowest six bits of a byte.
+    pub const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// The direction whose bit (see `bit()`) this is, if it is one.
+    pub fn from_bit(bit: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|direction| direction.bit() == bit)
+    }
+}
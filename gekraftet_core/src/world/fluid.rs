@@ -0,0 +1,89 @@
+use super::*;
+
+// The metadata of a fluid block is how far it is from a source, which is
+// zero for the sources themselves. Fluid falling down starts over at one.
+impl World {
+    /// Lets the fluid at the position settle: flowing fluid that lost what
+    /// fed it dries up, and fluid spreads downwards or, when it cannot fall
+    /// any further, sideways. Changed blocks are pushed into `changed`.
+    pub(super) fn update_fluid(&mut self, at: BlockPos, changed: &mut Vec<BlockPos>) {
+        let block = match self.get_block(at) {
+            Some(block) if block.is_fluid() => block.clone(),
+            _ => return,
+        };
+
+        if block.metadata > 0 {
+            let wanted = self.fed_level(at, block.id);
+
+            if wanted > MAX_FLUID_LEVEL {
+                self.replace(at, Block::new(Block::AIR), changed);
+                return
+            }
+
+            if wanted != block.metadata {
+                // The neighbours are rescheduled by the change, including
+                // this block, so spreading waits for the next tick.
+                self.replace(at, flowing(block.id, wanted), changed);
+                return
+            }
+        }
+
+        let below = Direction::Down.neighbour(at);
+        match self.get_block(below) {
+            Some(b) if b.is_air() => {
+                self.replace(below, flowing(block.id, 1), changed);
+                return
+            },
+            Some(b) if b.is_solid() => {},
+            // Fluid on top of fluid, or at the edge of the loaded world.
+            _ => return,
+        }
+
+        if block.metadata >= MAX_FLUID_LEVEL {
+            return
+        }
+
+        for face in SIDES.iter() {
+            let side = face.neighbour(at);
+
+            if self.get_block(side).map_or(false, Block::is_air) {
+                self.replace(side, flowing(block.id, block.metadata + 1), changed);
+            }
+        }
+    }
+
+    // The level flowing fluid at the position should have, given what is
+    // around it. Fluid right below more fluid is always fed.
+    fn fed_level(&self, at: BlockPos, id: u16) -> u16 {
+        let above = self.get_block(Direction::Up.neighbour(at));
+        if above.map_or(false, |b| b.id == id) {
+            return 1
+        }
+
+        SIDES.iter()
+            .filter_map(|face| self.get_block(face.neighbour(at)))
+            .filter(|b| b.id == id)
+            .map(|b| b.metadata + 1)
+            .min()
+            .unwrap_or(u16::max_value())
+    }
+
+    fn replace(&mut self, at: BlockPos, block: Block, changed: &mut Vec<BlockPos>) {
+        if self.set_block(at, block).is_some() {
+            changed.push(at);
+        }
+    }
+}
+
+fn flowing(id: u16, level: u16) -> Block {
+    Block {
+        id,
+        metadata: level,
+    }
+}
+
+// The directions fluids spread in when they cannot fall.
+const SIDES: [Direction; 4] = Direction::HORIZONTAL;
+
+// How far (in blocks) fluids flow away from their sources.
+const MAX_FLUID_LEVEL: u16 = 7;
@@ -0,0 +1,141 @@
+use std::time::{ Duration, Instant };
+use cgmath::Point3;
+use super::*;
+
+/// The kinds of terrain a world can be generated with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WorldPreset {
+    Default,
+    /// Much taller hills and deeper valleys.
+    Amplified,
+    /// A plain of stone a few blocks above the sea.
+    Flat,
+}
+
+impl WorldPreset {
+    pub const ALL: [WorldPreset; 3] = [
+        WorldPreset::Default,
+        WorldPreset::Amplified,
+        WorldPreset::Flat,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WorldPreset::Default => "Default",
+            WorldPreset::Amplified => "Amplified",
+            WorldPreset::Flat => "Flat",
+        }
+    }
+
+    /// The number the preset is saved as, see `from_id()`.
+    pub fn id(self) -> u8 {
+        match self {
+            WorldPreset::Default => 0,
+            WorldPreset::Amplified => 1,
+            WorldPreset::Flat => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.id() == id)
+    }
+
+    // Without any amplitude, the noise is zero everywhere and the ground is
+    // perfectly flat.
+    fn amplitude(self) -> f64 {
+        match self {
+            WorldPreset::Default => 10.0,
+            WorldPreset::Amplified => 30.0,
+            WorldPreset::Flat => 0.0,
+        }
+    }
+}
+
+impl Default for WorldPreset {
+    fn default() -> Self {
+        WorldPreset::Default
+    }
+}
+
+/// Generates the terrain of a world. The same seed and preset always give
+/// the same chunks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WorldGenerator {
+    seed: u64,
+    preset: WorldPreset,
+    border: WorldBorder,
+}
+
+impl WorldGenerator {
+    pub fn new(seed: u64, preset: WorldPreset) -> Self {
+        Self {
+            seed,
+            preset,
+            border: WorldBorder::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn preset(&self) -> WorldPreset {
+        self.preset
+    }
+
+    pub fn border(&self) -> WorldBorder {
+        self.border
+    }
+
+    /// Moves the border of the world, beyond which nothing is generated.
+    pub fn set_border(&mut self, border: WorldBorder) {
+        self.border = border;
+    }
+
+    /// Generates the chunk at the given position.
+    pub fn generate(&self, at: ChunkPos) -> Chunk {
+        self.generate_timed(at).0
+    }
+
+    /// Generates the chunk at the given position, returning how long every
+    /// stage of generating it took as well.
+    pub fn generate_timed(&self, at: ChunkPos) -> (Chunk, Vec<(&'static str, Duration)>) {
+        let started = Instant::now();
+        let (x, z) = (at.x, at.z);
+        let mut noise = Noise::<Perlin3D>::with_option(
+            NoiseGenOption::new()
+                .octaves(16)
+                .amplitude(self.preset.amplitude())
+                .persistance(0.5)
+                .frequency(628.318530)
+                .lacunarity(0.5),
+            self.seed ^ ((x << 6) ^ (z + 123456)) as u64,
+        );
+        let noise_done = Instant::now();
+
+        let mut chunk = Chunk::new(Point3::<i32>::new(x, 0, z), &mut noise);
+        let terrain_done = Instant::now();
+
+        for local_x in 0..CHUNK_LENGTH_X {
+            for local_z in 0..CHUNK_LENGTH_Z {
+                let column = BlockPos::new(
+                    x * CHUNK_LENGTH_X as i32 + local_x as i32,
+                    0,
+                    z * CHUNK_LENGTH_Z as i32 + local_z as i32,
+                );
+
+                if !self.border.contains(column) {
+                    chunk.clear_column(local_x, local_z);
+                }
+            }
+        }
+
+        let stages = vec![
+            ("noise", noise_done - started),
+            ("terrain", terrain_done - noise_done),
+            ("border", terrain_done.elapsed()),
+        ];
+
+        (chunk, stages)
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::{ BTreeMap, HashSet };
+use crate::events::WorldEvent;
+use super::*;
+
+/// The loaded part of a world: a set of chunks, indexed by their positions.
+#[derive(Clone, Debug)]
+pub struct World {
+    chunks: ChunkMap<Chunk>,
+    time: WorldTime,
+    // Blocks waiting to be ticked, by the tick they are due on.
+    scheduled: BTreeMap<u64, HashSet<BlockPos>>,
+    weather: Weather,
+    // The tick the weather changes on.
+    weather_change: u64,
+    border: WorldBorder,
+    // What happened since the events were last taken.
+    events: Vec<WorldEvent>,
+    // The chunks whose blocks changed since they were last saved.
+    modified: HashSet<ChunkPos>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            chunks: ChunkMap::default(),
+            time: WorldTime::default(),
+            scheduled: BTreeMap::new(),
+            weather: Weather::Clear,
+            weather_change: Weather::Clear.duration(weather::scramble(0)),
+            border: WorldBorder::default(),
+            events: Vec::new(),
+            modified: HashSet::new(),
+        }
+    }
+
+    pub fn time(&self) -> WorldTime {
+        self.time
+    }
+
+    pub fn set_time(&mut self, time: WorldTime) {
+        self.time = time;
+    }
+
+    pub fn border(&self) -> WorldBorder {
+        self.border
+    }
+
+    /// Moves the border of the world. Blocks already beyond it are left
+    /// alone, but no more can be placed there.
+    pub fn set_border(&mut self, border: WorldBorder) {
+        self.border = border;
+    }
+
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+
+    /// Changes the weather for the given number of ticks, after which it
+    /// changes on its own again.
+    pub fn set_weather(&mut self, weather: Weather, duration: u64) {
+        self.weather = weather;
+        self.weather_change = self.time.0 + duration;
+    }
+
+    /// Advances the world by a single tick: the weather changes when it is
+    /// time to, and the block ticks that are due are run. Returns the
+    /// positions of the blocks that changed.
+    pub fn tick(&mut self) -> Vec<BlockPos> {
+        self.time.0 += 1;
+
+        if self.time.0 >= self.weather_change {
+            let (weather, duration) = self.weather.next(weather::scramble(self.time.0));
+            self.set_weather(weather, duration);
+        }
+
+        let later = self.scheduled.split_off(&(self.time.0 + 1));
+        let due = std::mem::replace(&mut self.scheduled, later);
+
+        let mut changed = Vec::new();
+        for at in due.into_values().flatten() {
+            self.tick_block(at, &mut changed);
+        }
+
+        changed
+    }
+
+    /// Makes the block at the given position get ticked after `delay` ticks
+    /// (at least one). Blocks in chunks that are unloaded by then are skipped.
+    pub fn schedule_tick(&mut self, at: BlockPos, delay: u64) {
+        self.scheduled
+            .entry(self.time.0 + delay.max(1))
+            .or_insert_with(HashSet::new)
+            .insert(at);
+    }
+
+    /// Adds a chunk to the world, returning the chunk it replaces (if any).
+    pub fn insert_chunk(&mut self, chunk: Chunk) -> Option<Chunk> {
+        self.events.push(WorldEvent::ChunkLoaded(chunk.position()));
+        self.chunks.insert(chunk.position(), chunk)
+    }
+
+    /// Returns the blocks that changed and the chunks that were loaded since
+    /// the last call, in the order they happened. These pile up until they
+    /// are taken, which `EventBus::dispatch()` does.
+    pub fn take_events(&mut self) -> Vec<WorldEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn remove_chunk(&mut self, at: ChunkPos) -> Option<Chunk> {
+        self.chunks.remove(&at)
+    }
+
+    pub fn chunk(&self, at: ChunkPos) -> Option<&Chunk> {
+        self.chunks.get(&at)
+    }
+
+    pub fn chunk_mut(&mut self, at: ChunkPos) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&at)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
+    }
+
+    /// The loaded chunks whose blocks changed since `mark_saved()` was last
+    /// called, which are those that have to be saved.
+    pub fn modified_chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.modified.iter().filter_map(move |at| self.chunks.get(at))
+    }
+
+    /// Forgets which chunks changed, once they have been saved.
+    pub fn mark_saved(&mut self) {
+        self.modified.clear();
+    }
+
+    /// Returns the block at the given position, or `None` if the chunk it is
+    /// in is not loaded.
+    pub fn get_block(&self, at: BlockPos) -> Option<&Block> {
+        let chunk = self.chunks.get(&ChunkPos::from(at))?;
+        let (x, y, z) = Self::local_position(at);
+        chunk.block(x, y, z)
+    }
+
+    /// Returns the height of the column of blocks at (x, z), see
+    /// `Chunk::height()`. Returns `None` if the chunk is not loaded.
+    pub fn height(&self, x: i32, z: i32) -> Option<i32> {
+        let at = BlockPos::new(x, 0, z);
+        let chunk = self.chunks.get(&ChunkPos::from(at))?;
+        let (x, _, z) = Self::local_position(at);
+        chunk.height(x, z).map(i32::from)
+    }
+
+    /// Looks for somewhere safe to spawn, as close to (x, z) as possible but
+    /// no further than `radius` blocks away on either axis: the top of a
+    /// column of solid ground, not covered by water. Only loaded columns are
+    /// considered, so `None` may just mean the ground is not generated yet.
+    pub fn find_spawn(&self, x: i32, z: i32, radius: i32) -> Option<BlockPos> {
+        (0..=radius).find_map(|ring| {
+            let edge = -ring..=ring;
+
+            edge.clone()
+                .flat_map(|dx| edge.clone().map(move |dz| (dx, dz)))
+                .filter(|&(dx, dz)| dx.abs() == ring || dz.abs() == ring)
+                .find_map(|(dx, dz)| self.spawn_in_column(x + dx, z + dz))
+        })
+    }
+
+    fn spawn_in_column(&self, x: i32, z: i32) -> Option<BlockPos> {
+        let height = self.height(x, z)?;
+        let ground = self.get_block(BlockPos::new(x, height - 1, z))?;
+
+        match ground.is_solid() {
+            true => Some(BlockPos::new(x, height, z)),
+            false => None,
+        }
+    }
+
+    /// Replaces the block at the given position, returning the previous one.
+    /// Returns `None` (and does nothing) if the chunk is not loaded, or the
+    /// position is beyond the border.
+    pub fn set_block(&mut self, at: BlockPos, block: Block) -> Option<Block> {
+        if !self.border.contains(at) {
+            return None
+        }
+
+        let chunk = self.chunks.get_mut(&ChunkPos::from(at))?;
+        let (x, y, z) = Self::local_position(at);
+        let old = chunk.set_block(x, y, z, block.clone())?;
+        self.events.push(WorldEvent::BlockChanged { at, old: old.clone(), new: block });
+        self.modified.insert(ChunkPos::from(at));
+
+        // Fluids next to the block may have to flow in or dry up.
+        let around = std::iter::once(at).chain(Direction::ALL.iter().map(|f| f.neighbour(at)));
+        for at in around {
+            if self.get_block(at).map_or(false, Block::is_fluid) {
+                self.schedule_tick(at, FLUID_TICK_DELAY);
+            }
+        }
+
+        Some(old)
+    }
+
+    fn tick_block(&mut self, at: BlockPos, changed: &mut Vec<BlockPos>) {
+        match self.get_block(at) {
+            Some(block) if block.is_fluid() => self.update_fluid(at, changed),
+            _ => {},
+        }
+    }
+
+    // The position of the block within its chunk.
+    fn local_position(at: BlockPos) -> (usize, usize, usize) {
+        (
+            at.x.rem_euclid(CHUNK_LENGTH_X as i32) as usize,
+            at.y.rem_euclid(CHUNK_LENGTH_Y as i32) as usize,
+            at.z.rem_euclid(CHUNK_LENGTH_Z as i32) as usize,
+        )
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// How many ticks fluids take to flow by a block.
+const FLUID_TICK_DELAY: u64 = 5;
@@ -1,11 +1,30 @@
 mod block;
+mod border;
 mod chunk;
+mod direction;
+mod fluid;
+mod generator;
+mod map;
 mod noise;
 mod position;
+mod raycast;
+mod registry;
+mod schematic;
+mod tick;
+mod weather;
 
 pub use block::*;
+pub use border::WorldBorder;
 pub use chunk::*;
+pub use direction::*;
+pub use generator::*;
+pub use map::*;
 pub use position::*;
+pub use raycast::*;
+pub use registry::*;
+pub use schematic::{ Schematic, SchematicError };
+pub use tick::*;
+pub use weather::Weather;
 pub use noise::*;
 
 pub const CHUNK_LENGTH_X: usize = 16;
@@ -14,6 +33,10 @@ pub const CHUNK_LENGTH_Z: usize = 16;
 pub const SECTION_LENGTH_X: usize = 16;
 pub const SECTION_LENGTH_Y: usize = 16;
 pub const SECTION_LENGTH_Z: usize = 16;
+pub const SECTION_VOLUME: usize = SECTION_LENGTH_X * SECTION_LENGTH_Y * SECTION_LENGTH_Z;
+
+// Every empty block below this height is filled with water.
+pub const SEA_LEVEL: i32 = 60;
 
 // This is used for world generation - for X it means 2 samples for every
 // SECTION_LENGTH_X blocks. The samples are then interpolated using trilinear
@@ -1,27 +1,35 @@
+use std::collections::HashMap;
+use std::hash::{ BuildHasherDefault, Hash, Hasher };
 use std::ops::{ Deref, DerefMut };
 use cgmath::{ Point2, Point3 };
+use crate::utils::morton_encode2;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct BlockPos(pub Point3<i32>);
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct ChunkPos(pub Point3<i32>);
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct SectionPos(pub Point3<i32>);
 
+// Blocks at negative coordinates belong to the chunk below them, so these
+// round towards negative infinity rather than towards zero.
 impl From<BlockPos> for ChunkPos {
     fn from(b: BlockPos) -> Self {
-        let x = (b.0).x / 16;
-        let y = (b.0).y / 256;
-        let z = (b.0).z / 16;
+        let x = (b.0).x.div_euclid(16);
+        let y = (b.0).y.div_euclid(256);
+        let z = (b.0).z.div_euclid(16);
         Self(Point3::<i32>::new(x, y, z))
     }
 }
 
 impl From<BlockPos> for SectionPos {
     fn from(b: BlockPos) -> Self {
-        Self(b.0 / 16)
+        let x = (b.0).x.div_euclid(16);
+        let y = (b.0).y.div_euclid(16);
+        let z = (b.0).z.div_euclid(16);
+        Self(Point3::<i32>::new(x, y, z))
     }
 }
 
@@ -62,6 +70,51 @@ impl ChunkPos {
     pub fn new(x: i32, y: i32, z: i32) -> Self {
         Self(Point3::<i32>::new(x, y, z))
     }
+
+    /// The Morton code of the column of the chunk, as chunks are as high as
+    /// the world: x and z are interleaved, and y goes into the top bits.
+    /// Chunks near each other mostly get codes near each other.
+    pub fn morton_code(&self) -> u64 {
+        morton_encode2(self.0.x as u32, self.0.z as u32) ^ (self.0.y as u64) << 48
+    }
+}
+
+// Chunks are hashed by their Morton code, see `ChunkMap`.
+impl Hash for ChunkPos {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.morton_code());
+    }
+}
+
+/// A hash map keyed by chunks, which keeps chunks near each other mostly near
+/// each other in memory too.
+pub type ChunkMap<V> = HashMap<ChunkPos, V, BuildHasherDefault<ChunkHasher>>;
+
+/// Hashes chunks by their Morton code, see `ChunkMap`. Anything else is
+/// hashed byte by byte, like FNV-1a does.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChunkHasher(u64);
+
+impl Hasher for ChunkHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    // Hash maps pick where a key goes by the low bits of its hash, which are
+    // left as they are, so that chunks near each other go near each other.
+    // The top bits tell the keys that went to the same place apart, so they
+    // are mixed from the whole code.
+    fn write_u64(&mut self, code: u64) {
+        let low = code & LOW_BITS;
+        let high = code.wrapping_mul(MIX) & !LOW_BITS;
+        self.0 = self.0.rotate_left(ROTATION) ^ (low | high);
+    }
 }
 
 impl SectionPos {
@@ -111,3 +164,68 @@ impl DerefMut for SectionPos {
         &mut self.0
     }
 }
+
+// How many of the low bits of a Morton code are kept as they are when it is
+// hashed, and what the code is multiplied by to mix the others (the golden
+// ratio, in 64 bits).
+const LOW_BITS: u64 = (1 << 57) - 1;
+const MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// How far what was hashed before is rotated when a code follows it, and the
+// prime of FNV-1a, for keys that are not chunks.
+const ROTATION: u32 = 5;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::hash::BuildHasher;
+    use crate::utils::morton_decode2;
+
+    // The chunks around the origin, on both sides of every axis.
+    fn chunks_around_origin() -> impl Iterator<Item = ChunkPos> {
+        (-8..8).flat_map(|x| {
+            (-2..2).flat_map(move |y| (-8..8).map(move |z| ChunkPos::new(x, y, z)))
+        })
+    }
+
+    #[test]
+    fn morton_code_round_trip() {
+        for chunk in chunks_around_origin().chain(Some(ChunkPos::new(i32::MIN, 0, i32::MAX))) {
+            let code = chunk.morton_code() ^ (chunk.y as u64) << 48;
+            assert_eq!(morton_decode2(code), (chunk.x as u32, chunk.z as u32));
+        }
+    }
+
+    #[test]
+    fn nearby_chunks_hash_apart() {
+        let hasher = BuildHasherDefault::<ChunkHasher>::default();
+        let hashes = chunks_around_origin()
+            .map(|chunk| {
+                let mut state = hasher.build_hasher();
+                chunk.hash(&mut state);
+                state.finish()
+            })
+            .collect::<HashSet<_>>();
+
+        assert_eq!(hashes.len(), chunks_around_origin().count());
+    }
+
+    #[test]
+    fn chunk_map_negative_coordinates() {
+        let mut map = ChunkMap::default();
+        for chunk in chunks_around_origin() {
+            assert!(map.insert(chunk, chunk.x * 100 + chunk.z).is_none());
+        }
+
+        assert_eq!(map.len(), chunks_around_origin().count());
+        for chunk in chunks_around_origin() {
+            assert_eq!(map.get(&chunk), Some(&(chunk.x * 100 + chunk.z)));
+        }
+
+        assert_eq!(map.remove(&ChunkPos::new(-1, -1, -1)), Some(-101));
+        assert!(!map.contains_key(&ChunkPos::new(-1, -1, -1)));
+        assert!(map.contains_key(&ChunkPos::new(1, 1, 1)));
+    }
+}
@@ -0,0 +1,97 @@
+use cgmath::Point3;
+use crate::utils::Ray;
+use super::*;
+
+/// Where a ray hit a block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaycastHit {
+    pub block: BlockPos,
+    pub face: Direction,
+    /// The exact point the ray entered the block at.
+    pub point: Point3<f32>,
+    /// How far the point is from the origin of the ray.
+    pub distance: f32,
+}
+
+impl World {
+    /// Casts the ray through the world and returns the first solid block it
+    /// hits within `max_distance`. Positions are in blocks, where the block
+    /// at (x, y, z) spans from (x, y, z) to (x + 1, y + 1, z + 1). Blocks in
+    /// chunks that are not loaded are treated as empty.
+    ///
+    /// If the ray starts inside a solid block, that block is returned, with
+    /// the face pointing against the ray.
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<RaycastHit> {
+        // The ray has to end somewhere, or it could go on forever.
+        if !max_distance.is_finite() || max_distance < 0.0 {
+            return None
+        }
+
+        let Ray { origin, direction } = *ray;
+        let mut block = origin.map(|v| v.floor() as i32);
+
+        // For every axis: which way the ray steps, how far along the ray the
+        // next block boundary is, and how far apart the boundaries are.
+        let mut step = [0; 3];
+        let mut next_boundary = [f32::INFINITY; 3];
+        let mut boundary_distance = [f32::INFINITY; 3];
+
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                continue
+            }
+
+            step[axis] = direction[axis].signum() as i32;
+            boundary_distance[axis] = direction[axis].recip().abs();
+
+            let boundary = match step[axis] > 0 {
+                true => block[axis] as f32 + 1.0,
+                false => block[axis] as f32,
+            };
+            next_boundary[axis] = (boundary - origin[axis]) / direction[axis];
+        }
+
+        // Before the first step, the face is the one facing back at the ray.
+        let first_axis = (0..3)
+            .max_by(|&a, &b| direction[a].abs().partial_cmp(&direction[b].abs()).unwrap())
+            .unwrap();
+        let mut face = entered_along(first_axis, step[first_axis]);
+        let mut distance = 0.0;
+
+        loop {
+            let solid = self.get_block(BlockPos(block))
+                .map(|b| b.is_solid())
+                .unwrap_or(false);
+
+            if solid {
+                return Some(RaycastHit {
+                    block: BlockPos(block),
+                    face,
+                    point: ray.at(distance),
+                    distance,
+                })
+            }
+
+            // Step into the next block along the axis whose boundary is the
+            // closest.
+            let axis = (0..3)
+                .min_by(|&a, &b| next_boundary[a].partial_cmp(&next_boundary[b]).unwrap())
+                .unwrap();
+
+            distance = next_boundary[axis];
+            if distance > max_distance {
+                return None
+            }
+
+            block[axis] += step[axis];
+            next_boundary[axis] += boundary_distance[axis];
+            face = entered_along(axis, step[axis]);
+        }
+    }
+}
+
+// The side of a block a ray entering it along an axis hits. `step` is the
+// direction the ray is travelling in along that axis.
+fn entered_along(axis: usize, step: i32) -> Direction {
+    Direction::from_axis(Axis::ALL[axis], step <= 0)
+}
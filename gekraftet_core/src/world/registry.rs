@@ -0,0 +1,210 @@
+use std::{ fmt, fs, io };
+use std::path::Path;
+use std::sync::OnceLock;
+use serde::Deserialize;
+use super::{ Block, SoundMaterial };
+
+/// How the faces of a block are drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum RenderType {
+    /// Not drawn at all, e.g. air.
+    Invisible,
+    /// Hides whatever is behind it.
+    Opaque,
+    /// Lets the blocks behind it show through, so it neither hides the faces
+    /// of its neighbours nor gets drawn together with opaque blocks.
+    Translucent,
+}
+
+/// What a kind of block is like. Every field but the id and the name may be
+/// left out of a definition, taking its value from the default definition:
+/// an opaque, solid and breakable block.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlockDefinition {
+    pub id: u16,
+    pub name: String,
+    /// The color the block is drawn with, as RGBA from 0.0 to 1.0.
+    pub color: [f32; 4],
+    /// How far (in blocks) the light the block gives off reaches, from 0 to
+    /// 15. Most blocks give off none.
+    pub light: u8,
+    /// How long (in seconds) the block has to be hit before it breaks, or
+    /// `None` if it cannot be broken at all.
+    pub hardness: Option<f32>,
+    /// Whether the block cannot be walked (or flown) through.
+    pub solid: bool,
+    /// Whether the block can be swum in.
+    pub fluid: bool,
+    pub render: RenderType,
+    pub sound: SoundMaterial,
+}
+
+impl Default for BlockDefinition {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            color: [0.9, 0.9, 0.9, 1.0],
+            light: 0,
+            hardness: Some(0.75),
+            solid: true,
+            fluid: false,
+            render: RenderType::Opaque,
+            sound: SoundMaterial::Stone,
+        }
+    }
+}
+
+/// The definitions of every kind of block, by id. They are read from a RON
+/// file when the game starts, so that blocks can be added without
+/// rebuilding it. The blocks the world generator uses have to be defined.
+#[derive(Clone, Debug)]
+pub struct BlockRegistry {
+    // Indexed by id, as blocks are looked up all the time.
+    definitions: Vec<Option<BlockDefinition>>,
+    // What blocks of an id that is not defined are like.
+    unknown: BlockDefinition,
+}
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    /// Two definitions have the same id.
+    Duplicate(u16),
+    /// A block the game cannot do without is not defined.
+    Missing(u16),
+    /// A block gives off more light than the maximum of 15.
+    InvalidLight(u16),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::Io(e) => write!(f, "unable to access the block definitions: {}", e),
+            RegistryError::Parse(e) => write!(f, "invalid block definitions: {}", e),
+            RegistryError::Duplicate(id) => write!(f, "block {} is defined twice", id),
+            RegistryError::Missing(id) => write!(f, "block {} has to be defined", id),
+            RegistryError::InvalidLight(id) => write!(f, "block {} gives off more light than {}", id, MAX_LIGHT),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl From<io::Error> for RegistryError {
+    fn from(e: io::Error) -> Self {
+        RegistryError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for RegistryError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        RegistryError::Parse(e)
+    }
+}
+
+impl BlockRegistry {
+    /// The blocks the game comes with.
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_BLOCKS).expect("the built-in block definitions are invalid")
+    }
+
+    /// Parses a RON list of block definitions.
+    pub fn parse(text: &str) -> Result<Self, RegistryError> {
+        let list: Vec<BlockDefinition> = ron::from_str(text)?;
+
+        let mut definitions = Vec::new();
+        for definition in list {
+            if definition.light > MAX_LIGHT {
+                return Err(RegistryError::InvalidLight(definition.id))
+            }
+
+            let id = definition.id as usize;
+            if id >= definitions.len() {
+                definitions.resize(id + 1, None);
+            }
+            if definitions[id].is_some() {
+                return Err(RegistryError::Duplicate(definition.id))
+            }
+            definitions[id] = Some(definition);
+        }
+
+        for &id in [Block::AIR, Block::STONE, Block::WATER, Block::GLASS].iter() {
+            if definitions.get(id as usize).map_or(true, Option::is_none) {
+                return Err(RegistryError::Missing(id))
+            }
+        }
+
+        Ok(Self {
+            definitions,
+            unknown: BlockDefinition {
+                name: "unknown".to_owned(),
+                color: [1.0, 0.0, 1.0, 1.0],
+                ..BlockDefinition::default()
+            },
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RegistryError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Loads the definitions from the file. If there is no file yet, the
+    /// built-in definitions are written to it, to be changed from there.
+    pub fn load_or_create<P: AsRef<Path>>(path: P) -> Result<Self, RegistryError> {
+        match Self::load(path.as_ref()) {
+            Err(RegistryError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {
+                fs::write(path, BUILTIN_BLOCKS)?;
+                Ok(Self::builtin())
+            },
+            result => result,
+        }
+    }
+
+    /// Returns the definition of the block, or what unknown blocks are like
+    /// if it is not defined.
+    pub fn get(&self, id: u16) -> &BlockDefinition {
+        match self.definitions.get(id as usize) {
+            Some(Some(definition)) => definition,
+            _ => &self.unknown,
+        }
+    }
+
+    /// Every block that is defined, to be changed before the registry is
+    /// installed.
+    pub fn definitions_mut(&mut self) -> impl Iterator<Item = &mut BlockDefinition> + '_ {
+        self.definitions.iter_mut().flatten()
+    }
+
+    /// Looks a block up by its name, regardless of case.
+    pub fn find(&self, name: &str) -> Option<&BlockDefinition> {
+        self.definitions
+            .iter()
+            .flatten()
+            .find(|definition| definition.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Makes these the definitions every block is looked up in. This only
+    /// works once, and before any block is looked at; afterwards the
+    /// registry is given back.
+    pub fn install(self) -> Result<(), Self> {
+        REGISTRY.set(self)
+    }
+
+    /// The definitions every block is looked up in, which are the built-in
+    /// ones unless others were installed first.
+    pub fn global() -> &'static Self {
+        REGISTRY.get_or_init(Self::builtin)
+    }
+}
+
+static REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+
+// The definitions the game comes with, written out where the definitions are
+// loaded from if they are missing.
+const BUILTIN_BLOCKS: &'static str = include_str!("blocks.ron");
+
+// The most light a block may give off.
+const MAX_LIGHT: u8 = 15;
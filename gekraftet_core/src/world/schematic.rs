@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::{ fmt, fs, io };
+use std::io::{ Read, Write };
+use std::path::Path;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use crate::nbt::{ self, NamedBinaryTag, ValueData };
+use super::*;
+
+/// A box of blocks that can be copied out of a world and pasted into another,
+/// and kept in a file in the Sponge schematic format (`.schem`), which other
+/// tools read and write as well.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schematic {
+    width: u16,
+    height: u16,
+    length: u16,
+    // Ordered by y, then z, then x.
+    blocks: Vec<Block>,
+    // The names of the blocks in the file that are not defined, and were
+    // read as air.
+    unknown: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum SchematicError {
+    Io(io::Error),
+    Nbt(nbt::Error),
+    /// The tags are not those of a schematic.
+    Invalid(&'static str),
+    UnsupportedVersion(i32),
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchematicError::Io(e) => write!(f, "{}", e),
+            SchematicError::Nbt(e) => write!(f, "invalid schematic: {}", e),
+            SchematicError::Invalid(reason) => write!(f, "invalid schematic: {}", reason),
+            SchematicError::UnsupportedVersion(version) => write!(f, "schematics of version {} are not supported", version),
+        }
+    }
+}
+
+impl std::error::Error for SchematicError {}
+
+impl From<io::Error> for SchematicError {
+    fn from(e: io::Error) -> Self {
+        SchematicError::Io(e)
+    }
+}
+
+impl From<nbt::Error> for SchematicError {
+    fn from(e: nbt::Error) -> Self {
+        SchematicError::Nbt(e)
+    }
+}
+
+impl Schematic {
+    /// Copies the blocks in the box between the two corners, which are both
+    /// part of it. Returns `None` if part of the box is not loaded, or it is
+    /// larger than a schematic can be.
+    pub fn copy(world: &World, a: BlockPos, b: BlockPos) -> Option<Self> {
+        let min = BlockPos::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let max = BlockPos::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+
+        let size = |min: i32, max: i32| u16::try_from(max - min + 1).ok();
+        let (width, height, length) = (size(min.x, max.x)?, size(min.y, max.y)?, size(min.z, max.z)?);
+
+        if width as usize * height as usize * length as usize > MAX_VOLUME {
+            return None
+        }
+
+        let mut blocks = Vec::with_capacity(width as usize * height as usize * length as usize);
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    blocks.push(world.get_block(BlockPos::new(x, y, z))?.clone());
+                }
+            }
+        }
+
+        Some(Self {
+            width,
+            height,
+            length,
+            blocks,
+            unknown: Vec::new(),
+        })
+    }
+
+    /// The size of the box along x, y and z.
+    pub fn size(&self) -> (u16, u16, u16) {
+        (self.width, self.height, self.length)
+    }
+
+    /// The names of the blocks in the file the schematic was read from that
+    /// are not defined. They were read as air.
+    pub fn unknown_blocks(&self) -> &[String] {
+        &self.unknown
+    }
+
+    /// Returns the block at the position within the box.
+    pub fn get_block(&self, x: u16, y: u16, z: u16) -> Option<&Block> {
+        match x < self.width && y < self.height && z < self.length {
+            true => self.blocks.get(self.index(x, y, z)),
+            false => None,
+        }
+    }
+
+    /// Puts the blocks into the world, with the corner of the box that was
+    /// nearest to negative infinity at `at`. Air is pasted as well, so that
+    /// the box looks exactly as it did. Blocks in chunks that are not loaded
+    /// or beyond the border are left out. Returns how many blocks changed.
+    pub fn paste(&self, world: &mut World, at: BlockPos) -> usize {
+        let mut changed = 0;
+
+        for y in 0..self.height {
+            for z in 0..self.length {
+                for x in 0..self.width {
+                    let block = &self.blocks[self.index(x, y, z)];
+                    let position = BlockPos::new(at.x + x as i32, at.y + y as i32, at.z + z as i32);
+
+                    match world.get_block(position) {
+                        Some(old) if old != block => {},
+                        _ => continue,
+                    }
+
+                    if world.set_block(position, block.clone()).is_some() {
+                        changed += 1;
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Reads a schematic of version 1, 2 or 3, as written by WorldEdit and
+    /// others. Blocks are looked up by their names without the namespace or
+    /// the block state, e.g. `minecraft:glass_pane[east=true]` as
+    /// `glass_pane`.
+    pub fn from_nbt(nbt: &NamedBinaryTag) -> Result<Self, SchematicError> {
+        let root = nbt.root();
+        // Version 3 puts everything into a compound of its own.
+        let root = match root.get("Schematic").and_then(ValueData::compound) {
+            Some(schematic) => schematic,
+            None => root,
+        };
+
+        let version = *root.get("Version").and_then(ValueData::int).ok_or(SchematicError::Invalid("no version"))?;
+        let (palette, data) = match version {
+            1 | 2 => (root.get("Palette"), root.get("BlockData")),
+            3 => {
+                let blocks = root.get("Blocks").and_then(ValueData::compound);
+                (blocks.and_then(|b| b.get("Palette")), blocks.and_then(|b| b.get("Data")))
+            },
+            version => return Err(SchematicError::UnsupportedVersion(version)),
+        };
+
+        let size = |name| root
+            .get(name)
+            .and_then(ValueData::short)
+            .map(|&size| size as u16)
+            .ok_or(SchematicError::Invalid("no size"));
+        let (width, height, length) = (size("Width")?, size("Height")?, size("Length")?);
+
+        let palette = palette.and_then(ValueData::compound).ok_or(SchematicError::Invalid("no palette"))?;
+        let data = data.and_then(ValueData::byte_array).ok_or(SchematicError::Invalid("no block data"))?;
+
+        let registry = BlockRegistry::global();
+        let mut unknown = Vec::new();
+        let mut ids = HashMap::new();
+        for (name, index) in palette {
+            let index = *index.int().ok_or(SchematicError::Invalid("invalid palette"))?;
+            let id = match registry.find(block_name(name)) {
+                Some(definition) => definition.id,
+                None => {
+                    unknown.push(name.clone());
+                    Block::AIR
+                },
+            };
+            ids.insert(index, id);
+        }
+        unknown.sort();
+
+        let volume = width as usize * height as usize * length as usize;
+        let mut blocks = Vec::with_capacity(volume);
+        let mut bytes = data.iter().map(|&b| b as u8);
+        while blocks.len() < volume {
+            let index = read_varint(&mut bytes).ok_or(SchematicError::Invalid("not enough block data"))?;
+            let id = *ids.get(&index).ok_or(SchematicError::Invalid("block not in the palette"))?;
+            blocks.push(Block::new(id));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            length,
+            blocks,
+            unknown,
+        })
+    }
+
+    /// Writes the schematic as version 2, which most tools read. Blocks are
+    /// named as in Minecraft where there is such a block, and are in the
+    /// `gekraftet` namespace otherwise.
+    pub fn to_nbt(&self) -> NamedBinaryTag {
+        let mut palette = HashMap::new();
+        let mut data = Vec::with_capacity(self.blocks.len());
+
+        for block in self.blocks.iter() {
+            let name = block_state(block);
+            let count = palette.len() as i32;
+            let index = *palette.entry(name).or_insert(count);
+            write_varint(&mut data, index);
+        }
+
+        let palette_max = palette.len() as i32;
+        let palette = palette
+            .into_iter()
+            .map(|(name, index)| (name, ValueData::Int(index)))
+            .collect();
+
+        let mut nbt = NamedBinaryTag::new();
+        nbt.set_name("Schematic".to_owned());
+        nbt.insert("Version".to_owned(), ValueData::Int(2));
+        nbt.insert("DataVersion".to_owned(), ValueData::Int(DATA_VERSION));
+        nbt.insert("Width".to_owned(), ValueData::Short(self.width as i16));
+        nbt.insert("Height".to_owned(), ValueData::Short(self.height as i16));
+        nbt.insert("Length".to_owned(), ValueData::Short(self.length as i16));
+        nbt.insert("Offset".to_owned(), ValueData::IntArray(vec![0, 0, 0]));
+        nbt.insert("PaletteMax".to_owned(), ValueData::Int(palette_max));
+        nbt.insert("Palette".to_owned(), ValueData::Compound(palette));
+        nbt.insert("BlockData".to_owned(), ValueData::ByteArray(data.into_iter().map(|b| b as i8).collect()));
+        nbt
+    }
+
+    /// Reads a schematic file, which is usually compressed with gzip.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SchematicError> {
+        let bytes = fs::read(path)?;
+
+        let bytes = match bytes.starts_with(&GZIP_MAGIC) {
+            true => {
+                let mut decompressed = Vec::new();
+                GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+                decompressed
+            },
+            false => bytes,
+        };
+
+        Self::from_nbt(&NamedBinaryTag::from_binary(&bytes)?)
+    }
+
+    /// Writes the schematic to a file, compressed with gzip.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SchematicError> {
+        let bytes = self.to_nbt().to_binary()?;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&bytes)?;
+        fs::write(path, encoder.finish()?)?;
+        Ok(())
+    }
+
+    fn index(&self, x: u16, y: u16, z: u16) -> usize {
+        (y as usize * self.length as usize + z as usize) * self.width as usize + x as usize
+    }
+}
+
+// The name of a block in a palette, without the namespace or the state.
+fn block_name(state: &str) -> &str {
+    let name = state.split('[').next().unwrap_or(state);
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+// The name a block is written to a palette with.
+fn block_state(block: &Block) -> String {
+    let name = &block.definition().name;
+
+    match MINECRAFT_BLOCKS.contains(&name.as_str()) {
+        true => format!("minecraft:{}", name),
+        false => format!("gekraftet:{}", name),
+    }
+}
+
+// Reads an unsigned LEB128 number, as the indices of the blocks are written.
+fn read_varint<I: Iterator<Item = u8>>(bytes: &mut I) -> Option<i32> {
+    let mut value = 0;
+
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7F) as i32) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value)
+        }
+    }
+
+    None
+}
+
+fn write_varint(bytes: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        match value {
+            0 => return bytes.push(byte),
+            _ => bytes.push(byte | 0x80),
+        }
+    }
+}
+
+// The blocks that are named the same in Minecraft, and are written in its
+// namespace so that other tools know them.
+const MINECRAFT_BLOCKS: &[&str] = &["air", "stone", "water", "glass"];
+
+// The version of Minecraft the blocks are named after (1.16.5), which version
+// 2 requires.
+const DATA_VERSION: i32 = 2586;
+
+// The largest box that can be copied, so that a typo does not copy the whole
+// world.
+const MAX_VOLUME: usize = 256 * 256 * 256;
+
+// The bytes every gzip stream starts with.
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
@@ -0,0 +1,74 @@
+use crate::utils::FixedTimestep;
+
+/// Turns the time passed between frames into a number of fixed-length ticks,
+/// so that the world runs at the same rate whatever the frame rate is.
+#[derive(Clone, Debug)]
+pub struct TickClock {
+    timestep: FixedTimestep,
+}
+
+impl TickClock {
+    pub fn new() -> Self {
+        Self {
+            timestep: FixedTimestep::new(TICK_LENGTH).max_steps_per_frame(DEFAULT_MAX_TICKS_PER_FRAME),
+        }
+    }
+
+    /// How many ticks a single frame may catch up on at most. If the game
+    /// falls further behind than that (e.g. after a hitch), the rest of the
+    /// backlog is dropped and the world slows down instead.
+    pub fn max_ticks_per_frame(mut self, max: u32) -> Self {
+        self.timestep = self.timestep.max_steps_per_frame(max);
+        self
+    }
+
+    /// Adds `delta` seconds to the clock, and returns how many ticks have to
+    /// be run for the world to catch up.
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.timestep.advance(delta)
+    }
+
+    /// How far (from 0 to 1) the clock is into the next tick, for smoothing
+    /// what is drawn between ticks.
+    pub fn alpha(&self) -> f32 {
+        self.timestep.alpha()
+    }
+}
+
+impl Default for TickClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The time in the world, counted in ticks since it was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WorldTime(pub u64);
+
+impl WorldTime {
+    /// The number of ticks since the world was created.
+    pub fn ticks(self) -> u64 {
+        self.0
+    }
+
+    /// The number of days that have passed.
+    pub fn day(self) -> u64 {
+        self.0 / DAY_LENGTH
+    }
+
+    /// The ticks since the day started. A day starts at sunrise.
+    pub fn time_of_day(self) -> u64 {
+        self.0 % DAY_LENGTH
+    }
+}
+
+/// How many ticks the world runs per second, and how long (in seconds) each
+/// tick is.
+pub const TICKS_PER_SECOND: u32 = 20;
+pub const TICK_LENGTH: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
+/// How long a day lasts, in ticks.
+pub const DAY_LENGTH: u64 = 24000;
+
+// How many ticks a frame catches up on by default, a quarter of a second.
+const DEFAULT_MAX_TICKS_PER_FRAME: u32 = 5;
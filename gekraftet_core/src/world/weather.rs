@@ -0,0 +1,64 @@
+/// The weather over the whole world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Storm,
+}
+
+impl Weather {
+    pub fn is_raining(self) -> bool {
+        self != Weather::Clear
+    }
+
+    /// How heavy the rain is, from 0 (clear) to 1 (storm).
+    pub fn intensity(self) -> f32 {
+        match self {
+            Weather::Clear => 0.0,
+            Weather::Rain => 0.6,
+            Weather::Storm => 1.0,
+        }
+    }
+
+    // The weather that follows this one, picked with a random number, and how
+    // many ticks it lasts.
+    pub(super) fn next(self, random: u64) -> (Weather, u64) {
+        let roll = random % 100;
+
+        let next = match self {
+            Weather::Clear if roll < 80 => Weather::Rain,
+            Weather::Clear => Weather::Storm,
+            Weather::Rain if roll < 70 => Weather::Clear,
+            Weather::Rain => Weather::Storm,
+            Weather::Storm => Weather::Rain,
+        };
+
+        (next, next.duration(random >> 8))
+    }
+
+    // How long (in ticks) the weather lasts, picked with a random number.
+    pub(super) fn duration(self, random: u64) -> u64 {
+        let (min, max) = match self {
+            Weather::Clear => (12000, 36000),
+            Weather::Rain => (3000, 12000),
+            Weather::Storm => (2000, 6000),
+        };
+
+        min + random % (max - min)
+    }
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Weather::Clear
+    }
+}
+
+// Scrambles the bits of a number (SplitMix64), so that the weather can be
+// picked from the time without keeping a random number generator around.
+pub(super) fn scramble(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{ BufReader, BufRead, BufWriter, Write, Result as IoResult };
 use std::net::{ IpAddr, Ipv4Addr };
@@ -8,6 +9,24 @@ pub struct Config {
     pub ip: IpAddr,
     pub players: u32,
     pub port: u16,
+    /// The folder of the world to run, in the data directory.
+    pub world: String,
+    /// In online mode, players need a token to log in.
+    pub online: bool,
+    /// Whether clients may be sent where entities moved to over UDP, on the
+    /// same port. Everything else always goes over TCP.
+    pub udp: bool,
+    /// Whether players that are not operators may fly, in game modes that
+    /// let them.
+    pub flight: bool,
+    /// How often (in minutes) a snapshot of the world is taken, or 0 for
+    /// never.
+    pub backup_interval: u64,
+    /// How many snapshots of the world are kept.
+    pub backups_kept: usize,
+    /// The token of every player allowed in online mode, by name. They are
+    /// written as `token.<name>=<token>`.
+    pub tokens: HashMap<String, String>,
 }
 
 impl Config {
@@ -35,6 +54,20 @@ impl Config {
                     result.players = players.parse().expect("config: invalid players count"),
                 ("port", port) =>
                     result.port = port.parse().expect("config: invalid port"),
+                ("world", world) =>
+                    result.world = world.to_owned(),
+                ("online", online) =>
+                    result.online = online.parse().expect("config: invalid online mode"),
+                ("udp", udp) =>
+                    result.udp = udp.parse().expect("config: invalid udp setting"),
+                ("flight", flight) =>
+                    result.flight = flight.parse().expect("config: invalid flight setting"),
+                ("backup_interval", interval) =>
+                    result.backup_interval = interval.parse().expect("config: invalid backup interval"),
+                ("backups_kept", kept) =>
+                    result.backups_kept = kept.parse().expect("config: invalid backups kept"),
+                (name, token) if name.starts_with("token.") =>
+                    { result.tokens.insert(name["token.".len()..].to_owned(), token.to_owned()); },
                 
                 (_, _) => { }, // we ignore them
             }
@@ -50,6 +83,16 @@ impl Config {
         writeln!(config_file, "ip={}", self.ip)?;
         writeln!(config_file, "port={}", self.port)?;
         writeln!(config_file, "players={}", self.players)?;
+        writeln!(config_file, "world={}", self.world)?;
+        writeln!(config_file, "online={}", self.online)?;
+        writeln!(config_file, "udp={}", self.udp)?;
+        writeln!(config_file, "flight={}", self.flight)?;
+        writeln!(config_file, "backup_interval={}", self.backup_interval)?;
+        writeln!(config_file, "backups_kept={}", self.backups_kept)?;
+
+        for (name, token) in self.tokens.iter() {
+            writeln!(config_file, "token.{}={}", name, token)?;
+        }
 
         Ok(())
     }
@@ -61,6 +104,13 @@ impl Default for Config {
             ip: IpAddr::from(Ipv4Addr::new(127, 0, 0, 1)),
             players: 20,
             port: 25565,
+            world: "world".to_owned(),
+            online: false,
+            udp: false,
+            flight: false,
+            backup_interval: 30,
+            backups_kept: 10,
+            tokens: HashMap::new(),
         }
     }
 }
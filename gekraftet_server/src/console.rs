@@ -0,0 +1,549 @@
+use std::{ fmt, fs, io };
+use std::cmp::Ordering;
+use std::path::{ Path, PathBuf };
+use std::time::{ SystemTime, UNIX_EPOCH };
+use cgmath::Point3;
+use gekraftet_core::world::{ BlockPos, Schematic, DAY_LENGTH };
+use crate::server::Server;
+
+/// A line typed into the console of the server, or a message starting with a
+/// slash typed into the chat by a player.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Saves the world and shuts the server down.
+    Stop,
+    Save,
+    /// Prints what the server is running: the time, chunks, entities and
+    /// players.
+    Status,
+    /// Prints how many ticks are run per second, and how long every part of
+    /// a tick takes.
+    Tps,
+    /// Tells every player something, as the server.
+    Say(String),
+    /// Disconnects a player, telling them why if a reason is given.
+    Kick { name: String, reason: Option<String> },
+    /// Kicks a player, and keeps them from logging in again until they are
+    /// pardoned.
+    Ban { name: String, reason: Option<String> },
+    Pardon(String),
+    /// Lets a player run every command from the chat.
+    Op(String),
+    Deop(String),
+    Teleport { name: String, to: Destination },
+    Time(TimeChange),
+    /// Saves the blocks between two corners to a schematic file, which can be
+    /// opened by other tools.
+    SaveSchematic { name: String, from: BlockPos, to: BlockPos },
+    /// Pastes a schematic file, with its lowest corner at the destination.
+    PasteSchematic { name: String, at: Destination },
+    /// Saves the world and takes a snapshot of it.
+    Backup,
+    /// Prints the snapshots of the world.
+    ListBackups,
+    /// Puts the world back the way it was when the snapshot was taken.
+    Restore(String),
+    /// Prints the statistics of a player, or a summary of those of every
+    /// player.
+    Stats(Option<String>),
+    Help,
+}
+
+/// Where a player is teleported to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Destination {
+    /// The feet of the player go here, in blocks.
+    Position(Point3<f32>),
+    /// To where another player is standing.
+    Player(String),
+}
+
+/// How the time of the world is changed. It only ever goes forward.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeChange {
+    /// Skips to the next time the day is this many ticks old.
+    Set(u64),
+    /// Skips this many ticks.
+    Add(u64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandError {
+    Unknown(String),
+    /// The command was given too few arguments.
+    Usage(&'static str),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Unknown(name) => write!(f, "unknown command {}, try help", name),
+            CommandError::Usage(usage) => write!(f, "usage: {}", usage),
+        }
+    }
+}
+
+impl Command {
+    /// Parses the line, returning `None` if it is empty. Commands may be
+    /// typed with or without a leading slash.
+    pub fn parse(line: &str) -> Option<Result<Self, CommandError>> {
+        let line = line.trim();
+        let line = line.strip_prefix('/').unwrap_or(line);
+        let name = line.split_whitespace().next()?;
+        let rest = line[name.len()..].trim();
+
+        let args = rest.split_whitespace().collect::<Vec<_>>();
+
+        let command = match name {
+            "stop" => Ok(Command::Stop),
+            "save" | "save-all" => Ok(Command::Save),
+            "status" => Ok(Command::Status),
+            "tps" => Ok(Command::Tps),
+            "say" if rest.is_empty() => Err(CommandError::Usage("say <message>")),
+            "say" => Ok(Command::Say(rest.to_owned())),
+            "kick" => match split_name(rest) {
+                Some((name, reason)) => Ok(Command::Kick { name, reason }),
+                None => Err(CommandError::Usage("kick <player> [reason]")),
+            },
+            "ban" => match split_name(rest) {
+                Some((name, reason)) => Ok(Command::Ban { name, reason }),
+                None => Err(CommandError::Usage("ban <player> [reason]")),
+            },
+            "pardon" | "unban" => match args.as_slice() {
+                [name] => Ok(Command::Pardon(name.to_string())),
+                _ => Err(CommandError::Usage("pardon <player>")),
+            },
+            "op" => match args.as_slice() {
+                [name] => Ok(Command::Op(name.to_string())),
+                _ => Err(CommandError::Usage("op <player>")),
+            },
+            "deop" => match args.as_slice() {
+                [name] => Ok(Command::Deop(name.to_string())),
+                _ => Err(CommandError::Usage("deop <player>")),
+            },
+            "tp" | "teleport" => parse_teleport(&args)
+                .ok_or(CommandError::Usage("tp <player> <x> <y> <z> | tp <player> <other player>")),
+            "time" => parse_time(&args)
+                .map(Command::Time)
+                .ok_or(CommandError::Usage("time set <ticks|day|noon|night|midnight> | time add <ticks>")),
+            "schem" | "schematic" => parse_schematic(&args)
+                .ok_or(CommandError::Usage("schem save <name> <x1> <y1> <z1> <x2> <y2> <z2> | schem paste <name> <x> <y> <z|player>")),
+            "backup" => match args.as_slice() {
+                [] => Ok(Command::Backup),
+                ["list"] => Ok(Command::ListBackups),
+                ["restore", name] => Ok(Command::Restore(name.to_string())),
+                _ => Err(CommandError::Usage("backup | backup list | backup restore <snapshot>")),
+            },
+            "stats" | "statistics" => match args.as_slice() {
+                [] => Ok(Command::Stats(None)),
+                [name] => Ok(Command::Stats(Some(name.to_string()))),
+                _ => Err(CommandError::Usage("stats [player]")),
+            },
+            "help" => Ok(Command::Help),
+            _ => Err(CommandError::Unknown(name.to_owned())),
+        };
+
+        Some(command)
+    }
+
+    /// Whether only operators may run the command from the chat. Anything
+    /// typed into the console is run.
+    pub fn operator_only(&self) -> bool {
+        match self {
+            Command::Status | Command::Tps | Command::Stats(_) | Command::Help => false,
+            _ => true,
+        }
+    }
+}
+
+// Splits the arguments into the name of a player, and the rest of the line
+// if there is any.
+fn split_name(rest: &str) -> Option<(String, Option<String>)> {
+    let name = rest.split_whitespace().next()?;
+    let reason = rest[name.len()..].trim();
+
+    let reason = match reason.is_empty() {
+        true => None,
+        false => Some(reason.to_owned()),
+    };
+
+    Some((name.to_owned(), reason))
+}
+
+fn parse_teleport(args: &[&str]) -> Option<Command> {
+    let to = match args {
+        [_, other] => Destination::Player(other.to_string()),
+        [_, x, y, z] => Destination::Position(Point3::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?)),
+        _ => return None,
+    };
+
+    Some(Command::Teleport { name: args[0].to_owned(), to })
+}
+
+fn parse_schematic(args: &[&str]) -> Option<Command> {
+    let position = |x: &str, y: &str, z: &str| Some(BlockPos::new(x.parse().ok()?, y.parse().ok()?, z.parse().ok()?));
+
+    match args {
+        ["save", name, x1, y1, z1, x2, y2, z2] => Some(Command::SaveSchematic {
+            name: name.to_string(),
+            from: position(x1, y1, z1)?,
+            to: position(x2, y2, z2)?,
+        }),
+        ["paste", name, player] => Some(Command::PasteSchematic {
+            name: name.to_string(),
+            at: Destination::Player(player.to_string()),
+        }),
+        ["paste", name, x, y, z] => Some(Command::PasteSchematic {
+            name: name.to_string(),
+            at: Destination::Position(position(x, y, z)?.0.cast()?),
+        }),
+        _ => None,
+    }
+}
+
+fn parse_time(args: &[&str]) -> Option<TimeChange> {
+    match args {
+        ["set", time] => {
+            let time = match *time {
+                "day" => DAY,
+                "noon" => NOON,
+                "night" => NIGHT,
+                "midnight" => MIDNIGHT,
+                ticks => ticks.parse::<u64>().ok()? % DAY_LENGTH,
+            };
+            Some(TimeChange::Set(time))
+        },
+        ["add", ticks] => ticks.parse().ok().map(TimeChange::Add),
+        _ => None,
+    }
+}
+
+/// Runs the commands the players typed into the chat since the last call,
+/// telling each player what came of theirs. Players may only run the
+/// commands they are allowed to. Returns true if one of them stopped the
+/// server.
+pub fn run_player_commands(server: &mut Server) -> bool {
+    let mut stopping = false;
+
+    for (player, command) in server.network_mut().take_commands() {
+        match command {
+            _ if command.operator_only() && !server.network().is_operator(&player) =>
+                server.network_mut().tell(&player, "only operators may do that"),
+            Command::Stop => {
+                println!("{} stopped the server", player);
+                stopping = true;
+            },
+            command => if let Some(output) = run(server, command) {
+                server.network_mut().tell(&player, &output);
+            },
+        }
+    }
+
+    stopping
+}
+
+/// Runs a command, returning what it has to say about it, if anything.
+/// Stopping is left to whatever runs the server.
+pub fn run(server: &mut Server, command: Command) -> Option<String> {
+    let output = match command {
+        Command::Stop => return None,
+        Command::Save => save(server),
+        Command::Status => status(server),
+        Command::Tps => tps(server),
+        Command::Say(text) => {
+            println!("[server] {}", text);
+            server.network_mut().broadcast(None, &text);
+            return None
+        },
+        Command::Kick { name, reason } => {
+            let reason = reason.as_deref().unwrap_or("kicked by an operator");
+            match server.kick(&name, reason) {
+                true => format!("kicked {}", name),
+                false => format!("{} is not playing", name),
+            }
+        },
+        Command::Ban { name, reason } => {
+            let reason = reason.as_deref().unwrap_or("banned by an operator");
+            if let Err(e) = server.network_mut().permissions_mut().ban(&name, reason) {
+                return Some(format!("unable to save the permissions: {}", e))
+            }
+            server.kick(&name, reason);
+            format!("banned {}", name)
+        },
+        Command::Pardon(name) => match server.network_mut().permissions_mut().unban(&name) {
+            Ok(true) => format!("pardoned {}", name),
+            Ok(false) => format!("{} is not banned", name),
+            Err(e) => format!("unable to save the permissions: {}", e),
+        },
+        Command::Op(name) => match server.network_mut().permissions_mut().op(&name) {
+            Ok(true) => format!("made {} an operator", name),
+            Ok(false) => format!("{} is an operator already", name),
+            Err(e) => format!("unable to save the permissions: {}", e),
+        },
+        Command::Deop(name) => match server.network_mut().permissions_mut().deop(&name) {
+            Ok(true) => format!("{} is no longer an operator", name),
+            Ok(false) => format!("{} is not an operator", name),
+            Err(e) => format!("unable to save the permissions: {}", e),
+        },
+        Command::Teleport { name, to } => teleport(server, &name, to),
+        Command::Time(change) => time(server, change),
+        Command::SaveSchematic { name, from, to } => save_schematic(server, &name, from, to),
+        Command::PasteSchematic { name, at } => paste_schematic(server, &name, at),
+        Command::Backup => match server.backup() {
+            Ok(snapshot) => format!("took the snapshot {}", snapshot.name),
+            Err(e) => format!("unable to take a snapshot: {:?}", e),
+        },
+        Command::ListBackups => list_backups(server),
+        Command::Restore(name) => match server.restore(&name) {
+            Ok((undo, changed)) => format!("restored {}, changing {} blocks back (undo with backup restore {})", name, changed, undo.name),
+            Err(e) => format!("unable to restore {}: {:?}", name, e),
+        },
+        Command::Stats(name) => stats(server, name.as_deref()),
+        Command::Help => HELP.to_owned(),
+    };
+
+    Some(output)
+}
+
+/// Saves the world, returning how that went.
+pub fn save(server: &mut Server) -> String {
+    match server.save() {
+        Ok(()) => "saved the world".to_owned(),
+        Err(e) => format!("unable to save the world: {:?}", e),
+    }
+}
+
+fn status(server: &Server) -> String {
+    let time = server.world().time();
+    format!(
+        "day {}, tick {}: {} chunks, {} entities and {} players",
+        time.day(),
+        time.time_of_day(),
+        server.world().chunks().count(),
+        server.entities().len(),
+        server.network().len(),
+    )
+}
+
+fn teleport(server: &mut Server, name: &str, to: Destination) -> String {
+    let position = match to {
+        Destination::Position(position) => position,
+        Destination::Player(other) => match server.network().player_position(&other) {
+            Some(position) => position,
+            None => return format!("{} is not playing", other),
+        },
+    };
+
+    match server.teleport(name, position) {
+        true => format!("teleported {} to {:.1}, {:.1}, {:.1}", name, position.x, position.y, position.z),
+        false => format!("{} is not playing", name),
+    }
+}
+
+fn time(server: &mut Server, change: TimeChange) -> String {
+    let ticks = match change {
+        TimeChange::Set(time_of_day) => {
+            let now = server.world().time().time_of_day();
+            (time_of_day + DAY_LENGTH - now) % DAY_LENGTH
+        },
+        TimeChange::Add(ticks) => ticks,
+    };
+
+    server.skip_time(ticks);
+    let time = server.world().time();
+    format!("it is now day {}, tick {}", time.day(), time.time_of_day())
+}
+
+fn save_schematic(server: &Server, name: &str, from: BlockPos, to: BlockPos) -> String {
+    let path = match schematic_path(name) {
+        Some(path) => path,
+        None => return format!("invalid schematic name {}", name),
+    };
+
+    let schematic = match server.copy_schematic(from, to) {
+        Some(schematic) => schematic,
+        None => return "the blocks are not all loaded, or there are too many of them".to_owned(),
+    };
+
+    if let Err(e) = fs::create_dir_all(SCHEMATICS_PATH) {
+        return format!("unable to save the schematic: {}", e)
+    }
+
+    let (width, height, length) = schematic.size();
+    match schematic.save(&path) {
+        Ok(()) => format!("saved {}x{}x{} blocks to {}", width, height, length, path.display()),
+        Err(e) => format!("unable to save the schematic: {}", e),
+    }
+}
+
+fn paste_schematic(server: &mut Server, name: &str, at: Destination) -> String {
+    let path = match schematic_path(name) {
+        Some(path) => path,
+        None => return format!("invalid schematic name {}", name),
+    };
+
+    let at = match at {
+        Destination::Position(position) => position,
+        Destination::Player(player) => match server.network().player_position(&player) {
+            Some(position) => position,
+            None => return format!("{} is not playing", player),
+        },
+    };
+
+    let schematic = match Schematic::load(&path) {
+        Ok(schematic) => schematic,
+        Err(e) => return format!("unable to load {}: {}", path.display(), e),
+    };
+
+    let at = BlockPos(at.map(|v| v.floor() as i32));
+    let mut output = format!("changed {} blocks", server.paste_schematic(&schematic, at));
+    if !schematic.unknown_blocks().is_empty() {
+        output.push_str(&format!(", pasting air for {}", schematic.unknown_blocks().join(", ")));
+    }
+
+    output
+}
+
+/// Copies a schematic file into the folder schematics are pasted from, and
+/// returns the name it can be pasted by. The name is that of the file, with
+/// every character a name cannot have replaced. A schematic of the same name
+/// is replaced.
+pub fn import_schematic(path: &Path) -> io::Result<String> {
+    let name = path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| stem
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            })
+            .collect::<String>())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| IMPORTED_SCHEMATIC.to_owned());
+
+    fs::create_dir_all(SCHEMATICS_PATH)?;
+    fs::copy(path, PathBuf::from(SCHEMATICS_PATH).join(format!("{}.schem", name)))?;
+    Ok(name)
+}
+
+// Where the schematic of the name is kept. Names may only have letters,
+// digits, dashes and underscores, so that they cannot point outside the
+// folder.
+fn schematic_path(name: &str) -> Option<PathBuf> {
+    let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    match valid && !name.is_empty() {
+        true => Some(PathBuf::from(SCHEMATICS_PATH).join(format!("{}.schem", name))),
+        false => None,
+    }
+}
+
+fn list_backups(server: &Server) -> String {
+    let snapshots = match server.snapshots() {
+        Ok(snapshots) => snapshots,
+        Err(e) => return format!("unable to list the snapshots: {:?}", e),
+    };
+
+    if snapshots.is_empty() {
+        return "there are no snapshots".to_owned()
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut output = format!("{} snapshots, oldest first:", snapshots.len());
+    for snapshot in snapshots {
+        let minutes = now.saturating_sub(snapshot.taken) / 60;
+        output.push_str(&format!("\n  {:<14} {} minutes ago", snapshot.name, minutes));
+    }
+
+    output
+}
+
+fn stats(server: &Server, name: Option<&str>) -> String {
+    let name = match name {
+        Some(name) => name,
+        None => return stats_summary(server),
+    };
+
+    match server.stats().get(name) {
+        Some(stats) => {
+            let mut output = format!("statistics of {}:", name);
+            for line in stats.lines() {
+                output.push_str(&format!("\n  {}", line));
+            }
+            output
+        },
+        None => format!("{} has not played yet", name),
+    }
+}
+
+// A line for every player, the longest played first.
+fn stats_summary(server: &Server) -> String {
+    let mut players = server.stats().players().collect::<Vec<_>>();
+    if players.is_empty() {
+        return "nobody has played yet".to_owned()
+    }
+
+    players.sort_by(|(_, a), (_, b)| b.play_time.partial_cmp(&a.play_time).unwrap_or(Ordering::Equal));
+
+    let mut output = format!("{} players, the longest played first:", players.len());
+    for (name, stats) in players {
+        output.push_str(&format!(
+            "\n  {:<16} {:.0} minutes, {:.0} blocks travelled, {} broken, {} placed",
+            name,
+            stats.play_time / 60.0,
+            stats.distance,
+            stats.total_broken(),
+            stats.total_placed(),
+        ));
+    }
+
+    output
+}
+
+fn tps(server: &Server) -> String {
+    let metrics = server.metrics();
+    let mut output = format!("{:.1} tps, {:.2} mspt", metrics.tps(), metrics.mspt());
+
+    for (name, millis) in metrics.parts() {
+        output.push_str(&format!("\n  {:<16} {:.3} ms", name, millis));
+    }
+
+    output
+}
+
+// What is printed for the help command.
+pub const HELP: &'static str = "\
+commands:
+  stop    save the world and shut down
+  save    save the world, also save-all
+  status  show the time, loaded chunks, entities and players
+  tps     show the ticks per second and how long they take
+  say     tell every player something
+  kick    disconnect a player
+  ban     kick a player and keep them out
+  pardon  let a banned player back in
+  op      let a player run every command
+  deop    take that away again
+  tp      teleport a player to a position or another player
+  time    skip to a time of day, or by a number of ticks
+  schem   save blocks to a schematic, or paste one
+  backup  take a snapshot of the world, list them, or restore one
+  stats   show what a player did in the world, or every player
+  help    show this
+players that are not operators may only run status, tps, stats and help";
+
+// Where schematics are saved to and pasted from, as `<name>.schem`.
+const SCHEMATICS_PATH: &'static str = "./schematics";
+
+// The name of imported schematics whose files have no usable name.
+const IMPORTED_SCHEMATIC: &'static str = "imported";
+
+// The times of day the time command knows by name, in ticks since sunrise.
+const DAY: u64 = 1000;
+const NOON: u64 = 6000;
+const NIGHT: u64 = 13000;
+const MIDNIGHT: u64 = 18000;
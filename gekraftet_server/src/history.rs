@@ -0,0 +1,83 @@
+use std::collections::{ HashMap, HashSet, VecDeque };
+use gekraftet_core::world::{ BlockPos, ChunkPos };
+
+/// What changed in every chunk, so that players only have to be sent what
+/// they missed. Every change to a block of a chunk counts its revision up by
+/// one; unchanged chunks are at revision 0, which is the chunk as generated.
+///
+/// Only the changes since the server started are known. Chunks read from the
+/// disk may have changed before, see `read()`.
+pub struct ChunkHistory {
+    chunks: HashMap<ChunkPos, History>,
+}
+
+struct History {
+    revision: u64,
+    // The block changed by every one of the latest revisions. Older ones are
+    // forgotten.
+    changes: VecDeque<(u64, BlockPos)>,
+}
+
+impl ChunkHistory {
+    pub fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Counts the change to the block into the revision of its chunk.
+    pub fn record(&mut self, at: BlockPos) {
+        let history = self.chunks
+            .entry(ChunkPos::from(at))
+            .or_insert_with(|| History { revision: 0, changes: VecDeque::new() });
+
+        history.revision += 1;
+        history.changes.push_back((history.revision, at));
+
+        if history.changes.len() > MAX_REMEMBERED_CHANGES {
+            history.changes.pop_front();
+        }
+    }
+
+    /// Counts the chunk as changed from how it is generated, without knowing
+    /// what changed, as is the case for chunks read from the disk. Players
+    /// that have the chunk as generated are then sent all of it.
+    pub fn read(&mut self, at: ChunkPos) {
+        self.chunks
+            .entry(at)
+            .or_insert_with(|| History { revision: 1, changes: VecDeque::new() });
+    }
+
+    pub fn revision(&self, at: ChunkPos) -> u64 {
+        self.chunks.get(&at).map_or(0, |history| history.revision)
+    }
+
+    /// The blocks of the chunk that changed after the revision, each once.
+    /// Returns `None` if the revision is so old that not all of its changes
+    /// are remembered.
+    pub fn changes_since(&self, at: ChunkPos, revision: u64) -> Option<Vec<BlockPos>> {
+        let history = match self.chunks.get(&at) {
+            Some(history) => history,
+            None => return Some(Vec::new()),
+        };
+
+        let oldest = history.changes.front().map_or(history.revision + 1, |&(r, _)| r);
+        if revision + 1 < oldest {
+            return None
+        }
+
+        let mut seen = HashSet::new();
+        let changed = history.changes
+            .iter()
+            .filter(|&&(r, _)| r > revision)
+            .map(|&(_, at)| at)
+            .filter(|&at| seen.insert(at))
+            .collect();
+
+        Some(changed)
+    }
+}
+
+// How many changes of a chunk are remembered. Players further behind than
+// that are sent the whole chunk.
+const MAX_REMEMBERED_CHANGES: usize = 4096;
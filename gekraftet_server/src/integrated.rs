@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc;
+use std::thread::{ self, JoinHandle };
+use std::time::{ Duration, Instant };
+use gekraftet_core::net::{ Connection, MemoryTransport };
+use gekraftet_core::saves::{ self, WorldManager };
+use gekraftet_core::world::TICK_LENGTH;
+use crate::console;
+use crate::login::Login;
+use crate::network::Network;
+use crate::permissions::Permissions;
+use crate::server::{ Server, MAX_CATCH_UP };
+
+/// A server running in a thread of the client, for playing alone. The client
+/// talks to it over a `MemoryTransport`, just like it talks to any other
+/// server over TCP. The server stops, saving the world, once this is dropped.
+pub struct IntegratedServer {
+    thread: Option<JoinHandle<()>>,
+    stopping: Arc<AtomicBool>,
+}
+
+impl IntegratedServer {
+    /// Starts running the world saved in the folder, with the player of the
+    /// name as its only player and operator. Returns the connection of the
+    /// player, which still has to log in.
+    pub fn start(saves: WorldManager, folder: &str, player: &str) -> saves::Result<(Self, Connection)> {
+        let (client_end, server_end) = MemoryTransport::pair();
+        let (started_sender, started) = mpsc::channel();
+        let stopping = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let stopping = stopping.clone();
+            let (folder, player) = (folder.to_owned(), player.to_owned());
+
+            thread::spawn(move || {
+                let login = Login::new(false, HashMap::new(), Permissions::in_memory(vec![player.clone()]), 1);
+                let mut network = Network::local(login, &player);
+                network.add_connection(Connection::new(server_end), "local");
+
+                match Server::open(saves, &folder, network) {
+                    Ok(server) => {
+                        let _ = started_sender.send(Ok(()));
+                        run(server, &stopping);
+                    },
+                    Err(e) => { let _ = started_sender.send(Err(e)); },
+                }
+            })
+        };
+
+        // The thread only goes away without a word if it panicked.
+        match started.recv() {
+            Ok(Ok(())) => {},
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(saves::Error::Io(io::Error::new(io::ErrorKind::Other, "the server panicked"))),
+        }
+
+        let server = Self {
+            thread: Some(thread),
+            stopping,
+        };
+
+        Ok((server, Connection::new(client_end)))
+    }
+
+    /// Stops the server, and waits for it to save the world.
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for IntegratedServer {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// Ticks the server at a fixed rate, like a dedicated one, until it is told
+// to stop or the player stops it.
+fn run(mut server: Server, stopping: &AtomicBool) {
+    let tick_length = Duration::from_secs_f32(TICK_LENGTH);
+    let mut next_tick = Instant::now();
+
+    while !stopping.load(Ordering::Relaxed) {
+        server.tick();
+        if console::run_player_commands(&mut server) {
+            break
+        }
+
+        next_tick += tick_length;
+        let now = Instant::now();
+        match next_tick > now {
+            true => thread::sleep(next_tick - now),
+            false if now - next_tick > MAX_CATCH_UP => next_tick = now,
+            false => {},
+        }
+    }
+
+    println!("{}", console::save(&mut server));
+}
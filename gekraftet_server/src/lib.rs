@@ -0,0 +1,18 @@
+//! The server of the game, which runs a world for players to connect to.
+//! It runs on its own, or inside the client when playing alone, see
+//! `IntegratedServer`.
+
+pub mod config;
+pub mod console;
+mod history;
+pub mod integrated;
+pub mod login;
+pub mod metrics;
+pub mod network;
+pub mod packet;
+pub mod permissions;
+mod rate_limit;
+pub mod server;
+mod world;
+
+pub use integrated::IntegratedServer;
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::fmt;
+use gekraftet_core::net::{ MIN_PROTOCOL_VERSION, PROTOCOL_VERSION };
+use crate::permissions::Permissions;
+
+/// Decides who may play on the server. In online mode, every player needs the
+/// token given to their name; otherwise anyone may play under any free name.
+/// Banned players never may.
+pub struct Login {
+    online: bool,
+    tokens: HashMap<String, String>,
+    permissions: Permissions,
+    max_players: usize,
+}
+
+/// Why a player was not let in, as told to the player.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rejection {
+    /// The client speaks a version of the protocol the server does not.
+    Version(u32),
+    InvalidName,
+    MissingToken,
+    WrongToken,
+    /// Someone with the same name is playing already.
+    NameTaken,
+    Full,
+    /// The player was banned, for the reason given.
+    Banned(String),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rejection::Version(version) => write!(
+                f, "the server runs versions {} to {} of the protocol, not {}",
+                MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, version
+            ),
+            Rejection::InvalidName => write!(
+                f, "names are 1 to {} letters, digits or underscores", MAX_NAME_LENGTH
+            ),
+            Rejection::MissingToken => write!(f, "the server is in online mode, and needs a token"),
+            Rejection::WrongToken => write!(f, "the token does not belong to the name"),
+            Rejection::NameTaken => write!(f, "someone with the same name is playing already"),
+            Rejection::Full => write!(f, "the server is full"),
+            Rejection::Banned(reason) => write!(f, "banned: {}", reason),
+        }
+    }
+}
+
+impl Login {
+    pub fn new(online: bool, tokens: HashMap<String, String>, permissions: Permissions, max_players: usize) -> Self {
+        Self {
+            online,
+            tokens,
+            permissions,
+            max_players,
+        }
+    }
+
+    pub fn permissions(&self) -> &Permissions {
+        &self.permissions
+    }
+
+    pub fn permissions_mut(&mut self) -> &mut Permissions {
+        &mut self.permissions
+    }
+
+    /// Checks a login against the rules, and the names of the players that
+    /// are playing already.
+    pub fn check<'a, I>(&self, version: u32, name: &str, token: Option<&str>, playing: I) -> Result<(), Rejection>
+    where
+        I: Iterator<Item = &'a str>
+    {
+        if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+            return Err(Rejection::Version(version))
+        }
+
+        let valid_name = (1..=MAX_NAME_LENGTH).contains(&name.chars().count())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(Rejection::InvalidName)
+        }
+
+        if let Some(reason) = self.permissions.ban_reason(name) {
+            return Err(Rejection::Banned(reason.to_owned()))
+        }
+
+        // Offline players may still bring a token, which is then ignored.
+        if self.online {
+            match token {
+                None => return Err(Rejection::MissingToken),
+                Some(token) if self.tokens.get(name).map(String::as_str) != Some(token) =>
+                    return Err(Rejection::WrongToken),
+                Some(_) => {},
+            }
+        }
+
+        let mut count = 0;
+        for other in playing {
+            if other.eq_ignore_ascii_case(name) {
+                return Err(Rejection::NameTaken)
+            }
+            count += 1;
+        }
+
+        match count < self.max_players {
+            true => Ok(()),
+            false => Err(Rejection::Full),
+        }
+    }
+}
+
+// The longest name a player may have, in characters.
+const MAX_NAME_LENGTH: usize = 16;
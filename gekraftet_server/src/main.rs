@@ -1,32 +1,84 @@
-mod config;
-mod packet;
-mod world;
-
-use config::Config;
-use tokio::io::BufReader;
-use tokio::net::TcpListener;
+use gekraftet_core::saves::WorldManager;
+use gekraftet_core::world::{ BlockRegistry, TICK_LENGTH };
+use gekraftet_server::config::Config;
+use gekraftet_server::console::{ self, Command };
+use gekraftet_server::login::Login;
+use gekraftet_server::network::Network;
+use gekraftet_server::permissions::{ Permissions, PERMISSIONS_PATH };
+use gekraftet_server::server::{ Server, MAX_CATCH_UP };
+use std::time::Duration;
+use tokio::io::{ AsyncBufReadExt, BufReader };
+use tokio::time::Instant;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let conf = Config::try_read()?;
-    let mut listener = TcpListener::bind((conf.ip, conf.port)).await?;
+    let _ = BlockRegistry::load_or_create(BLOCKS_PATH)?.install();
+    let permissions = Permissions::read(PERMISSIONS_PATH)?;
+    let login = Login::new(conf.online, conf.tokens.clone(), permissions, conf.players as usize);
+    let mut network = Network::bind((conf.ip, conf.port), login, conf.udp)?;
+    network.set_flight(conf.flight);
+    let mut server = Server::open(WorldManager::new(SAVES_PATH), &conf.world, network)
+        .map_err(|e| format!("unable to open the world {}: {:?}", conf.world, e))?;
+    let backup_interval = match conf.backup_interval {
+        0 => None,
+        minutes => Some(Duration::from_secs(minutes * 60)),
+    };
+    server.set_backups(backup_interval, conf.backups_kept);
+    println!("running world {} (seed {})", server.level().name, server.level().generator.seed());
+
+    // The world runs at a fixed rate, and catches up on ticks it missed,
+    // unless it fell too far behind. The console is read between the ticks,
+    // and the network during them.
+    let tick_length = Duration::from_secs_f32(TICK_LENGTH);
+    let mut next_tick = Instant::now();
+    let mut console = BufReader::new(tokio::io::stdin()).lines();
+    let mut console_open = true;
 
     loop {
-        let (stream, addr) = listener.accept().await?;
-        println!("received connection: {}", addr);
-
-        tokio::spawn(async move {
-            let mut buffer = BufReader::new(stream);
-            loop {
-                match packet::Packet::read_packet(&mut buffer).await {
-                    Err(e) if e.kind() == tokio::io::ErrorKind::UnexpectedEof => {
-                        println!("connection to {} ended", addr);
-                        break;
-                    }
-                    Err(e) => Err(e).unwrap(),
-                    _ => {},
+        tokio::select! {
+            _ = tokio::time::sleep_until(next_tick) => {
+                server.tick();
+                next_tick += tick_length;
+
+                let behind = Instant::now().saturating_duration_since(next_tick);
+                if behind > MAX_CATCH_UP {
+                    println!("can't keep up, skipping {} ticks", behind.as_millis() / tick_length.as_millis());
+                    next_tick = Instant::now();
+                }
+
+                if console::run_player_commands(&mut server) {
+                    break
+                }
+            },
+
+            line = console.next_line(), if console_open => {
+                // Without a console, the server runs until it is killed.
+                let line = match line? {
+                    Some(line) => line,
+                    None => {
+                        console_open = false;
+                        continue
+                    },
                 };
-            }
-        });
+
+                match Command::parse(&line) {
+                    Some(Ok(Command::Stop)) => break,
+                    Some(Ok(command)) => if let Some(output) = console::run(&mut server, command) {
+                        println!("{}", output);
+                    },
+                    Some(Err(e)) => println!("{}", e),
+                    None => {},
+                }
+            },
+        }
     }
+
+    println!("{}", console::save(&mut server));
+    Ok(())
 }
+
+// Where the worlds are saved and the blocks are loaded from, like the client
+// does.
+const SAVES_PATH: &'static str = "saves";
+const BLOCKS_PATH: &'static str = "blocks.ron";
@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+use std::time::{ Duration, Instant };
+
+/// How long the ticks of the last few seconds took, as a whole and by part,
+/// e.g. generating chunks or running a system.
+pub struct TickMetrics {
+    ticks: VecDeque<TickTiming>,
+}
+
+/// How long a tick took, and the parts it was made of.
+pub struct TickTiming {
+    pub started: Instant,
+    pub total: Duration,
+    pub parts: Vec<(&'static str, Duration)>,
+}
+
+impl TickMetrics {
+    pub fn new() -> Self {
+        Self {
+            ticks: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, timing: TickTiming) {
+        self.ticks.push_back(timing);
+
+        while self.ticks.front().map_or(false, |tick| tick.started.elapsed() > METRICS_WINDOW) {
+            self.ticks.pop_front();
+        }
+    }
+
+    /// How many ticks were run per second. This is at most the tick rate,
+    /// unless the server is catching up.
+    pub fn tps(&self) -> f32 {
+        // Counting over at least a second keeps the first few ticks from
+        // looking like a burst.
+        let window = self.ticks
+            .front()
+            .map_or(Duration::ZERO, |first| first.started.elapsed())
+            .max(Duration::from_secs(1));
+
+        self.ticks.len() as f32 / window.as_secs_f32()
+    }
+
+    /// How many milliseconds a tick took, on average.
+    pub fn mspt(&self) -> f32 {
+        average_millis(self.ticks.iter().map(|tick| tick.total))
+    }
+
+    /// How many milliseconds every part of a tick took, on average, in the
+    /// order the parts are run in.
+    pub fn parts(&self) -> Vec<(&'static str, f32)> {
+        let mut names = Vec::new();
+        for tick in self.ticks.iter() {
+            for &(name, _) in tick.parts.iter() {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        names
+            .into_iter()
+            .map(|name| {
+                let durations = self.ticks
+                    .iter()
+                    .map(|tick| tick.parts.iter().find(|&&(n, _)| n == name).map_or(Duration::ZERO, |&(_, d)| d));
+
+                (name, average_millis(durations))
+            })
+            .collect()
+    }
+}
+
+fn average_millis<I: Iterator<Item = Duration>>(durations: I) -> f32 {
+    let (count, total) = durations.fold((0, Duration::ZERO), |(count, total), d| (count + 1, total + d));
+
+    match count {
+        0 => 0.0,
+        _ => total.as_secs_f32() * 1000.0 / count as f32,
+    }
+}
+
+// How far back the metrics go.
+const METRICS_WINDOW: Duration = Duration::from_secs(5);
@@ -0,0 +1,857 @@
+use std::collections::{ HashMap, HashSet };
+use std::collections::hash_map::RandomState;
+use std::hash::{ BuildHasher, Hasher };
+use std::io;
+use std::net::{ SocketAddr, TcpListener, ToSocketAddrs };
+use std::time::{ Duration, Instant };
+use cgmath::{ MetricSpace, Point3, Vector4 };
+use gekraftet_core::entity::*;
+use gekraftet_core::level::Level;
+use gekraftet_core::movement::{ PlayerBody, MAX_FLYING_SPEED, WALKING_SPEED };
+use gekraftet_core::events::WorldEvent;
+use gekraftet_core::net::{ self, Capabilities, ClientMessage, Connection, DatagramSocket, ServerMessage, TcpTransport };
+use gekraftet_core::stats::{ PlayerStats, WorldStats };
+use gekraftet_core::world::{ Block, BlockPos, ChunkPos, World, CHUNK_LENGTH_Y, TICK_LENGTH };
+use crate::console::Command;
+use crate::history::ChunkHistory;
+use crate::login::{ Login, Rejection };
+use crate::metrics::TickMetrics;
+use crate::permissions::Permissions;
+use crate::rate_limit::{ Limited, RateLimit };
+
+/// The players connected to the server. Every player is an entity in the
+/// world, which is told about the entities around it, and the changes to the
+/// chunks it has, every tick. Players only ask for blocks to be changed; the
+/// server decides. Likewise, players are only moved by what the client says
+/// it is doing, never to where it says it is, and no faster than time passes.
+/// Clients that send too much are kicked.
+pub struct Network {
+    // Where players connect to, unless the server only runs for a player in
+    // the same process.
+    listener: Option<TcpListener>,
+    // Where entities moved to is sent over this, to the clients that agreed
+    // to it. Without it, the `DATAGRAMS` capability is not offered.
+    datagrams: Option<DatagramSocket>,
+    capabilities: Capabilities,
+    login: Login,
+    history: ChunkHistory,
+    // Connections that have not logged in yet, where they came from, and
+    // since when.
+    pending: Vec<(Connection, String, Instant)>,
+    clients: Vec<Client>,
+    // The player whose position is saved in the level, who plays alone on an
+    // integrated server.
+    owner: Option<String>,
+    // The commands the players typed into the chat, by the name of the
+    // player, until the server runs them.
+    commands: Vec<(String, Command)>,
+    // Whether players that are not operators may fly.
+    flight: bool,
+}
+
+struct Client {
+    connection: Connection,
+    address: String,
+    name: String,
+    entity: Entity,
+    // The player, moved by what the client sends, and the last input it was
+    // moved by, until the client is told. The last input is also kept after
+    // that, for when the player is moved by the server.
+    body: PlayerBody,
+    acknowledged: Option<u32>,
+    last_input: u32,
+    // How many seconds of input the client may still send. It grows as time
+    // passes, and inputs beyond it are cut short.
+    input_budget: f32,
+    // What the player may do to move, as of the last update.
+    may_fly: bool,
+    may_noclip: bool,
+    // How many block edits and chat messages the client may still send.
+    edits: RateLimit,
+    messages: RateLimit,
+    // The entities the client has been told about, and where it was last
+    // told they are.
+    known: HashMap<Entity, Point3<f32>>,
+    // The entities whose last move was sent over UDP, and may have been
+    // lost. Once they stop, where they stopped is sent over TCP.
+    unsettled: HashSet<Entity>,
+    // The chunks the client has, and the revision it has them at.
+    chunks: HashMap<ChunkPos, u64>,
+    // The capabilities both the client and the server support.
+    capabilities: Capabilities,
+    // The token the client says hello over UDP with, and where the hello
+    // came from, once it did.
+    session: Option<u64>,
+    datagram_address: Option<SocketAddr>,
+}
+
+impl Network {
+    /// Starts listening for players on the address, letting in those the
+    /// login allows. With `udp`, the same port is listened on for UDP too.
+    pub fn bind<A: ToSocketAddrs>(address: A, login: Login, udp: bool) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+
+        let datagrams = match udp {
+            true => Some(DatagramSocket::bind(listener.local_addr()?)?),
+            false => None,
+        };
+
+        let capabilities = match datagrams.is_some() {
+            true => Capabilities::SUPPORTED,
+            false => Capabilities::SUPPORTED.difference(Capabilities::DATAGRAMS),
+        };
+
+        Ok(Self {
+            listener: Some(listener),
+            datagrams,
+            capabilities,
+            login,
+            history: ChunkHistory::new(),
+            pending: Vec::new(),
+            clients: Vec::new(),
+            owner: None,
+            commands: Vec::new(),
+            flight: false,
+        })
+    }
+
+    /// Listens for no one, letting in only the connections it is given. The
+    /// owner starts where the player saved in the level was, and may fly in
+    /// game modes that let it. Nothing is compressed, as nothing leaves the
+    /// process.
+    pub fn local(login: Login, owner: &str) -> Self {
+        let capabilities = Capabilities::SUPPORTED
+            .difference(Capabilities::DATAGRAMS)
+            .difference(Capabilities::COMPRESSION);
+
+        Self {
+            listener: None,
+            datagrams: None,
+            capabilities,
+            login,
+            history: ChunkHistory::new(),
+            pending: Vec::new(),
+            clients: Vec::new(),
+            owner: Some(owner.to_owned()),
+            commands: Vec::new(),
+            flight: true,
+        }
+    }
+
+    /// Takes in a connection that did not come through the listener, e.g.
+    /// one over a `MemoryTransport`. It still has to log in.
+    pub fn add_connection(&mut self, connection: Connection, address: &str) {
+        self.pending.push((connection, address.to_owned(), Instant::now()));
+    }
+
+    /// Lets everyone fly in game modes that let them, or only operators.
+    /// Only operators may by default, and only operators may ever fly through
+    /// blocks.
+    pub fn set_flight(&mut self, flight: bool) {
+        self.flight = flight;
+    }
+
+    /// Tells the network that the chunk was read from the disk, and may
+    /// differ from the chunk the clients generate, see `ChunkHistory::read`.
+    pub fn chunk_read(&mut self, at: ChunkPos) {
+        self.history.read(at);
+    }
+
+    /// How many players are logged in.
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_operator(&self, name: &str) -> bool {
+        self.login.permissions().is_operator(name)
+    }
+
+    pub fn permissions_mut(&mut self) -> &mut Permissions {
+        self.login.permissions_mut()
+    }
+
+    /// Where the feet of every player are, in blocks.
+    pub fn player_positions(&self) -> impl Iterator<Item = Point3<f32>> + '_ {
+        self.clients.iter().map(|client| client.body.position())
+    }
+
+    /// Where the feet of the player are, if they are playing.
+    pub fn player_position(&self, name: &str) -> Option<Point3<f32>> {
+        self.client(name).map(|client| client.body.position())
+    }
+
+    /// Tells the player why they are being kicked, and removes them from the
+    /// world. Returns false if no one with the name is playing.
+    pub fn kick(&mut self, entities: &mut Entities, name: &str, reason: &str) -> bool {
+        let index = match self.clients.iter().position(|client| client.name.eq_ignore_ascii_case(name)) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let mut client = self.clients.remove(index);
+        let _ = client.connection.send(&ServerMessage::Rejected { reason: format!("kicked: {}", reason) });
+        entities.despawn(client.entity);
+        println!("kicked {}: {}", client.name, reason);
+
+        true
+    }
+
+    /// Moves the feet of the player to the position, in blocks. The client
+    /// is told as if it had moved there with the last input it sent. Returns
+    /// false if no one with the name is playing.
+    pub fn teleport(&mut self, entities: &mut Entities, name: &str, to: Point3<f32>) -> bool {
+        let client = match self.clients.iter_mut().find(|client| client.name.eq_ignore_ascii_case(name)) {
+            Some(client) => client,
+            None => return false,
+        };
+
+        client.body.teleport(to);
+        entities.insert(client.entity, Position(to));
+        client.acknowledged = Some(client.last_input);
+
+        true
+    }
+
+    /// Takes in the players that connected, and what the players sent since
+    /// the last tick, and tells them what changed around them. Players that
+    /// disconnected are removed from the world. What the players did is
+    /// counted towards their statistics.
+    pub fn update(&mut self, entities: &mut Entities, world: &mut World, level: &Level, stats: &mut WorldStats) {
+        self.accept();
+        self.log_in(entities, level);
+        self.receive_datagrams();
+
+        let players = self.clients.iter().map(|client| client.entity).collect::<Vec<_>>();
+        let permissions = self.login.permissions();
+        let mut chat = Vec::new();
+        for client in self.clients.iter_mut() {
+            let operator = permissions.is_operator(&client.name);
+            client.may_fly = operator || (self.flight && client.body.game_mode().can_fly());
+            client.may_noclip = operator;
+            client.refill(TICK_LENGTH);
+
+            let stats = stats.get_mut(&client.name);
+            stats.play_time += TICK_LENGTH as f64;
+
+            if let Err(e) = client.receive(entities, world, level, &players, &mut chat, stats) {
+                disconnect(client, entities, e);
+            }
+        }
+
+        for (name, text) in chat {
+            self.chat(name, text);
+        }
+
+        // Whatever changed the blocks, be it a player or the world itself, is
+        // told to the players that have the chunk.
+        for event in world.take_events() {
+            if let WorldEvent::BlockChanged { at, .. } = event {
+                self.history.record(at);
+            }
+        }
+
+        let tick = world.time().ticks();
+        let datagrams = self.datagrams.as_ref();
+        for client in self.clients.iter_mut() {
+            let result = client.send_chunks(world, &self.history)
+                .and_then(|()| client.sync(entities, tick, datagrams));
+
+            if let Err(e) = result {
+                disconnect(client, entities, e);
+            }
+        }
+
+        self.clients.retain(|client| entities.is_alive(client.entity));
+    }
+
+    /// Takes the commands the players typed into the chat since the last
+    /// call, with the name of the player that typed each.
+    pub fn take_commands(&mut self) -> Vec<(String, Command)> {
+        std::mem::take(&mut self.commands)
+    }
+
+    /// Sends a line of the chat to every player. Without a sender, it is the
+    /// server saying it.
+    pub fn broadcast(&mut self, sender: Option<&str>, text: &str) {
+        let message = ServerMessage::Chat {
+            sender: sender.map(str::to_owned),
+            text: text.to_owned(),
+        };
+
+        for client in self.clients.iter_mut() {
+            let _ = client.connection.send(&message);
+        }
+    }
+
+    /// Tells a single player something, e.g. the output of a command. Every
+    /// line of the text becomes a line of the chat.
+    pub fn tell(&mut self, name: &str, text: &str) {
+        let client = match self.clients.iter_mut().find(|client| client.name.eq_ignore_ascii_case(name)) {
+            Some(client) => client,
+            None => return,
+        };
+
+        for line in text.lines() {
+            let _ = client.connection.send(&ServerMessage::Chat { sender: None, text: line.to_owned() });
+        }
+    }
+
+    // Passes on what a player said to everyone, unless it is a command,
+    // which is kept for the server to run.
+    fn chat(&mut self, name: String, text: String) {
+        if !text.starts_with('/') {
+            println!("<{}> {}", name, text);
+            return self.broadcast(Some(&name), &text)
+        }
+
+        match Command::parse(&text) {
+            Some(Ok(command)) => self.commands.push((name, command)),
+            Some(Err(e)) => self.tell(&name, &e.to_string()),
+            None => {},
+        }
+    }
+
+    /// Tells the operators that are playing how the server is doing. A
+    /// client that cannot be sent to is disconnected by the next update.
+    pub fn send_metrics(&mut self, metrics: &TickMetrics) {
+        let message = ServerMessage::Metrics {
+            tps: metrics.tps(),
+            mspt: metrics.mspt(),
+            parts: metrics.parts().into_iter().map(|(name, millis)| (name.to_owned(), millis)).collect(),
+        };
+
+        let permissions = self.login.permissions();
+        for client in self.clients.iter_mut().filter(|client| permissions.is_operator(&client.name)) {
+            let _ = client.connection.send(&message);
+        }
+    }
+
+    fn client(&self, name: &str) -> Option<&Client> {
+        self.clients.iter().find(|client| client.name.eq_ignore_ascii_case(name))
+    }
+
+    fn accept(&mut self) {
+        let listener = match self.listener.as_ref() {
+            Some(listener) => listener,
+            None => return,
+        };
+
+        loop {
+            let (stream, address) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    println!("unable to accept a connection: {}", e);
+                    break
+                },
+            };
+
+            match TcpTransport::new(stream) {
+                Ok(transport) => self.pending.push((Connection::new(transport), address.to_string(), Instant::now())),
+                Err(e) => println!("unable to set up the connection to {}: {:?}", address, e),
+            }
+        }
+    }
+
+    // Lets in the connections that logged in as the login allows, giving
+    // each a player in the world. The rest are told why, and closed. So are
+    // those that take too long to log in.
+    fn log_in(&mut self, entities: &mut Entities, level: &Level) {
+        for (mut connection, address, since) in std::mem::take(&mut self.pending) {
+            let (name, token, capabilities) = match connection.receive::<ClientMessage>() {
+                Ok(Some(ClientMessage::Login { version, capabilities, name, token })) => {
+                    let playing = self.clients.iter().map(|client| client.name.as_str());
+
+                    match self.login.check(version, &name, token.as_deref(), playing) {
+                        Ok(()) => (name, token, capabilities.intersection(self.capabilities)),
+                        Err(rejection) => {
+                            reject(&mut connection, &address, rejection);
+                            continue
+                        },
+                    }
+                },
+                Ok(Some(_)) => {
+                    println!("{} sent something before logging in", address);
+                    continue
+                },
+                Ok(None) if since.elapsed() < LOGIN_TIMEOUT => {
+                    self.pending.push((connection, address, since));
+                    continue
+                },
+                Ok(None) => {
+                    println!("{} did not log in in time", address);
+                    continue
+                },
+                Err(_) => continue,
+            };
+
+            let spawn = match (&level.player, self.owner.as_ref()) {
+                (Some(saved), Some(owner)) if *owner == name => saved.position,
+                _ => spawn_position(level),
+            };
+            let entity = spawn_player(entities, spawn);
+
+            let welcome = ServerMessage::Welcome {
+                player: entity,
+                generator: level.generator,
+                capabilities,
+            };
+
+            let session = match capabilities.contains(Capabilities::DATAGRAMS) {
+                true => Some(session_token()),
+                false => None,
+            };
+
+            let result = connection.send(&welcome).and_then(|()| match session {
+                Some(token) => connection.send(&ServerMessage::DatagramSession { token }),
+                None => Ok(()),
+            });
+
+            match result {
+                Ok(()) => {
+                    connection.set_compression(capabilities.contains(Capabilities::COMPRESSION));
+
+                    let mode = match token {
+                        Some(_) => "",
+                        None => ", offline",
+                    };
+                    println!("{} joined ({}{})", name, address, mode);
+
+                    self.clients.push(Client {
+                        connection,
+                        address,
+                        name,
+                        entity,
+                        body: PlayerBody::new(spawn),
+                        acknowledged: Some(0),
+                        last_input: 0,
+                        input_budget: MAX_INPUT_BACKLOG,
+                        may_fly: false,
+                        may_noclip: false,
+                        edits: RateLimit::new(EDITS_PER_SECOND, EDIT_BURST),
+                        messages: RateLimit::new(MESSAGES_PER_SECOND, MESSAGE_BURST),
+                        known: HashMap::new(),
+                        unsettled: HashSet::new(),
+                        chunks: HashMap::new(),
+                        capabilities,
+                        session,
+                        datagram_address: None,
+                    });
+                },
+                Err(e) => {
+                    println!("unable to welcome {}: {:?}", address, e);
+                    entities.despawn(entity);
+                },
+            }
+        }
+    }
+
+    // Learns where the clients that said hello over UDP are, and tells them
+    // it arrived. Datagrams from anyone else are ignored.
+    fn receive_datagrams(&mut self) {
+        let socket = match self.datagrams.as_ref() {
+            Some(socket) => socket,
+            None => return,
+        };
+
+        loop {
+            let (token, address) = match socket.receive_from::<ClientMessage>() {
+                Ok(Some((ClientMessage::DatagramHello { token }, address))) => (token, address),
+                Ok(Some(_)) => continue,
+                Ok(None) => break,
+                Err(e) => {
+                    println!("unable to receive datagrams: {:?}", e);
+                    break
+                },
+            };
+
+            if let Some(client) = self.clients.iter_mut().find(|client| client.session == Some(token)) {
+                client.datagram_address = Some(address);
+                let _ = socket.send_to(&ServerMessage::DatagramSession { token }, address);
+            }
+        }
+    }
+}
+
+impl Client {
+    fn receive(
+        &mut self,
+        entities: &mut Entities,
+        world: &mut World,
+        level: &Level,
+        players: &[Entity],
+        chat: &mut Vec<(String, String)>,
+        stats: &mut PlayerStats,
+    ) -> net::Result<()> {
+        while let Some(message) = self.connection.receive::<ClientMessage>()? {
+            match message {
+                // The player is moved just like the client moved it, though a
+                // frame cannot take forever, the frames cannot add up to more
+                // time than passed, and the player cannot go faster than the
+                // client lets it. Whatever the player may not do is ignored,
+                // and the client is put back where the player really is.
+                ClientMessage::PlayerInput { sequence, delta, mut input, forward, flying, noclip } => {
+                    let delta = delta.max(0.0).min(MAX_INPUT_DELTA).min(self.input_budget);
+                    self.input_budget -= delta;
+
+                    let flying = flying && self.may_fly;
+                    if flying != self.body.flying() {
+                        self.body.set_flying(flying);
+                    }
+                    self.body.set_noclip(noclip && self.may_noclip);
+
+                    let max_speed = match flying {
+                        true => MAX_FLYING_SPEED,
+                        false => WALKING_SPEED,
+                    };
+                    input.speed = input.speed.max(0.0).min(max_speed);
+
+                    let from = self.body.position();
+                    self.body.update(&input, forward, delta, world);
+
+                    // Players that fall out of the world come back at the
+                    // spawn point, which does not count as travelling.
+                    match self.body.position().y < VOID_HEIGHT {
+                        true => self.body.teleport(spawn_position(level)),
+                        false => stats.distance += from.distance(self.body.position()) as f64,
+                    }
+
+                    entities.insert(self.entity, Position(self.body.position()));
+                    self.acknowledged = Some(sequence);
+                    self.last_input = sequence;
+                },
+                ClientMessage::BreakBlock { at } => {
+                    if !self.may_edit()? {
+                        self.refuse(world, at)?;
+                        continue
+                    }
+
+                    let breakable = world.get_block(at).map_or(false, |b| b.hardness().is_some());
+
+                    if breakable && self.reaches(at) {
+                        if let Some(old) = world.set_block(at, Block::new(Block::AIR)) {
+                            stats.broke(old.id);
+                        }
+                    } else {
+                        self.refuse(world, at)?;
+                    }
+                },
+                ClientMessage::PlaceBlock { at, block } => {
+                    if !self.may_edit()? {
+                        self.refuse(world, at)?;
+                        continue
+                    }
+
+                    let free = world.get_block(at).map_or(false, |b| !b.is_solid());
+                    let blocked = players.iter().any(|&player| touches_block(entities, player, at));
+
+                    if free && block.is_solid() && !blocked && self.reaches(at) {
+                        let id = block.id;
+                        if world.set_block(at, block).is_some() {
+                            stats.placed(id);
+                        }
+                    } else {
+                        self.refuse(world, at)?;
+                    }
+                },
+                ClientMessage::ChunkLoaded { at, revision } => {
+                    self.chunks.insert(at, revision);
+                },
+                ClientMessage::ChunkUnloaded { at } => {
+                    self.chunks.remove(&at);
+                },
+                // Only printable text is passed on, and not too much of it.
+                ClientMessage::Chat { text } => {
+                    match self.messages.take() {
+                        Limited::Allowed => {},
+                        Limited::Refused => {
+                            self.connection.send(&ServerMessage::Chat {
+                                sender: None,
+                                text: "you are sending messages too quickly".to_owned(),
+                            })?;
+                            continue
+                        },
+                        Limited::Abused => return self.kick("sending too many messages"),
+                    }
+
+                    let text = text
+                        .chars()
+                        .filter(|c| !c.is_control())
+                        .take(net::MAX_CHAT_LENGTH)
+                        .collect::<String>();
+
+                    if !text.trim().is_empty() {
+                        chat.push((self.name.clone(), text.trim().to_owned()));
+                    }
+                },
+                ClientMessage::RequestStats => {
+                    if !self.capabilities.contains(Capabilities::STATS) {
+                        return Err(net::Error::InvalidMessage("asked for stats without the capability"))
+                    }
+
+                    self.connection.send(&ServerMessage::Stats { stats: stats.clone() })?;
+                },
+                ClientMessage::Login { .. } => return Err(net::Error::InvalidMessage("logged in twice")),
+                ClientMessage::DatagramHello { .. } => return Err(net::Error::InvalidMessage("said hello over tcp")),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Lets the time that passed count towards the inputs and the limits of
+    // the client.
+    fn refill(&mut self, seconds: f32) {
+        self.input_budget = (self.input_budget + seconds).min(MAX_INPUT_BACKLOG);
+        self.edits.refill(seconds);
+        self.messages.refill(seconds);
+    }
+
+    // Returns whether the client may change another block. Clients that
+    // keep trying to change blocks too quickly are kicked.
+    fn may_edit(&mut self) -> net::Result<bool> {
+        match self.edits.take() {
+            Limited::Allowed => Ok(true),
+            Limited::Refused => Ok(false),
+            Limited::Abused => self.kick("changing blocks too quickly").map(|()| false),
+        }
+    }
+
+    // Tells the client why it is being kicked, and fails so that it is
+    // disconnected.
+    fn kick(&mut self, reason: &'static str) -> net::Result<()> {
+        let _ = self.connection.send(&ServerMessage::Rejected { reason: format!("kicked: {}", reason) });
+        Err(net::Error::InvalidMessage(reason))
+    }
+
+    // Returns true if the block is within reach of the eyes of the player.
+    fn reaches(&self, at: BlockPos) -> bool {
+        let center = at.0.map(|v| v as f32 + 0.5);
+        self.body.eye().distance2(center) <= MAX_REACH * MAX_REACH
+    }
+
+    // Tells the client what the block really is after an edit it asked for
+    // was refused, in case it already showed it changed.
+    fn refuse(&mut self, world: &World, at: BlockPos) -> net::Result<()> {
+        match world.get_block(at) {
+            Some(block) => self.connection.send(&ServerMessage::BlockChanged { at, block: block.clone() }),
+            None => Ok(()),
+        }
+    }
+
+    // Brings the chunks of the client up to date: only the blocks that
+    // changed are sent, unless that is more than sending the whole chunk.
+    fn send_chunks(&mut self, world: &World, history: &ChunkHistory) -> net::Result<()> {
+        for (&at, known) in self.chunks.iter_mut() {
+            let revision = history.revision(at);
+            if revision == *known {
+                continue
+            }
+
+            let chunk = match world.chunk(at) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+
+            // Clients that do not take deltas are sent every change as the
+            // whole chunk.
+            let changes = match self.capabilities.contains(Capabilities::CHUNK_DELTAS) {
+                true => history.changes_since(at, *known),
+                false => None,
+            };
+
+            let message = match changes {
+                Some(changed) if changed.len() <= MAX_DELTA_CHANGES => ServerMessage::ChunkDelta {
+                    at,
+                    base: *known,
+                    revision,
+                    changes: changed
+                        .into_iter()
+                        .filter_map(|position| Some((position, world.get_block(position)?.clone())))
+                        .collect(),
+                },
+                _ => ServerMessage::ChunkData {
+                    at,
+                    revision,
+                    blocks: chunk.blocks().cloned().collect(),
+                },
+            };
+
+            self.connection.send(&message)?;
+            *known = revision;
+        }
+
+        Ok(())
+    }
+
+    // Tells the client where its player really is, and about the entities
+    // that came near it, moved, or went away. The player is never told about
+    // itself as an entity. Moves go over UDP if the client said hello over
+    // it.
+    fn sync(&mut self, entities: &Entities, tick: u64, datagrams: Option<&DatagramSocket>) -> net::Result<()> {
+        if let Some(sequence) = self.acknowledged.take() {
+            self.connection.send(&ServerMessage::PlayerAcknowledged { sequence, state: self.body.state() })?;
+        }
+
+        let positions = entities.storage::<Position>();
+        let renders = entities.storage::<Render>();
+        let boxes = entities.storage::<Aabb>();
+        let center = match positions.get(self.entity) {
+            Some(position) => position.0,
+            None => return Ok(()),
+        };
+
+        let nearby = renders
+            .iter()
+            .filter(|&(entity, _)| entity != self.entity)
+            .filter_map(|(entity, render)| Some((entity, render, positions.get(entity)?.0)))
+            .filter(|&(_, _, position)| position.distance2(center) <= SYNC_DISTANCE * SYNC_DISTANCE)
+            .collect::<Vec<_>>();
+
+        for &(entity, render, position) in nearby.iter() {
+            let message = match self.known.get(&entity) {
+                Some(&known) if known == position => match self.unsettled.remove(&entity) {
+                    true => ServerMessage::EntityMoved { entity, tick, position },
+                    false => continue,
+                },
+                Some(_) => {
+                    // Moves that cannot be sent over UDP go over TCP.
+                    let message = ServerMessage::EntityMoved { entity, tick, position };
+                    let sent = match (datagrams, self.datagram_address) {
+                        (Some(socket), Some(address)) => socket.send_to(&message, address).is_ok(),
+                        _ => false,
+                    };
+
+                    if sent {
+                        self.unsettled.insert(entity);
+                        self.known.insert(entity, position);
+                        continue
+                    }
+
+                    message
+                },
+                None => ServerMessage::EntitySpawned {
+                    entity,
+                    tick,
+                    position,
+                    size: boxes.get(entity).map_or(Aabb::new(1.0, 1.0).size, |aabb| aabb.size),
+                    render: *render,
+                },
+            };
+
+            self.connection.send(&message)?;
+            self.known.insert(entity, position);
+        }
+
+        let gone = self.known
+            .keys()
+            .copied()
+            .filter(|entity| !nearby.iter().any(|&(e, _, _)| e == *entity))
+            .collect::<Vec<_>>();
+
+        for entity in gone {
+            self.connection.send(&ServerMessage::EntityDespawned { entity })?;
+            self.known.remove(&entity);
+            self.unsettled.remove(&entity);
+        }
+
+        Ok(())
+    }
+}
+
+// Removes the player of the client from the world. The client itself is
+// dropped once the tick is over.
+fn disconnect(client: &Client, entities: &mut Entities, error: net::Error) {
+    if !entities.despawn(client.entity) {
+        return
+    }
+
+    match error {
+        net::Error::Disconnected => println!("{} left", client.name),
+        e => println!("lost the connection to {} ({}): {:?}", client.name, client.address, e),
+    }
+}
+
+// A token for a client to say hello over UDP with. It only has to be hard
+// to guess, which the random keys of a hasher are.
+fn session_token() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+// Tells the connection why it may not play. It is closed right after.
+fn reject(connection: &mut Connection, address: &str, rejection: Rejection) {
+    println!("rejected {}: {}", address, rejection);
+    let _ = connection.send(&ServerMessage::Rejected { reason: rejection.to_string() });
+}
+
+// Returns true if the box of the entity overlaps the block.
+fn touches_block(entities: &Entities, entity: Entity, at: BlockPos) -> bool {
+    let (position, aabb) = match (entities.get::<Position>(entity), entities.get::<Aabb>(entity)) {
+        (Some(position), Some(aabb)) => (position.0, aabb),
+        _ => return false,
+    };
+
+    let (min, max) = aabb.bounds(position);
+    let block_min = at.0.map(|v| v as f32);
+    let block_max = at.0.map(|v| v as f32 + 1.0);
+
+    (0..3).all(|i| min[i] < block_max[i] && max[i] > block_min[i])
+}
+
+// Where the feet of a (re)spawning player go: the middle of the spawn point,
+// or the top of the world above the origin if it has not been picked yet.
+fn spawn_position(level: &Level) -> Point3<f32> {
+    match level.spawn {
+        Some(at) => Point3::new(at.x as f32 + 0.5, at.y as f32, at.z as f32 + 0.5),
+        None => Point3::new(0.5, CHUNK_LENGTH_Y as f32, 0.5),
+    }
+}
+
+// Adds a player to the world, with its feet at the position.
+fn spawn_player(entities: &mut Entities, position: Point3<f32>) -> Entity {
+    let player = entities.spawn();
+    entities.insert(player, Position(position));
+    entities.insert(player, Aabb::new(PLAYER_WIDTH, PLAYER_HEIGHT));
+    entities.insert(player, Render {
+        shape: RenderShape::Cube,
+        color: Vector4::new(0.9, 0.8, 0.6, 1.0),
+    });
+    player
+}
+
+// How far away (in blocks) entities can be for a player to be told about
+// them.
+const SYNC_DISTANCE: f32 = 96.0;
+
+// How many blocks of a chunk may have changed for only them to be sent,
+// rather than the whole chunk.
+const MAX_DELTA_CHANGES: usize = 1024;
+
+// The longest frame (in seconds) a player is moved by at once, and how far
+// (in blocks) a player falls before it is put back at the spawn point.
+const MAX_INPUT_DELTA: f32 = 1.0;
+const VOID_HEIGHT: f32 = -64.0;
+
+// How many seconds of input a client may send ahead of the time that
+// passed, to make up for a connection that stutters.
+const MAX_INPUT_BACKLOG: f32 = 0.5;
+
+// How many blocks a client may change, and chat messages it may send, per
+// second, and how many of them at once.
+const EDITS_PER_SECOND: f32 = 10.0;
+const EDIT_BURST: f32 = 20.0;
+const MESSAGES_PER_SECOND: f32 = 1.0;
+const MESSAGE_BURST: f32 = 5.0;
+
+// How long a connection has to log in.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The size of the box of a player, in blocks.
+const PLAYER_WIDTH: f32 = 0.6;
+const PLAYER_HEIGHT: f32 = 1.8;
+
+// How far away (in blocks) from its eyes a player may change blocks. This is
+// a little more than the client allows, as the server sees the player a bit
+// late.
+const MAX_REACH: f32 = 6.0;
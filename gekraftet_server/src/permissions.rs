@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{ BufReader, BufRead, BufWriter, Write, Result as IoResult };
+use std::path::{ Path, PathBuf };
+
+/// Who may run the privileged commands, and who may not play at all. Names
+/// are matched regardless of case. Every change is written back to the file
+/// the permissions were read from, if any.
+pub struct Permissions {
+    path: Option<PathBuf>,
+    operators: Vec<String>,
+    // The reason every banned player was banned for, by name.
+    bans: HashMap<String, String>,
+}
+
+impl Permissions {
+    /// Reads the permissions from the file, which is created if it does not
+    /// exist. The operators are written as a comma separated list, and the
+    /// bans as `ban.<name>=<reason>`.
+    pub fn read<P: AsRef<Path>>(path: P) -> IoResult<Self> {
+        let mut result = Self {
+            path: Some(path.as_ref().to_owned()),
+            operators: Vec::new(),
+            bans: HashMap::new(),
+        };
+
+        if !path.as_ref().exists() {
+            result.try_write()?;
+            return Ok(result)
+        }
+
+        let file = File::open(path).map(|x| BufReader::new(x))?;
+
+        for line in file.lines() {
+            let line = line?;
+            let mut values = line.splitn(2, '=');
+            let name = values.next().unwrap_or("").trim();
+            let value = values.next().unwrap_or("").trim();
+
+            match (name, value) {
+                ("operators", operators) =>
+                    result.operators = operators.split(',').map(str::trim).filter(|o| !o.is_empty()).map(str::to_lowercase).collect(),
+                (name, reason) if name.starts_with("ban.") =>
+                    { result.bans.insert(name["ban.".len()..].to_lowercase(), reason.to_owned()); },
+
+                (_, _) => { }, // we ignore them
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Permissions that are never written anywhere, with the given operators
+    /// and no bans.
+    pub fn in_memory(operators: Vec<String>) -> Self {
+        Self {
+            path: None,
+            operators: operators.iter().map(|name| name.to_lowercase()).collect(),
+            bans: HashMap::new(),
+        }
+    }
+
+    pub fn is_operator(&self, name: &str) -> bool {
+        self.operators.contains(&name.to_lowercase())
+    }
+
+    /// Why the player was banned, if they were.
+    pub fn ban_reason(&self, name: &str) -> Option<&str> {
+        self.bans.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    /// Makes the player an operator. Returns false if they were one already.
+    pub fn op(&mut self, name: &str) -> IoResult<bool> {
+        let name = name.to_lowercase();
+        if self.operators.contains(&name) {
+            return Ok(false)
+        }
+
+        self.operators.push(name);
+        self.try_write().map(|()| true)
+    }
+
+    /// Returns false if the player was not an operator.
+    pub fn deop(&mut self, name: &str) -> IoResult<bool> {
+        let name = name.to_lowercase();
+        let count = self.operators.len();
+        self.operators.retain(|operator| *operator != name);
+
+        match self.operators.len() != count {
+            true => self.try_write().map(|()| true),
+            false => Ok(false),
+        }
+    }
+
+    /// Bans the player, or changes the reason they were banned for.
+    pub fn ban(&mut self, name: &str, reason: &str) -> IoResult<()> {
+        self.bans.insert(name.to_lowercase(), reason.to_owned());
+        self.try_write()
+    }
+
+    /// Returns false if the player was not banned.
+    pub fn unban(&mut self, name: &str) -> IoResult<bool> {
+        match self.bans.remove(&name.to_lowercase()) {
+            Some(_) => self.try_write().map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    fn try_write(&self) -> IoResult<()> {
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut file = File::create(path).map(|x| BufWriter::new(x))?;
+
+        writeln!(file, "operators={}", self.operators.join(","))?;
+
+        for (name, reason) in self.bans.iter() {
+            writeln!(file, "ban.{}={}", name, reason)?;
+        }
+
+        file.flush()
+    }
+}
+
+// Where a dedicated server keeps its permissions, next to its config.
+pub const PERMISSIONS_PATH: &'static str = "./permissions.conf";
@@ -0,0 +1,48 @@
+/// Limits how often something may be done, e.g. how many chat messages a
+/// player may send. Every time it is done takes a token, and tokens come
+/// back at a fixed rate, up to a burst.
+///
+/// Going over the limit still takes a token, so that someone that keeps at
+/// it goes into debt. Once the debt is as large as the burst, they are
+/// abusing it rather than being unlucky.
+pub struct RateLimit {
+    tokens: f32,
+    per_second: f32,
+    burst: f32,
+}
+
+/// What came of trying to do something under a limit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Limited {
+    Allowed,
+    Refused,
+    /// Refused, and far too often for it to be an accident.
+    Abused,
+}
+
+impl RateLimit {
+    pub fn new(per_second: f32, burst: f32) -> Self {
+        Self {
+            tokens: burst,
+            per_second,
+            burst,
+        }
+    }
+
+    /// Gives back the tokens that came back in the time that passed.
+    pub fn refill(&mut self, seconds: f32) {
+        self.tokens = (self.tokens + seconds * self.per_second).min(self.burst);
+    }
+
+    pub fn take(&mut self) -> Limited {
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            Limited::Allowed
+        } else if self.tokens > -self.burst {
+            Limited::Refused
+        } else {
+            Limited::Abused
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
+use cgmath::Point3;
+use gekraftet_core::backups::{ Backups, Snapshot };
+use gekraftet_core::entity::*;
+use gekraftet_core::level::Level;
+use gekraftet_core::region::RegionStore;
+use gekraftet_core::saves::{ self, WorldManager };
+use gekraftet_core::stats::WorldStats;
+use gekraftet_core::world::*;
+use crate::metrics::{ TickMetrics, TickTiming };
+use crate::network::Network;
+
+/// Runs a world without anyone looking at it: the chunks around the spawn
+/// point are generated, and the blocks and entities in them are ticked.
+/// Players connect to it over the network.
+pub struct Server {
+    saves: WorldManager,
+    // The folder of the world in the data directory.
+    folder: String,
+    level: Level,
+    stats: WorldStats,
+    // The chunks that were saved, or generated ahead of time.
+    regions: RegionStore,
+    world: World,
+    entities: Entities,
+    schedule: Schedule,
+    network: Network,
+    // Chunks still to be generated, nearest to the spawn point first.
+    pending_chunks: VecDeque<ChunkPos>,
+    metrics: TickMetrics,
+    backups: Backups,
+    // How often a snapshot is taken on its own, if at all, and how many are
+    // kept.
+    backup_interval: Option<Duration>,
+    backups_kept: usize,
+    last_backup: Instant,
+}
+
+impl Server {
+    /// Opens the world saved in the folder, or creates it (with a random
+    /// seed) if there is none. A world saved by an earlier version of the
+    /// game is migrated first.
+    pub fn open(saves: WorldManager, folder: &str, network: Network) -> saves::Result<Self> {
+        let regions = saves.regions(folder);
+        match regions.recover() {
+            Ok(recovered) if !recovered.is_empty() =>
+                println!("saving {} regions was cut short, they may hold older chunks", recovered.len()),
+            Ok(_) => {},
+            Err(e) => println!("unable to recover the regions: {}", e),
+        }
+
+        let level = match saves.list()?.iter().any(|f| f == folder) {
+            true => {
+                for change in saves.migrate(folder, false)? {
+                    println!("migrated {}", change);
+                }
+                saves.load(folder)?
+            },
+            false => {
+                let level = Level::new(folder.to_owned(), WorldGenerator::new(random_seed(), WorldPreset::Default));
+                saves.save(folder, &level)?;
+                level
+            },
+        };
+
+        let stats = saves.load_stats(folder)?;
+
+        let mut world = World::new();
+        world.set_border(level.generator.border());
+
+        let seed = level.generator.seed();
+        let schedule = Schedule::new()
+            .system(MobSpawner::new(seed))
+            .system(WanderSystem::new(seed))
+            .system(ItemDropSystem)
+            .system(MovementSystem);
+
+        let mut server = Self {
+            regions,
+            saves,
+            folder: folder.to_owned(),
+            level,
+            stats,
+            world,
+            entities: Entities::new(),
+            schedule,
+            network,
+            pending_chunks: VecDeque::new(),
+            metrics: TickMetrics::new(),
+            backups: Backups::new(BACKUPS_PATH),
+            backup_interval: None,
+            backups_kept: BACKUPS_KEPT,
+            last_backup: Instant::now(),
+        };
+
+        server.queue_spawn_area();
+        Ok(server)
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
+    /// What the players did in the world, including those that are not
+    /// connected.
+    pub fn stats(&self) -> &WorldStats {
+        &self.stats
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
+    pub fn network_mut(&mut self) -> &mut Network {
+        &mut self.network
+    }
+
+    pub fn metrics(&self) -> &TickMetrics {
+        &self.metrics
+    }
+
+    /// Takes a snapshot of the world every `interval`, if there is one, and
+    /// keeps the latest `kept` snapshots, whether taken on a timer or not.
+    /// No snapshots are taken on a timer by default.
+    pub fn set_backups(&mut self, interval: Option<Duration>, kept: usize) {
+        self.backup_interval = interval;
+        self.backups_kept = kept;
+    }
+
+    /// Advances the world by a single tick. A few of the chunks that are
+    /// still missing are generated first. Every part of the tick is timed,
+    /// and the operators are sent the metrics once a second.
+    pub fn tick(&mut self) {
+        let started = Instant::now();
+        let mut parts = Vec::new();
+
+        self.queue_player_areas();
+
+        for _ in 0..CHUNKS_PER_TICK {
+            match self.pending_chunks.pop_front() {
+                Some(at) => {
+                    let chunk = self.load_chunk(at);
+                    self.world.insert_chunk(chunk);
+                },
+                None => break,
+            }
+        }
+
+        if self.level.spawn.is_none() {
+            let center = self.spawn_center();
+            self.level.spawn = self.world.find_spawn(center.x, center.z, SPAWN_SEARCH_RADIUS);
+        }
+        parts.push(("chunks", started.elapsed()));
+
+        let world_started = Instant::now();
+        self.world.tick();
+        parts.push(("world", world_started.elapsed()));
+
+        let network_started = Instant::now();
+        self.network.update(&mut self.entities, &mut self.world, &self.level, &mut self.stats);
+        parts.push(("network", network_started.elapsed()));
+
+        parts.extend(self.schedule.run_timed(&mut self.entities, &self.world, TICK_LENGTH));
+
+        self.metrics.record(TickTiming { started, total: started.elapsed(), parts });
+
+        if self.world.time().ticks() % TICKS_PER_SECOND as u64 == 0 {
+            self.network.send_metrics(&self.metrics);
+        }
+
+        if self.backup_interval.map_or(false, |interval| self.last_backup.elapsed() >= interval) {
+            match self.backup() {
+                Ok(snapshot) => println!("took the snapshot {}", snapshot.name),
+                Err(e) => println!("unable to take a snapshot: {:?}", e),
+            }
+        }
+    }
+
+    /// Kicks the player out of the server, see `Network::kick`.
+    pub fn kick(&mut self, name: &str, reason: &str) -> bool {
+        self.network.kick(&mut self.entities, name, reason)
+    }
+
+    /// Moves the player to the position, see `Network::teleport`.
+    pub fn teleport(&mut self, name: &str, to: Point3<f32>) -> bool {
+        self.network.teleport(&mut self.entities, name, to)
+    }
+
+    /// Moves the time of the world forward by a number of ticks. Time never
+    /// goes back, as the clients order the moves of entities by it.
+    pub fn skip_time(&mut self, ticks: u64) {
+        let time = self.world.time().ticks();
+        self.world.set_time(WorldTime(time + ticks));
+    }
+
+    /// Copies the blocks between the two corners, see `Schematic::copy`.
+    pub fn copy_schematic(&self, a: BlockPos, b: BlockPos) -> Option<Schematic> {
+        Schematic::copy(&self.world, a, b)
+    }
+
+    /// Pastes the blocks into the world, see `Schematic::paste`. The players
+    /// are told about the blocks that changed like about any other.
+    pub fn paste_schematic(&mut self, schematic: &Schematic, at: BlockPos) -> usize {
+        schematic.paste(&mut self.world, at)
+    }
+
+    /// Saves the world and takes a snapshot of it, deleting the oldest
+    /// snapshots beyond those that are kept.
+    pub fn backup(&mut self) -> saves::Result<Snapshot> {
+        self.last_backup = Instant::now();
+        self.save()?;
+
+        let snapshot = self.backups.snapshot(&self.saves, &self.folder, &self.world)?;
+        self.backups.prune(&self.folder, self.backups_kept)?;
+        Ok(snapshot)
+    }
+
+    /// The snapshots of the world, oldest first.
+    pub fn snapshots(&self) -> saves::Result<Vec<Snapshot>> {
+        Ok(self.backups.list(&self.folder)?)
+    }
+
+    /// Puts the world back the way it was when the snapshot was taken, see
+    /// `Backups::restore`. A snapshot is taken first, so that this can be
+    /// undone as well. Returns that snapshot, and how many blocks changed.
+    /// The statistics of the players are not put back.
+    pub fn restore(&mut self, name: &str) -> saves::Result<(Snapshot, usize)> {
+        if !self.snapshots()?.iter().any(|snapshot| snapshot.name == name) {
+            let e = io::Error::new(io::ErrorKind::NotFound, format!("there is no snapshot {}", name));
+            return Err(e.into())
+        }
+
+        // Pruning waits until the snapshot being restored is no longer
+        // needed.
+        self.save()?;
+        let snapshot = self.backups.snapshot(&self.saves, &self.folder, &self.world)?;
+        let (level, changed) = self.backups.restore(&self.saves, &self.folder, name, &mut self.world)?;
+
+        self.world.set_border(level.generator.border());
+        self.level = level;
+        self.backups.prune(&self.folder, self.backups_kept)?;
+        Ok((snapshot, changed))
+    }
+
+    /// Saves the level, the chunks that changed since the last save and the
+    /// statistics of the players into the folder of the world.
+    pub fn save(&mut self) -> saves::Result<()> {
+        self.saves.save(&self.folder, &self.level)?;
+        self.regions.save(self.world.modified_chunks())?;
+        self.world.mark_saved();
+        self.saves.save_stats(&self.folder, &self.stats)
+    }
+
+    // Queues the chunks around the spawn point (or the origin, until there is
+    // one) that are inside the border, nearest first.
+    fn queue_spawn_area(&mut self) {
+        let center = ChunkPos::from(self.spawn_center());
+        let border = self.level.generator.border();
+        let radius = SPAWN_CHUNK_RADIUS;
+
+        let mut chunks = (-radius..=radius)
+            .flat_map(|x| (-radius..=radius).map(move |z| (x, z)))
+            .map(|(x, z)| ChunkPos::new(center.x + x, 0, center.z + z))
+            .filter(|&at| border.intersects_chunk(at))
+            .collect::<Vec<_>>();
+
+        chunks.sort_by_key(|at| (at.x - center.x).pow(2) + (at.z - center.z).pow(2));
+        self.pending_chunks = chunks.into();
+    }
+
+    // Queues the chunks around every player that are still missing, nearest
+    // to the player first. Chunks are never unloaded yet.
+    fn queue_player_areas(&mut self) {
+        let border = self.level.generator.border();
+        let radius = PLAYER_CHUNK_RADIUS;
+        let players = self.network.player_positions().collect::<Vec<_>>();
+
+        for position in players {
+            let center = ChunkPos::from(BlockPos(position.map(|v| v.floor() as i32)));
+
+            let mut chunks = (-radius..=radius)
+                .flat_map(|x| (-radius..=radius).map(move |z| (x, z)))
+                .map(|(x, z)| ChunkPos::new(center.x + x, 0, center.z + z))
+                .filter(|&at| border.intersects_chunk(at) && self.world.chunk(at).is_none())
+                .filter(|at| !self.pending_chunks.contains(at))
+                .collect::<Vec<_>>();
+
+            chunks.sort_by_key(|at| (at.x - center.x).pow(2) + (at.z - center.z).pow(2));
+            self.pending_chunks.extend(chunks);
+        }
+    }
+
+    // Reads the chunk if it was saved (or generated ahead of time), and
+    // generates it otherwise. Only chunks whose blocks changed are saved, and
+    // the clients are sent the whole of those, as they only generate the
+    // chunks themselves.
+    fn load_chunk(&mut self, at: ChunkPos) -> Chunk {
+        match self.regions.load(at) {
+            Ok(Some(chunk)) => {
+                self.network.chunk_read(at);
+                chunk
+            },
+            Ok(None) => self.level.generator.generate(at),
+            Err(e) => {
+                println!("unable to load the chunk at {:?}: {}", at, e);
+                self.level.generator.generate(at)
+            },
+        }
+    }
+
+    fn spawn_center(&self) -> BlockPos {
+        self.level.spawn.unwrap_or(BlockPos::new(0, 0, 0))
+    }
+}
+
+// A seed for a new world. There is no need for it to be unpredictable, only
+// for it to differ between worlds.
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+// How many chunks (on either axis) around the spawn point are kept loaded,
+// and how many of them are generated per tick while they are missing.
+const SPAWN_CHUNK_RADIUS: i32 = 4;
+const CHUNKS_PER_TICK: usize = 4;
+
+// How many chunks (on either axis) around every player are generated. The
+// player is moved by the server, so it needs the ground below it.
+const PLAYER_CHUNK_RADIUS: i32 = 3;
+
+// How far from the center of the spawn area (in blocks) the spawn point may
+// be.
+const SPAWN_SEARCH_RADIUS: i32 = 32;
+
+// Where the snapshots of the worlds are kept, and how many of every world
+// are kept unless told otherwise.
+const BACKUPS_PATH: &'static str = "./backups";
+const BACKUPS_KEPT: usize = 10;
+
+/// How far behind (in time) a server may fall before it gives up on catching
+/// up on the ticks it missed.
+pub const MAX_CATCH_UP: Duration = Duration::from_secs(2);
@@ -43,7 +43,7 @@ impl Region {
             let mut bytes = [0u8; 4];
             reader.read_exact(&mut bytes)?;
             let location = ChunkLocation {
-                sector_count: bytes[4],
+                sector_count: bytes[3],
                 sector_offset: u32::from_be_bytes([
                     0, bytes[0], bytes[1], bytes[2]
                 ]),
@@ -0,0 +1,148 @@
+use std::time::{ Instant, SystemTime, UNIX_EPOCH };
+use gekraftet_core::level::Level;
+use gekraftet_core::world::{ BlockPos, ChunkPos, WorldGenerator, WorldPreset };
+use crate::options::Options;
+use crate::timing::Timings;
+
+/// Generates the chunks around the spawn point of a world and saves them, so
+/// that the server reads them instead of generating them.
+pub fn pregen(options: &Options) -> Result<(), String> {
+    let folder = options.positional(0, "world")?;
+    let radius = options.parse("radius", PREGEN_RADIUS)?;
+    let saves = options.saves();
+
+    let exists = saves.list().map_err(|e| format!("unable to list the worlds: {:?}", e))?.iter().any(|f| f == folder);
+    let level = match exists {
+        true => {
+            if options.get("seed").is_some() || options.get("preset").is_some() {
+                println!("{} exists already, keeping its seed and preset", folder);
+            }
+            saves.load(folder).map_err(|e| format!("unable to open the world {}: {:?}", folder, e))?
+        },
+        false => {
+            let generator = WorldGenerator::new(options.parse("seed", random_seed())?, options.preset()?.unwrap_or_default());
+            let level = Level::new(folder.to_owned(), generator);
+            saves.save(folder, &level).map_err(|e| format!("unable to create the world {}: {:?}", folder, e))?;
+            println!("created {}", folder);
+            level
+        },
+    };
+
+    let generator = level.generator;
+    let center = ChunkPos::from(level.spawn.unwrap_or(BlockPos::new(0, 0, 0)));
+    let mut chunks = (-radius..=radius)
+        .flat_map(|x| (-radius..=radius).map(move |z| (x, z)))
+        .map(|(x, z)| ChunkPos::new(center.x + x, 0, center.z + z))
+        .filter(|&at| generator.border().intersects_chunk(at))
+        .collect::<Vec<_>>();
+    // Chunks of the same region (32 by 32 chunks) are saved together.
+    chunks.sort_by_key(|at| (at.x.div_euclid(32), at.z.div_euclid(32), at.x, at.z));
+
+    println!(
+        "generating {} chunks of {} (seed {}, {})",
+        chunks.len(), level.name, generator.seed(), generator.preset().name(),
+    );
+
+    let regions = saves.regions(folder);
+    let mut timings = Timings::new();
+    let started = Instant::now();
+
+    for (i, batch) in chunks.chunks(PREGEN_BATCH).enumerate() {
+        let generated = batch
+            .iter()
+            .map(|&at| {
+                let (chunk, stages) = generator.generate_timed(at);
+                timings.record(&stages);
+                chunk
+            })
+            .collect::<Vec<_>>();
+
+        let saving = Instant::now();
+        regions.save(generated.iter()).map_err(|e| format!("unable to save the chunks: {}", e))?;
+        timings.record(&[("save", saving.elapsed() / batch.len() as u32)]);
+
+        println!("  {}/{} chunks", (i * PREGEN_BATCH + batch.len()), chunks.len());
+    }
+
+    let seconds = started.elapsed().as_secs_f64();
+    println!("done in {:.1} s, {:.1} chunks per second", seconds, chunks.len() as f64 / seconds);
+    timings.print();
+    Ok(())
+}
+
+/// Times how fast every preset (or the one given) generates chunks.
+pub fn bench(options: &Options) -> Result<(), String> {
+    let count = options.parse("chunks", BENCH_CHUNKS)?;
+    let seed = options.parse("seed", BENCH_SEED)?;
+    let presets = match options.preset()? {
+        Some(preset) => vec![preset],
+        None => WorldPreset::ALL.to_vec(),
+    };
+
+    println!("generating {} chunks per preset (seed {})", count, seed);
+    println!("  {:<10} {:>12} {:>14}", "preset", "ms/chunk", "chunks/second");
+
+    for preset in presets {
+        let generator = WorldGenerator::new(seed, preset);
+        let started = Instant::now();
+
+        for at in bench_chunks(count) {
+            generator.generate(at);
+        }
+
+        let seconds = started.elapsed().as_secs_f64();
+        println!(
+            "  {:<10} {:>12.3} {:>14.1}",
+            preset.name(), seconds * 1000.0 / count as f64, count as f64 / seconds,
+        );
+    }
+
+    Ok(())
+}
+
+/// Times every stage of generating chunks.
+pub fn stats(options: &Options) -> Result<(), String> {
+    let count = options.parse("chunks", BENCH_CHUNKS)?;
+    let seed = options.parse("seed", BENCH_SEED)?;
+    let preset = options.preset()?.unwrap_or_default();
+    let generator = WorldGenerator::new(seed, preset);
+
+    println!("generating {} chunks (seed {}, {})", count, seed, preset.name());
+
+    let mut timings = Timings::new();
+    for at in bench_chunks(count) {
+        let started = Instant::now();
+        let (_, stages) = generator.generate_timed(at);
+        timings.record(&stages);
+        timings.record(&[("total", started.elapsed())]);
+    }
+
+    timings.print();
+    Ok(())
+}
+
+// The chunks that are generated to time a generator: a square of them
+// around the origin, row by row.
+fn bench_chunks(count: usize) -> impl Iterator<Item = ChunkPos> {
+    let side = (count as f64).sqrt().ceil().max(1.0) as i32;
+
+    (0..count as i32).map(move |i| ChunkPos::new(i % side - side / 2, 0, i / side - side / 2))
+}
+
+// A seed for a new world, like the server picks.
+fn random_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+// How many chunks around the spawn point (on either axis) are generated
+// unless told otherwise, and how many are saved at once.
+const PREGEN_RADIUS: i32 = 8;
+const PREGEN_BATCH: usize = 64;
+
+// How many chunks are generated to time a generator, and the seed they are
+// generated with, so that runs can be compared.
+const BENCH_CHUNKS: usize = 64;
+const BENCH_SEED: u64 = 12345;
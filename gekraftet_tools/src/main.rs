@@ -0,0 +1,66 @@
+mod generation;
+mod map;
+mod migration;
+mod options;
+mod timing;
+
+use options::Options;
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    let (command, options) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), Options::new(rest)),
+        None => return println!("{}", USAGE),
+    };
+
+    let result = match command {
+        "pregen" => generation::pregen(&options),
+        "bench" => generation::bench(&options),
+        "stats" => generation::stats(&options),
+        "map" => map::map(&options),
+        "migrate" => migration::migrate(&options),
+        "help" | "--help" | "-h" => Ok(println!("{}", USAGE)),
+        command => Err(format!("unknown command {}, try help", command)),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+// What is printed for the help command.
+const USAGE: &'static str = "\
+usage: gekraftet_tools <command> [options]
+
+commands:
+  pregen <world>  generate the chunks around the spawn point of a saved world
+                  ahead of time, creating the world if there is none
+                    --radius <chunks>  how far around it, 8 by default
+                    --seed <seed>      the seed of a new world
+                    --preset <preset>  default, amplified or flat
+                    --saves <folder>   where the worlds are, saves by default
+  bench           time how fast every preset generates chunks
+                    --chunks <count>   how many chunks, 64 by default
+                    --seed <seed>
+                    --preset <preset>  only this preset
+  stats           time every stage of generating chunks
+                    --chunks <count>
+                    --seed <seed>
+                    --preset <preset>
+  map <image>     render a map of the terrain, seen from above, to a PNG
+                    --world <world>    a saved world, rather than generating
+                    --saves <folder>
+                    --seed <seed>      the seed if there is no world
+                    --preset <preset>  the preset if there is no world
+                    --radius <chunks>  how far around the center, 8 by default
+                    --center <x,z>     the chunk in the middle, by default the
+                                       spawn point
+                    --blocks <file>    the block definitions the colors are
+                                       taken from
+  migrate <world> rewrite a world saved by an earlier version the way this one
+                  saves it, which is otherwise done when it is opened
+                    --dry-run          only tell what would be rewritten
+                    --saves <folder>
+  help            show this";
@@ -0,0 +1,178 @@
+use std::fs::File;
+use std::io::BufWriter;
+use gekraftet_core::region::RegionStore;
+use gekraftet_core::world::{
+    BlockPos, BlockRegistry, Chunk, ChunkPos, WorldGenerator, CHUNK_LENGTH_X, CHUNK_LENGTH_Y, CHUNK_LENGTH_Z,
+};
+use crate::options::Options;
+
+/// Renders a map of the chunks around a point, seen from above, to a PNG.
+/// Every pixel is the color of the highest block of its column, looking
+/// through water and glass, brighter the higher the column is and shaded by
+/// the slope, so that hills stand out.
+///
+/// The chunks are those of a saved world if one is given, generated if they
+/// were never saved, or else generated from a seed and a preset.
+pub fn map(options: &Options) -> Result<(), String> {
+    let output = options.positional(0, "image")?;
+    let radius = options.parse("radius", MAP_RADIUS)?;
+
+    if let Some(path) = options.get("blocks") {
+        let blocks = BlockRegistry::load(path).map_err(|e| format!("unable to load the blocks of {}: {}", path, e))?;
+        let _ = blocks.install();
+    }
+
+    let (generator, regions, spawn) = match options.get("world") {
+        Some(folder) => {
+            let saves = options.saves();
+            let level = saves.load(folder).map_err(|e| format!("unable to open the world {}: {:?}", folder, e))?;
+            (level.generator, Some(saves.regions(folder)), level.spawn)
+        },
+        None => {
+            let seed = options.parse("seed", MAP_SEED)?;
+            (WorldGenerator::new(seed, options.preset()?.unwrap_or_default()), None, None)
+        },
+    };
+
+    let center = match options.get("center") {
+        Some(center) => parse_center(center)?,
+        None => ChunkPos::from(spawn.unwrap_or(BlockPos::new(0, 0, 0))),
+    };
+
+    println!(
+        "rendering {} by {} chunks around {}, {} (seed {}, {})",
+        radius * 2 + 1, radius * 2 + 1, center.x, center.z, generator.seed(), generator.preset().name(),
+    );
+
+    let side = (radius * 2 + 1) as usize;
+    let width = side * CHUNK_LENGTH_X;
+    let height = side * CHUNK_LENGTH_Z;
+    let mut columns = vec![Column::default(); width * height];
+
+    for (i, z) in (-radius..=radius).enumerate() {
+        for (j, x) in (-radius..=radius).enumerate() {
+            let at = ChunkPos::new(center.x + x, 0, center.z + z);
+            let chunk = load_chunk(&generator, regions.as_ref(), at)?;
+
+            for cz in 0..CHUNK_LENGTH_Z {
+                for cx in 0..CHUNK_LENGTH_X {
+                    let pixel = (i * CHUNK_LENGTH_Z + cz) * width + j * CHUNK_LENGTH_X + cx;
+                    columns[pixel] = Column::of(&chunk, cx, cz);
+                }
+            }
+        }
+    }
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    for (i, column) in columns.iter().enumerate() {
+        // The column to the north (towards -z) is compared with, as if the
+        // sun shone from there.
+        let north = match i >= width {
+            true => columns[i - width].height,
+            false => column.height,
+        };
+
+        let slope = match column.height as i32 - north as i32 {
+            d if d > 0 => SLOPE_LIT,
+            d if d < 0 => SLOPE_SHADED,
+            _ => 1.0,
+        };
+        let brightness = slope * (MIN_BRIGHTNESS + (1.0 - MIN_BRIGHTNESS) * column.height as f32 / CHUNK_LENGTH_Y as f32);
+
+        pixels.extend(column.color.iter().map(|c| (c * brightness).max(0.0).min(1.0) * 255.0).map(|c| c.round() as u8));
+    }
+
+    let file = File::create(output).map_err(|e| format!("unable to create {}: {}", output, e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(|e| format!("unable to write {}: {}", output, e))?;
+
+    println!("saved {} ({} by {} pixels)", output, width, height);
+    Ok(())
+}
+
+// What a column of blocks looks like from above.
+#[derive(Clone, Copy, Debug, Default)]
+struct Column {
+    height: u16,
+    color: [f32; 3],
+}
+
+impl Column {
+    fn of(chunk: &Chunk, x: usize, z: usize) -> Self {
+        let height = chunk.height(x, z).unwrap_or(0);
+
+        // Translucent blocks are blended with whatever is below them, until
+        // an opaque block is found or nothing is left to see through.
+        let mut color = [0.0; 3];
+        let mut remaining = 1.0;
+        for y in (0..height as usize).rev() {
+            let block = match chunk.block(x, y, z) {
+                Some(block) if !block.is_air() => block,
+                _ => continue,
+            };
+
+            let [r, g, b, a] = block.definition().color;
+            let opacity = match block.is_translucent() {
+                true => a.max(MIN_OPACITY),
+                false => 1.0,
+            };
+
+            for (c, &channel) in color.iter_mut().zip([r, g, b].iter()) {
+                *c += channel * opacity * remaining;
+            }
+
+            remaining *= 1.0 - opacity;
+            if remaining <= 0.01 {
+                break
+            }
+        }
+
+        Self {
+            height,
+            color,
+        }
+    }
+}
+
+// Reads the chunk from the save, or generates it if there is no save or it
+// was never saved.
+fn load_chunk(generator: &WorldGenerator, regions: Option<&RegionStore>, at: ChunkPos) -> Result<Chunk, String> {
+    let saved = match regions {
+        Some(regions) => regions.load(at).map_err(|e| format!("unable to read the chunk at {}, {}: {}", at.x, at.z, e))?,
+        None => None,
+    };
+
+    Ok(saved.unwrap_or_else(|| generator.generate(at)))
+}
+
+// Parses the chunk the map is centered on, given as `x,z`.
+fn parse_center(text: &str) -> Result<ChunkPos, String> {
+    let invalid = || format!("invalid --center {}, expected x,z in chunks", text);
+    let (x, z) = text.split_once(',').ok_or_else(invalid)?;
+
+    Ok(ChunkPos::new(
+        x.trim().parse().map_err(|_| invalid())?,
+        0,
+        z.trim().parse().map_err(|_| invalid())?,
+    ))
+}
+
+// How many chunks around the center (on either axis) are drawn unless told
+// otherwise, and the seed chunks are generated with if there is no world.
+const MAP_RADIUS: i32 = 8;
+const MAP_SEED: u64 = 12345;
+
+// How bright the lowest columns are, and how much brighter (or darker) a
+// column is for being higher (or lower) than the one north of it.
+const MIN_BRIGHTNESS: f32 = 0.5;
+const SLOPE_LIT: f32 = 1.1;
+const SLOPE_SHADED: f32 = 0.8;
+
+// How much of what is below it even the clearest translucent block hides, so
+// that water can be told apart from the ground under it.
+const MIN_OPACITY: f32 = 0.5;
@@ -0,0 +1,29 @@
+use crate::options::Options;
+
+/// Migrates a world saved by an earlier version of the game, or only tells
+/// what would be migrated with `--dry-run`.
+pub fn migrate(options: &Options) -> Result<(), String> {
+    let folder = options.positional(0, "world")?;
+    let dry_run = options.flag("dry-run");
+
+    let changes = options
+        .saves()
+        .migrate(folder, dry_run)
+        .map_err(|e| format!("unable to migrate the world {}: {:?}", folder, e))?;
+
+    if changes.is_empty() {
+        println!("{} is up to date", folder);
+        return Ok(())
+    }
+
+    match dry_run {
+        true => println!("would migrate {}:", folder),
+        false => println!("migrated {}:", folder),
+    }
+
+    for change in changes {
+        println!("  {}", change);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::str::FromStr;
+use gekraftet_core::saves::WorldManager;
+use gekraftet_core::world::WorldPreset;
+
+/// The arguments given after a command: the positional ones, and options
+/// like `--radius 8`.
+pub struct Options {
+    positional: Vec<String>,
+    named: Vec<(String, String)>,
+}
+
+impl Options {
+    pub fn new(args: &[String]) -> Self {
+        let mut positional = Vec::new();
+        let mut named = Vec::new();
+
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                // Options without a value, like `--dry-run`, are followed by
+                // another option or nothing.
+                Some(name) => match args.peek().map_or(true, |next| next.starts_with("--")) {
+                    true => named.push((name.to_owned(), String::new())),
+                    false => named.push((name.to_owned(), args.next().cloned().unwrap_or_default())),
+                },
+                None => positional.push(arg.clone()),
+            }
+        }
+
+        Self {
+            positional,
+            named,
+        }
+    }
+
+    /// The positional argument at the index, which is required.
+    pub fn positional(&self, index: usize, what: &str) -> Result<&str, String> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or(format!("missing the {}", what))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.named
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the option was given, with or without a value.
+    pub fn flag(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Parses the option, or returns `default` if it was not given.
+    pub fn parse<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.get(name) {
+            Some(value) => value.parse().map_err(|_| format!("invalid --{} {}", name, value)),
+            None => Ok(default),
+        }
+    }
+
+    pub fn preset(&self) -> Result<Option<WorldPreset>, String> {
+        match self.get("preset") {
+            Some(name) => WorldPreset::ALL
+                .iter()
+                .copied()
+                .find(|preset| preset.name().eq_ignore_ascii_case(name))
+                .map(Some)
+                .ok_or(format!("unknown preset {}", name)),
+            None => Ok(None),
+        }
+    }
+
+    /// The worlds in the folder given by `--saves`, or where the client and
+    /// the server keep them.
+    pub fn saves(&self) -> WorldManager {
+        WorldManager::new(self.get("saves").unwrap_or(SAVES_PATH))
+    }
+}
+
+// Where the worlds are, as for the client and the server.
+const SAVES_PATH: &'static str = "saves";
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+/// How long every stage of something took, every time it was done.
+pub struct Timings {
+    // In the order the stages were first seen.
+    stages: Vec<(&'static str, Vec<Duration>)>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self {
+            stages: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, stages: &[(&'static str, Duration)]) {
+        for &(name, duration) in stages {
+            match self.stages.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, durations)) => durations.push(duration),
+                None => self.stages.push((name, vec![duration])),
+            }
+        }
+    }
+
+    /// Prints the mean, median, 95th percentile and maximum of every stage,
+    /// in milliseconds.
+    pub fn print(&self) {
+        println!("  {:<10} {:>9} {:>9} {:>9} {:>9}", "stage", "mean", "median", "p95", "max");
+
+        for (name, durations) in self.stages.iter() {
+            let mut millis = durations.iter().map(|d| d.as_secs_f64() * 1000.0).collect::<Vec<_>>();
+            millis.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            let mean = millis.iter().sum::<f64>() / millis.len() as f64;
+            let percentile = |p: f64| millis[((millis.len() - 1) as f64 * p).round() as usize];
+
+            println!(
+                "  {:<10} {:>9.3} {:>9.3} {:>9.3} {:>9.3}",
+                name, mean, percentile(0.5), percentile(0.95), millis[millis.len() - 1],
+            );
+        }
+    }
+}